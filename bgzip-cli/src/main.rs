@@ -1,4 +1,5 @@
 use anyhow::Context;
+use bgzip::tabix::TabixConfig;
 use bgzip::{read::BGZFMultiThreadReader, write::BGZFMultiThreadWriter, BGZFReader, BGZFWriter};
 use clap::Parser;
 use is_terminal::IsTerminal;
@@ -46,6 +47,21 @@ struct Cli {
         default_value = "-1"
     )]
     compress_level: i32,
+    #[arg(
+        short = 'o',
+        long = "output",
+        help = "write to file, keep original files unchanged",
+        conflicts_with = "stdout"
+    )]
+    output: Option<String>,
+    #[arg(
+        short = 'p',
+        long = "tabix",
+        value_name = "PRESET",
+        help = "compress and create tabix index [file.gz.tbi]; gff, bed, sam or vcf",
+        conflicts_with = "decompress"
+    )]
+    tabix: Option<TabixConfig>,
     // #[arg(short = 'r', long = "reindex", help = "(re)index compressed file")]
     // reindex: bool,
     // #[arg(
@@ -75,6 +91,16 @@ fn main() -> anyhow::Result<()> {
         .build_global()
         .context("Failed to set number of threads in thread pool")?;
 
+    if cli.test {
+        return test_files(&cli);
+    }
+
+    if cli.output.is_some() && cli.files.len() > 1 {
+        return Err(anyhow::anyhow!(
+            "-o cannot be used with more than one input file"
+        ));
+    }
+
     if cli.files.is_empty() {
         process_file(&cli, None)?;
     } else {
@@ -90,10 +116,37 @@ fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+fn test_files(cli: &Cli) -> anyhow::Result<()> {
+    let mut failed = false;
+    let files: Vec<&str> = if cli.files.is_empty() {
+        vec!["-"]
+    } else {
+        cli.files.iter().map(|x| x.as_str()).collect()
+    };
+    for path in files {
+        let report = if path == "-" {
+            bgzip::validate(std::io::stdin().lock())?
+        } else {
+            bgzip::validate(std::io::BufReader::new(File::open(path)?))?
+        };
+        if let Some(error) = report.error {
+            eprintln!("{}: {}", path, error);
+            failed = true;
+        } else if !report.has_eof_marker {
+            eprintln!("{}: no valid EOF marker was found", path);
+            failed = true;
+        }
+    }
+    if failed {
+        return Err(anyhow::anyhow!("integrity test failed"));
+    }
+    Ok(())
+}
+
 fn process_file(cli: &Cli, input_path: Option<&str>) -> anyhow::Result<()> {
     let compression = match cli.compress_level {
         -1 => bgzip::Compression::default(),
-        i if i >= 0 && i <= 12 => bgzip::Compression::new(
+        i if (0..=12).contains(&i) => bgzip::Compression::new(
             cli.compress_level
                 .try_into()
                 .context("Compression level must be -1 to 12")?,
@@ -115,48 +168,62 @@ fn process_file(cli: &Cli, input_path: Option<&str>) -> anyhow::Result<()> {
         Box::new(std::io::stdin().lock())
     };
 
-    let (mut output, index_out): (Box<dyn Write>, Option<File>) = if let Some(path) = input_path
-        .map(|x| if cli.stdout { None } else { Some(x) })
-        .flatten()
-    {
-        let new_path = if cli.decompress {
-            if path.ends_with(".gz") {
-                path[..(path.len() - 3)].to_string()
+    let output_path = if let Some(path) = cli.output.as_ref() {
+        delete_input = false;
+        Some(path.clone())
+    } else if cli.stdout {
+        None
+    } else if let Some(path) = input_path {
+        if cli.decompress {
+            if let Some(stripped) = path.strip_suffix(".gz") {
+                Some(stripped.to_string())
             } else {
                 return Err(anyhow::anyhow!("{}: unknown suffix", path));
             }
         } else {
-            format!("{}.gz", path)
-        };
-        let index_path = if cli.index && !cli.decompress {
-            Some(
-                cli.index_name
-                    .clone()
-                    .unwrap_or_else(|| format!("{}.gzi", new_path)),
-            )
-        } else {
-            None
-        };
-
-        if std::path::Path::new(new_path.as_str()).exists() && !cli.force {
-            return Err(anyhow::anyhow!(
-                "{} already exists. Use -f to force overwrite.",
-                new_path
-            ));
+            Some(format!("{}.gz", path))
         }
-        (
-            Box::new(File::create(new_path)?),
-            index_path.map(|x| File::create(x)).transpose()?,
-        )
     } else {
-        if std::io::stdout().is_terminal() && !cli.force && !cli.decompress {
-            return Err(anyhow::anyhow!(
-                "compressed data not written to a terminal. Use -f to force compression."
-            ));
-        }
-        delete_input = false;
-        (Box::new(std::io::stdout().lock()), None)
+        None
     };
+    let (mut output, index_out, tabix_out): (Box<dyn Write>, Option<File>, Option<File>) =
+        if let Some(new_path) = output_path {
+            let index_path = if cli.index && !cli.decompress {
+                Some(
+                    cli.index_name
+                        .clone()
+                        .unwrap_or_else(|| format!("{}.gzi", new_path)),
+                )
+            } else {
+                None
+            };
+            let tabix_path = cli.tabix.map(|_| format!("{}.tbi", new_path));
+
+            if std::path::Path::new(new_path.as_str()).exists() && !cli.force {
+                return Err(anyhow::anyhow!(
+                    "{} already exists. Use -f to force overwrite.",
+                    new_path
+                ));
+            }
+            (
+                Box::new(File::create(new_path)?),
+                index_path.map(File::create).transpose()?,
+                tabix_path.map(File::create).transpose()?,
+            )
+        } else {
+            if cli.tabix.is_some() {
+                return Err(anyhow::anyhow!(
+                    "tabix index cannot be created for standard output. Use -o to set output file."
+                ));
+            }
+            if std::io::stdout().is_terminal() && !cli.force && !cli.decompress {
+                return Err(anyhow::anyhow!(
+                    "compressed data not written to a terminal. Use -f to force compression."
+                ));
+            }
+            delete_input = false;
+            (Box::new(std::io::stdout().lock()), None, None)
+        };
 
     if cli.decompress {
         if cli.threads.is_some() {
@@ -166,6 +233,19 @@ fn process_file(cli: &Cli, input_path: Option<&str>) -> anyhow::Result<()> {
             let mut reader = BGZFReader::new(&mut input)?;
             std::io::copy(&mut reader, &mut output)?;
         }
+    } else if let (Some(preset), Some(tabix_out)) = (cli.tabix, tabix_out) {
+        // tabix index is built while compressing, so the input can be a pipe
+        let mut writer = BGZFWriter::new(&mut output, compression);
+        let tabix = bgzip::tabix::compress_and_index(
+            std::io::BufReader::new(&mut input),
+            &mut writer,
+            preset,
+        )?;
+        let index = writer.close()?;
+        tabix.write(std::io::BufWriter::new(tabix_out))?;
+        if let Some(index_out) = index_out {
+            index.unwrap().write(std::io::BufWriter::new(index_out))?;
+        }
     } else {
         if cli.threads.is_some() {
             let mut writer = BGZFMultiThreadWriter::new(&mut output, compression);
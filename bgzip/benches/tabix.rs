@@ -0,0 +1,98 @@
+use bgzip::tabix::{TabixConfig, TabixFile};
+use bgzip::BGZFReader;
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use std::fs::File;
+use std::io::BufRead;
+
+const VCF_PATH: &str = "testfiles/common_all_20180418_half.vcf.gz";
+
+/// Previous parser, which collected all columns of a line before parsing positions.
+///
+/// This is the baseline of `parse_record/vcf`.
+fn parse_vcf_columns(line: &[u8]) -> Option<(Vec<u8>, u64, u64)> {
+    let columns: Vec<Vec<u8>> = line.split(|x| *x == b'\t').map(|x| x.to_vec()).collect();
+    let position = |data: &[u8]| -> Option<u64> { std::str::from_utf8(data).ok()?.parse().ok() };
+    let begin = position(columns.get(1)?)?.saturating_sub(1);
+    let mut end = begin + u64::try_from(columns.get(3)?.len()).unwrap();
+    if let Some(info_end) = columns
+        .get(7)?
+        .split(|x| *x == b';')
+        .find_map(|x| x.strip_prefix(b"END="))
+    {
+        end = end.max(position(info_end)?);
+    }
+    Some((columns[0].clone(), begin, end.max(begin + 1)))
+}
+
+fn parse_record(c: &mut Criterion) {
+    let reader = BGZFReader::new(File::open(VCF_PATH).unwrap()).unwrap();
+    let lines: Vec<Vec<u8>> = reader
+        .split(b'\n')
+        .map(|x| x.unwrap())
+        .filter(|x| !TabixConfig::VCF.is_meta(x))
+        .take(100_000)
+        .collect();
+    let bytes: usize = lines.iter().map(|x| x.len()).sum();
+
+    let mut group = c.benchmark_group("parse_record");
+    group.throughput(Throughput::Bytes(bytes.try_into().unwrap()));
+    group.bench_function("vcf", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(TabixConfig::VCF.parse_record(line).unwrap());
+            }
+        })
+    });
+    group.bench_function("vcf_collect_columns", |b| {
+        b.iter(|| {
+            for line in &lines {
+                black_box(parse_vcf_columns(line).unwrap());
+            }
+        })
+    });
+    group.finish();
+}
+
+fn fetch_dense_region(c: &mut Criterion) {
+    let mut file = TabixFile::from_path(VCF_PATH).unwrap();
+    let rid = file.index().name2rid(b"1").unwrap();
+    let count = file.count(rid, 1_000_000, 5_000_000).unwrap();
+
+    let mut group = c.benchmark_group("fetch");
+    group.throughput(Throughput::Elements(count));
+    group.bench_function("vcf_dense_region", |b| {
+        b.iter(|| {
+            for record in file.fetch(rid, 1_000_000, 5_000_000) {
+                black_box(record.unwrap());
+            }
+        })
+    });
+    group.bench_function("vcf_dense_region_count", |b| {
+        b.iter(|| black_box(file.count(rid, 1_000_000, 5_000_000).unwrap()))
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("vcf_dense_region_parallel", |b| {
+        b.iter(|| black_box(file.fetch_parallel(rid, 1_000_000, 5_000_000).unwrap()))
+    });
+    group.finish();
+
+    let count = file.count(rid, 0, 100_000_000).unwrap();
+    let mut group = c.benchmark_group("fetch_large");
+    group.throughput(Throughput::Elements(count));
+    group.sample_size(10);
+    group.bench_function("vcf_large_region", |b| {
+        b.iter(|| {
+            for record in file.fetch(rid, 0, 100_000_000) {
+                black_box(record.unwrap());
+            }
+        })
+    });
+    #[cfg(feature = "rayon")]
+    group.bench_function("vcf_large_region_parallel", |b| {
+        b.iter(|| black_box(file.fetch_parallel(rid, 0, 100_000_000).unwrap()))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_record, fetch_dense_region);
+criterion_main!(benches);
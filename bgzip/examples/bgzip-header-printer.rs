@@ -21,7 +21,7 @@ fn main() -> anyhow::Result<()> {
     };
     let mut csv_out = csv::WriterBuilder::new().from_writer(out);
 
-    csv_out.write_record(&[
+    csv_out.write_record([
         "offset",
         "header-size",
         "compressed-size",
@@ -29,7 +29,7 @@ fn main() -> anyhow::Result<()> {
     ])?;
 
     loop {
-        let offset = file.seek(SeekFrom::Current(0))?;
+        let offset = file.stream_position()?;
         let header = bgzip::header::BGZFHeader::from_reader(&mut file)?;
         let compressed_size = header.block_size()?;
         file.seek(SeekFrom::Current(compressed_size as i64 - 20 - 6 + 4))?;
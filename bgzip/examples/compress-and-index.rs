@@ -0,0 +1,34 @@
+use bgzip::tabix::{compress_and_index, TabixConfig};
+use bgzip::write::BGZFWriter;
+use clap::Parser;
+use std::fs::File;
+use std::io::{self, prelude::*};
+
+/// Compress sorted data and create tabix index in one pass.
+///
+/// Input can be a pipe. e.g. `sort -k1,1 -k2,2n data.bed | compress-and-index -p bed -o data.bed.gz`
+#[derive(Debug, Clone, Parser, PartialEq)]
+struct Cli {
+    #[arg(help = "input file [stdin]")]
+    input_file: Option<String>,
+    #[arg(short, long)]
+    output: String,
+    #[arg(short, long, help = "gff, bed, sam or vcf")]
+    preset: TabixConfig,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let reader: Box<dyn BufRead> = if let Some(input_file) = cli.input_file.as_ref() {
+        Box::new(io::BufReader::new(File::open(input_file)?))
+    } else {
+        Box::new(io::stdin().lock())
+    };
+
+    let mut writer = BGZFWriter::new(File::create(&cli.output)?, bgzip::Compression::default());
+    let index = compress_and_index(reader, &mut writer, cli.preset)?;
+    writer.close()?;
+    index.write(File::create(format!("{}.tbi", cli.output))?)?;
+
+    Ok(())
+}
@@ -0,0 +1,154 @@
+//! Serve region queries of a tabix indexed file over HTTP.
+//!
+//! * `GET /header` returns header lines.
+//! * `GET /query?region=chr1:100-200&offset=0&limit=1000` streams records overlapping with the region.
+//!   Use `offset` and `limit` to page through large regions.
+
+use bgzip::tabix::{Region, SharedTabixFile};
+use bgzip::BGZFError;
+use clap::Parser;
+use std::io::{self, prelude::*};
+use std::net::{TcpListener, TcpStream};
+
+const DEFAULT_LIMIT: usize = 1000;
+
+#[derive(Debug, Parser)]
+struct Cli {
+    #[arg(help = "BGZF compressed file with .tbi index")]
+    file: String,
+    #[arg(short, long, default_value = "127.0.0.1:8080")]
+    listen: String,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let shared = SharedTabixFile::from_path(&cli.file)?;
+    let header = shared.open()?.header_lines()?;
+
+    let listener = TcpListener::bind(&cli.listen)?;
+    eprintln!("Listening on http://{}", cli.listen);
+    std::thread::scope(|s| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Failed to accept: {}", e);
+                    continue;
+                }
+            };
+            let shared = &shared;
+            let header = &header;
+            s.spawn(move || {
+                if let Err(e) = handle(stream, shared, header) {
+                    eprintln!("Error: {}", e);
+                }
+            });
+        }
+    });
+
+    Ok(())
+}
+
+fn handle(stream: TcpStream, shared: &SharedTabixFile, header: &[Vec<u8>]) -> anyhow::Result<()> {
+    let mut request_line = String::new();
+    let mut reader = io::BufReader::new(&stream);
+    reader.read_line(&mut request_line)?;
+    let mut writer = io::BufWriter::new(&stream);
+
+    let target = match request_line.split(' ').collect::<Vec<_>>()[..] {
+        ["GET", target, _] => target.to_string(),
+        _ => return respond_error(&mut writer, "405 Method Not Allowed", "GET only"),
+    };
+    let (path, query) = target.split_once('?').unwrap_or((&target, ""));
+    let params: Vec<(String, String)> = query
+        .split('&')
+        .filter_map(|x| x.split_once('='))
+        .map(|(k, v)| (percent_decode(k), percent_decode(v)))
+        .collect();
+    let param = |key: &str| {
+        params
+            .iter()
+            .find(|(k, _)| k == key)
+            .map(|(_, v)| v.as_str())
+    };
+
+    match path {
+        "/header" => {
+            write_ok_header(&mut writer)?;
+            for line in header {
+                writer.write_all(line)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        "/query" => {
+            let region = match param("region") {
+                Some(region) => region,
+                None => return respond_error(&mut writer, "400 Bad Request", "no region"),
+            };
+            let region = match Region::parse(region) {
+                Ok(region) => region,
+                Err(_) => return respond_error(&mut writer, "400 Bad Request", "invalid region"),
+            };
+            let offset: usize = param("offset").and_then(|x| x.parse().ok()).unwrap_or(0);
+            let limit: usize = param("limit")
+                .and_then(|x| x.parse().ok())
+                .unwrap_or(DEFAULT_LIMIT);
+
+            let mut file = shared.open()?;
+            let records = match file.fetch_name(region.name.as_bytes(), region.begin, region.end) {
+                Ok(records) => records,
+                Err(BGZFError::UnknownContig(_)) => {
+                    return respond_error(&mut writer, "404 Not Found", "unknown sequence")
+                }
+                Err(e) => return Err(e.into()),
+            };
+            write_ok_header(&mut writer)?;
+            for record in records.skip(offset).take(limit) {
+                writer.write_all(&record?.data)?;
+                writer.write_all(b"\n")?;
+            }
+        }
+        _ => return respond_error(&mut writer, "404 Not Found", "not found"),
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+fn write_ok_header<W: Write>(writer: &mut W) -> io::Result<()> {
+    writer.write_all(b"HTTP/1.1 200 OK\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n")
+}
+
+fn respond_error<W: Write>(writer: &mut W, status: &str, message: &str) -> anyhow::Result<()> {
+    write!(
+        writer,
+        "HTTP/1.1 {}\r\nContent-Type: text/plain\r\nConnection: close\r\n\r\n{}\n",
+        status, message
+    )?;
+    writer.flush()?;
+    Ok(())
+}
+
+fn percent_decode(value: &str) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match bytes[i] {
+            b'%' if i + 2 < bytes.len() => {
+                let hex = std::str::from_utf8(&bytes[(i + 1)..(i + 3)]).unwrap_or("");
+                match u8::from_str_radix(hex, 16) {
+                    Ok(x) => {
+                        decoded.push(x);
+                        i += 3;
+                        continue;
+                    }
+                    Err(_) => decoded.push(b'%'),
+                }
+            }
+            b'+' => decoded.push(b' '),
+            x => decoded.push(x),
+        }
+        i += 1;
+    }
+    String::from_utf8_lossy(&decoded).to_string()
+}
@@ -0,0 +1,49 @@
+use bgzip::tabix::{index_bgzf, TabixConfig};
+use clap::Parser;
+use std::fs::File;
+
+/// Create tabix index of coordinate-sorted BGZF file.
+///
+/// Columns are 1-based. Column options override the preset.
+#[derive(Debug, Clone, Parser, PartialEq)]
+struct Cli {
+    #[arg(help = "BGZF compressed input file")]
+    input_file: String,
+    #[arg(short, long, default_value = "gff", help = "gff, bed, sam or vcf")]
+    preset: TabixConfig,
+    #[arg(short, long, help = "column number for sequence names")]
+    sequence: Option<i32>,
+    #[arg(short, long, help = "column number for region begin")]
+    begin: Option<i32>,
+    #[arg(short, long, help = "column number for region end")]
+    end: Option<i32>,
+    #[arg(short = '0', long, help = "coordinates are zero-based")]
+    zero_based: bool,
+    #[arg(short, long, help = "output file [<input_file>.tbi]")]
+    output: Option<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let cli = Cli::parse();
+    let mut config = cli.preset;
+    if let Some(sequence) = cli.sequence {
+        config.column_for_sequence = sequence;
+    }
+    if let Some(begin) = cli.begin {
+        config.column_for_begin = begin;
+    }
+    if let Some(end) = cli.end {
+        config.column_for_end = end;
+    }
+    if cli.zero_based {
+        config.format |= bgzip::tabix::FORMAT_ZERO_BASED;
+    }
+
+    let index = index_bgzf(File::open(&cli.input_file)?, config)?;
+    let output = cli
+        .output
+        .unwrap_or_else(|| format!("{}.tbi", cli.input_file));
+    index.write(File::create(output)?)?;
+
+    Ok(())
+}
@@ -22,7 +22,7 @@ fn main() -> anyhow::Result<()> {
     };
     let mut csv_out = csv::WriterBuilder::new().flexible(true).from_writer(out);
 
-    csv_out.write_record(&[
+    csv_out.write_record([
         "# of sequences",
         "format",
         "coordinate rule",
@@ -34,7 +34,7 @@ fn main() -> anyhow::Result<()> {
         "Length of concatenated sequence names",
     ])?;
 
-    csv_out.write_record(&[
+    csv_out.write_record([
         format!("{}", file.number_of_references),
         match file.format & 0xffff {
             0 => "Generic".to_string(),
@@ -54,8 +54,8 @@ fn main() -> anyhow::Result<()> {
         format!("{}", file.length_of_concatenated_sequence_names),
     ])?;
 
-    csv_out.write_record(&[""])?;
-    csv_out.write_record(&[
+    csv_out.write_record([""])?;
+    csv_out.write_record([
         "sequence index",
         "sequence name",
         "bin index",
@@ -69,7 +69,7 @@ fn main() -> anyhow::Result<()> {
         bins.sort_by_key(|x| x.bin);
         for (j, bin) in bins.iter().enumerate() {
             for (k, x) in bin.chunks.iter().enumerate() {
-                csv_out.write_record(&[
+                csv_out.write_record([
                     format!("{}", i),
                     String::from_utf8_lossy(ref_name).to_string(),
                     format!("{}", j),
@@ -0,0 +1,160 @@
+use crate::tabix::{RegionIndex, TabixChunk, TabixConfig, TabixSequence};
+use crate::{BGZFError, BinaryReader, Limits, VirtualOffset};
+use std::convert::TryInto;
+use std::io::{self, Read};
+
+/// BAI index.
+///
+/// Bins and the linear index of each reference sequence have the same layout as tabix index,
+/// so they are loaded into [`TabixSequence`]. BAI index does not have sequence names, which are
+/// written in BAM header. `names` is empty after loading; set names with [`BaiIndex::with_names`]
+/// to find reference sequence IDs by name.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BaiIndex {
+    /// Sequence names without NUL character
+    pub names: Vec<Vec<u8>>,
+    pub sequences: Vec<TabixSequence>,
+    /// Number of unplaced unmapped reads, if it is recorded.
+    pub number_of_unplaced: Option<u64>,
+}
+
+impl BaiIndex {
+    /// Load BAI index. Unlike tabix and CSI index, BAI index is not BGZF compressed.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, BGZFError> {
+        BaiIndex::from_reader_with_limits(reader, Limits::UNLIMITED)
+    }
+
+    /// Load BAI index with resource limits.
+    pub fn from_reader_with_limits<R: Read>(reader: R, limits: Limits) -> Result<Self, BGZFError> {
+        let mut reader = io::BufReader::new(reader);
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != [b'B', b'A', b'I', 1] {
+            return Err(BGZFError::InvalidMagic("BAI"));
+        }
+
+        let number_of_references = reader.read_le_i32()?;
+        if u32::try_from(number_of_references).unwrap_or(0) > limits.max_references {
+            return Err(BGZFError::LimitExceeded("Too many reference sequences"));
+        }
+        let mut sequences = Vec::new();
+        for _ in 0..number_of_references {
+            sequences.push(TabixSequence::from_reader(&mut reader, &limits)?);
+        }
+
+        let number_of_unplaced = match reader.read_le_u64() {
+            Ok(value) => Some(value),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(BaiIndex {
+            names: Vec::new(),
+            sequences,
+            number_of_unplaced,
+        })
+    }
+
+    /// Set sequence names, typically loaded from BAM header.
+    pub fn with_names(mut self, names: Vec<Vec<u8>>) -> Self {
+        self.names = names;
+        self
+    }
+}
+
+impl RegionIndex for BaiIndex {
+    /// SAM configuration is always returned.
+    fn config(&self) -> TabixConfig {
+        TabixConfig::SAM
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|x| x == name)
+            .map(|x| x.try_into().unwrap())
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        self.names
+            .get(TryInto::<usize>::try_into(rid).unwrap())
+            .map(|x| &x[..])
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.sequences.len()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        match self.sequences.get(TryInto::<usize>::try_into(rid).unwrap()) {
+            Some(sequence) => sequence.region_chunks(begin, end),
+            None => Vec::new(),
+        }
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        self.sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?
+            .first_offset()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tabix::Tabix;
+    use std::fs::File;
+
+    #[test]
+    fn test_bai() -> anyhow::Result<()> {
+        let tabix =
+            Tabix::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?)?;
+
+        // BAI index with the same bins as the tabix index
+        let mut data = vec![b'B', b'A', b'I', 1];
+        data.extend_from_slice(&i32::try_from(tabix.sequences.len())?.to_le_bytes());
+        for one in &tabix.sequences {
+            one.write(&mut data)?;
+        }
+        let bai = BaiIndex::from_reader(&data[..])?;
+        assert_eq!(bai.number_of_unplaced, None);
+        assert_eq!(bai.sequence_count(), tabix.sequences.len());
+        assert_eq!(bai.name2rid(b"1"), None);
+
+        let bai = bai.with_names(
+            (0..tabix.sequences.len())
+                .map(|x| tabix.rid2name(x.try_into().unwrap()).unwrap().to_vec())
+                .collect(),
+        );
+        for rid in 0..tabix.sequences.len() {
+            let rid: u32 = rid.try_into()?;
+            assert_eq!(bai.name2rid(tabix.rid2name(rid).unwrap()), Some(rid));
+            assert_eq!(
+                RegionIndex::first_offset(&bai, rid),
+                RegionIndex::first_offset(&tabix, rid)
+            );
+            assert_eq!(
+                bai.region_chunks(rid, 1_000_000, 1_200_000),
+                tabix.region_chunks(rid, 1_000_000, 1_200_000)
+            );
+        }
+
+        data.extend_from_slice(&10u64.to_le_bytes());
+        assert_eq!(
+            BaiIndex::from_reader(&data[..])?.number_of_unplaced,
+            Some(10)
+        );
+
+        assert!(BaiIndex::from_reader_with_limits(
+            &data[..],
+            Limits {
+                max_references: 1,
+                ..Limits::HARDENED
+            }
+        )
+        .is_err());
+        assert!(BaiIndex::from_reader(&b"TBI\x01"[..]).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,581 @@
+use crate::bai::BaiIndex;
+use crate::csi::CsiIndex;
+use crate::tabix::{RegionIndex, TabixChunk};
+use crate::{BGZFError, BGZFReader, BinaryReader, VirtualOffset};
+use std::convert::TryInto;
+use std::io::{BufRead, Read, Seek};
+use std::path::Path;
+
+/// Flag of unmapped reads
+pub const FLAG_UNMAPPED: u16 = 0x4;
+
+const SEQUENCE_CODE: &[u8; 16] = b"=ACMGRSVTWYHKDBN";
+const CIGAR_CODE: &[u8; 9] = b"MIDNSHP=X";
+
+/// A reference sequence in BAM header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BamReference {
+    /// Sequence name without NUL character
+    pub name: Vec<u8>,
+    pub length: u32,
+}
+
+/// BAM header
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BamHeader {
+    /// SAM header text
+    pub text: Vec<u8>,
+    pub references: Vec<BamReference>,
+}
+
+impl BamHeader {
+    /// Load BAM header from decompressed BAM data.
+    pub fn from_reader<R: Read>(reader: &mut R) -> Result<Self, BGZFError> {
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != [b'B', b'A', b'M', 1] {
+            return Err(BGZFError::InvalidMagic("BAM"));
+        }
+        let text_length = read_length(reader)?;
+        let mut text = read_bytes(reader, text_length)?;
+        if let Some(i) = text.iter().position(|x| *x == 0) {
+            text.truncate(i);
+        }
+
+        let number_of_references = reader.read_le_i32()?;
+        let mut references = Vec::new();
+        for _ in 0..number_of_references {
+            let name_length = read_length(reader)?;
+            let mut name = read_bytes(reader, name_length)?;
+            if name.ends_with(&[0]) {
+                name.pop();
+            }
+            let length = reader
+                .read_le_i32()?
+                .try_into()
+                .map_err(|_| BGZFError::Other("Invalid length of reference sequence"))?;
+            references.push(BamReference { name, length });
+        }
+
+        Ok(BamHeader { text, references })
+    }
+
+    /// Sequence names without NUL character
+    pub fn names(&self) -> Vec<Vec<u8>> {
+        self.references.iter().map(|x| x.name.clone()).collect()
+    }
+
+    /// Find reference sequence ID from sequence name.
+    pub fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.references
+            .iter()
+            .position(|x| x.name == name)
+            .map(|x| x.try_into().unwrap())
+    }
+}
+
+/// A CIGAR operation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CigarOp {
+    pub length: u32,
+    /// Operation character, one of `MIDNSHP=X`
+    pub op: u8,
+}
+
+impl CigarOp {
+    /// Returns true if this operation consumes reference sequence.
+    pub fn consumes_reference(&self) -> bool {
+        matches!(self.op, b'M' | b'D' | b'N' | b'=' | b'X')
+    }
+}
+
+/// A BAM record
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BamRecord {
+    /// Reference sequence ID. `-1` if unplaced.
+    pub ref_id: i32,
+    /// Zero-based position. `-1` if unplaced.
+    pub pos: i32,
+    pub mapq: u8,
+    pub bin: u16,
+    pub flag: u16,
+    pub next_ref_id: i32,
+    pub next_pos: i32,
+    pub tlen: i32,
+    /// Read name without NUL character
+    pub read_name: Vec<u8>,
+    pub cigar: Vec<CigarOp>,
+    /// Decoded sequence such as `ACGT`
+    pub seq: Vec<u8>,
+    /// Phred quality scores without offset. All values are `0xff` if quality is not stored.
+    pub qual: Vec<u8>,
+    /// Raw auxiliary data
+    pub aux: Vec<u8>,
+}
+
+impl BamRecord {
+    /// Read a record from decompressed BAM data. Returns `None` at the end of data.
+    pub fn from_reader<R: BufRead>(reader: &mut R) -> Result<Option<Self>, BGZFError> {
+        if reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+        let block_size = read_length(reader)?;
+        let data = read_bytes(reader, block_size)?;
+        BamRecord::from_bytes(&data).map(Some)
+    }
+
+    /// Decode a record from `data`, which does not include `block_size` field.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, BGZFError> {
+        const INVALID: BGZFError = BGZFError::Other("Invalid BAM record");
+        let mut reader = data;
+        let ref_id = reader.read_le_i32()?;
+        let pos = reader.read_le_i32()?;
+        let mut fixed = [0u8; 4];
+        reader.read_exact(&mut fixed)?;
+        let read_name_length = usize::from(fixed[0]);
+        let mapq = fixed[1];
+        let bin = u16::from_le_bytes([fixed[2], fixed[3]]);
+        reader.read_exact(&mut fixed)?;
+        let cigar_length = usize::from(u16::from_le_bytes([fixed[0], fixed[1]]));
+        let flag = u16::from_le_bytes([fixed[2], fixed[3]]);
+        let seq_length = read_length(&mut reader)?;
+        let next_ref_id = reader.read_le_i32()?;
+        let next_pos = reader.read_le_i32()?;
+        let tlen = reader.read_le_i32()?;
+
+        let read_name = reader.get(..read_name_length).ok_or(INVALID)?;
+        let read_name = read_name.strip_suffix(&[0]).unwrap_or(read_name).to_vec();
+        reader = &reader[read_name_length..];
+
+        let mut cigar = Vec::with_capacity(cigar_length);
+        for _ in 0..cigar_length {
+            let value = reader.read_le_u32()?;
+            let op = *CIGAR_CODE
+                .get(TryInto::<usize>::try_into(value & 0xf).unwrap())
+                .ok_or(INVALID)?;
+            cigar.push(CigarOp {
+                length: value >> 4,
+                op,
+            });
+        }
+
+        let packed_length = seq_length.div_ceil(2);
+        let packed = reader.get(..packed_length).ok_or(INVALID)?;
+        let seq = (0..seq_length)
+            .map(|i| {
+                let code = if i % 2 == 0 {
+                    packed[i / 2] >> 4
+                } else {
+                    packed[i / 2] & 0xf
+                };
+                SEQUENCE_CODE[usize::from(code)]
+            })
+            .collect();
+        reader = &reader[packed_length..];
+
+        let qual = reader.get(..seq_length).ok_or(INVALID)?.to_vec();
+        let aux = reader[seq_length..].to_vec();
+
+        Ok(BamRecord {
+            ref_id,
+            pos,
+            mapq,
+            bin,
+            flag,
+            next_ref_id,
+            next_pos,
+            tlen,
+            read_name,
+            cigar,
+            seq,
+            qual,
+            aux,
+        })
+    }
+
+    /// Returns true if this record is unmapped.
+    pub fn is_unmapped(&self) -> bool {
+        self.flag & FLAG_UNMAPPED != 0
+    }
+
+    /// Zero-based, exclusive end position calculated from CIGAR.
+    ///
+    /// If the record does not consume reference sequence, such as unmapped reads,
+    /// the region is 1bp length as same as `htslib`.
+    pub fn end(&self) -> i64 {
+        let length: i64 = self
+            .cigar
+            .iter()
+            .filter(|x| x.consumes_reference())
+            .map(|x| i64::from(x.length))
+            .sum();
+        i64::from(self.pos) + length.max(1)
+    }
+}
+
+fn read_length<R: Read>(reader: &mut R) -> Result<usize, BGZFError> {
+    reader
+        .read_le_i32()?
+        .try_into()
+        .map_err(|_| BGZFError::Other("Invalid length in BAM"))
+}
+
+/// Read `length` bytes. Memory is not allocated in advance, because lengths in files are not trusted.
+fn read_bytes<R: Read>(reader: &mut R, length: usize) -> Result<Vec<u8>, BGZFError> {
+    let mut data = Vec::new();
+    reader
+        .by_ref()
+        .take(length.try_into().unwrap())
+        .read_to_end(&mut data)?;
+    if data.len() != length {
+        return Err(std::io::Error::from(std::io::ErrorKind::UnexpectedEof).into());
+    }
+    Ok(data)
+}
+
+/// BAM file reader with index.
+///
+/// Default index type is [`BaiIndex`]. [`CsiIndex`] can be used for references longer than 512M bases.
+pub struct BamFile<R: Read + Seek, I: RegionIndex = BaiIndex> {
+    reader: BGZFReader<R>,
+    header: BamHeader,
+    index: I,
+    first_record: VirtualOffset,
+}
+
+impl BamFile<std::fs::File> {
+    /// Open BAM file and its BAI index (`path` + `.bai`).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".bai");
+        let index = BaiIndex::from_reader(std::fs::File::open(index_path)?)?;
+        let mut file = BamFile::new(std::fs::File::open(path)?, index)?;
+        file.index.names = file.header.names();
+        Ok(file)
+    }
+}
+
+impl BamFile<std::fs::File, CsiIndex> {
+    /// Open BAM file and its CSI index (`path` + `.csi`).
+    pub fn from_path_csi<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".csi");
+        let index = CsiIndex::from_reader(std::fs::File::open(index_path)?)?;
+        let mut file = BamFile::new(std::fs::File::open(path)?, index)?;
+        file.index.names = file.header.names();
+        Ok(file)
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> BamFile<R, I> {
+    /// Create new [`BamFile`] from BGZF compressed BAM data and its index.
+    pub fn new(reader: R, index: I) -> Result<Self, BGZFError> {
+        let mut reader = BGZFReader::new(reader)?;
+        let header = BamHeader::from_reader(&mut reader)?;
+        reader.fill_buf()?;
+        let first_record = reader.bgzf_pos();
+        Ok(BamFile {
+            reader,
+            header,
+            index,
+            first_record,
+        })
+    }
+
+    pub fn header(&self) -> &BamHeader {
+        &self.header
+    }
+
+    pub fn index(&self) -> &I {
+        &self.index
+    }
+
+    /// Fetch records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    ///
+    /// Reference sequence IDs are same as the order of [`BamHeader::references`].
+    pub fn fetch(&mut self, rid: u32, begin: u64, end: u64) -> BamRecords<'_, R, I> {
+        let chunks = self.index.region_chunks(rid, begin, end);
+        debug!(
+            "fetch BAM rid {}: {}-{}: {} chunks",
+            rid,
+            begin,
+            end,
+            chunks.len()
+        );
+        BamRecords {
+            file: self,
+            rid,
+            begin,
+            end,
+            chunks,
+            current_chunk: 0,
+            current_pos: None,
+        }
+    }
+
+    /// Iterate all records including unplaced reads from the beginning of the file.
+    pub fn records(&mut self) -> Result<BamAllRecords<'_, R, I>, BGZFError> {
+        self.reader.bgzf_seek(self.first_record)?;
+        Ok(BamAllRecords { file: self })
+    }
+}
+
+/// Iterator of records in a region, created by [`BamFile::fetch`].
+pub struct BamRecords<'a, R: Read + Seek, I: RegionIndex = BaiIndex> {
+    file: &'a mut BamFile<R, I>,
+    rid: u32,
+    begin: u64,
+    end: u64,
+    chunks: Vec<TabixChunk>,
+    current_chunk: usize,
+    current_pos: Option<VirtualOffset>,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> BamRecords<'a, R, I> {
+    fn next_record(&mut self) -> Result<Option<BamRecord>, BGZFError> {
+        loop {
+            let chunk = match self.chunks.get(self.current_chunk) {
+                Some(chunk) => chunk,
+                None => return Ok(None),
+            };
+            match self.current_pos {
+                Some(pos) if pos >= chunk.end => {
+                    trace!("end of chunk {} at {:#x}", self.current_chunk, pos);
+                    self.current_chunk += 1;
+                    continue;
+                }
+                Some(pos) if pos >= chunk.begin => (),
+                _ => {
+                    trace!(
+                        "seek to chunk {}: {:#x}-{:#x}",
+                        self.current_chunk,
+                        chunk.begin,
+                        chunk.end
+                    );
+                    self.file.reader.bgzf_seek(chunk.begin)?;
+                    self.current_pos = Some(chunk.begin);
+                }
+            }
+
+            let record = match BamRecord::from_reader(&mut self.file.reader)? {
+                Some(record) => record,
+                None => {
+                    self.chunks.clear();
+                    return Ok(None);
+                }
+            };
+            self.file.reader.fill_buf()?;
+            self.current_pos = Some(self.file.reader.bgzf_pos());
+
+            if u32::try_from(record.ref_id).ok() != Some(self.rid)
+                || i64::from(record.pos) >= i64::try_from(self.end).unwrap_or(i64::MAX)
+            {
+                self.chunks.clear();
+                return Ok(None);
+            }
+            if record.end() <= i64::try_from(self.begin).unwrap_or(i64::MAX) {
+                continue;
+            }
+            return Ok(Some(record));
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for BamRecords<'a, R, I> {
+    type Item = Result<BamRecord, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Iterator of all records, created by [`BamFile::records`].
+pub struct BamAllRecords<'a, R: Read + Seek, I: RegionIndex = BaiIndex> {
+    file: &'a mut BamFile<R, I>,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for BamAllRecords<'a, R, I> {
+    type Item = Result<BamRecord, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        BamRecord::from_reader(&mut self.file.reader).transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tabix::{TabixBuilder, TabixConfig};
+    use crate::{BGZFWriter, Compression};
+    use rand::prelude::*;
+    use std::io::Write;
+
+    fn encode_record(record: &BamRecord) -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend_from_slice(&record.ref_id.to_le_bytes());
+        data.extend_from_slice(&record.pos.to_le_bytes());
+        data.push(u8::try_from(record.read_name.len() + 1).unwrap());
+        data.push(record.mapq);
+        data.extend_from_slice(&record.bin.to_le_bytes());
+        data.extend_from_slice(&u16::try_from(record.cigar.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&record.flag.to_le_bytes());
+        data.extend_from_slice(&u32::try_from(record.seq.len()).unwrap().to_le_bytes());
+        data.extend_from_slice(&record.next_ref_id.to_le_bytes());
+        data.extend_from_slice(&record.next_pos.to_le_bytes());
+        data.extend_from_slice(&record.tlen.to_le_bytes());
+        data.extend_from_slice(&record.read_name);
+        data.push(0);
+        for one in &record.cigar {
+            let op = CIGAR_CODE.iter().position(|x| *x == one.op).unwrap();
+            data.extend_from_slice(&(one.length << 4 | u32::try_from(op).unwrap()).to_le_bytes());
+        }
+        for pair in record.seq.chunks(2) {
+            let code = |x: u8| u8::try_from(SEQUENCE_CODE.iter().position(|y| *y == x).unwrap());
+            let low = pair.get(1).map(|x| code(*x).unwrap()).unwrap_or(0);
+            data.push(code(pair[0]).unwrap() << 4 | low);
+        }
+        data.extend_from_slice(&record.qual);
+        data.extend_from_slice(&record.aux);
+
+        let mut block = u32::try_from(data.len()).unwrap().to_le_bytes().to_vec();
+        block.extend_from_slice(&data);
+        block
+    }
+
+    #[test]
+    fn test_bam() -> anyhow::Result<()> {
+        let mut rand = rand_pcg::Pcg64Mcg::seed_from_u64(0x5873);
+        let names = [&b"chr1"[..], b"chr2"];
+        let mut records = Vec::new();
+        for (rid, _) in names.iter().enumerate() {
+            let mut positions: Vec<i32> = (0..3000).map(|_| rand.gen_range(0..2_000_000)).collect();
+            positions.sort_unstable();
+            for (i, pos) in positions.into_iter().enumerate() {
+                let seq: Vec<u8> = (0..rand.gen_range(1..150))
+                    .map(|_| b"ACGTN"[rand.gen_range(0..5)])
+                    .collect();
+                records.push(BamRecord {
+                    ref_id: rid.try_into()?,
+                    pos,
+                    mapq: 60,
+                    bin: 0,
+                    flag: 0,
+                    next_ref_id: -1,
+                    next_pos: -1,
+                    tlen: 0,
+                    read_name: format!("read{}-{}", rid, i).into_bytes(),
+                    cigar: vec![
+                        CigarOp {
+                            length: 5,
+                            op: b'S',
+                        },
+                        CigarOp {
+                            length: rand.gen_range(1..5000),
+                            op: b'M',
+                        },
+                        CigarOp {
+                            length: rand.gen_range(0..100),
+                            op: b'D',
+                        },
+                    ],
+                    qual: seq.iter().map(|_| rand.gen_range(0..40)).collect(),
+                    seq,
+                    aux: b"NMC\x01".to_vec(),
+                });
+            }
+        }
+        records.push(BamRecord {
+            ref_id: -1,
+            pos: -1,
+            flag: FLAG_UNMAPPED,
+            cigar: Vec::new(),
+            read_name: b"unmapped".to_vec(),
+            aux: Vec::new(),
+            ..records[0].clone()
+        });
+
+        // write BAM and build index
+        let mut writer = BGZFWriter::new(
+            std::fs::File::create("tmp/test-bam.bam")?,
+            Compression::default(),
+        );
+        let text = b"@HD\tVN:1.6\tSO:coordinate\n";
+        writer.write_all(b"BAM\x01")?;
+        writer.write_all(&u32::try_from(text.len())?.to_le_bytes())?;
+        writer.write_all(text)?;
+        writer.write_all(&u32::try_from(names.len())?.to_le_bytes())?;
+        for name in names {
+            writer.write_all(&u32::try_from(name.len() + 1)?.to_le_bytes())?;
+            writer.write_all(name)?;
+            writer.write_all(&[0])?;
+            writer.write_all(&2_000_000u32.to_le_bytes())?;
+        }
+        let mut builder = TabixBuilder::with_names(TabixConfig::SAM, &names);
+        builder.skip_to(writer.bgzf_pos());
+        for one in &records {
+            writer.write_all(&encode_record(one))?;
+            if one.ref_id >= 0 {
+                builder.push_rid(
+                    one.ref_id.try_into()?,
+                    one.pos.try_into()?,
+                    one.end().try_into()?,
+                    writer.bgzf_pos(),
+                )?;
+            }
+        }
+        let tabix = builder.finish(writer.bgzf_pos());
+        writer.close()?;
+
+        let mut bai = vec![b'B', b'A', b'I', 1];
+        bai.extend_from_slice(&i32::try_from(tabix.sequences.len())?.to_le_bytes());
+        for one in &tabix.sequences {
+            one.write(&mut bai)?;
+        }
+        let index = BaiIndex::from_reader(&bai[..])?;
+
+        let mut file = BamFile::new(std::fs::File::open("tmp/test-bam.bam")?, index)?;
+        assert_eq!(file.header().text, text);
+        assert_eq!(file.header().name2rid(b"chr2"), Some(1));
+        assert_eq!(file.header().references[0].length, 2_000_000);
+
+        let all = file.records()?.collect::<Result<Vec<_>, _>>()?;
+        assert!(all == records);
+
+        for _ in 0..30 {
+            let rid = rand.gen_range(0..2);
+            let begin = rand.gen_range(0..2_000_000);
+            let end = begin + rand.gen_range(1..100_000);
+            let expected: Vec<_> = records
+                .iter()
+                .filter(|x| x.ref_id == rid && i64::from(x.pos) < end && x.end() > begin)
+                .collect();
+            let fetched = file
+                .fetch(rid.try_into()?, begin.try_into()?, end.try_into()?)
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!fetched.is_empty());
+            assert_eq!(fetched.iter().collect::<Vec<_>>(), expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_truncated_bam() {
+        // lengths in headers are not allocated before data is read
+        let mut header = b"BAM\x01".to_vec();
+        header.extend_from_slice(&i32::MAX.to_le_bytes());
+        header.extend_from_slice(b"@HD");
+        assert!(BamHeader::from_reader(&mut &header[..]).is_err());
+
+        let mut header = b"BAM\x01".to_vec();
+        header.extend_from_slice(&0i32.to_le_bytes());
+        header.extend_from_slice(&1i32.to_le_bytes());
+        header.extend_from_slice(&i32::MAX.to_le_bytes());
+        header.extend_from_slice(b"chr1");
+        assert!(BamHeader::from_reader(&mut &header[..]).is_err());
+
+        let mut record = i32::MAX.to_le_bytes().to_vec();
+        record.extend_from_slice(&[0; 32]);
+        assert!(BamRecord::from_reader(&mut &record[..]).is_err());
+    }
+}
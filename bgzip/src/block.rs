@@ -0,0 +1,379 @@
+//! BGZF block codec which depends only on `core` and `alloc`.
+//!
+//! This module contains block framing, header parsing, CRC32 calculation and glue for raw
+//! deflate implementations. It does not use `std`, so that it can be copied into or reused by
+//! tools without full standard library. Deflate backends are plugged in with [`RawDeflate`] and
+//! [`RawInflate`] traits. [`crate::deflate::Compress`] and [`crate::deflate::Decompress`]
+//! implement these traits.
+
+use alloc::vec::Vec;
+use core::fmt;
+
+/// Size of BGZF block header with `BC` extra subfield.
+pub const HEADER_SIZE: usize = 18;
+/// Size of gzip footer (CRC32 and ISIZE).
+pub const FOOTER_SIZE: usize = 8;
+/// Maximum size of a BGZF block, and maximum size of uncompressed data in a block.
+pub const MAX_BLOCK_SIZE: usize = 65536;
+
+const GZIP_ID1: u8 = 31;
+const GZIP_ID2: u8 = 139;
+const DEFLATE: u8 = 8;
+const FLAG_FEXTRA: u8 = 4;
+const FLAG_FNAME: u8 = 8;
+const FLAG_FCOMMENT: u8 = 16;
+const FLAG_FHCRC: u8 = 2;
+
+/// An error of block encoding or decoding.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockError {
+    /// Data is shorter than a block
+    UnexpectedEnd,
+    /// Not gzip format
+    NotGzip,
+    /// Gzip header does not have `BC` extra subfield
+    NotBGZF,
+    /// Block or uncompressed data is larger than 64k bytes
+    TooLargeBlock,
+    /// Deflate backend failed to compress data
+    Deflate,
+    /// Deflate backend failed to decompress data
+    Inflate,
+    /// CRC32 of decompressed data does not match with the footer
+    CrcMismatch,
+    /// Size of decompressed data does not match with the footer
+    SizeMismatch,
+}
+
+impl BlockError {
+    /// Short description of the error.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            BlockError::UnexpectedEnd => "Unexpected end of BGZF block",
+            BlockError::NotGzip => "not gzip format",
+            BlockError::NotBGZF => "not BGZF format",
+            BlockError::TooLargeBlock => "Too large BGZF block",
+            BlockError::Deflate => "Failed to compress BGZF block",
+            BlockError::Inflate => "Failed to decompress BGZF block",
+            BlockError::CrcMismatch => "unmatched CRC32 of decompressed data",
+            BlockError::SizeMismatch => "unmatched size of decompressed data",
+        }
+    }
+}
+
+impl fmt::Display for BlockError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Raw deflate compressor used by [`encode_block`].
+pub trait RawDeflate {
+    /// Compress `input` into `output` as raw deflate stream, and return compressed size.
+    fn deflate(&mut self, input: &[u8], output: &mut [u8]) -> Result<usize, BlockError>;
+}
+
+/// Raw deflate decompressor used by [`decode_block`].
+pub trait RawInflate {
+    /// Decompress raw deflate stream `input` into `output`, and return decompressed size.
+    fn inflate(&mut self, input: &[u8], output: &mut [u8]) -> Result<usize, BlockError>;
+}
+
+/// Sizes parsed from a BGZF block header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockHeader {
+    /// Total size of the block including header and footer
+    pub block_size: usize,
+    /// Size of the header
+    pub header_size: usize,
+}
+
+/// Footer of a BGZF block.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockFooter {
+    /// CRC32 of uncompressed data
+    pub crc32: u32,
+    /// Size of uncompressed data
+    pub isize: u32,
+}
+
+/// Parse a BGZF block header at the beginning of `data`.
+///
+/// Optional gzip fields (FNAME, FCOMMENT and FHCRC) are skipped.
+pub fn parse_header(data: &[u8]) -> Result<BlockHeader, BlockError> {
+    if data.len() < 12 {
+        return Err(BlockError::UnexpectedEnd);
+    }
+    if data[0] != GZIP_ID1 || data[1] != GZIP_ID2 || data[2] != DEFLATE {
+        return Err(BlockError::NotGzip);
+    }
+    let flags = data[3];
+    if flags & FLAG_FEXTRA == 0 {
+        return Err(BlockError::NotBGZF);
+    }
+    let extra_len = usize::from(read_u16(&data[10..]));
+    let extra = data
+        .get(12..(12 + extra_len))
+        .ok_or(BlockError::UnexpectedEnd)?;
+
+    let mut block_size = None;
+    let mut rest = extra;
+    while rest.len() >= 4 {
+        let field_len = usize::from(read_u16(&rest[2..]));
+        let field = rest
+            .get(4..(4 + field_len))
+            .ok_or(BlockError::UnexpectedEnd)?;
+        if rest[0] == b'B' && rest[1] == b'C' && field_len == 2 {
+            block_size = Some(usize::from(read_u16(field)) + 1);
+        }
+        rest = &rest[(4 + field_len)..];
+    }
+    let block_size = block_size.ok_or(BlockError::NotBGZF)?;
+
+    let mut header_size = 12 + extra_len;
+    for flag in [FLAG_FNAME, FLAG_FCOMMENT] {
+        if flags & flag != 0 {
+            let len = data
+                .get(header_size..)
+                .and_then(|x| x.iter().position(|x| *x == 0))
+                .ok_or(BlockError::UnexpectedEnd)?;
+            header_size += len + 1;
+        }
+    }
+    if flags & FLAG_FHCRC != 0 {
+        header_size += 2;
+    }
+    if header_size + FOOTER_SIZE > block_size {
+        return Err(BlockError::NotBGZF);
+    }
+
+    Ok(BlockHeader {
+        block_size,
+        header_size,
+    })
+}
+
+/// Create a BGZF block header for a block of `block_size` bytes.
+pub fn write_header(block_size: usize) -> Result<[u8; HEADER_SIZE], BlockError> {
+    if !(HEADER_SIZE + FOOTER_SIZE..=MAX_BLOCK_SIZE).contains(&block_size) {
+        return Err(BlockError::TooLargeBlock);
+    }
+    let bsize = u16::try_from(block_size - 1).map_err(|_| BlockError::TooLargeBlock)?;
+    let bsize = bsize.to_le_bytes();
+    Ok([
+        GZIP_ID1,
+        GZIP_ID2,
+        DEFLATE,
+        FLAG_FEXTRA,
+        0,
+        0,
+        0,
+        0,
+        0,
+        0xff,
+        6,
+        0,
+        b'B',
+        b'C',
+        2,
+        0,
+        bsize[0],
+        bsize[1],
+    ])
+}
+
+/// Parse the footer at the end of a BGZF block.
+pub fn parse_footer(block: &[u8]) -> Result<BlockFooter, BlockError> {
+    if block.len() < FOOTER_SIZE {
+        return Err(BlockError::UnexpectedEnd);
+    }
+    let footer = &block[(block.len() - FOOTER_SIZE)..];
+    Ok(BlockFooter {
+        crc32: read_u32(footer),
+        isize: read_u32(&footer[4..]),
+    })
+}
+
+/// Update CRC32 (ISO-HDLC, same as gzip) of `data`. Pass `0` as `crc` to start a new checksum.
+pub fn crc32(crc: u32, data: &[u8]) -> u32 {
+    let mut crc = !crc;
+    for &byte in data {
+        crc = CRC32_TABLE[usize::from((crc as u8) ^ byte)] ^ (crc >> 8);
+    }
+    !crc
+}
+
+const CRC32_TABLE: [u32; 256] = crc32_table();
+
+const fn crc32_table() -> [u32; 256] {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut value = i as u32;
+        let mut j = 0;
+        while j < 8 {
+            value = if value & 1 != 0 {
+                0xedb88320 ^ (value >> 1)
+            } else {
+                value >> 1
+            };
+            j += 1;
+        }
+        table[i] = value;
+        i += 1;
+    }
+    table
+}
+
+/// Compress `data` into one BGZF block and append it to `output`. Returns the size of the block.
+///
+/// `data` must not be larger than [`MAX_BLOCK_SIZE`]. If compressed data does not fit into a block,
+/// [`BlockError::TooLargeBlock`] is returned.
+pub fn encode_block<D: RawDeflate>(
+    data: &[u8],
+    deflate: &mut D,
+    output: &mut Vec<u8>,
+) -> Result<usize, BlockError> {
+    if data.len() > MAX_BLOCK_SIZE {
+        return Err(BlockError::TooLargeBlock);
+    }
+    let original_len = output.len();
+    output.resize(original_len + MAX_BLOCK_SIZE, 0);
+    let compressed_len = match deflate.deflate(
+        data,
+        &mut output[(original_len + HEADER_SIZE)..(original_len + MAX_BLOCK_SIZE - FOOTER_SIZE)],
+    ) {
+        Ok(len) => len,
+        Err(e) => {
+            output.truncate(original_len);
+            return Err(e);
+        }
+    };
+    let block_size = HEADER_SIZE + compressed_len + FOOTER_SIZE;
+    output.truncate(original_len + HEADER_SIZE + compressed_len);
+    output[original_len..(original_len + HEADER_SIZE)].copy_from_slice(&write_header(block_size)?);
+    output.extend_from_slice(&crc32(0, data).to_le_bytes());
+    output.extend_from_slice(&(data.len() as u32).to_le_bytes());
+    Ok(block_size)
+}
+
+/// Decompress one BGZF block at the beginning of `block` and append the data to `output`.
+///
+/// CRC32 and size of decompressed data are verified. Returns the size of the block, which is the
+/// offset of the next block in `block`.
+pub fn decode_block<I: RawInflate>(
+    block: &[u8],
+    inflate: &mut I,
+    output: &mut Vec<u8>,
+) -> Result<usize, BlockError> {
+    let header = parse_header(block)?;
+    let block = block
+        .get(..header.block_size)
+        .ok_or(BlockError::UnexpectedEnd)?;
+    let footer = parse_footer(block)?;
+    let data_size = usize::try_from(footer.isize).map_err(|_| BlockError::TooLargeBlock)?;
+    if data_size > MAX_BLOCK_SIZE {
+        return Err(BlockError::TooLargeBlock);
+    }
+
+    let original_len = output.len();
+    output.resize(original_len + data_size, 0);
+    let result = inflate.inflate(
+        &block[header.header_size..(block.len() - FOOTER_SIZE)],
+        &mut output[original_len..],
+    );
+    let checked = match result {
+        Ok(len) if len != data_size => Err(BlockError::SizeMismatch),
+        Ok(_) if crc32(0, &output[original_len..]) != footer.crc32 => Err(BlockError::CrcMismatch),
+        Ok(_) => Ok(header.block_size),
+        Err(e) => Err(e),
+    };
+    if checked.is_err() {
+        output.truncate(original_len);
+    }
+    checked
+}
+
+fn read_u16(data: &[u8]) -> u16 {
+    u16::from_le_bytes([data[0], data[1]])
+}
+
+fn read_u32(data: &[u8]) -> u32 {
+    u32::from_le_bytes([data[0], data[1], data[2], data[3]])
+}
+
+#[cfg(all(test, feature = "std"))]
+mod test {
+    use super::*;
+    use crate::deflate::{Compress, Compression, Crc, Decompress};
+    use rand::prelude::*;
+    use std::io::Read;
+
+    #[test]
+    fn test_crc32() {
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(497);
+        let mut data = vec![0; 10000];
+        rng.fill_bytes(&mut data);
+        let mut expected = Crc::new();
+        expected.update(&data);
+        assert_eq!(crc32(0, &data), expected.sum());
+        assert_eq!(
+            crc32(crc32(0, &data[..3000]), &data[3000..]),
+            expected.sum()
+        );
+        assert_eq!(crc32(0, b""), 0);
+    }
+
+    #[test]
+    fn test_encode_decode_block() -> anyhow::Result<()> {
+        let data = b"chr1\t100\t200\tfoo\nchr1\t150\t300\tbar\n".repeat(100);
+        let mut compress = Compress::new(Compression::default());
+        let mut encoded = Vec::new();
+        let block_size = encode_block(&data, &mut compress, &mut encoded)?;
+        encoded.extend_from_slice(&crate::EOF_MARKER);
+        assert_eq!(block_size + crate::EOF_MARKER.len(), encoded.len());
+
+        let mut gz_decoded = Vec::new();
+        flate2::read::MultiGzDecoder::new(&encoded[..]).read_to_end(&mut gz_decoded)?;
+        assert_eq!(gz_decoded, data);
+
+        let mut decompress = Decompress::new();
+        let mut decoded = Vec::new();
+        assert_eq!(
+            decode_block(&encoded, &mut decompress, &mut decoded)?,
+            block_size
+        );
+        assert_eq!(
+            decode_block(&encoded[block_size..], &mut decompress, &mut decoded)?,
+            crate::EOF_MARKER.len()
+        );
+        assert_eq!(decoded, data);
+
+        let mut corrupted = encoded.clone();
+        corrupted[block_size - 8] ^= 1;
+        assert_eq!(
+            decode_block(&corrupted, &mut decompress, &mut Vec::new()),
+            Err(BlockError::CrcMismatch)
+        );
+        assert_eq!(
+            decode_block(&encoded[..10], &mut decompress, &mut Vec::new()),
+            Err(BlockError::UnexpectedEnd)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode_file() -> anyhow::Result<()> {
+        let compressed = std::fs::read("testfiles/generated.bed.gz")?;
+        let mut decompress = Decompress::new();
+        let mut decoded = Vec::new();
+        let mut position = 0;
+        while position < compressed.len() {
+            position += decode_block(&compressed[position..], &mut decompress, &mut decoded)?;
+        }
+        let mut expected = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut expected)?;
+        assert_eq!(decoded, expected);
+        Ok(())
+    }
+}
@@ -0,0 +1,197 @@
+//! Access to BGZF files in cloud storages, such as Amazon S3, Google Cloud Storage and Azure Blob Storage,
+//! through [object_store](https://crates.io/crates/object_store) crate.
+//!
+//! This module is available with `object_store` feature. Enable features of object_store crate
+//! to use each storage service.
+
+use crate::range::RangeWindow;
+use crate::tabix::{Tabix, TabixFile};
+use crate::{BGZFError, Limits};
+use object_store::path::Path;
+use object_store::ObjectStore;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::sync::Arc;
+
+enum Runtime {
+    Owned(tokio::runtime::Runtime),
+    Handle(tokio::runtime::Handle),
+}
+
+impl Runtime {
+    fn block_on<F: std::future::Future>(&self, future: F) -> F::Output {
+        match self {
+            Runtime::Owned(runtime) => runtime.block_on(future),
+            Runtime::Handle(handle) => handle.block_on(future),
+        }
+    }
+}
+
+/// A [`Read`] + [`Seek`] adapter of an object in [`ObjectStore`].
+///
+/// Data is fetched by `fetch_size` bytes with [`ObjectStore::get_range`] when the current position is
+/// not in the fetched range. Requests are run in a dedicated single thread runtime of tokio by default.
+pub struct ObjectStoreReader {
+    store: Arc<dyn ObjectStore>,
+    path: Path,
+    runtime: Runtime,
+    length: u64,
+    window: RangeWindow,
+}
+
+impl ObjectStoreReader {
+    /// Create new reader of an object at `path` in `store`. The size of the object is requested at once.
+    pub fn new(store: Arc<dyn ObjectStore>, path: Path) -> Result<Self, BGZFError> {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()?;
+        Self::with_runtime(store, path, Runtime::Owned(runtime))
+    }
+
+    /// Create new reader which runs requests with `handle`.
+    ///
+    /// The runtime must be a multi-thread runtime, and this reader must not be used in asynchronous context.
+    pub fn with_handle(
+        store: Arc<dyn ObjectStore>,
+        path: Path,
+        handle: tokio::runtime::Handle,
+    ) -> Result<Self, BGZFError> {
+        Self::with_runtime(store, path, Runtime::Handle(handle))
+    }
+
+    fn with_runtime(
+        store: Arc<dyn ObjectStore>,
+        path: Path,
+        runtime: Runtime,
+    ) -> Result<Self, BGZFError> {
+        let meta = runtime
+            .block_on(store.head(&path))
+            .map_err(io::Error::from)?;
+        Ok(ObjectStoreReader {
+            store,
+            path,
+            runtime,
+            length: u64::try_from(meta.size).unwrap(),
+            window: RangeWindow::new(),
+        })
+    }
+
+    /// Set the number of bytes fetched by a request. Default value is 256 KiB.
+    pub fn with_fetch_size(mut self, fetch_size: usize) -> Self {
+        self.window.set_fetch_size(fetch_size);
+        self
+    }
+
+    /// Path of the object
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Size of the object
+    pub fn len(&self) -> u64 {
+        self.length
+    }
+
+    /// `true` if the object is empty.
+    pub fn is_empty(&self) -> bool {
+        self.length == 0
+    }
+}
+
+impl Read for ObjectStoreReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        self.window
+            .read(buf, Some(self.length), |offset, size, buffer| {
+                let begin = usize::try_from(offset).unwrap();
+                let end = usize::try_from(self.length).unwrap().min(begin + size);
+                trace!("fetch {}: {}-{}", self.path, begin, end);
+                let data = self
+                    .runtime
+                    .block_on(self.store.get_range(&self.path, begin..end))?;
+                buffer.extend_from_slice(&data);
+                Ok(())
+            })
+    }
+}
+
+impl Seek for ObjectStoreReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.window.seek(pos, || Ok(self.length))
+    }
+}
+
+impl TabixFile<ObjectStoreReader, Tabix> {
+    /// Open BGZF compressed object at `path` in `store` and its tabix index (`path` + `.tbi`).
+    pub fn from_object_store(store: Arc<dyn ObjectStore>, path: &Path) -> Result<Self, BGZFError> {
+        let index_path = Path::from(format!("{}.tbi", path));
+        TabixFile::from_object_store_with_limits(store, path, &index_path, Limits::UNLIMITED)
+    }
+
+    /// Open BGZF compressed object and tabix index in `store` with resource limits.
+    pub fn from_object_store_with_limits(
+        store: Arc<dyn ObjectStore>,
+        path: &Path,
+        index_path: &Path,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        let index = Tabix::from_reader_with_limits(
+            ObjectStoreReader::new(store.clone(), index_path.clone())?,
+            limits,
+        )?;
+        TabixFile::with_limits(ObjectStoreReader::new(store, path.clone())?, index, limits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use object_store::memory::InMemory;
+
+    #[test]
+    fn test_object_store_reader() -> anyhow::Result<()> {
+        let expected = std::fs::read("testfiles/generated.bed.gz")?;
+        let store = Arc::new(InMemory::new());
+        let runtime = tokio::runtime::Builder::new_current_thread().build()?;
+        runtime.block_on(async {
+            store
+                .put(
+                    &Path::from("data/generated.bed.gz"),
+                    expected.clone().into(),
+                )
+                .await?;
+            store
+                .put(
+                    &Path::from("data/generated.bed.gz.tbi"),
+                    std::fs::read("testfiles/generated.bed.gz.tbi")?.into(),
+                )
+                .await?;
+            Ok::<_, anyhow::Error>(())
+        })?;
+
+        let mut reader =
+            ObjectStoreReader::new(store.clone(), Path::from("data/generated.bed.gz"))?
+                .with_fetch_size(1000);
+        assert_eq!(reader.len(), u64::try_from(expected.len())?);
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual)?;
+        assert!(actual == expected);
+        let mut buf = [0u8; 100];
+        reader.seek(SeekFrom::End(-100))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf[..], &expected[(expected.len() - 100)..]);
+
+        assert!(ObjectStoreReader::new(store.clone(), Path::from("data/missing.bed.gz")).is_err());
+
+        let mut remote = TabixFile::from_object_store(store, &Path::from("data/generated.bed.gz"))?;
+        let mut local = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let remote_records = remote
+            .fetch_region("chr1:12345678-13000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        let local_records = local
+            .fetch_region("chr1:12345678-13000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!remote_records.is_empty());
+        assert_eq!(remote_records, local_records);
+
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+use crate::deflate::{Compress, Compression, Decompress};
+use crate::read::{decompress_block as decompress_loaded_block, load_block};
+use crate::write::{write_block, DEFAULT_COMPRESS_UNIT_SIZE};
+use crate::BGZFError;
+
+/// Compress `data` into one BGZF block with header and footer.
+///
+/// `data` must not be larger than [`DEFAULT_COMPRESS_UNIT_SIZE`], so that compressed data fits into a block
+/// even if it is not compressible. Use [`crate::write::write_block`] to reuse buffers and a compressor.
+pub fn compress_block(data: &[u8], level: Compression) -> Result<Vec<u8>, BGZFError> {
+    if data.len() > DEFAULT_COMPRESS_UNIT_SIZE {
+        return Err(BGZFError::TooLargeCompressUnit);
+    }
+    let mut block = Vec::new();
+    write_block(&mut block, data, &mut Compress::new(level))?;
+    Ok(block)
+}
+
+/// Decompress one BGZF block at the beginning of `block`.
+///
+/// CRC32 and size of decompressed data are verified. Use [`crate::block::decode_block`] to know
+/// the size of the block, or [`crate::read::decompress_block`] to reuse buffers and a decompressor.
+pub fn decompress_block(block: &[u8]) -> Result<Vec<u8>, BGZFError> {
+    let mut compressed = Vec::new();
+    load_block(block, &mut compressed)?;
+    let mut data = Vec::new();
+    decompress_loaded_block(&mut data, &compressed, &mut Decompress::new())?;
+    Ok(data)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_compress_block() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        std::io::Read::read_to_end(&mut crate::open("testfiles/generated.bed.gz")?, &mut data)?;
+        let data = &data[..DEFAULT_COMPRESS_UNIT_SIZE];
+        let block = compress_block(data, Compression::default())?;
+        assert_eq!(
+            usize::from(u16::from_le_bytes([block[16], block[17]])) + 1,
+            block.len()
+        );
+        assert_eq!(decompress_block(&block)?, data);
+
+        let mut reader = crate::BGZFReader::new(&block[..])?;
+        let mut read_data = Vec::new();
+        std::io::Read::read_to_end(&mut reader, &mut read_data)?;
+        assert_eq!(read_data, data);
+
+        assert!(decompress_block(&crate::EOF_MARKER)?.is_empty());
+        assert_eq!(
+            decompress_block(&compress_block(b"", Compression::best())?)?,
+            b""
+        );
+        assert!(matches!(
+            compress_block(&[0; DEFAULT_COMPRESS_UNIT_SIZE + 1], Compression::default()),
+            Err(BGZFError::TooLargeCompressUnit)
+        ));
+        assert!(matches!(
+            decompress_block(&block[..(block.len() - 1)]),
+            Err(BGZFError::TruncatedBlock)
+        ));
+
+        Ok(())
+    }
+}
@@ -1,6 +1,301 @@
+use crate::tabix::{merge_chunks, BinningScheme, RegionIndex, TabixChunk, TabixConfig, TabixFile};
+use crate::{BGZFError, BGZFWriter, BinaryReader, Compression, Limits, VirtualOffset};
+use std::collections::HashMap;
 use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::path::Path;
 
-/// calculate bin given an alignment covering [beg,end) (zero-based, half-close-half-open)
+/// A bin of CSI index
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsiBin {
+    pub bin: u32,
+    /// Virtual file offset of the first record overlapping with the bin
+    pub loffset: u64,
+    pub chunks: Vec<TabixChunk>,
+}
+
+/// Bins of a reference sequence in CSI index
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct CsiSequence {
+    pub bins: HashMap<u32, CsiBin>,
+}
+
+/// CSI index.
+///
+/// Unlike tabix index, `min_shift` and `depth` of the binning scheme are variable, so
+/// sequences longer than 512M bases can be indexed. Column configuration and sequence names
+/// are loaded from the auxiliary data written by `tabix --csi`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsiIndex {
+    pub min_shift: u32,
+    pub depth: u32,
+    /// Auxiliary data
+    pub aux: Vec<u8>,
+    /// Column configuration in the auxiliary data. `None` if the auxiliary data is not tabix format.
+    pub config: Option<TabixConfig>,
+    /// Sequence names without NUL character
+    pub names: Vec<Vec<u8>>,
+    pub sequences: Vec<CsiSequence>,
+    /// Number of records without coordinates, if it is recorded.
+    pub number_of_unplaced: Option<u64>,
+}
+
+impl CsiIndex {
+    /// Load BGZF compressed CSI index.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, BGZFError> {
+        CsiIndex::from_reader_with_limits(reader, Limits::UNLIMITED)
+    }
+
+    /// Load BGZF compressed CSI index with resource limits.
+    ///
+    /// [`Limits::max_names_length`] is applied to the size of the auxiliary data.
+    pub fn from_reader_with_limits<R: Read>(reader: R, limits: Limits) -> Result<Self, BGZFError> {
+        let mut reader = io::BufReader::new(crate::read::BGZFReader::with_limits(reader, limits)?);
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != [b'C', b'S', b'I', 1] {
+            return Err(BGZFError::InvalidMagic("CSI"));
+        }
+        let min_shift: u32 = reader
+            .read_le_i32()?
+            .try_into()
+            .map_err(|_| BGZFError::BadIndex("Invalid min_shift"))?;
+        let depth: u32 = reader
+            .read_le_i32()?
+            .try_into()
+            .map_err(|_| BGZFError::BadIndex("Invalid depth"))?;
+        BinningScheme::new(min_shift, depth)?;
+        let aux_length: u32 = reader
+            .read_le_i32()?
+            .try_into()
+            .map_err(|_| BGZFError::BadIndex("Invalid length of auxiliary data"))?;
+        if aux_length > limits.max_names_length {
+            return Err(BGZFError::LimitExceeded("Too long auxiliary data"));
+        }
+        let mut aux = vec![0; aux_length.try_into().unwrap()];
+        reader.read_exact(&mut aux)?;
+        let (config, names) = parse_aux(&aux)?;
+
+        let number_of_references = reader.read_le_i32()?;
+        if u32::try_from(number_of_references).unwrap_or(0) > limits.max_references {
+            return Err(BGZFError::LimitExceeded("Too many reference sequences"));
+        }
+        let mut sequences = Vec::new();
+        for _ in 0..number_of_references {
+            let number_of_bins = reader.read_le_i32()?;
+            let mut bins = HashMap::new();
+            for _ in 0..number_of_bins {
+                let bin = reader.read_le_u32()?;
+                let loffset = reader.read_le_u64()?;
+                let number_of_chunks = reader.read_le_i32()?;
+                if u32::try_from(number_of_chunks).unwrap_or(0) > limits.max_chunks_per_bin {
+                    return Err(BGZFError::LimitExceeded("Too many chunks in a bin"));
+                }
+                let mut chunks = Vec::new();
+                for _ in 0..number_of_chunks {
+                    chunks.push(TabixChunk::from_reader(&mut reader)?);
+                }
+                bins.insert(
+                    bin,
+                    CsiBin {
+                        bin,
+                        loffset,
+                        chunks,
+                    },
+                );
+            }
+            sequences.push(CsiSequence { bins });
+        }
+
+        let number_of_unplaced = match reader.read_le_u64() {
+            Ok(value) => Some(value),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(CsiIndex {
+            min_shift,
+            depth,
+            aux,
+            config,
+            names,
+            sequences,
+            number_of_unplaced,
+        })
+    }
+
+    /// Write BGZF compressed CSI index. Bins are written in ascending order of bin numbers.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), BGZFError> {
+        let mut writer = BGZFWriter::with_compress_unit_size(
+            writer,
+            Compression::default(),
+            crate::write::DEFAULT_COMPRESS_UNIT_SIZE,
+            false,
+        )?;
+        writer.write_all(&[b'C', b'S', b'I', 1])?;
+        writer.write_all(&count_to_i32(self.min_shift)?.to_le_bytes())?;
+        writer.write_all(&count_to_i32(self.depth)?.to_le_bytes())?;
+        writer.write_all(&count_to_i32(self.aux.len())?.to_le_bytes())?;
+        writer.write_all(&self.aux)?;
+        writer.write_all(&count_to_i32(self.sequences.len())?.to_le_bytes())?;
+        for one in &self.sequences {
+            writer.write_all(&count_to_i32(one.bins.len())?.to_le_bytes())?;
+            let mut bins: Vec<_> = one.bins.values().collect();
+            bins.sort_by_key(|x| x.bin);
+            for one_bin in bins {
+                writer.write_all(&one_bin.bin.to_le_bytes())?;
+                writer.write_all(&one_bin.loffset.to_le_bytes())?;
+                writer.write_all(&count_to_i32(one_bin.chunks.len())?.to_le_bytes())?;
+                for chunk in &one_bin.chunks {
+                    chunk.write(&mut writer)?;
+                }
+            }
+        }
+        if let Some(number_of_unplaced) = self.number_of_unplaced {
+            writer.write_all(&number_of_unplaced.to_le_bytes())?;
+        }
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Binning scheme given by `min_shift` and `depth`
+    pub fn binning_scheme(&self) -> BinningScheme {
+        BinningScheme::new(self.min_shift, self.depth).expect("Invalid binning scheme")
+    }
+
+    /// Bin number of the pseudo-bin which holds per-sequence metadata
+    pub fn meta_bin(&self) -> u32 {
+        self.binning_scheme().meta_bin()
+    }
+
+    /// Maximum position which can be indexed with the binning scheme
+    pub fn max_position(&self) -> u64 {
+        self.binning_scheme().max_position()
+    }
+}
+
+fn count_to_i32<T: TryInto<i32>>(count: T) -> io::Result<i32> {
+    count
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Too many items in CSI index"))
+}
+
+/// Parse column configuration and sequence names written by `tabix --csi`.
+fn parse_aux(aux: &[u8]) -> Result<(Option<TabixConfig>, Vec<Vec<u8>>), BGZFError> {
+    if aux.len() < 28 {
+        return Ok((None, Vec::new()));
+    }
+    let mut reader = aux;
+    let format = reader.read_le_i32()?;
+    let column_for_sequence = reader.read_le_i32()?;
+    let column_for_begin = reader.read_le_i32()?;
+    let column_for_end = reader.read_le_i32()?;
+    let meta = reader.read_le_i32()?;
+    let skip = reader.read_le_i32()?;
+    let names_length: usize = reader
+        .read_le_i32()?
+        .try_into()
+        .map_err(|_| BGZFError::BadIndex("Invalid length of sequence names"))?;
+    let names = reader
+        .get(..names_length)
+        .ok_or(BGZFError::BadIndex("Invalid length of sequence names"))?
+        .split(|x| *x == 0)
+        .filter(|x| !x.is_empty())
+        .map(|x| x.to_vec())
+        .collect();
+    let config = TabixConfig {
+        format,
+        column_for_sequence,
+        column_for_begin,
+        column_for_end,
+        meta: meta.try_into().unwrap_or(b'#'),
+        skip,
+    };
+    Ok((Some(config), names))
+}
+
+impl RegionIndex for CsiIndex {
+    /// Column configuration in the auxiliary data. VCF configuration is returned if the
+    /// auxiliary data is not available.
+    fn config(&self) -> TabixConfig {
+        self.config.unwrap_or(TabixConfig::VCF)
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.names
+            .iter()
+            .position(|x| x == name)
+            .map(|x| x.try_into().unwrap())
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        self.names
+            .get(TryInto::<usize>::try_into(rid).unwrap())
+            .map(|x| &x[..])
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.sequences.len()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        let sequence = match self.sequences.get(TryInto::<usize>::try_into(rid).unwrap()) {
+            Some(sequence) => sequence,
+            None => return Vec::new(),
+        };
+        let chunks: Vec<TabixChunk> = self
+            .binning_scheme()
+            .reg2bins(begin, end)
+            .into_iter()
+            .filter_map(|x| sequence.bins.get(&x))
+            .flat_map(|x| x.chunks.iter().cloned())
+            .collect();
+        merge_chunks(chunks)
+    }
+
+    fn binning_scheme(&self) -> BinningScheme {
+        CsiIndex::binning_scheme(self)
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        let sequence = self
+            .sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?;
+        sequence
+            .bins
+            .get(&self.meta_bin())
+            .and_then(|x| x.chunks.first())
+            .map(|x| x.begin)
+            .or_else(|| {
+                sequence
+                    .bins
+                    .values()
+                    .map(|x| x.loffset)
+                    .min()
+                    .map(VirtualOffset::from)
+            })
+    }
+}
+
+impl TabixFile<std::fs::File, CsiIndex> {
+    /// Open BGZF compressed file and its CSI index (`path` + `.csi`).
+    pub fn from_path_csi<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".csi");
+        let index = CsiIndex::from_reader(std::fs::File::open(index_path)?)?;
+        if index.config.is_none() {
+            return Err(BGZFError::BadIndex(
+                "CSI index does not have column configuration",
+            ));
+        }
+        TabixFile::new(std::fs::File::open(path)?, index)
+    }
+}
+
+/// Calculate the bin of a record covering [beg, end) (zero-based, half-open) with
+/// binning scheme of `min_shift` and `depth`.
+///
+/// `end` must be larger than `beg`. See [`crate::tabix::BinningScheme`] for a checked version.
 pub fn reg2bin(beg: i64, end: i64, min_shift: u32, depth: u32) -> u32 {
     let end = end - 1;
     let mut s = min_shift;
@@ -22,7 +317,10 @@ pub fn reg2bin(beg: i64, end: i64, min_shift: u32, depth: u32) -> u32 {
     0
 }
 
-/// calculate the list of bins that may overlap with region [beg,end) (zero-based)
+/// Calculate the list of bins that may overlap with region [beg, end) (zero-based, half-open) with
+/// binning scheme of `min_shift` and `depth`.
+///
+/// Bins are listed from the root bin to the smallest bins. `end` must be larger than `beg`.
 pub fn reg2bins(beg: i64, end: i64, min_shift: u32, depth: u32) -> Vec<u32> {
     let mut bins: Vec<u32> = Vec::new();
     let end = end - 1;
@@ -41,3 +339,60 @@ pub fn reg2bins(beg: i64, end: i64, min_shift: u32, depth: u32) -> Vec<u32> {
 
     bins
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tabix::Tabix;
+    use std::fs::File;
+
+    #[test]
+    fn test_csi() -> anyhow::Result<()> {
+        let csi =
+            CsiIndex::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.csi")?)?;
+        let tabix =
+            Tabix::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?)?;
+        assert_eq!((csi.min_shift, csi.depth), (14, 6));
+        assert_eq!(
+            RegionIndex::binning_scheme(&csi),
+            BinningScheme::new(14, 6)?
+        );
+        assert_eq!(RegionIndex::binning_scheme(&tabix), BinningScheme::TABIX);
+        assert_eq!(csi.config, Some(tabix.config()));
+        assert_eq!(csi.sequences.len(), tabix.sequences.len());
+        for rid in 0..tabix.sequences.len() {
+            let rid = rid.try_into()?;
+            assert_eq!(RegionIndex::rid2name(&csi, rid), tabix.rid2name(rid));
+            assert_eq!(
+                RegionIndex::first_offset(&csi, rid),
+                RegionIndex::first_offset(&tabix, rid)
+            );
+        }
+
+        assert!(matches!(
+            CsiIndex::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?),
+            Err(BGZFError::InvalidMagic("CSI"))
+        ));
+
+        let mut csi_file = TabixFile::from_path_csi("testfiles/common_all_20180418_half.vcf.gz")?;
+        let mut tabix_file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        for (name, begin, end) in [
+            (&b"1"[..], 1_000_000, 1_200_000),
+            (b"5", 30_000_000, 30_100_000),
+            (b"22", 0, 20_000_000),
+        ] {
+            let rid = csi_file.index().name2rid(name).unwrap();
+            let csi_records = csi_file
+                .fetch(rid, begin, end)
+                .collect::<Result<Vec<_>, _>>()?;
+            let rid = tabix_file.index().name2rid(name).unwrap();
+            let tabix_records = tabix_file
+                .fetch(rid, begin, end)
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!csi_records.is_empty());
+            assert_eq!(csi_records, tabix_records);
+        }
+
+        Ok(())
+    }
+}
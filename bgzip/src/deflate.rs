@@ -16,14 +16,40 @@ pub use libdeflater::Crc;
 
 use crate::BGZFError;
 
+/// Name of DEFLATE implementation selected by feature flags.
+///
+/// All readers and writers, including multi-threaded ones, use this implementation.
+/// flate2 prefers C zlib implementations to miniz_oxide, so `zlib-ng` is used even if
+/// `rust_backend` is enabled by default features of this crate or another crate.
+#[cfg(feature = "libdeflater")]
+pub const BACKEND: &str = "libdeflater";
+
+/// Name of DEFLATE implementation selected by feature flags.
+///
+/// All readers and writers, including multi-threaded ones, use this implementation.
+/// flate2 prefers C zlib implementations to miniz_oxide, so `zlib-ng` is used even if
+/// `rust_backend` is enabled by default features of this crate or another crate.
+#[cfg(not(feature = "libdeflater"))]
+pub const BACKEND: &str = if cfg!(feature = "zlib-ng") {
+    "zlib-ng"
+} else if cfg!(feature = "cloudflare_zlib") {
+    "cloudflare_zlib"
+} else if cfg!(feature = "zlib-ng-compat") {
+    "zlib-ng-compat"
+} else if cfg!(feature = "zlib") {
+    "zlib"
+} else {
+    "miniz_oxide"
+};
+
 /// Compression Level
 #[cfg(not(feature = "libdeflater"))]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Compression(flate2::Compression);
 
 /// Compression Level
 #[cfg(feature = "libdeflater")]
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct Compression(libdeflater::CompressionLvl);
 
 #[cfg(not(feature = "libdeflater"))]
@@ -69,20 +95,6 @@ impl Compression {
     }
 }
 
-#[cfg(not(feature = "libdeflater"))]
-impl Default for Compression {
-    fn default() -> Self {
-        Compression(flate2::Compression::default())
-    }
-}
-
-#[cfg(feature = "libdeflater")]
-impl Default for Compression {
-    fn default() -> Self {
-        Compression(libdeflater::CompressionLvl::default())
-    }
-}
-
 /// Compression Error
 #[derive(Debug, Error, Clone, PartialEq)]
 pub enum CompressError {
@@ -168,6 +180,12 @@ pub enum DecompressError {
 #[derive(Debug)]
 pub struct Decompress(flate2::Decompress);
 
+impl Default for Decompress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 #[cfg(not(feature = "libdeflater"))]
 impl Decompress {
     pub fn new() -> Self {
@@ -223,6 +241,30 @@ impl Decompress {
     }
 }
 
+impl crate::block::RawDeflate for Compress {
+    fn deflate(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, crate::block::BlockError> {
+        self.compress(input, output).map_err(|e| match e {
+            CompressError::InsufficientSpace => crate::block::BlockError::TooLargeBlock,
+            _ => crate::block::BlockError::Deflate,
+        })
+    }
+}
+
+impl crate::block::RawInflate for Decompress {
+    fn inflate(
+        &mut self,
+        input: &[u8],
+        output: &mut [u8],
+    ) -> Result<usize, crate::block::BlockError> {
+        self.decompress(input, output)
+            .map_err(|_| crate::block::BlockError::Inflate)
+    }
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
@@ -36,16 +36,71 @@ pub enum BGZFError {
     /// Invalid compression level
     #[error("Invalid Compression Level")]
     InvalidCompressionLevel,
+    /// Deadline of a query is exceeded
+    #[error("Query timeout")]
+    Timeout,
+    /// A size in the input exceeds [`crate::Limits`]
+    #[error("Limit exceeded: {0}")]
+    LimitExceeded(&'static str),
+    /// Magic number of a file does not match. The expected format name is included.
+    #[error("not {0} format")]
+    InvalidMagic(&'static str),
+    /// A BGZF block ends before its declared size
+    #[error("truncated BGZF block")]
+    TruncatedBlock,
+    /// An index file is inconsistent or corrupted
+    #[error("Bad index: {0}")]
+    BadIndex(&'static str),
+    /// A sequence name is not found in the index
+    #[error("Unknown contig: {0}")]
+    UnknownContig(String),
+    /// Failed to parse a column of a text record.
+    ///
+    /// `line` is 1-based line number if it is known, and `column` is 1-based column number.
+    #[error("Parse error at column {column}{}", line_suffix(*.line))]
+    ParseError { line: Option<u64>, column: usize },
+    /// CRC32 or size of decompressed data does not match with the block footer.
+    ///
+    /// `offset` is the file offset of the block if it is known.
+    #[error("Corrupted BGZF block{}: {reason}", offset_suffix(*.offset))]
+    CorruptedBlock {
+        offset: Option<u64>,
+        reason: &'static str,
+    },
     /// Other error
     #[error("Error: {0}")]
     Other(&'static str),
 }
 
-impl Into<std::io::Error> for BGZFError {
-    fn into(self) -> std::io::Error {
-        match self {
+impl From<BGZFError> for std::io::Error {
+    fn from(value: BGZFError) -> Self {
+        match value {
             BGZFError::IoError(e) => e,
-            other => std::io::Error::new(std::io::ErrorKind::Other, other),
+            BGZFError::Timeout => std::io::Error::new(std::io::ErrorKind::TimedOut, value),
+            BGZFError::TruncatedBlock => {
+                std::io::Error::new(std::io::ErrorKind::UnexpectedEof, value)
+            }
+            other => std::io::Error::other(other),
+        }
+    }
+}
+
+impl std::error::Error for crate::block::BlockError {}
+
+impl From<crate::block::BlockError> for BGZFError {
+    fn from(value: crate::block::BlockError) -> Self {
+        match value {
+            crate::block::BlockError::NotGzip => BGZFError::NotGzip,
+            crate::block::BlockError::NotBGZF => BGZFError::NotBGZF,
+            crate::block::BlockError::TooLargeBlock => BGZFError::TooLargeCompressUnit,
+            crate::block::BlockError::UnexpectedEnd => BGZFError::TruncatedBlock,
+            crate::block::BlockError::CrcMismatch | crate::block::BlockError::SizeMismatch => {
+                BGZFError::CorruptedBlock {
+                    offset: None,
+                    reason: value.as_str(),
+                }
+            }
+            other => BGZFError::Other(other.as_str()),
         }
     }
 }
@@ -54,4 +109,44 @@ impl BGZFError {
     pub fn into_io_error(self) -> std::io::Error {
         self.into()
     }
+
+    /// Create [`BGZFError::UnknownContig`] from a sequence name.
+    pub(crate) fn unknown_contig(name: &[u8]) -> Self {
+        BGZFError::UnknownContig(String::from_utf8_lossy(name).into_owned())
+    }
+
+    /// Set line number of [`BGZFError::ParseError`] if it is not set.
+    pub(crate) fn with_line(self, line: u64) -> Self {
+        match self {
+            BGZFError::ParseError { line: None, column } => BGZFError::ParseError {
+                line: Some(line),
+                column,
+            },
+            other => other,
+        }
+    }
+
+    /// Set block offset of [`BGZFError::CorruptedBlock`] if it is not set.
+    pub(crate) fn with_block_offset(self, offset: u64) -> Self {
+        match self {
+            BGZFError::CorruptedBlock {
+                offset: None,
+                reason,
+            } => BGZFError::CorruptedBlock {
+                offset: Some(offset),
+                reason,
+            },
+            other => other,
+        }
+    }
+}
+
+fn offset_suffix(offset: Option<u64>) -> String {
+    offset
+        .map(|x| format!(" at offset {}", x))
+        .unwrap_or_default()
+}
+
+fn line_suffix(line: Option<u64>) -> String {
+    line.map(|x| format!(" of line {}", x)).unwrap_or_default()
 }
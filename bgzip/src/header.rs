@@ -1,7 +1,6 @@
 use crate::*;
 use std::convert::TryInto;
 use std::io;
-use std::u32;
 
 pub const GZIP_ID1: u8 = 31;
 pub const GZIP_ID2: u8 = 139;
@@ -265,7 +264,7 @@ impl BGZFHeader {
             calculated_flags |= FLAG_FEXTRA;
         }
         if calculated_flags != self.flags {
-            return Err(io::Error::new(io::ErrorKind::Other, "Invalid bgzip flag"));
+            return Err(io::Error::other("Invalid bgzip flag"));
         }
 
         writer.write_all(&[
@@ -279,10 +278,7 @@ impl BGZFHeader {
         if let Some(extra_field_len) = self.extra_field_len {
             let total_xlen: u16 = self.extra_field.iter().map(|x| x.field_len()).sum();
             if total_xlen != extra_field_len {
-                return Err(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Invalid bgzip extra field length",
-                ));
+                return Err(io::Error::other("Invalid bgzip extra field length"));
             }
 
             writer.write_all(&extra_field_len.to_le_bytes())?;
@@ -326,7 +322,7 @@ mod test {
         assert_eq!(header.flags, 4);
         assert_eq!(header.extra_field_len, Some(6));
         assert_eq!(header.extra_field[0].data.len(), 2);
-        let pos = reader.seek(SeekFrom::Current(0))?;
+        let pos = reader.stream_position()?;
         let mut buf: Vec<u8> = Vec::new();
         header.write(&mut buf)?;
         assert_eq!(buf.len(), header.header_size() as usize);
@@ -360,7 +356,7 @@ mod test {
             Some(b"common_all_20180418_half.vcf.nobgzip\0".to_vec())
         );
 
-        let pos = reader.seek(SeekFrom::Current(0))?;
+        let pos = reader.stream_position()?;
         let mut buf: Vec<u8> = Vec::new();
         header.write(&mut buf)?;
         assert_eq!(buf.len(), header.header_size() as usize);
@@ -0,0 +1,277 @@
+//! Client of [htsget protocol](https://samtools.github.io/hts-specs/htsget.html).
+//!
+//! This module is available with `htsget` feature.
+
+use crate::tabix::Region;
+use crate::{BGZFError, BGZFReader};
+use base64::Engine;
+use std::io::{self, Cursor, Read};
+
+/// Type of data served by htsget server
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HtsgetEndpoint {
+    /// Alignments, such as BAM
+    Reads,
+    /// Variants, such as bgzip compressed VCF
+    Variants,
+}
+
+impl HtsgetEndpoint {
+    fn path(self) -> &'static str {
+        match self {
+            HtsgetEndpoint::Reads => "reads",
+            HtsgetEndpoint::Variants => "variants",
+        }
+    }
+}
+
+/// A URL to download a part of data, which is listed in [`HtsgetTicket`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtsgetUrl {
+    /// `http://`, `https://` or `data:` URL
+    pub url: String,
+    /// HTTP headers to send, such as `Range`
+    pub headers: Vec<(String, String)>,
+    /// `header` or `body` if the server provides the class of data
+    pub class: Option<String>,
+}
+
+/// Response of htsget server, which lists URLs of data blocks.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HtsgetTicket {
+    /// Format of data, such as `VCF` or `BAM`
+    pub format: Option<String>,
+    /// URLs of data. The concatenation of downloaded data is a valid file.
+    pub urls: Vec<HtsgetUrl>,
+}
+
+impl HtsgetTicket {
+    /// Parse JSON ticket returned from htsget server.
+    pub fn parse(json: &[u8]) -> Result<Self, BGZFError> {
+        let value: serde_json::Value = serde_json::from_slice(json)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let htsget = &value["htsget"];
+        if let Some(error) = htsget["error"].as_str() {
+            return Err(io::Error::other(format!(
+                "htsget error: {}: {}",
+                error,
+                htsget["message"].as_str().unwrap_or("")
+            ))
+            .into());
+        }
+        let urls = htsget["urls"]
+            .as_array()
+            .ok_or(BGZFError::Other("no URLs in htsget ticket"))?
+            .iter()
+            .map(|one| {
+                Ok(HtsgetUrl {
+                    url: one["url"]
+                        .as_str()
+                        .ok_or(BGZFError::Other("no URL in htsget ticket"))?
+                        .to_string(),
+                    headers: one["headers"]
+                        .as_object()
+                        .map(|x| {
+                            x.iter()
+                                .filter_map(|(k, v)| Some((k.clone(), v.as_str()?.to_string())))
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                    class: one["class"].as_str().map(|x| x.to_string()),
+                })
+            })
+            .collect::<Result<_, BGZFError>>()?;
+        Ok(HtsgetTicket {
+            format: htsget["format"].as_str().map(|x| x.to_string()),
+            urls,
+        })
+    }
+}
+
+/// A client of htsget server.
+///
+/// Data blocks listed in a ticket are downloaded and stitched into a BGZF stream, which can be
+/// read with [`BGZFReader`]. Note that records outside of the requested region may be included,
+/// because the server returns whole blocks.
+pub struct HtsgetClient {
+    agent: ureq::Agent,
+    base_url: String,
+}
+
+impl HtsgetClient {
+    /// Create new client of htsget server at `base_url`, such as `https://htsget.example.org/`.
+    pub fn new(base_url: &str) -> Self {
+        HtsgetClient {
+            agent: ureq::Agent::new(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Use `agent` to send requests, e.g. to set timeouts or authorization.
+    pub fn with_agent(mut self, agent: ureq::Agent) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    /// Request a ticket of data `id`.
+    ///
+    /// `region` is a region string such as `chr17:42,990,000-42,990,600` or `chr17`, which is
+    /// parsed with [`Region::parse`]. Whole data is requested if `region` is `None`.
+    pub fn ticket(
+        &self,
+        endpoint: HtsgetEndpoint,
+        id: &str,
+        region: Option<&str>,
+    ) -> Result<HtsgetTicket, BGZFError> {
+        let mut url = format!("{}/{}/{}", self.base_url, endpoint.path(), encode(id));
+        if let Some(region) = region {
+            let region = Region::parse(region)?;
+            url.push_str(&format!("?referenceName={}", encode(&region.name)));
+            if region.begin > 0 {
+                url.push_str(&format!("&start={}", region.begin));
+            }
+            if region.end != u64::MAX {
+                url.push_str(&format!("&end={}", region.end));
+            }
+        }
+        debug!("htsget request: {}", url);
+        let body = match self.agent.get(&url).call() {
+            Ok(response) => read_body(response)?,
+            // error details are returned as JSON
+            Err(ureq::Error::Status(_, response)) => read_body(response)?,
+            Err(e) => return Err(io::Error::other(e.to_string()).into()),
+        };
+        HtsgetTicket::parse(&body)
+    }
+
+    /// Download and concatenate all data listed in `ticket`.
+    pub fn download(&self, ticket: &HtsgetTicket) -> Result<Vec<u8>, BGZFError> {
+        let mut data = Vec::new();
+        for one in &ticket.urls {
+            if let Some(encoded) = one.url.strip_prefix("data:") {
+                let (media_type, payload) = encoded
+                    .split_once(',')
+                    .ok_or(BGZFError::Other("invalid data URL in htsget ticket"))?;
+                if !media_type.ends_with(";base64") {
+                    return Err(BGZFError::Other("data URL is not base64 encoded"));
+                }
+                let decoded = base64::engine::general_purpose::STANDARD
+                    .decode(payload)
+                    .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+                data.extend_from_slice(&decoded);
+            } else {
+                trace!("htsget download: {}", one.url);
+                let mut request = self.agent.get(&one.url);
+                for (key, value) in &one.headers {
+                    request = request.set(key, value);
+                }
+                let response = request
+                    .call()
+                    .map_err(|e| io::Error::other(e.to_string()))?;
+                response.into_reader().read_to_end(&mut data)?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Request a ticket and download data, and open the data with [`BGZFReader`].
+    pub fn fetch(
+        &self,
+        endpoint: HtsgetEndpoint,
+        id: &str,
+        region: Option<&str>,
+    ) -> Result<BGZFReader<Cursor<Vec<u8>>>, BGZFError> {
+        let ticket = self.ticket(endpoint, id, region)?;
+        BGZFReader::new(Cursor::new(self.download(&ticket)?))
+    }
+}
+
+fn read_body(response: ureq::Response) -> io::Result<Vec<u8>> {
+    let mut body = Vec::new();
+    response.into_reader().read_to_end(&mut body)?;
+    Ok(body)
+}
+
+/// Percent-encode characters except unreserved characters of URI
+fn encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|x| match x {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                char::from(x).to_string()
+            }
+            _ => format!("%{:02X}", x),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::http::test::start_server;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_htsget_client() -> anyhow::Result<()> {
+        let address = start_server(".")?;
+        let data = std::fs::read("testfiles/generated.bed.gz")?;
+
+        // split into blocks
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        while offset < data.len() {
+            let size = usize::from(u16::from_le_bytes([data[offset + 16], data[offset + 17]])) + 1;
+            blocks.push(offset..(offset + size));
+            offset += size;
+        }
+
+        // the first block as header, the third and fourth blocks as body, and EOF marker
+        let ticket = format!(
+            r#"{{"htsget": {{"format": "VCF", "urls": [
+                {{"url": "data:application/octet-stream;base64,{}", "class": "header"}},
+                {{"url": "{}/testfiles/generated.bed.gz", "headers": {{"Range": "bytes={}-{}"}}, "class": "body"}},
+                {{"url": "data:;base64,{}"}}
+            ]}}}}"#,
+            base64::engine::general_purpose::STANDARD.encode(&data[blocks[0].clone()]),
+            address,
+            blocks[2].start,
+            blocks[3].end - 1,
+            base64::engine::general_purpose::STANDARD.encode(crate::EOF_MARKER),
+        );
+        std::fs::create_dir_all("tmp/htsget/variants")?;
+        std::fs::write("tmp/htsget/variants/test-sample", &ticket)?;
+
+        let client = HtsgetClient::new(&format!("{}/tmp/htsget/", address));
+        let parsed = client.ticket(
+            HtsgetEndpoint::Variants,
+            "test-sample",
+            Some("chr1:1,000-2,000"),
+        )?;
+        assert_eq!(parsed, HtsgetTicket::parse(ticket.as_bytes())?);
+        assert_eq!(parsed.format.as_deref(), Some("VCF"));
+        assert_eq!(parsed.urls.len(), 3);
+        assert_eq!(parsed.urls[1].class.as_deref(), Some("body"));
+
+        let mut expected = Vec::new();
+        for range in [&blocks[0], &blocks[2], &blocks[3]] {
+            BGZFReader::new(&data[range.clone()])?.read_to_end(&mut expected)?;
+        }
+        let mut reader = client.fetch(HtsgetEndpoint::Variants, "test-sample", None)?;
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual)?;
+        assert!(actual == expected);
+        reader.bgzf_seek(0)?;
+        assert!(reader.lines().next().is_some());
+
+        std::fs::write(
+            "tmp/htsget/variants/test-error",
+            r#"{"htsget": {"error": "NotFound", "message": "No such accession"}}"#,
+        )?;
+        assert!(client
+            .ticket(HtsgetEndpoint::Variants, "test-error", None)
+            .is_err());
+        assert_eq!(encode("chr1 a/b"), "chr1%20a%2Fb");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,280 @@
+//! Access to BGZF files on a web server with HTTP range requests.
+//!
+//! This module is available with `http` feature.
+
+use crate::range::RangeWindow;
+use crate::tabix::{Tabix, TabixFile};
+use crate::{BGZFError, Limits};
+use std::io::{self, Read, Seek, SeekFrom};
+
+/// A [`Read`] + [`Seek`] adapter backed by HTTP range requests.
+///
+/// Data is fetched by `fetch_size` bytes when the current position is not in the fetched range,
+/// so only blocks which are actually read are downloaded. The server must support range requests.
+pub struct HttpRangeReader {
+    agent: ureq::Agent,
+    url: String,
+    length: Option<u64>,
+    window: RangeWindow,
+}
+
+impl HttpRangeReader {
+    /// Create new reader of `url`. No request is sent until the first read.
+    pub fn new(url: &str) -> Self {
+        HttpRangeReader {
+            agent: ureq::Agent::new(),
+            url: url.to_string(),
+            length: None,
+            window: RangeWindow::new(),
+        }
+    }
+
+    /// Use `agent` to send requests, e.g. to set timeouts or a proxy.
+    pub fn with_agent(mut self, agent: ureq::Agent) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    /// Set the number of bytes fetched by a request. Default value is 256 KiB.
+    pub fn with_fetch_size(mut self, fetch_size: usize) -> Self {
+        self.window.set_fetch_size(fetch_size);
+        self
+    }
+
+    /// URL of this reader
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+
+    /// Length of the remote file. A HEAD request is sent if the length is not known yet.
+    pub fn len(&mut self) -> io::Result<u64> {
+        content_length(&self.agent, &self.url, &mut self.length)
+    }
+
+    /// `true` if the remote file is empty.
+    pub fn is_empty(&mut self) -> io::Result<bool> {
+        Ok(self.len()? == 0)
+    }
+}
+
+/// Return `length` if it is known, or send a HEAD request and save the length into `length`.
+fn content_length(agent: &ureq::Agent, url: &str, length: &mut Option<u64>) -> io::Result<u64> {
+    if let Some(length) = *length {
+        return Ok(length);
+    }
+    let response = agent.head(url).call().map_err(into_io_error)?;
+    let content_length = response
+        .header("Content-Length")
+        .and_then(|x| x.trim().parse().ok())
+        .ok_or_else(|| {
+            io::Error::new(io::ErrorKind::InvalidData, "no Content-Length in response")
+        })?;
+    *length = Some(content_length);
+    Ok(content_length)
+}
+
+/// Fetch up to `size` bytes from `offset` into `buffer`, and update `length` with the response.
+fn fetch_range(
+    agent: &ureq::Agent,
+    url: &str,
+    length: &mut Option<u64>,
+    offset: u64,
+    size: usize,
+    buffer: &mut Vec<u8>,
+) -> io::Result<()> {
+    let last = offset + u64::try_from(size).unwrap() - 1;
+    trace!("fetch {}: {}-{}", url, offset, last);
+    let response = match agent
+        .get(url)
+        .set("Range", &format!("bytes={}-{}", offset, last))
+        .call()
+    {
+        Ok(response) => response,
+        // requested range starts at or after the end of file
+        Err(ureq::Error::Status(416, _)) => return Ok(()),
+        Err(e) => return Err(into_io_error(e)),
+    };
+    if response.status() != 206 {
+        return Err(io::Error::new(
+            io::ErrorKind::Unsupported,
+            "server does not support range requests",
+        ));
+    }
+    if let Some(x) = response
+        .header("Content-Range")
+        .and_then(|x| x.rsplit('/').next())
+        .and_then(|x| x.trim().parse().ok())
+    {
+        *length = Some(x);
+    }
+    response
+        .into_reader()
+        .take(u64::try_from(size).unwrap())
+        .read_to_end(buffer)?;
+    Ok(())
+}
+
+impl Read for HttpRangeReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let length = self.length;
+        self.window.read(buf, length, |offset, size, buffer| {
+            fetch_range(
+                &self.agent,
+                &self.url,
+                &mut self.length,
+                offset,
+                size,
+                buffer,
+            )
+        })
+    }
+}
+
+impl Seek for HttpRangeReader {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.window.seek(pos, || {
+            content_length(&self.agent, &self.url, &mut self.length)
+        })
+    }
+}
+
+fn into_io_error(e: ureq::Error) -> io::Error {
+    match e {
+        ureq::Error::Status(404, _) => io::Error::new(io::ErrorKind::NotFound, e.to_string()),
+        e => io::Error::other(e.to_string()),
+    }
+}
+
+impl TabixFile<HttpRangeReader, Tabix> {
+    /// Open BGZF compressed file on a web server and its tabix index (`url` + `.tbi`).
+    pub fn from_url(url: &str) -> Result<Self, BGZFError> {
+        TabixFile::from_urls(url, &format!("{}.tbi", url), Limits::UNLIMITED)
+    }
+
+    /// Open BGZF compressed file and tabix index on web servers with resource limits.
+    pub fn from_urls(url: &str, index_url: &str, limits: Limits) -> Result<Self, BGZFError> {
+        let index = Tabix::from_reader_with_limits(HttpRangeReader::new(index_url), limits)?;
+        TabixFile::with_limits(HttpRangeReader::new(url), index, limits)
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+
+    /// Start a minimal HTTP server which supports range requests of files in `root`
+    pub(crate) fn start_server(root: &'static str) -> anyhow::Result<String> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let address = format!("http://{}", listener.local_addr()?);
+        std::thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut range = None;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if line.trim().is_empty() {
+                        break;
+                    }
+                    if let Some(value) = line.to_ascii_lowercase().strip_prefix("range: bytes=") {
+                        let (begin, end) = value.trim().split_once('-').unwrap();
+                        range = Some((
+                            begin.parse::<usize>().unwrap(),
+                            end.parse::<usize>().unwrap(),
+                        ));
+                    }
+                }
+                let mut items = request_line.split_whitespace();
+                let method = items.next().unwrap();
+                let path = items.next().unwrap().split('?').next().unwrap();
+                let data = match std::fs::read(format!("{}{}", root, path)) {
+                    Ok(data) => data,
+                    Err(_) => {
+                        let _ = stream
+                            .write_all(b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n");
+                        continue;
+                    }
+                };
+                let response = match range {
+                    _ if method == "HEAD" => format!(
+                        "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                        data.len()
+                    )
+                    .into_bytes(),
+                    Some((begin, _)) if begin >= data.len() => {
+                        b"HTTP/1.1 416 Range Not Satisfiable\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_vec()
+                    }
+                    Some((begin, end)) => {
+                        let end = end.min(data.len() - 1);
+                        let mut response = format!(
+                            "HTTP/1.1 206 Partial Content\r\nContent-Length: {}\r\nContent-Range: bytes {}-{}/{}\r\nConnection: close\r\n\r\n",
+                            end - begin + 1,
+                            begin,
+                            end,
+                            data.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(&data[begin..=end]);
+                        response
+                    }
+                    None => {
+                        let mut response = format!(
+                            "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                            data.len()
+                        )
+                        .into_bytes();
+                        response.extend_from_slice(&data);
+                        response
+                    }
+                };
+                let _ = stream.write_all(&response);
+            }
+        });
+        Ok(address)
+    }
+
+    #[test]
+    fn test_http_range_reader() -> anyhow::Result<()> {
+        let address = start_server("testfiles")?;
+        let expected = std::fs::read("testfiles/generated.bed.gz")?;
+
+        let mut reader =
+            HttpRangeReader::new(&format!("{}/generated.bed.gz", address)).with_fetch_size(1000);
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual)?;
+        assert!(actual == expected);
+
+        let mut buf = [0u8; 100];
+        reader.seek(SeekFrom::Start(12345))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf[..], &expected[12345..12445]);
+        reader.seek(SeekFrom::End(-100))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf[..], &expected[(expected.len() - 100)..]);
+        assert_eq!(reader.read(&mut buf)?, 0);
+
+        let mut missing = HttpRangeReader::new(&format!("{}/missing.bed.gz", address));
+        assert_eq!(
+            missing.read(&mut buf).unwrap_err().kind(),
+            io::ErrorKind::NotFound
+        );
+
+        let mut remote = TabixFile::from_url(&format!("{}/generated.bed.gz", address))?;
+        let mut local = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let remote_records = remote
+            .fetch_region("chr1:12345678-13000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        let local_records = local
+            .fetch_region("chr1:12345678-13000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!remote_records.is_empty());
+        assert_eq!(remote_records, local_records);
+
+        Ok(())
+    }
+}
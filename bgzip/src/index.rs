@@ -35,6 +35,39 @@ impl BGZFIndex {
         Ok(result)
     }
 
+    /// Build .gzi index by scanning all blocks of BGZF data from `reader`.
+    ///
+    /// Blocks are not decompressed. Uncompressed sizes are read from block footers.
+    pub fn from_bgzf<R: std::io::Read>(mut reader: R) -> Result<Self, BGZFError> {
+        let mut result = BGZFIndex::default();
+        let mut buffer = Vec::new();
+        let mut compressed_offset = 0;
+        let mut uncompressed_offset = 0;
+        loop {
+            buffer.clear();
+            let header = match crate::read::load_block(&mut reader, &mut buffer) {
+                Ok(header) => header,
+                Err(BGZFError::IoError(e))
+                    if e.kind() == std::io::ErrorKind::UnexpectedEof && buffer.is_empty() =>
+                {
+                    break;
+                }
+                Err(e) => return Err(e),
+            };
+            let footer = crate::block::parse_footer(&buffer)?;
+            if footer.isize > 0 && compressed_offset > 0 {
+                result.entries.push(BGZFIndexEntry {
+                    compressed_offset,
+                    uncompressed_offset,
+                });
+            }
+            compressed_offset +=
+                header.header_size() + TryInto::<u64>::try_into(buffer.len()).unwrap();
+            uncompressed_offset += u64::from(footer.isize);
+        }
+        Ok(result)
+    }
+
     /// Write .gzi index file into `writer`
     pub fn write<W: std::io::Write>(&self, mut writer: W) -> std::io::Result<()> {
         let entries: u64 = self.entries.len().try_into().unwrap();
@@ -56,7 +89,7 @@ impl BGZFIndex {
                 compressed_offset: 0,
                 uncompressed_offset: 0,
             },
-            i => self.entries[i - 1].clone(),
+            i => self.entries[i - 1],
         };
         // eprintln!(
         //     "[{}/{}] {} / {} ",
@@ -77,7 +110,7 @@ impl BGZFIndex {
         let i = self
             .entries
             .binary_search_by(|x| x.compressed_offset.cmp(&compressed_pos))
-            .map_err(|_| BGZFError::Other("Invalid BGZF position"))?;
+            .map_err(|_| BGZFError::BadIndex("Invalid BGZF position"))?;
         Ok(self.entries[i].uncompressed_offset + (bgzf_pos & ((1 << 16) - 1)))
     }
 }
@@ -105,6 +138,9 @@ mod test {
         index.write(&mut generated_data)?;
         assert_eq!(data, generated_data);
 
+        let built = BGZFIndex::from_bgzf(fs::File::open("testfiles/generated.bed.gz")?)?;
+        assert_eq!(built, index);
+
         Ok(())
     }
 
@@ -128,19 +164,19 @@ mod test {
             if size == 0 {
                 break;
             }
-            writer.write_all(&line.as_bytes())?;
+            writer.write_all(line.as_bytes())?;
             line_list.push((bgzf_pos, uncompressed_pos, line.clone()));
         }
         let index = writer.close()?.unwrap();
 
         for (bgzf_pos, uncompressed_pos, _) in &line_list {
             assert_eq!(
-                index.bgzf_pos_to_uncompressed_pos(*bgzf_pos)?,
+                index.bgzf_pos_to_uncompressed_pos((*bgzf_pos).into())?,
                 *uncompressed_pos
             );
             assert_eq!(
                 index.uncompressed_pos_to_bgzf_pos(*uncompressed_pos)?,
-                *bgzf_pos
+                u64::from(*bgzf_pos)
             );
         }
 
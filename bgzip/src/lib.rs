@@ -12,8 +12,17 @@
 //! Feature flags
 //! -------------
 //!
+//! * `std`: Enable all modules except [`block`], which require the standard library. This is default feature.
+//!   One of DEFLATE backends, `rust_backend`, `zlib`, `zlib-ng`, `zlib-ng-compat`, `cloudflare_zlib` or `libdeflater`, must be enabled with it.
 //! * `rayon`: Enable [rayon](https://github.com/rayon-rs/rayon) based multi-threaded writer. This is default feature.
-//! * `log`: Enable [log](https://github.com/rust-lang/log) crate to log warnings. This is default feature.
+//! * `std_thread`: Enable multi-threaded reader [`read::BGZFThreadReader`] based on [`std::thread`], which does not depend on rayon.
+//! * `log`: Enable [log](https://github.com/rust-lang/log) crate to log diagnostics of index queries. This is default feature.
+//! * `tokio`: Enable [tokio](https://tokio.rs) based asynchronous reader and writer, [`read::BGZFAsyncReader`] and [`write::BGZFAsyncWriter`], and asynchronous region queries with [`tabix::AsyncTabixFile`] and [`tabix::SharedTabixFile::fetch_stream`].
+//! * `http`: Enable [`http::HttpRangeReader`] to query BGZF files on a web server with HTTP range requests, using [ureq](https://crates.io/crates/ureq).
+//! * `htsget`: Enable [`htsget::HtsgetClient`] to download region slices from [htsget](https://samtools.github.io/hts-specs/htsget.html) servers.
+//! * `object_store`: Enable [`cloud::ObjectStoreReader`] to query BGZF files in cloud storages with [object_store](https://crates.io/crates/object_store) crate.
+//! * `contig_aliases`: Enable built-in tables of GRCh37/GRCh38 chromosome name aliases, [`tabix::ContigAliases::grch38`] and [`tabix::ContigAliases::grch37`].
+//! * `mmap`: Enable [`tabix::MappedTabix::open`] to memory-map decompressed tabix indexes through [memmap2](https://crates.io/crates/memmap2) crate.
 //! * `rust_backend`: use use [miniz_oxide](https://crates.io/crates/miniz_oxide) crate for [flate2](https://github.com/rust-lang/flate2-rs) backend. This is default feature.
 //! * `zlib`: use `zlib` for flate2 backend. Please read [flate2](https://github.com/rust-lang/flate2-rs) description for the detail.
 //! * `zlib-ng`: use `zlib-ng` for flate2 backend. Please read [flate2](https://github.com/rust-lang/flate2-rs) description for the detail.
@@ -21,6 +30,10 @@
 //! * `cloudflare_zlib`: Please read [flate2](https://github.com/rust-lang/flate2-rs) description for the detail.
 //! * `libdeflater`: use [libdeflater](https://github.com/adamkewley/libdeflater) instead of [flate2](https://github.com/rust-lang/flate2-rs) crate.
 //!
+//! flate2 uses C zlib implementations in preference to miniz_oxide, so adding `zlib-ng` feature
+//! is enough to switch the backend even if default features are enabled through another dependency.
+//! Selected implementation is available as [`deflate::BACKEND`].
+//!
 //! Write Examples
 //! --------
 //! ```rust
@@ -38,6 +51,10 @@
 //!
 //! Multi-thread support is available via [`write::BGZFMultiThreadWriter`]. `rayon` flag is required to use this feature.
 //!
+//! Block encoding and decoding logic which depends only on `core` and `alloc` is available in [`block`] module.
+//! Disable default `std` feature to build only this module without the standard library.
+//! [`compress_block`] and [`decompress_block`] compress and decompress a single block to build your own pipeline.
+//!
 //! Read Examples
 //! --------
 //! ```rust
@@ -58,28 +75,108 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! Tabix Examples
+//! --------
+//! Records in a region are read lazily, so a large region is processed in constant memory.
+//! ```rust
+//! use bgzip::{tabix::TabixFile, BGZFError};
+//! fn main() -> Result<(), BGZFError> {
+//!     let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+//!     let mut records = file.fetch_region("1:72,700,000-72,800,000")?;
+//!     let mut count = 0;
+//!     while let Some(record) = records.next_record()? {
+//!         assert_eq!(record.column(0), Some(&b"1"[..]));
+//!         count += 1;
+//!     }
+//!     assert!(count > 0);
+//!     Ok(())
+//! }
+//! ```
+
+#![cfg_attr(not(feature = "std"), no_std)]
+
+extern crate alloc;
+
+#[cfg(all(feature = "std", not(any(feature = "flate2", feature = "libdeflater"))))]
+compile_error!(
+    "`std` feature requires a DEFLATE backend. Enable one of `rust_backend`, `zlib`, `zlib-ng`, \
+     `zlib-ng-compat`, `cloudflare_zlib` and `libdeflater` features."
+);
+
+#[cfg(feature = "std")]
+#[macro_use]
+mod logging;
 
+#[cfg(feature = "std")]
 mod error;
 
-pub(crate) mod csi;
+/// BAI index parser
+#[cfg(feature = "std")]
+pub mod bai;
+/// BAM reader
+#[cfg(feature = "std")]
+pub mod bam;
+/// BGZF block codec without `std`
+pub mod block;
+#[cfg(feature = "object_store")]
+pub mod cloud;
+#[cfg(feature = "std")]
+mod codec;
+/// CSI index parser
+#[cfg(feature = "std")]
+pub mod csi;
+#[cfg(feature = "std")]
 pub mod deflate;
 /// BGZ header parser
+#[cfg(feature = "std")]
 pub mod header;
+#[cfg(feature = "htsget")]
+pub mod htsget;
+#[cfg(feature = "http")]
+pub mod http;
+#[cfg(feature = "std")]
 pub mod index;
+#[cfg(feature = "std")]
+mod limits;
+#[cfg(any(feature = "http", feature = "object_store"))]
+mod range;
 #[cfg(feature = "rayon")]
 pub(crate) mod rayon;
+#[cfg(feature = "std")]
 pub mod read;
+#[cfg(feature = "std")]
+mod validate;
+#[cfg(feature = "std")]
+mod virtual_offset;
 
+#[cfg(feature = "std")]
+pub use codec::{compress_block, decompress_block};
+#[cfg(feature = "std")]
 pub use deflate::Compression;
 /// Tabix file parser. (This module is alpha state.)
+#[cfg(feature = "std")]
 pub mod tabix;
+#[cfg(feature = "std")]
 pub mod write;
+#[cfg(feature = "std")]
 pub use error::BGZFError;
+#[cfg(feature = "std")]
+pub use limits::Limits;
+#[cfg(feature = "std")]
 pub use read::BGZFReader;
-pub use read::{new_reader, open};
+#[cfg(feature = "std")]
+pub use read::{has_eof_marker, is_bgzf, new_reader, open};
+#[cfg(feature = "std")]
+pub use validate::{validate, ValidationReport};
+#[cfg(feature = "std")]
+pub use virtual_offset::VirtualOffset;
+#[cfg(feature = "std")]
 pub use write::create;
+#[cfg(feature = "std")]
 pub use write::BGZFWriter;
 
+#[cfg(feature = "std")]
 use std::io;
 
 /// End-of-file maker.
@@ -90,7 +187,9 @@ pub const EOF_MARKER: [u8; 28] = [
     0x1b, 0x00, 0x03, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00,
 ];
 
+#[cfg(feature = "std")]
 pub(crate) trait BinaryReader: io::Read {
+    #[allow(dead_code)]
     fn read_le_u8(&mut self) -> io::Result<u8> {
         let mut buf: [u8; 1] = [0];
         self.read_exact(&mut buf)?;
@@ -135,9 +234,10 @@ pub(crate) trait BinaryReader: io::Read {
     }
 }
 
+#[cfg(feature = "std")]
 impl<R: io::Read> BinaryReader for R {}
 
-#[cfg(test)]
+#[cfg(all(test, feature = "std"))]
 mod test {
     use crate::index::BGZFIndex;
 
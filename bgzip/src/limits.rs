@@ -0,0 +1,122 @@
+/// Resource limits for parsing untrusted BGZF and tabix files.
+///
+/// Sizes recorded in files are checked before memory is allocated, and [`crate::BGZFError::LimitExceeded`]
+/// is returned if one of the limits is exceeded. Default value is [`Limits::UNLIMITED`], which
+/// keeps the behaviour of readers created without limits. Use [`Limits::HARDENED`] for services
+/// accepting user-uploaded files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Limits {
+    /// Maximum uncompressed size of a BGZF block (ISIZE in the block footer)
+    pub max_block_size: u32,
+    /// Maximum number of reference sequences in a tabix index
+    pub max_references: u32,
+    /// Maximum length of concatenated sequence names in a tabix index
+    pub max_names_length: u32,
+    /// Maximum number of chunks in a bin of a tabix index
+    pub max_chunks_per_bin: u32,
+    /// Maximum length of a line read by [`crate::tabix::TabixFile`]
+    pub max_line_length: usize,
+}
+
+impl Limits {
+    /// No limits
+    pub const UNLIMITED: Limits = Limits {
+        max_block_size: u32::MAX,
+        max_references: u32::MAX,
+        max_names_length: u32::MAX,
+        max_chunks_per_bin: u32::MAX,
+        max_line_length: usize::MAX,
+    };
+
+    /// Limits which accept valid BGZF files and most real-world tabix indices.
+    pub const HARDENED: Limits = Limits {
+        max_block_size: 65536,
+        max_references: 1 << 20,
+        max_names_length: 64 << 20,
+        max_chunks_per_bin: 1 << 20,
+        max_line_length: 16 << 20,
+    };
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits::UNLIMITED
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::tabix::{Tabix, TabixFile};
+    use crate::BGZFError;
+    use std::fs::File;
+
+    #[test]
+    fn test_limits() -> anyhow::Result<()> {
+        let index = Tabix::from_reader_with_limits(
+            File::open("testfiles/generated.bed.gz.tbi")?,
+            Limits::HARDENED,
+        )?;
+        assert_eq!(
+            index,
+            Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?
+        );
+
+        for limits in [
+            Limits {
+                max_references: 10,
+                ..Limits::HARDENED
+            },
+            Limits {
+                max_names_length: 10,
+                ..Limits::HARDENED
+            },
+            Limits {
+                max_chunks_per_bin: 0,
+                ..Limits::HARDENED
+            },
+            Limits {
+                max_block_size: 100,
+                ..Limits::HARDENED
+            },
+        ] {
+            assert!(matches!(
+                Tabix::from_reader_with_limits(
+                    File::open("testfiles/generated.bed.gz.tbi")?,
+                    limits
+                ),
+                Err(BGZFError::LimitExceeded(_))
+            ));
+        }
+
+        assert!(matches!(
+            crate::BGZFReader::with_limits(
+                File::open("testfiles/generated.bed.gz")?,
+                Limits {
+                    max_block_size: 1000,
+                    ..Limits::HARDENED
+                },
+            ),
+            Err(BGZFError::LimitExceeded(_))
+        ));
+
+        let mut file = TabixFile::from_path_with_limits(
+            "testfiles/generated.bed.gz",
+            Limits {
+                max_line_length: 10,
+                ..Limits::HARDENED
+            },
+        )?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        assert!(matches!(
+            file.fetch(rid, 0, 1_000_000).next(),
+            Some(Err(BGZFError::LimitExceeded(_)))
+        ));
+
+        let mut file =
+            TabixFile::from_path_with_limits("testfiles/generated.bed.gz", Limits::HARDENED)?;
+        assert!(file.fetch(rid, 0, 1_000_000).next().transpose()?.is_some());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,80 @@
+//! Buffered window over a remote file, which is shared by readers backed by range requests.
+
+use std::io::{self, SeekFrom};
+
+const DEFAULT_FETCH_SIZE: usize = 256 * 1024;
+
+/// Current position and the last fetched range of a remote file.
+///
+/// Data is fetched by `fetch_size` bytes with a closure when the current position is not in the fetched range.
+#[derive(Debug)]
+pub(crate) struct RangeWindow {
+    position: u64,
+    buffer: Vec<u8>,
+    buffer_offset: u64,
+    fetch_size: usize,
+}
+
+impl RangeWindow {
+    pub(crate) fn new() -> Self {
+        RangeWindow {
+            position: 0,
+            buffer: Vec::new(),
+            buffer_offset: 0,
+            fetch_size: DEFAULT_FETCH_SIZE,
+        }
+    }
+
+    pub(crate) fn set_fetch_size(&mut self, fetch_size: usize) {
+        self.fetch_size = fetch_size.max(1);
+    }
+
+    /// Read data at the current position into `buf`.
+    ///
+    /// `fetch(offset, size, buffer)` is called to append up to `size` bytes from `offset` to empty `buffer`
+    /// if the current position is not in the fetched range. Nothing is read at or after `length` if it is known.
+    pub(crate) fn read<F>(
+        &mut self,
+        buf: &mut [u8],
+        length: Option<u64>,
+        fetch: F,
+    ) -> io::Result<usize>
+    where
+        F: FnOnce(u64, usize, &mut Vec<u8>) -> io::Result<()>,
+    {
+        if length.map(|x| self.position >= x).unwrap_or(false) {
+            return Ok(0);
+        }
+        let buffer_end = self.buffer_offset + u64::try_from(self.buffer.len()).unwrap();
+        if self.position < self.buffer_offset || self.position >= buffer_end {
+            self.buffer.clear();
+            self.buffer_offset = self.position;
+            fetch(self.position, self.fetch_size, &mut self.buffer)?;
+        }
+        let start = usize::try_from(self.position - self.buffer_offset).unwrap();
+        let available = &self.buffer[start.min(self.buffer.len())..];
+        let bytes_to_copy = buf.len().min(available.len());
+        buf[..bytes_to_copy].copy_from_slice(&available[..bytes_to_copy]);
+        self.position += u64::try_from(bytes_to_copy).unwrap();
+        Ok(bytes_to_copy)
+    }
+
+    /// Move the current position. `length` is called only to seek from the end.
+    pub(crate) fn seek<F>(&mut self, pos: SeekFrom, length: F) -> io::Result<u64>
+    where
+        F: FnOnce() -> io::Result<u64>,
+    {
+        let new_position = match pos {
+            SeekFrom::Start(x) => Some(x),
+            SeekFrom::Current(x) => self.position.checked_add_signed(x),
+            SeekFrom::End(x) => length()?.checked_add_signed(x),
+        };
+        self.position = new_position.ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )
+        })?;
+        Ok(self.position)
+    }
+}
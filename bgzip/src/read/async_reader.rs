@@ -0,0 +1,226 @@
+use crate::block::{decode_block, parse_header};
+use crate::deflate::Decompress;
+use crate::{BGZFError, VirtualOffset};
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::{AsyncBufRead, AsyncRead, AsyncSeek, AsyncSeekExt, ReadBuf};
+
+/// Size of gzip header before extra fields
+const FIXED_HEADER_SIZE: usize = 12;
+
+/// An asynchronous BGZF reader for [tokio](https://tokio.rs).
+///
+/// Blocks are read from the underlying reader without blocking, and decompressed one by one
+/// in the task which polls this reader. `tokio` flag is required to use this struct.
+pub struct BGZFAsyncReader<R: AsyncRead + Unpin> {
+    reader: R,
+    decompress: Decompress,
+    compressed_buffer: Vec<u8>,
+    filled: usize,
+    needed: usize,
+    current_buffer: Vec<u8>,
+    current_position_in_block: usize,
+    current_block: u64,
+    next_block: u64,
+    eof: bool,
+}
+
+impl<R: AsyncRead + Unpin> BGZFAsyncReader<R> {
+    /// Create a new asynchronous BGZF reader.
+    ///
+    /// Unlike [`crate::BGZFReader::new`], the first block is not loaded until the first read.
+    pub fn new(reader: R) -> Self {
+        BGZFAsyncReader {
+            reader,
+            decompress: Decompress::new(),
+            compressed_buffer: vec![0; FIXED_HEADER_SIZE],
+            filled: 0,
+            needed: FIXED_HEADER_SIZE,
+            current_buffer: Vec::new(),
+            current_position_in_block: 0,
+            current_block: 0,
+            next_block: 0,
+            eof: false,
+        }
+    }
+
+    /// Get BGZF virtual file offset of the current position. See [`crate::BGZFReader::bgzf_pos`].
+    pub fn bgzf_pos(&self) -> VirtualOffset {
+        VirtualOffset::new(
+            self.current_block,
+            (self.current_position_in_block & 0xffff) as u16,
+        )
+    }
+
+    /// Unwraps this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read the next block and decompress it into `current_buffer`.
+    fn poll_load_block(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), BGZFError>> {
+        loop {
+            while self.filled < self.needed {
+                let mut buf = ReadBuf::new(&mut self.compressed_buffer[self.filled..self.needed]);
+                ready!(Pin::new(&mut self.reader).poll_read(cx, &mut buf))?;
+                let read_bytes = buf.filled().len();
+                if read_bytes == 0 {
+                    if self.filled == 0 {
+                        // reached end of file at block boundary
+                        self.eof = true;
+                        self.current_block = self.next_block;
+                        self.current_buffer.clear();
+                        self.current_position_in_block = 0;
+                        return Poll::Ready(Ok(()));
+                    }
+                    return Poll::Ready(Err(BGZFError::TruncatedBlock));
+                }
+                self.filled += read_bytes;
+            }
+
+            if self.needed == FIXED_HEADER_SIZE {
+                // extra fields including block size
+                let extra_len =
+                    u16::from_le_bytes([self.compressed_buffer[10], self.compressed_buffer[11]]);
+                if extra_len == 0 {
+                    return Poll::Ready(Err(BGZFError::NotBGZF));
+                }
+                self.needed += usize::from(extra_len);
+            } else {
+                let header = parse_header(&self.compressed_buffer[..self.filled])?;
+                if header.block_size > self.filled {
+                    self.needed = header.block_size;
+                } else {
+                    self.current_buffer.clear();
+                    self.current_position_in_block = 0;
+                    decode_block(
+                        &self.compressed_buffer[..self.filled],
+                        &mut self.decompress,
+                        &mut self.current_buffer,
+                    )?;
+                    self.current_block = self.next_block;
+                    self.next_block += u64::try_from(self.filled).unwrap();
+                    self.filled = 0;
+                    self.needed = FIXED_HEADER_SIZE;
+                    return Poll::Ready(Ok(()));
+                }
+            }
+            self.compressed_buffer.resize(self.needed, 0);
+        }
+    }
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin> BGZFAsyncReader<R> {
+    /// Seek to BGZF virtual file offset `position`. See [`crate::BGZFReader::bgzf_seek`].
+    ///
+    /// The block at `position` is loaded before this function returns.
+    pub async fn bgzf_seek<P: Into<VirtualOffset>>(
+        &mut self,
+        position: P,
+    ) -> Result<(), BGZFError> {
+        let position = position.into();
+        self.reader
+            .seek(io::SeekFrom::Start(position.compressed_offset()))
+            .await?;
+        self.filled = 0;
+        self.needed = FIXED_HEADER_SIZE;
+        self.eof = false;
+        self.next_block = position.compressed_offset();
+        std::future::poll_fn(|cx| self.poll_load_block(cx)).await?;
+        self.current_position_in_block = position.uncompressed_offset().into();
+        Ok(())
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncBufRead for BGZFAsyncReader<R> {
+    fn poll_fill_buf(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<&[u8]>> {
+        let this = self.get_mut();
+        while this.current_position_in_block >= this.current_buffer.len() && !this.eof {
+            ready!(this.poll_load_block(cx)).map_err(|e| e.into_io_error())?;
+        }
+        Poll::Ready(Ok(&this.current_buffer[this
+            .current_position_in_block
+            .min(this.current_buffer.len())..]))
+    }
+
+    fn consume(self: Pin<&mut Self>, amt: usize) {
+        let this = self.get_mut();
+        this.current_position_in_block =
+            (this.current_position_in_block + amt).min(this.current_buffer.len());
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for BGZFAsyncReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        let data = ready!(self.as_mut().poll_fill_buf(cx))?;
+        let bytes_to_copy = data.len().min(buf.remaining());
+        buf.put_slice(&data[..bytes_to_copy]);
+        self.consume(bytes_to_copy);
+        Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use tokio::io::{AsyncBufReadExt, AsyncReadExt};
+
+    /// Reader which returns at most `unit` bytes for each read
+    struct SlowReader<'a> {
+        data: &'a [u8],
+        unit: usize,
+    }
+
+    impl<'a> AsyncRead for SlowReader<'a> {
+        fn poll_read(
+            mut self: Pin<&mut Self>,
+            _cx: &mut Context<'_>,
+            buf: &mut ReadBuf<'_>,
+        ) -> Poll<io::Result<()>> {
+            let len = self.unit.min(self.data.len()).min(buf.remaining());
+            buf.put_slice(&self.data[..len]);
+            self.data = &self.data[len..];
+            Poll::Ready(Ok(()))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_reader() -> anyhow::Result<()> {
+        let compressed = std::fs::read("testfiles/generated.bed.gz")?;
+        let mut expected = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut expected)?;
+
+        let mut reader = BGZFAsyncReader::new(&compressed[..]);
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data).await?;
+        assert!(data == expected);
+
+        let reader = BGZFAsyncReader::new(SlowReader {
+            data: &compressed,
+            unit: 7,
+        });
+        let mut lines = reader.lines();
+        let mut expected_lines = expected.split(|x| *x == b'\n');
+        while let Some(line) = lines.next_line().await? {
+            assert_eq!(
+                line.trim_end_matches('\r').as_bytes(),
+                expected_lines
+                    .next()
+                    .unwrap()
+                    .strip_suffix(b"\r")
+                    .unwrap_or_default()
+            );
+        }
+
+        let mut reader = BGZFAsyncReader::new(&compressed[..1000]);
+        assert!(reader.read_to_end(&mut Vec::new()).await.is_err());
+
+        Ok(())
+    }
+}
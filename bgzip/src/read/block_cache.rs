@@ -0,0 +1,94 @@
+use std::collections::HashMap;
+use std::io;
+
+/// LRU cache of decompressed blocks keyed by file offsets of blocks. See [`super::BGZFReader::with_block_cache`].
+pub(crate) struct BlockCache<R> {
+    capacity: usize,
+    blocks: HashMap<u64, CachedBlock>,
+    tick: u64,
+    /// Seek of the underlying reader, which is deferred while cached blocks are used
+    seek: fn(&mut R, u64) -> io::Result<u64>,
+    /// `true` if the underlying reader is not at the offset of the next block
+    stale: bool,
+}
+
+struct CachedBlock {
+    data: Vec<u8>,
+    /// Size of the compressed block
+    size: u64,
+    last_used: u64,
+}
+
+impl<R> BlockCache<R> {
+    pub(crate) fn new(capacity: usize, seek: fn(&mut R, u64) -> io::Result<u64>) -> Self {
+        BlockCache {
+            capacity,
+            blocks: HashMap::with_capacity(capacity),
+            tick: 0,
+            seek,
+            stale: false,
+        }
+    }
+
+    /// Maximum number of cached blocks
+    pub(crate) fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Copy the cached block at file offset `block` into `buffer`, and return the size of the compressed block.
+    ///
+    /// The underlying reader is marked to be moved before the next read.
+    pub(crate) fn load(&mut self, block: u64, buffer: &mut Vec<u8>) -> Option<u64> {
+        self.tick += 1;
+        let cached = self.blocks.get_mut(&block)?;
+        cached.last_used = self.tick;
+        buffer.clear();
+        buffer.extend_from_slice(&cached.data);
+        self.stale = true;
+        Some(cached.size)
+    }
+
+    /// Add a decompressed block. The least recently used block is removed if the cache is full.
+    pub(crate) fn insert(&mut self, block: u64, data: &[u8], size: u64) {
+        if !self.blocks.contains_key(&block) && self.blocks.len() >= self.capacity {
+            if let Some(oldest) = self
+                .blocks
+                .iter()
+                .min_by_key(|(_, x)| x.last_used)
+                .map(|(x, _)| *x)
+            {
+                self.blocks.remove(&oldest);
+            }
+        }
+        self.tick += 1;
+        self.blocks.insert(
+            block,
+            CachedBlock {
+                data: data.to_vec(),
+                size,
+                last_used: self.tick,
+            },
+        );
+    }
+
+    /// Defer the seek of the underlying reader until a block which is not cached is read.
+    pub(crate) fn invalidate_position(&mut self) {
+        self.stale = true;
+    }
+
+    /// Mark the underlying reader is at the offset of the next block.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn sync_position(&mut self) {
+        self.stale = false;
+    }
+
+    /// Move the underlying reader to `block` if cached blocks were used. Returns `true` if the reader was moved.
+    pub(crate) fn prepare_read(&mut self, reader: &mut R, block: u64) -> io::Result<bool> {
+        if !self.stale {
+            return Ok(false);
+        }
+        (self.seek)(reader, block)?;
+        self.stale = false;
+        Ok(true)
+    }
+}
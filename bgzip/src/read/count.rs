@@ -0,0 +1,94 @@
+use super::{decompress_block, load_block};
+use crate::deflate::Decompress;
+use crate::BGZFError;
+use rayon::prelude::*;
+use std::io::{self, BufRead};
+use std::path::Path;
+
+/// Number of blocks loaded at once per thread
+const BLOCKS_PER_THREAD: usize = 16;
+
+/// Count lines of BGZF file with decompressing blocks in parallel.
+///
+/// A last line without new line character is also counted.
+/// `threads` is the number of worker threads. If `threads` is 0, rayon's default value is used.
+pub fn count_lines<P: AsRef<Path>>(path: P, threads: usize) -> Result<u64, BGZFError> {
+    let pool = rayon::ThreadPoolBuilder::new()
+        .num_threads(threads)
+        .build()
+        .map_err(|_| BGZFError::Other("Failed to create thread pool"))?;
+    let mut reader = io::BufReader::new(std::fs::File::open(path)?);
+    let batch_size = pool.current_num_threads() * BLOCKS_PER_THREAD;
+
+    let mut lines = 0;
+    let mut last_byte = None;
+    let mut blocks: Vec<Vec<u8>> = Vec::with_capacity(batch_size);
+    loop {
+        blocks.clear();
+        while blocks.len() < batch_size && !reader.fill_buf()?.is_empty() {
+            let mut block = Vec::new();
+            load_block(&mut reader, &mut block)?;
+            blocks.push(block);
+        }
+        if blocks.is_empty() {
+            break;
+        }
+
+        let counts = pool.install(|| {
+            blocks
+                .par_iter()
+                .map_init(
+                    || (Decompress::new(), Vec::new()),
+                    |(decompress, buffer), block| -> Result<(u64, Option<u8>), BGZFError> {
+                        buffer.clear();
+                        decompress_block(buffer, block, decompress)?;
+                        let newlines = memchr::memchr_iter(b'\n', buffer).count();
+                        Ok((newlines.try_into().unwrap(), buffer.last().copied()))
+                    },
+                )
+                .collect::<Result<Vec<_>, _>>()
+        })?;
+        for (newlines, block_last_byte) in counts {
+            lines += newlines;
+            if block_last_byte.is_some() {
+                last_byte = block_last_byte;
+            }
+        }
+    }
+
+    // a line spanning to the end of file without new line character
+    if last_byte.is_some() && last_byte != Some(b'\n') {
+        lines += 1;
+    }
+
+    Ok(lines)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_count_lines() -> anyhow::Result<()> {
+        for path in [
+            "testfiles/common_all_20180418_half.vcf.gz",
+            "testfiles/generated.bed.gz",
+        ] {
+            let expected = io::BufReader::new(flate2::read::MultiGzDecoder::new(
+                std::fs::File::open(path)?,
+            ))
+            .split(b'\n')
+            .count();
+            assert_eq!(count_lines(path, 4)?, u64::try_from(expected)?);
+            assert_eq!(count_lines(path, 1)?, u64::try_from(expected)?);
+        }
+
+        let path = "tmp/count_lines_no_last_newline.txt.gz";
+        let mut writer = crate::BGZFWriter::new(std::fs::File::create(path)?, Default::default());
+        io::Write::write_all(&mut writer, b"a\nb\nc")?;
+        writer.close()?;
+        assert_eq!(count_lines(path, 0)?, 3);
+
+        Ok(())
+    }
+}
@@ -1,14 +1,31 @@
 //! BGZF reader
 
+#[cfg(feature = "tokio")]
+mod async_reader;
+mod block_cache;
+#[cfg(feature = "rayon")]
+mod count;
+mod recovery;
+#[cfg(feature = "std_thread")]
+mod std_thread;
 #[cfg(feature = "rayon")]
 mod thread;
 
+#[cfg(feature = "tokio")]
+pub use async_reader::BGZFAsyncReader;
+#[cfg(feature = "rayon")]
+pub use count::count_lines;
+pub use recovery::BGZFRecoveryReader;
+#[cfg(feature = "std_thread")]
+pub use std_thread::BGZFThreadReader;
 #[cfg(feature = "rayon")]
 pub use thread::BGZFMultiThreadReader;
 
 use crate::deflate::*;
 use crate::index::BGZFIndex;
-use crate::{header::BGZFHeader, BGZFError};
+use crate::{header::BGZFHeader, BGZFError, Limits, VirtualOffset};
+use block_cache::BlockCache;
+use std::collections::VecDeque;
 use std::convert::TryInto;
 use std::io::{self, prelude::*};
 use std::path::Path;
@@ -16,8 +33,8 @@ use std::path::Path;
 enum AdaptiveReader<R: BufRead> {
     Plain(R),
     #[cfg(feature = "flate2")]
-    Gzip(io::BufReader<flate2::read::MultiGzDecoder<R>>),
-    Bgzip(BGZFReader<R>),
+    Gzip(Box<io::BufReader<flate2::read::MultiGzDecoder<R>>>),
+    Bgzip(Box<BGZFReader<R>>),
 }
 
 impl<R: BufRead> Read for AdaptiveReader<R> {
@@ -67,15 +84,15 @@ pub fn open<P: AsRef<Path>>(path: P) -> io::Result<impl BufRead> {
 pub fn new_reader<R: BufRead>(mut reader: R) -> Result<impl BufRead, BGZFError> {
     let magics = reader.fill_buf()?;
     if magics[0] == crate::header::GZIP_ID1 && magics[1] == crate::header::GZIP_ID2 {
-        if let Ok(header) = crate::header::BGZFHeader::from_reader(&magics[..]) {
+        if let Ok(header) = crate::header::BGZFHeader::from_reader(magics) {
             if header.block_size().is_ok() {
-                return Ok(AdaptiveReader::Bgzip(BGZFReader::new(reader)?));
+                return Ok(AdaptiveReader::Bgzip(Box::new(BGZFReader::new(reader)?)));
             }
         }
         #[cfg(feature = "flate2")]
-        return Ok(AdaptiveReader::Gzip(io::BufReader::new(
+        return Ok(AdaptiveReader::Gzip(Box::new(io::BufReader::new(
             flate2::read::MultiGzDecoder::new(reader),
-        )));
+        ))));
         #[cfg(not(feature = "flate2"))]
         return Err(crate::error::BGZFError::Other(
             "Standard gzip is not supported",
@@ -85,6 +102,32 @@ pub fn new_reader<R: BufRead>(mut reader: R) -> Result<impl BufRead, BGZFError>
     }
 }
 
+/// Check whether a stream starts with a BGZF block header, which has `BC` extra subfield.
+///
+/// Data is peeked with [`BufRead::fill_buf`], so nothing is consumed from `reader`.
+pub fn is_bgzf<R: BufRead>(reader: &mut R) -> io::Result<bool> {
+    Ok(crate::block::parse_header(reader.fill_buf()?).is_ok())
+}
+
+/// Check whether a file ends with BGZF end-of-file marker ([`crate::EOF_MARKER`]).
+///
+/// A file without the marker may be truncated. The position of `reader` is restored after the check.
+pub fn has_eof_marker<R: Read + Seek>(reader: &mut R) -> io::Result<bool> {
+    let original_position = reader.stream_position()?;
+    let length = reader.seek(io::SeekFrom::End(0))?;
+    let marker_length: u64 = crate::EOF_MARKER.len().try_into().unwrap();
+    let result = if length < marker_length {
+        false
+    } else {
+        let mut buffer = [0u8; 28];
+        reader.seek(io::SeekFrom::Start(length - marker_length))?;
+        reader.read_exact(&mut buffer)?;
+        buffer == crate::EOF_MARKER
+    };
+    reader.seek(io::SeekFrom::Start(original_position))?;
+    Ok(result)
+}
+
 /// Load single block from reader.
 ///
 /// This function is useful when writing your own parallelized BGZF reader.
@@ -94,18 +137,32 @@ pub fn load_block<R: Read>(mut reader: R, buffer: &mut Vec<u8>) -> Result<BGZFHe
     let block_size: u64 = header.block_size()?.into();
     buffer.clear();
     buffer.resize((block_size - header.header_size()).try_into().unwrap(), 0);
-    reader.read_exact(buffer)?;
+    reader.read_exact(buffer).map_err(|e| match e.kind() {
+        io::ErrorKind::UnexpectedEof => BGZFError::TruncatedBlock,
+        _ => e.into(),
+    })?;
 
     Ok(header)
 }
 
 /// Decompress single BGZF block from buffer. The buffer should be loaded with [`load_block`] function.
 ///
-/// This function is useful when writing your own parallelized BGZF reader.
+/// CRC32 and size of decompressed data are verified, and [`BGZFError::CorruptedBlock`] is returned if they
+/// do not match with the footer. This function is useful when writing your own parallelized BGZF reader.
 pub fn decompress_block(
     decompressed_data: &mut Vec<u8>,
     compressed_block: &[u8],
     decompress: &mut Decompress,
+) -> Result<(), BGZFError> {
+    decompress_block_with_check(decompressed_data, compressed_block, decompress, true)
+}
+
+/// Decompress single BGZF block. CRC32 is verified only if `check_crc` is `true`.
+fn decompress_block_with_check(
+    decompressed_data: &mut Vec<u8>,
+    compressed_block: &[u8],
+    decompress: &mut Decompress,
+    check_crc: bool,
 ) -> Result<(), BGZFError> {
     let original_decompress_data_len = decompressed_data.len();
     let mut crc = Crc::new();
@@ -119,10 +176,19 @@ pub fn decompress_block(
     let expected_len: usize = u32::from_le_bytes(expected_len_data).try_into().unwrap();
     decompressed_data.resize(original_decompress_data_len + expected_len, 0);
 
-    decompress.decompress(
+    let decompressed_len = decompress.decompress(
         compressed_block,
         &mut decompressed_data[original_decompress_data_len..],
     )?;
+    if decompressed_len != expected_len {
+        return Err(BGZFError::CorruptedBlock {
+            offset: None,
+            reason: "unmatched size of decompressed data",
+        });
+    }
+    if !check_crc {
+        return Ok(());
+    }
 
     let expected_crc_data = [
         compressed_block[compressed_block.len() - 8],
@@ -134,15 +200,32 @@ pub fn decompress_block(
     let expected_crc = u32::from_le_bytes(expected_crc_data);
     crc.update(&decompressed_data[original_decompress_data_len..]);
     if expected_crc != crc.sum() {
-        return Err(BGZFError::Other("unmatched CRC32 of decompressed data"));
+        return Err(BGZFError::CorruptedBlock {
+            offset: None,
+            reason: "unmatched CRC32 of decompressed data",
+        });
     }
 
     Ok(())
 }
 
+/// Check uncompressed size of a block loaded with [`load_block`] before decompression.
+fn check_block_size(compressed_block: &[u8], limits: &Limits) -> Result<(), BGZFError> {
+    let footer = crate::block::parse_footer(compressed_block)?;
+    if footer.isize > limits.max_block_size {
+        return Err(BGZFError::LimitExceeded(
+            "Too large uncompressed block size",
+        ));
+    }
+    Ok(())
+}
+
 /// A BGZF reader
 ///
 /// Decode BGZF file with seek support.
+///
+/// [`BufRead`] is implemented on decompressed blocks, so this reader can be passed to parsers
+/// which require [`BufRead`] without wrapping in [`io::BufReader`].
 pub struct BGZFReader<R: Read> {
     reader: R,
     decompress: Decompress,
@@ -152,75 +235,587 @@ pub struct BGZFReader<R: Read> {
     next_block: u64,
     current_position_in_block: usize,
     eof_pos: u64,
+    peeked_blocks: VecDeque<PeekedBlock>,
+    peek_error: Option<BGZFError>,
+    peek_buffer: Vec<u8>,
+    limits: Limits,
+    index: Option<BGZFIndex>,
+    current_block_uncompressed_pos: Option<u64>,
+    check_crc: bool,
+    stats: ReadStats,
+    cache: Option<BlockCache<R>>,
+    readahead: usize,
+}
+
+/// Counters of blocks and bytes read by [`BGZFReader`]. See [`BGZFReader::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadStats {
+    /// Number of decompressed blocks
+    pub blocks_read: u64,
+    /// Bytes of compressed blocks including headers
+    pub compressed_bytes: u64,
+    /// Bytes of decompressed data
+    pub uncompressed_bytes: u64,
+    /// Number of seeks in the underlying reader
+    pub seeks: u64,
+    /// Number of blocks loaded from the block cache. See [`BGZFReader::with_block_cache`].
+    pub cache_hits: u64,
+    /// Number of blocks which are not found in the block cache
+    pub cache_misses: u64,
+}
+
+impl ReadStats {
+    fn add_block(&mut self, compressed_bytes: u64, uncompressed_bytes: usize) {
+        self.blocks_read += 1;
+        self.compressed_bytes += compressed_bytes;
+        self.uncompressed_bytes += TryInto::<u64>::try_into(uncompressed_bytes).unwrap();
+    }
+}
+
+/// Decompressed blocks of a range and the file offset following the last block
+#[cfg(feature = "rayon")]
+pub(crate) type DecompressedRange = (Vec<(u64, Vec<u8>)>, u64);
+
+/// A block loaded ahead by peek methods
+struct PeekedBlock {
+    block: u64,
+    next_block: u64,
+    data: Vec<u8>,
 }
 
 impl<R: Read + Seek> BGZFReader<R> {
     /// Seek BGZF with position. This position is not equal to real file offset,
     /// but equal to virtual file offset described in [BGZF format](https://samtools.github.io/hts-specs/SAMv1.pdf).
     /// Please read "4.1.1 Random access" to learn more.
-    pub fn bgzf_seek(&mut self, position: u64) -> Result<(), BGZFError> {
-        self.next_block = position >> 16;
-        self.reader.seek(io::SeekFrom::Start(self.next_block))?;
+    pub fn bgzf_seek<P: Into<VirtualOffset>>(&mut self, position: P) -> Result<(), BGZFError> {
+        let position = position.into();
+        self.next_block = position.compressed_offset();
+        self.peeked_blocks.clear();
+        self.peek_error = None;
+        match self.cache.as_mut() {
+            Some(cache) => cache.invalidate_position(),
+            None => {
+                self.reader.seek(io::SeekFrom::Start(self.next_block))?;
+                self.stats.seeks += 1;
+            }
+        }
+        self.current_block_uncompressed_pos = None;
         self.load_next()?;
-        self.current_position_in_block = (position & 0xffff) as usize;
+        self.read_ahead(self.readahead);
+        self.current_position_in_block = position.uncompressed_offset().into();
+        self.current_block_uncompressed_pos = if self.current_block == 0 {
+            Some(0)
+        } else {
+            self.index.as_ref().and_then(|x| {
+                x.bgzf_pos_to_uncompressed_pos(self.current_block << 16)
+                    .ok()
+            })
+        };
+
+        Ok(())
+    }
+
+    /// Keep up to `capacity` decompressed blocks in a LRU cache keyed by file offsets of blocks.
+    ///
+    /// Repeated queries in nearby regions, like a genome browser, load cached blocks
+    /// without reading and decompressing them again. Hits and misses are counted in [`BGZFReader::stats`].
+    /// Each cached block uses up to 64KiB of memory.
+    pub fn with_block_cache(mut self, capacity: usize) -> Self {
+        self.cache = if capacity > 0 {
+            Some(BlockCache::new(capacity, |reader, pos| {
+                reader.seek(io::SeekFrom::Start(pos))
+            }))
+        } else {
+            None
+        };
+        self
+    }
+
+    /// Create a reader of another handle to the same data with the options of this reader.
+    ///
+    /// Limits, CRC check, readahead, .gzi index and capacity of the block cache are copied.
+    /// Cached blocks and counters are not shared.
+    pub(crate) fn reopen<R2: Read + Seek>(&self, reader: R2) -> Result<BGZFReader<R2>, BGZFError> {
+        let mut reopened = BGZFReader::with_limits(reader, self.limits)?
+            .with_crc_check(self.check_crc)
+            .with_readahead(self.readahead)
+            .with_block_cache(self.cache.as_ref().map(|x| x.capacity()).unwrap_or(0));
+        reopened.index = self.index.clone();
+        Ok(reopened)
+    }
 
+    /// Read and decompress up to `blocks` blocks following the current block right after each seek.
+    ///
+    /// Region scans are mostly sequential after the initial jump, so the following blocks are
+    /// loaded in one pass instead of being read on demand between consumer calls.
+    /// Readahead is disabled by default.
+    pub fn with_readahead(mut self, blocks: usize) -> Self {
+        self.readahead = blocks;
+        self
+    }
+
+    /// Seek BGZF with uncompressed position using .gzi index.
+    ///
+    /// The index can be loaded with [`BGZFIndex::from_reader`] or built with [`BGZFIndex::from_bgzf`].
+    pub fn seek_uncompressed(&mut self, index: &BGZFIndex, position: u64) -> Result<(), BGZFError> {
+        self.bgzf_seek(index.uncompressed_pos_to_bgzf_pos(position)?)
+    }
+
+    /// Use .gzi index for [`Seek`] of uncompressed position.
+    ///
+    /// Without index, seek scans blocks from the current position or the beginning of the file.
+    pub fn with_index(mut self, index: BGZFIndex) -> Self {
+        self.index = Some(index);
+        self
+    }
+
+    /// Move to uncompressed position `position` by decompressing blocks from the current block
+    /// or the beginning of the file.
+    fn scan_to(&mut self, position: u64) -> Result<(), BGZFError> {
+        let block_pos = match self.current_block_uncompressed_pos {
+            Some(block_pos) if block_pos <= position => block_pos,
+            _ => {
+                self.bgzf_seek(0)?;
+                0
+            }
+        };
+        let mut block_pos = block_pos;
+        while position - block_pos >= TryInto::<u64>::try_into(self.current_buffer.len()).unwrap() {
+            if self.current_buffer.is_empty() {
+                if position == block_pos {
+                    // end of file
+                    break;
+                }
+                return Err(BGZFError::IoError(io::ErrorKind::UnexpectedEof.into()));
+            }
+            self.current_position_in_block = self.current_buffer.len();
+            self.load_next()?;
+            block_pos = self
+                .current_block_uncompressed_pos
+                .expect("Unreachable (BGZFReader::scan_to)");
+        }
+        self.current_position_in_block = (position - block_pos).try_into().unwrap();
         Ok(())
     }
+
+    /// Uncompressed length of the whole file.
+    fn uncompressed_len(&mut self) -> Result<u64, BGZFError> {
+        if let Some(last_entry) = self.index.as_ref().and_then(|x| x.entries.last().copied()) {
+            if self.current_block_uncompressed_pos < Some(last_entry.uncompressed_offset) {
+                self.bgzf_seek(VirtualOffset::new(last_entry.compressed_offset, 0))?;
+            }
+        }
+        if self.current_block_uncompressed_pos.is_none() {
+            self.bgzf_seek(0)?;
+        }
+        while !self.current_buffer.is_empty() {
+            self.current_position_in_block = self.current_buffer.len();
+            self.load_next()?;
+        }
+        Ok(self
+            .current_block_uncompressed_pos
+            .expect("Unreachable (BGZFReader::uncompressed_len)"))
+    }
+
+    /// Decompress blocks whose file offsets are in `begin..=end` of each range with rayon's global thread pool.
+    ///
+    /// Returns file offsets and decompressed data of blocks, and the file offset following the last block,
+    /// for each range. Compressed blocks are loaded sequentially, and then all blocks are decompressed in parallel.
+    /// The position of this reader is not changed.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn decompress_ranges(
+        &mut self,
+        ranges: &[(u64, u64)],
+    ) -> Result<Vec<DecompressedRange>, BGZFError> {
+        use rayon::prelude::*;
+
+        let loaded = self.load_ranges(ranges);
+        // restore the position of the underlying reader after blocks loaded by peek methods
+        let resume = self
+            .peeked_blocks
+            .back()
+            .map(|x| x.next_block)
+            .unwrap_or(self.next_block);
+        self.reader.seek(io::SeekFrom::Start(resume))?;
+        if let Some(cache) = self.cache.as_mut() {
+            cache.sync_position();
+        }
+        let (compressed, next_blocks) = loaded?;
+        for ((begin, _), next_block) in ranges.iter().zip(&next_blocks) {
+            self.stats.seeks += 1;
+            self.stats.compressed_bytes += next_block - begin;
+        }
+
+        let check_crc = self.check_crc;
+        let decompressed = compressed
+            .into_par_iter()
+            .map_init(Decompress::new, |decompress, (i, block, data)| {
+                let mut buffer = Vec::new();
+                decompress_block_with_check(&mut buffer, &data, decompress, check_crc)
+                    .map_err(|e| e.with_block_offset(block))?;
+                Ok((i, block, buffer))
+            })
+            .collect::<Result<Vec<_>, BGZFError>>()?;
+
+        let mut result: Vec<DecompressedRange> =
+            next_blocks.into_iter().map(|x| (Vec::new(), x)).collect();
+        for (i, block, data) in decompressed {
+            self.stats.blocks_read += 1;
+            self.stats.uncompressed_bytes += TryInto::<u64>::try_into(data.len()).unwrap();
+            result[i].0.push((block, data));
+        }
+        Ok(result)
+    }
+
+    /// Load compressed blocks of ranges for [`BGZFReader::decompress_ranges`].
+    #[cfg(feature = "rayon")]
+    #[allow(clippy::type_complexity)]
+    fn load_ranges(
+        &mut self,
+        ranges: &[(u64, u64)],
+    ) -> Result<(Vec<(usize, u64, Vec<u8>)>, Vec<u64>), BGZFError> {
+        let mut compressed = Vec::new();
+        let mut next_blocks = Vec::with_capacity(ranges.len());
+        for (i, (begin, end)) in ranges.iter().enumerate() {
+            self.reader.seek(io::SeekFrom::Start(*begin))?;
+            let mut block = *begin;
+            while block <= *end && block < self.eof_pos {
+                let mut data = Vec::new();
+                let header = match load_block(&mut self.reader, &mut data) {
+                    Ok(header) => header,
+                    Err(BGZFError::IoError(e))
+                        if e.kind() == io::ErrorKind::UnexpectedEof && data.is_empty() =>
+                    {
+                        break;
+                    }
+                    Err(e) => return Err(e),
+                };
+                check_block_size(&data, &self.limits)?;
+                let next_block =
+                    block + TryInto::<u64>::try_into(data.len()).unwrap() + header.header_size();
+                compressed.push((i, block, data));
+                block = next_block;
+            }
+            next_blocks.push(block);
+        }
+        Ok((compressed, next_blocks))
+    }
+
+    /// Create a new BGZF reader positioned at BGZF virtual file offset `position`.
+    pub fn at<P: Into<VirtualOffset>>(reader: R, position: P) -> Result<Self, BGZFError> {
+        let mut reader = BGZFReader::new(reader)?;
+        reader.bgzf_seek(position)?;
+        Ok(reader)
+    }
+}
+
+impl BGZFReader<std::fs::File> {
+    /// Open BGZF file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        BGZFReader::new(std::fs::File::open(path)?)
+    }
 }
 
 impl<R: Read> BGZFReader<R> {
     /// Create a new BGZF reader from [`std::io::Read`]
-    pub fn new(mut reader: R) -> Result<Self, BGZFError> {
+    pub fn new(reader: R) -> Result<Self, BGZFError> {
+        BGZFReader::with_limits(reader, Limits::UNLIMITED)
+    }
+
+    /// Create a new BGZF reader which rejects blocks exceeding `limits`.
+    pub fn with_limits(mut reader: R, limits: Limits) -> Result<Self, BGZFError> {
         let mut decompress = Decompress::new();
         let mut compressed_buffer = Vec::new();
-        load_block(&mut reader, &mut compressed_buffer)?;
+        let header = load_block(&mut reader, &mut compressed_buffer)?;
+        check_block_size(&compressed_buffer, &limits)?;
         let mut buffer = Vec::new();
-        decompress_block(&mut buffer, &compressed_buffer, &mut decompress)?;
+        decompress_block(&mut buffer, &compressed_buffer, &mut decompress)
+            .map_err(|e| e.with_block_offset(0))?;
+        let first_block_size: u64 = compressed_buffer.len().try_into().unwrap();
+        let mut stats = ReadStats::default();
+        stats.add_block(first_block_size + header.header_size(), buffer.len());
 
         Ok(BGZFReader {
             reader,
             decompress,
             current_buffer: buffer,
             current_block: 0,
-            next_block: compressed_buffer.len().try_into().unwrap(),
+            next_block: first_block_size + header.header_size(),
             current_position_in_block: 0,
             eof_pos: u64::MAX,
             compressed_buffer,
+            peeked_blocks: VecDeque::new(),
+            peek_error: None,
+            peek_buffer: Vec::new(),
+            limits,
+            index: None,
+            current_block_uncompressed_pos: Some(0),
+            check_crc: true,
+            stats,
+            cache: None,
+            readahead: 0,
         })
     }
 
+    /// Counters of blocks and bytes read since this reader was created or [`BGZFReader::reset_stats`] was called.
+    pub fn stats(&self) -> ReadStats {
+        self.stats
+    }
+
+    /// Reset counters returned by [`BGZFReader::stats`].
+    pub fn reset_stats(&mut self) {
+        self.stats = ReadStats::default();
+    }
+
+    /// Enable or disable verification of CRC32 of each block. The verification is enabled by default.
+    ///
+    /// Sizes of decompressed data are always verified. Disabling CRC32 check makes decompression
+    /// slightly faster, but corrupted data may be returned silently. The first block is always verified.
+    pub fn with_crc_check(mut self, enabled: bool) -> Self {
+        self.check_crc = enabled;
+        self
+    }
+
     /// Get BGZF virtual file offset. This position is not equal to real file offset,
     /// but equal to virtual file offset described in [BGZF format](https://samtools.github.io/hts-specs/SAMv1.pdf).
     /// Please read "4.1.1 Random access" to learn more.    
-    pub fn bgzf_pos(&self) -> u64 {
-        self.current_block << 16 | (self.current_position_in_block & 0xffff) as u64
+    pub fn bgzf_pos(&self) -> VirtualOffset {
+        VirtualOffset::new(
+            self.current_block,
+            (self.current_position_in_block & 0xffff) as u16,
+        )
     }
 
-    fn load_next(&mut self) -> Result<(), BGZFError> {
-        if self.next_block >= self.eof_pos {
-            return Ok(());
+    /// Read a line including a new line character into `buf` like [`BufRead::read_until`].
+    ///
+    /// [`BGZFError::LimitExceeded`] is returned if the line is longer than
+    /// [`Limits::max_line_length`], before the whole line is loaded into memory.
+    pub fn read_limited_line(&mut self, buf: &mut Vec<u8>) -> Result<usize, BGZFError> {
+        let mut total = 0;
+        loop {
+            let max_line_length = self.limits.max_line_length;
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(total);
+            }
+            let (len, found) = match memchr::memchr(b'\n', available) {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            if total + len > max_line_length {
+                return Err(BGZFError::LimitExceeded("Too long line"));
+            }
+            buf.extend_from_slice(&available[..len]);
+            self.consume(len);
+            total += len;
+            if found {
+                return Ok(total);
+            }
         }
+    }
 
+    /// Iterate lines without new line characters (`\n` or `\r\n`).
+    ///
+    /// The last line is returned even if it does not end with a new line character.
+    /// Lines longer than [`Limits::max_line_length`] are reported as errors.
+    /// Use [`BufRead::lines`] to iterate lines as [`String`].
+    pub fn byte_lines(&mut self) -> ByteLines<'_, R> {
+        ByteLines { reader: self }
+    }
+
+    /// Returns upcoming bytes up to `n` bytes without consuming them.
+    ///
+    /// Returned data is shorter than `n` bytes only when the reader reaches the end of file.
+    pub fn peek(&mut self, n: usize) -> Result<&[u8], BGZFError> {
+        let current = &self.current_buffer[self.current_position_in_block..];
+        if current.len() >= n {
+            return Ok(&self.current_buffer
+                [self.current_position_in_block..(self.current_position_in_block + n)]);
+        }
+        self.fill_peek_buffer(|x| x.len() >= n)?;
+        let len = self.peek_buffer.len().min(n);
+        Ok(&self.peek_buffer[..len])
+    }
+
+    /// Returns the next line including a new line character without consuming it.
+    ///
+    /// An empty slice is returned at the end of file.
+    pub fn peek_line(&mut self) -> Result<&[u8], BGZFError> {
+        let current = &self.current_buffer[self.current_position_in_block..];
+        if let Some(i) = memchr::memchr(b'\n', current).filter(|x| *x < self.limits.max_line_length)
+        {
+            return Ok(&self.current_buffer
+                [self.current_position_in_block..(self.current_position_in_block + i + 1)]);
+        }
+        let max_line_length = self.limits.max_line_length;
+        self.fill_peek_buffer(|x| memchr::memchr(b'\n', x).is_some() || x.len() > max_line_length)?;
+        let len = memchr::memchr(b'\n', &self.peek_buffer)
+            .map(|x| x + 1)
+            .unwrap_or(self.peek_buffer.len());
+        if len > max_line_length {
+            return Err(BGZFError::LimitExceeded("Too long line"));
+        }
+        Ok(&self.peek_buffer[..len])
+    }
+
+    /// Collect remaining data of the current block and following blocks into `peek_buffer`
+    /// until `enough` returns `true` or the reader reaches the end of file.
+    fn fill_peek_buffer(&mut self, enough: impl Fn(&[u8]) -> bool) -> Result<(), BGZFError> {
+        self.peek_buffer.clear();
+        self.peek_buffer
+            .extend_from_slice(&self.current_buffer[self.current_position_in_block..]);
+        for one in &self.peeked_blocks {
+            if enough(&self.peek_buffer) {
+                return Ok(());
+            }
+            self.peek_buffer.extend_from_slice(&one.data);
+        }
+
+        while !enough(&self.peek_buffer) {
+            if !self.peek_next_block()? {
+                break;
+            }
+            let data = &self.peeked_blocks.back().expect("Unreachable").data;
+            self.peek_buffer.extend_from_slice(data);
+        }
+        Ok(())
+    }
+
+    /// Load blocks following the current block until `blocks` blocks are peeked or the reader reaches the end of file.
+    ///
+    /// An error is kept until the failed block is consumed, so that blocks before it can be read.
+    fn read_ahead(&mut self, blocks: usize) {
+        while self.peek_error.is_none() && self.peeked_blocks.len() < blocks {
+            match self.peek_next_block() {
+                Ok(true) => (),
+                Ok(false) => break,
+                Err(e) => self.peek_error = Some(e),
+            }
+        }
+    }
+
+    /// Load the block following the last peeked block into `peeked_blocks`.
+    ///
+    /// Returns `false` at the end of file.
+    fn peek_next_block(&mut self) -> Result<bool, BGZFError> {
+        if let Some(e) = self.peek_error.take() {
+            return Err(e);
+        }
+        let block = self
+            .peeked_blocks
+            .back()
+            .map(|x| x.next_block)
+            .unwrap_or(self.next_block);
+        if block >= self.eof_pos {
+            return Ok(false);
+        }
+        let mut data = Vec::new();
+        let block_size = match self.load_block_at(block, &mut data)? {
+            Some(block_size) => block_size,
+            None => return Ok(false),
+        };
+        self.peeked_blocks.push_back(PeekedBlock {
+            block,
+            next_block: block + block_size,
+            data,
+        });
+        Ok(true)
+    }
+
+    /// Load the block at file offset `block` from the block cache or the underlying reader into `buffer`.
+    ///
+    /// Returns the size of the compressed block, or `None` at the end of file.
+    fn load_block_at(
+        &mut self,
+        block: u64,
+        buffer: &mut Vec<u8>,
+    ) -> Result<Option<u64>, BGZFError> {
+        if let Some(cache) = self.cache.as_mut() {
+            if let Some(block_size) = cache.load(block, buffer) {
+                self.stats.cache_hits += 1;
+                return Ok(Some(block_size));
+            }
+            self.stats.cache_misses += 1;
+            if cache.prepare_read(&mut self.reader, block)? {
+                self.stats.seeks += 1;
+            }
+        }
+        let block_size = self.read_block(block, buffer)?;
+        if let (Some(cache), Some(block_size)) = (self.cache.as_mut(), block_size) {
+            cache.insert(block, buffer, block_size);
+        }
+        Ok(block_size)
+    }
+
+    /// Load and decompress the next block at file offset `block` from the underlying reader into `buffer`.
+    ///
+    /// Returns the size of the compressed block, or `None` at the end of file.
+    fn read_block(&mut self, block: u64, buffer: &mut Vec<u8>) -> Result<Option<u64>, BGZFError> {
         self.compressed_buffer.clear();
-        let header = load_block(&mut self.reader, &mut self.compressed_buffer)?;
-        let header_size = header.header_size();
-        if self.compressed_buffer == crate::EOF_MARKER {
-            self.eof_pos = self.next_block;
-            self.current_buffer.clear();
-            self.current_block = self.next_block;
+        let header = match load_block(&mut self.reader, &mut self.compressed_buffer) {
+            Ok(header) => header,
+            Err(BGZFError::IoError(e))
+                if e.kind() == io::ErrorKind::UnexpectedEof
+                    && self.compressed_buffer.is_empty() =>
+            {
+                // reached end of file at block boundary
+                return Ok(None);
+            }
+            Err(e) => return Err(e),
+        };
+
+        check_block_size(&self.compressed_buffer, &self.limits)?;
+        buffer.clear();
+        decompress_block_with_check(
+            buffer,
+            &self.compressed_buffer,
+            &mut self.decompress,
+            self.check_crc,
+        )
+        .map_err(|e| e.with_block_offset(block))?;
+        let block_size: u64 = self.compressed_buffer.len().try_into().unwrap();
+        self.stats
+            .add_block(block_size + header.header_size(), buffer.len());
+        Ok(Some(block_size + header.header_size()))
+    }
+
+    fn load_next(&mut self) -> Result<(), BGZFError> {
+        let previous_block_len: u64 = self.current_buffer.len().try_into().unwrap();
+        if let Some(pos) = self.current_block_uncompressed_pos.as_mut() {
+            *pos += previous_block_len;
+        }
+        if let Some(peeked) = self.peeked_blocks.pop_front() {
+            self.current_buffer = peeked.data;
+            self.current_block = peeked.block;
+            self.next_block = peeked.next_block;
             self.current_position_in_block = 0;
             return Ok(());
         }
+        if let Some(e) = self.peek_error.take() {
+            self.current_block_uncompressed_pos = None;
+            return Err(e);
+        }
+        if self.next_block >= self.eof_pos {
+            return Ok(());
+        }
 
-        self.current_buffer.clear();
-        decompress_block(
-            &mut self.current_buffer,
-            &self.compressed_buffer,
-            &mut self.decompress,
-        )?;
-        self.current_block = self.next_block;
-        let current_block_size: u64 = self.compressed_buffer.len().try_into().unwrap();
-        self.next_block += current_block_size + header_size;
+        let mut buffer = std::mem::take(&mut self.current_buffer);
+        let block_size = self.load_block_at(self.next_block, &mut buffer);
+        self.current_buffer = buffer;
+        if block_size.is_err() {
+            self.current_block_uncompressed_pos = None;
+        }
+        match block_size? {
+            Some(block_size) => {
+                self.current_block = self.next_block;
+                self.next_block += block_size;
+            }
+            None => {
+                self.eof_pos = self.next_block;
+                self.current_buffer.clear();
+                self.current_block = self.next_block;
+            }
+        }
         self.current_position_in_block = 0;
 
         Ok(())
@@ -249,6 +844,26 @@ impl<R: Read> BufRead for BGZFReader<R> {
             unreachable!()
         }
     }
+
+    fn read_until(&mut self, byte: u8, buf: &mut Vec<u8>) -> io::Result<usize> {
+        let mut total = 0;
+        loop {
+            let available = self.fill_buf()?;
+            if available.is_empty() {
+                return Ok(total);
+            }
+            let (len, found) = match memchr::memchr(byte, available) {
+                Some(i) => (i + 1, true),
+                None => (available.len(), false),
+            };
+            buf.extend_from_slice(&available[..len]);
+            self.consume(len);
+            total += len;
+            if found {
+                return Ok(total);
+            }
+        }
+    }
 }
 
 impl<R: Read> Read for BGZFReader<R> {
@@ -263,6 +878,78 @@ impl<R: Read> Read for BGZFReader<R> {
     }
 }
 
+/// An iterator over lines of [`BGZFReader`]. This struct is created by [`BGZFReader::byte_lines`].
+pub struct ByteLines<'a, R: Read> {
+    reader: &'a mut BGZFReader<R>,
+}
+
+impl<'a, R: Read> Iterator for ByteLines<'a, R> {
+    type Item = io::Result<Vec<u8>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let mut line = Vec::new();
+        match self.reader.read_limited_line(&mut line) {
+            Ok(0) => None,
+            Ok(_) => {
+                if line.ends_with(b"\n") {
+                    line.pop();
+                    if line.ends_with(b"\r") {
+                        line.pop();
+                    }
+                }
+                Some(Ok(line))
+            }
+            Err(e) => Some(Err(e.into_io_error())),
+        }
+    }
+}
+
+/// Seek with uncompressed position.
+///
+/// If .gzi index is set with [`BGZFReader::with_index`], the index is used to find a block.
+/// Otherwise, blocks are decompressed from the current block or the beginning of the file.
+/// [`io::SeekFrom::Current`] fails after [`BGZFReader::bgzf_seek`] without index, because
+/// the current uncompressed position is unknown. Seeking beyond the end of file is an error.
+impl<R: Read + Seek> Seek for BGZFReader<R> {
+    fn seek(&mut self, pos: io::SeekFrom) -> io::Result<u64> {
+        let new_pos = match pos {
+            io::SeekFrom::Start(p) => Some(p),
+            io::SeekFrom::Current(p) => self
+                .current_block_uncompressed_pos
+                .and_then(|x| {
+                    x.checked_add(TryInto::<u64>::try_into(self.current_position_in_block).unwrap())
+                })
+                .and_then(|x| x.checked_add_signed(p)),
+            io::SeekFrom::End(p) => self
+                .uncompressed_len()
+                .map_err(|e| e.into_io_error())?
+                .checked_add_signed(p),
+        }
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid or unknown uncompressed position",
+            )
+        })?;
+
+        match self
+            .index
+            .as_ref()
+            .map(|x| x.uncompressed_pos_to_bgzf_pos(new_pos))
+        {
+            Some(bgzf_pos) => {
+                let bgzf_pos = bgzf_pos.map_err(|e| e.into_io_error())?;
+                self.bgzf_seek(bgzf_pos & !0xffff)
+                    .map_err(|e| e.into_io_error())?;
+                self.scan_to(new_pos)
+            }
+            None => self.scan_to(new_pos),
+        }
+        .map_err(|e| e.into_io_error())?;
+        Ok(new_pos)
+    }
+}
+
 /// Seekable BGZF reader.
 pub struct IndexedBGZFReader<R: Read + Seek> {
     reader: BGZFReader<R>,
@@ -277,9 +964,9 @@ impl<R: Read + Seek> IndexedBGZFReader<R> {
         let last_entry = index
             .entries
             .last()
-            .ok_or(BGZFError::Other("Invalid index file"))?
-            .clone();
-        reader.bgzf_seek(last_entry.compressed_offset << 16)?;
+            .copied()
+            .ok_or(BGZFError::BadIndex("Empty index"))?;
+        reader.bgzf_seek(VirtualOffset::new(last_entry.compressed_offset, 0))?;
         let mut buf = Vec::new();
         reader.read_to_end(&mut buf)?;
         reader.bgzf_seek(0)?;
@@ -294,14 +981,20 @@ impl<R: Read + Seek> IndexedBGZFReader<R> {
 }
 
 impl IndexedBGZFReader<std::fs::File> {
-    /// Create new [`IndexedBGZFReader`] from file path.
-    pub fn from_path<P: AsRef<std::path::Path>>(path: P) -> Result<Self, BGZFError> {
-        let reader = BGZFReader::new(std::fs::File::open(path.as_ref())?)?;
-        let index = BGZFIndex::from_reader(std::fs::File::open(
-            path.as_ref()
-                .to_str()
-                .ok_or(BGZFError::PathConvertionError)?,
-        )?)?;
+    /// Create new [`IndexedBGZFReader`] from file path. The index is loaded from `path` + `.gzi`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".gzi");
+        IndexedBGZFReader::from_paths(path, index_path)
+    }
+
+    /// Create new [`IndexedBGZFReader`] from paths of BGZF file and its `.gzi` index.
+    pub fn from_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        index_path: Q,
+    ) -> Result<Self, BGZFError> {
+        let reader = BGZFReader::from_path(path)?;
+        let index = BGZFIndex::from_reader(std::fs::File::open(index_path)?)?;
         IndexedBGZFReader::new(reader, index)
     }
 }
@@ -323,9 +1016,10 @@ impl<R: Read + Seek> Seek for IndexedBGZFReader<R> {
             .bgzf_seek(
                 self.index
                     .uncompressed_pos_to_bgzf_pos(new_pos)
-                    .map_err(|x| Into::<io::Error>::into(x))?,
+                    .map_err(Into::<io::Error>::into)?,
             )
-            .map_err(|x| Into::<io::Error>::into(x))?;
+            .map_err(Into::<io::Error>::into)?;
+        self.current_pos = new_pos;
         Ok(new_pos)
     }
 }
@@ -385,7 +1079,7 @@ mod test {
 
         loop {
             load_block(&mut reader, &mut block_data)?;
-            if block_data == &[3, 0, 0, 0, 0, 0, 0, 0, 0, 0] {
+            if block_data == [3, 0, 0, 0, 0, 0, 0, 0, 0, 0] {
                 break;
             }
 
@@ -429,10 +1123,10 @@ mod test {
         let mut buffer = [0; 30];
 
         reader.bgzf_seek(0)?;
-        assert_eq!(reader.bgzf_pos(), 0);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(0));
 
         reader.bgzf_seek(35973)?;
-        assert_eq!(reader.bgzf_pos(), 35973);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(35973));
         reader.read_exact(&mut buffer)?;
         assert!(
             buffer.starts_with(b"1\t4008153"),
@@ -441,16 +1135,16 @@ mod test {
         );
         //reader.bgzf_seek(reader.cache.get(&0).unwrap().next_block_position() << 16)?;
         reader.bgzf_seek(4210818610)?;
-        assert_eq!(reader.bgzf_pos(), 4210818610);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(4210818610));
         reader.read_exact(&mut buffer)?;
         assert!(buffer.starts_with(b"1\t72700625"));
         //eprintln!("data: {}", String::from_utf8_lossy(&buffer));
         reader.bgzf_seek(9618658636)?;
-        assert_eq!(reader.bgzf_pos(), 9618658636);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(9618658636));
         reader.read_exact(&mut buffer)?;
         assert!(buffer.starts_with(b"1\t"));
         reader.bgzf_seek(135183301012)?;
-        assert_eq!(reader.bgzf_pos(), 135183301012);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(135183301012));
         reader.read_exact(&mut buffer)?;
         assert!(buffer.starts_with(b"11\t"));
 
@@ -458,7 +1152,7 @@ mod test {
         reader.bgzf_seek(0)?;
         reader.read_exact(&mut tmp_buf)?;
         //eprintln!("data: {}", String::from_utf8_lossy(&buffer));
-        assert_eq!(reader.bgzf_pos(), 4210818610);
+        assert_eq!(reader.bgzf_pos(), VirtualOffset::from(4210818610));
         reader.read_exact(&mut buffer)?;
         assert!(
             buffer.starts_with(b"1\t72700625"),
@@ -481,6 +1175,40 @@ mod test {
         data_reader.read_to_end(&mut data)?;
         assert_eq!(data, expected_data);
 
+        // break CRC32 of the second block
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::with_compress_unit_size(
+            &mut compressed,
+            Compression::default(),
+            1000,
+            false,
+        )?;
+        writer.write_all(&expected_data[..3000])?;
+        writer.close()?;
+        let second_block = usize::from(u16::from_le_bytes([compressed[16], compressed[17]])) + 1;
+        let second_block_end = second_block
+            + usize::from(u16::from_le_bytes([
+                compressed[second_block + 16],
+                compressed[second_block + 17],
+            ]))
+            + 1;
+        compressed[second_block_end - 8] ^= 0xff;
+
+        let error = crate::BGZFReader::new(&compressed[..])?
+            .read_to_end(&mut data)
+            .unwrap_err();
+        let error = error.into_inner().unwrap().downcast::<BGZFError>().unwrap();
+        assert!(
+            matches!(*error, BGZFError::CorruptedBlock { offset: Some(offset), .. } if offset == u64::try_from(second_block)?),
+            "{}",
+            error
+        );
+        data.clear();
+        crate::BGZFReader::new(&compressed[..])?
+            .with_crc_check(false)
+            .read_to_end(&mut data)?;
+        assert_eq!(data, &expected_data[..3000]);
+
         Ok(())
     }
 
@@ -505,8 +1233,8 @@ mod test {
             if size == 0 {
                 break;
             }
-            writer.write_all(&line.as_bytes())?;
-            total_len += line.as_bytes().len();
+            writer.write_all(line.as_bytes())?;
+            total_len += line.len();
             line_list.push((bgzf_pos, uncompressed_pos, line.clone()));
         }
         let index = writer.close()?.unwrap();
@@ -530,6 +1258,267 @@ mod test {
         }
 
         assert_eq!(TryInto::<u64>::try_into(total_len).unwrap(), reader.end_pos);
+        reader.seek(std::io::SeekFrom::Start(line_list.last().unwrap().1))?;
+        line.clear();
+        reader.read_line(&mut line)?;
+        assert_eq!(
+            reader.stream_position()?,
+            TryInto::<u64>::try_into(total_len).unwrap()
+        );
+
+        let index = BGZFIndex::from_bgzf(fs::File::open("tmp/test-indexed-reader.bed.gz")?)?;
+        let mut reader = BGZFReader::new(fs::File::open("tmp/test-indexed-reader.bed.gz")?)?;
+        for _ in 0..100 {
+            let i = rand.gen_range(0..line_list.len());
+            reader.seek_uncompressed(&index, line_list[i].1)?;
+            assert_eq!(reader.bgzf_pos(), line_list[i].0);
+            line.clear();
+            reader.read_line(&mut line)?;
+            assert_eq!(line, line_list[i].2);
+        }
+
+        let mut reader = IndexedBGZFReader::from_path("testfiles/generated.bed.gz")?;
+        reader.seek(std::io::SeekFrom::Start(1000))?;
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf)?;
+        let mut expected = [0u8; 100];
+        let mut expected_reader = crate::open("testfiles/generated.bed.gz")?;
+        io::copy(&mut (&mut expected_reader).take(1000), &mut io::sink())?;
+        expected_reader.read_exact(&mut expected)?;
+        assert_eq!(buf, expected);
+
+        let mut reader = IndexedBGZFReader::from_paths(
+            "testfiles/generated.bed.gz",
+            "testfiles/generated.bed.gz.gzi",
+        )?;
+        reader.seek(std::io::SeekFrom::Start(1000))?;
+        reader.read_exact(&mut buf)?;
+        assert_eq!(buf, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_seek() -> anyhow::Result<()> {
+        let mut expected_data = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open("testfiles/generated.bed.gz")?)
+            .read_to_end(&mut expected_data)?;
+        let len: u64 = expected_data.len().try_into()?;
+        let index = BGZFIndex::from_reader(File::open("testfiles/generated.bed.gz.gzi")?)?;
+
+        for with_index in [false, true] {
+            let mut reader = BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?;
+            if with_index {
+                reader = reader.with_index(index.clone());
+            }
+            assert_eq!(reader.seek(io::SeekFrom::End(0))?, len);
+            assert_eq!(reader.read(&mut [0u8; 10])?, 0);
+            assert!(reader.seek(io::SeekFrom::End(1)).is_err());
+
+            let mut rand = rand_pcg::Pcg64Mcg::seed_from_u64(0x3482);
+            let mut buf = [0u8; 100];
+            for _ in 0..50 {
+                let pos = rand.gen_range(0..(len - 100));
+                assert_eq!(reader.seek(io::SeekFrom::Start(pos))?, pos);
+                reader.read_exact(&mut buf)?;
+                let pos: usize = pos.try_into()?;
+                assert_eq!(buf, expected_data[pos..(pos + 100)]);
+                assert_eq!(reader.stream_position()?, u64::try_from(pos + 100)?);
+
+                let back = rand.gen_range(0..=pos);
+                reader.seek(io::SeekFrom::Current(-i64::try_from(back + 100)?))?;
+                reader.read_exact(&mut buf)?;
+                assert_eq!(buf, expected_data[(pos - back)..(pos - back + 100)]);
+            }
+
+            reader.seek(io::SeekFrom::End(-100))?;
+            reader.read_exact(&mut buf)?;
+            assert_eq!(buf, expected_data[(expected_data.len() - 100)..]);
+
+            reader.bgzf_seek(VirtualOffset::new(index.entries()[10].compressed_offset, 0))?;
+            assert_eq!(
+                reader.stream_position().ok(),
+                with_index.then_some(index.entries()[10].uncompressed_offset)
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_bufread() -> anyhow::Result<()> {
+        let mut expected_data = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open("testfiles/generated.bed.gz")?)
+            .read_to_end(&mut expected_data)?;
+
+        fn collect_lines(reader: impl BufRead) -> io::Result<Vec<String>> {
+            reader.lines().collect()
+        }
+        let reader = BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?;
+        let expected_lines: Vec<_> = std::str::from_utf8(&expected_data)?
+            .lines()
+            .map(|x| x.to_string())
+            .collect();
+        assert_eq!(collect_lines(reader)?, expected_lines);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_byte_lines() -> anyhow::Result<()> {
+        let mut expected_data = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open("testfiles/generated.bed.gz")?)
+            .read_to_end(&mut expected_data)?;
+        let expected_lines: Vec<_> = expected_data
+            .strip_suffix(b"\n")
+            .unwrap_or(&expected_data)
+            .split(|x| *x == b'\n')
+            .map(|x| x.strip_suffix(b"\r").unwrap_or(x).to_vec())
+            .collect();
+
+        let mut reader = BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?;
+        let lines = reader.byte_lines().collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(lines, expected_lines);
+
+        // the last line without new line and CRLF
+        let mut data = Vec::new();
+        let mut writer = crate::BGZFWriter::new(&mut data, crate::Compression::default());
+        writer.write_all(b"a\tb\r\n\nc")?;
+        writer.close()?;
+        let mut reader = BGZFReader::new(&data[..])?;
+        let lines = reader.byte_lines().collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(lines, vec![b"a\tb".to_vec(), vec![], b"c".to_vec()]);
+
+        // too long line
+        let mut reader = BGZFReader::with_limits(
+            &data[..],
+            Limits {
+                max_line_length: 2,
+                ..Limits::UNLIMITED
+            },
+        )?;
+        assert!(reader.byte_lines().next().unwrap().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_readahead() -> anyhow::Result<()> {
+        let mut expected_data = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open("testfiles/generated.bed.gz")?)
+            .read_to_end(&mut expected_data)?;
+        let index = BGZFIndex::from_reader(File::open("testfiles/generated.bed.gz.gzi")?)?;
+        let mut reader =
+            BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?.with_readahead(3);
+
+        for entry in [10, 2, 30] {
+            let entry = index.entries()[entry];
+            reader.reset_stats();
+            reader.bgzf_seek(VirtualOffset::new(entry.compressed_offset, 0))?;
+            assert_eq!(reader.stats().blocks_read, 4);
+            assert_eq!(reader.stats().seeks, 1);
+
+            let mut buf = vec![0u8; 300_000];
+            reader.read_exact(&mut buf)?;
+            let begin: usize = entry.uncompressed_offset.try_into()?;
+            assert_eq!(buf, expected_data[begin..(begin + buf.len())]);
+        }
+
+        // near the end of file
+        let last = *index.entries().last().unwrap();
+        reader.bgzf_seek(VirtualOffset::new(last.compressed_offset, 0))?;
+        let mut buf = Vec::new();
+        reader.read_to_end(&mut buf)?;
+        let begin: usize = last.uncompressed_offset.try_into()?;
+        assert_eq!(buf, expected_data[begin..]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_readahead_error() -> anyhow::Result<()> {
+        let mut data = std::fs::read("testfiles/generated.bed.gz")?;
+        let index = BGZFIndex::from_reader(File::open("testfiles/generated.bed.gz.gzi")?)?;
+        let entries = index.entries();
+        // break CRC32 of the block following the first entry
+        let crc_pos: usize = (entries[2].compressed_offset - 8).try_into()?;
+        data[crc_pos] ^= 0xff;
+
+        let mut reader = BGZFReader::new(io::Cursor::new(data))?.with_readahead(3);
+        reader.bgzf_seek(VirtualOffset::new(entries[0].compressed_offset, 0))?;
+        let mut buf = vec![
+            0u8;
+            (entries[1].uncompressed_offset - entries[0].uncompressed_offset)
+                .try_into()?
+        ];
+        reader.read_exact(&mut buf)?;
+        assert!(reader.read(&mut buf).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_peek() -> anyhow::Result<()> {
+        let mut expected_data = Vec::new();
+        flate2::read::MultiGzDecoder::new(File::open("testfiles/generated.bed.gz")?)
+            .read_to_end(&mut expected_data)?;
+
+        let mut reader = BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?;
+        assert_eq!(reader.peek(10)?, &expected_data[..10]);
+        // longer than a block
+        assert_eq!(reader.peek(200_000)?, &expected_data[..200_000]);
+
+        let mut rand = rand_pcg::Pcg64Mcg::seed_from_u64(489);
+        let mut consumed = 0;
+        let mut line = Vec::new();
+        while consumed < expected_data.len() {
+            let pos = reader.bgzf_pos();
+            let peeked_line = reader.peek_line()?.to_vec();
+            let n = rand.gen_range(0..100_000);
+            let peeked = reader.peek(n)?.to_vec();
+            assert_eq!(reader.bgzf_pos(), pos);
+            assert_eq!(
+                peeked,
+                &expected_data[consumed..(consumed + n).min(expected_data.len())]
+            );
+
+            line.clear();
+            reader.read_until(b'\n', &mut line)?;
+            assert_eq!(peeked_line, line);
+            consumed += line.len();
+        }
+        assert_eq!(reader.peek_line()?, b"");
+        assert_eq!(reader.peek(10)?, b"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_is_bgzf() -> anyhow::Result<()> {
+        let mut reader = io::BufReader::new(File::open("testfiles/generated.bed.gz")?);
+        assert!(is_bgzf(&mut reader)?);
+        assert!(has_eof_marker(&mut reader)?);
+        assert_eq!(reader.stream_position()?, 0);
+        assert!(BGZFReader::new(reader).is_ok());
+
+        for path in [
+            "testfiles/common_all_20180418_half.vcf.nobgzip.gz",
+            "testfiles/reg2bin.c",
+        ] {
+            let mut reader = io::BufReader::new(File::open(path)?);
+            assert!(!is_bgzf(&mut reader)?);
+            assert!(!has_eof_marker(&mut reader)?);
+        }
+        assert!(!is_bgzf(&mut &b""[..])?);
+
+        let mut data = Vec::new();
+        File::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+        data.truncate(data.len() - 28);
+        let mut reader = io::Cursor::new(&data);
+        reader.seek(io::SeekFrom::Start(100))?;
+        assert!(!has_eof_marker(&mut reader)?);
+        assert_eq!(reader.position(), 100);
+        assert!(!has_eof_marker(&mut io::Cursor::new(&b"short"[..]))?);
 
         Ok(())
     }
@@ -0,0 +1,224 @@
+use crate::block::{decode_block, MAX_BLOCK_SIZE};
+use crate::deflate::Decompress;
+use crate::BGZFError;
+use std::io::{self, BufRead, Read};
+
+/// First bytes of BGZF block header: gzip magic, deflate method and FEXTRA flag.
+const BLOCK_MAGIC: [u8; 4] = [31, 139, 8, 4];
+
+/// A lenient BGZF reader to salvage data from partially damaged files.
+///
+/// When a block has a broken header or does not match with its CRC32 and size, the reader
+/// scans forward for the next BGZF block header and resumes from there. Skipped byte ranges
+/// are recorded and can be retrieved with [`BGZFRecoveryReader::skipped_ranges`].
+/// Errors of the underlying reader are returned as usual.
+pub struct BGZFRecoveryReader<R: Read> {
+    reader: R,
+    decompress: Decompress,
+    compressed_buffer: Vec<u8>,
+    compressed_buffer_offset: u64,
+    reader_eof: bool,
+    current_buffer: Vec<u8>,
+    current_position_in_block: usize,
+    skipping_from: Option<u64>,
+    skipped_ranges: Vec<(u64, u64)>,
+}
+
+impl<R: Read> BGZFRecoveryReader<R> {
+    /// Create a new recovery reader from [`std::io::Read`]
+    pub fn new(reader: R) -> Self {
+        BGZFRecoveryReader {
+            reader,
+            decompress: Decompress::new(),
+            compressed_buffer: Vec::new(),
+            compressed_buffer_offset: 0,
+            reader_eof: false,
+            current_buffer: Vec::new(),
+            current_position_in_block: 0,
+            skipping_from: None,
+            skipped_ranges: Vec::new(),
+        }
+    }
+
+    /// File offsets [begin, end) of damaged data which were skipped so far.
+    pub fn skipped_ranges(&self) -> &[(u64, u64)] {
+        &self.skipped_ranges
+    }
+
+    /// Unwraps this reader, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+
+    /// Read compressed data until a whole block is available.
+    fn fill_compressed_buffer(&mut self) -> io::Result<()> {
+        let mut buffer = [0u8; 8192];
+        while !self.reader_eof && self.compressed_buffer.len() < MAX_BLOCK_SIZE {
+            match self.reader.read(&mut buffer) {
+                Ok(0) => self.reader_eof = true,
+                Ok(n) => self.compressed_buffer.extend_from_slice(&buffer[..n]),
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => (),
+                Err(e) => return Err(e),
+            }
+        }
+        Ok(())
+    }
+
+    fn consume_compressed(&mut self, length: usize) {
+        self.compressed_buffer.drain(..length);
+        self.compressed_buffer_offset += u64::try_from(length).unwrap();
+    }
+
+    fn finish_skipping(&mut self) {
+        if let Some(begin) = self.skipping_from.take() {
+            debug!(
+                "skipped damaged data: {}-{}",
+                begin, self.compressed_buffer_offset
+            );
+            self.skipped_ranges
+                .push((begin, self.compressed_buffer_offset));
+        }
+    }
+
+    /// Load the next valid block. Returns `false` at the end of file.
+    fn load_next(&mut self) -> io::Result<bool> {
+        loop {
+            self.fill_compressed_buffer()?;
+            if self.compressed_buffer.is_empty() {
+                self.finish_skipping();
+                return Ok(false);
+            }
+
+            self.current_buffer.clear();
+            self.current_position_in_block = 0;
+            match decode_block(
+                &self.compressed_buffer,
+                &mut self.decompress,
+                &mut self.current_buffer,
+            ) {
+                Ok(block_size) => {
+                    self.finish_skipping();
+                    self.consume_compressed(block_size);
+                    return Ok(true);
+                }
+                Err(e) => {
+                    if self.skipping_from.is_none() {
+                        debug!(
+                            "damaged block at {}: {}",
+                            self.compressed_buffer_offset,
+                            BGZFError::from(e)
+                        );
+                        self.skipping_from = Some(self.compressed_buffer_offset);
+                    }
+                    let next_block = self.compressed_buffer[1..]
+                        .windows(BLOCK_MAGIC.len())
+                        .position(|x| x == BLOCK_MAGIC)
+                        .map(|x| x + 1);
+                    let skip_length = match next_block {
+                        Some(next_block) => next_block,
+                        // keep bytes which may be the beginning of a header
+                        None if !self.reader_eof => {
+                            self.compressed_buffer.len() - (BLOCK_MAGIC.len() - 1)
+                        }
+                        None => self.compressed_buffer.len(),
+                    };
+                    self.consume_compressed(skip_length);
+                }
+            }
+        }
+    }
+}
+
+impl<R: Read> BufRead for BGZFRecoveryReader<R> {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.current_position_in_block >= self.current_buffer.len() {
+            if !self.load_next()? {
+                return Ok(&[]);
+            }
+        }
+        Ok(&self.current_buffer[self.current_position_in_block..])
+    }
+
+    fn consume(&mut self, amt: usize) {
+        self.current_position_in_block =
+            (self.current_position_in_block + amt).min(self.current_buffer.len());
+    }
+}
+
+impl<R: Read> Read for BGZFRecoveryReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let internal_buf = self.fill_buf()?;
+        let bytes_to_copy = buf.len().min(internal_buf.len());
+        buf[0..bytes_to_copy].copy_from_slice(&internal_buf[0..bytes_to_copy]);
+        self.consume(bytes_to_copy);
+        Ok(bytes_to_copy)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_recovery_reader() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        File::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+
+        // split into blocks
+        let mut blocks = Vec::new();
+        let mut offset = 0;
+        let mut decompress = Decompress::new();
+        while offset < data.len() {
+            let mut decompressed = Vec::new();
+            let block_size = decode_block(&data[offset..], &mut decompress, &mut decompressed)?;
+            blocks.push((offset, block_size, decompressed));
+            offset += block_size;
+        }
+
+        // break CRC32 of the second block, and insert garbage before the fourth block
+        let mut damaged = data[..blocks[3].0].to_vec();
+        damaged[blocks[1].0 + blocks[1].1 - 8] ^= 0xff;
+        damaged.extend_from_slice(b"garbage\x1f\x8b\x08\x04broken header");
+        let garbage_end = damaged.len();
+        damaged.extend_from_slice(&data[blocks[3].0..]);
+        // truncated block at the end
+        damaged.extend_from_slice(&data[blocks[2].0..(blocks[2].0 + 100)]);
+
+        let mut reader = BGZFRecoveryReader::new(&damaged[..]);
+        let mut actual = Vec::new();
+        reader.read_to_end(&mut actual)?;
+
+        let expected: Vec<u8> = blocks
+            .iter()
+            .enumerate()
+            .filter(|(i, _)| *i != 1)
+            .flat_map(|(_, x)| x.2.iter().copied())
+            .collect();
+        assert!(actual == expected);
+        let to_u64 = |x: usize| u64::try_from(x).unwrap();
+        assert_eq!(
+            reader.skipped_ranges(),
+            &[
+                (to_u64(blocks[1].0), to_u64(blocks[2].0)),
+                (to_u64(blocks[3].0), to_u64(garbage_end)),
+                (
+                    to_u64(garbage_end + data.len() - blocks[3].0),
+                    to_u64(damaged.len())
+                )
+            ]
+        );
+
+        // undamaged file
+        let mut reader = BGZFRecoveryReader::new(&data[..]);
+        actual.clear();
+        reader.read_to_end(&mut actual)?;
+        assert_eq!(
+            actual.len(),
+            blocks.iter().map(|x| x.2.len()).sum::<usize>()
+        );
+        assert!(reader.skipped_ranges().is_empty());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,299 @@
+use std::collections::HashMap;
+use std::io::{self, BufRead, Read};
+use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::thread::JoinHandle;
+
+use crate::deflate::Decompress;
+use crate::BGZFError;
+
+const EOF_BLOCK: [u8; 10] = [3, 0, 0, 0, 0, 0, 0, 0, 0, 0];
+const DEFAULT_PROCESS_BLOCK_NUM: usize = 50;
+
+/// A group of blocks decompressed in a worker thread. Buffers are reused after the data is read.
+struct ReadBlock {
+    index: u64,
+    decompressed_data: Vec<u8>,
+    compressed_data: Vec<Vec<u8>>,
+    /// Number of loaded blocks in `compressed_data`
+    loaded_blocks: usize,
+    decompress: Decompress,
+    error: Option<BGZFError>,
+}
+
+impl ReadBlock {
+    fn new(process_block_num: usize) -> Self {
+        ReadBlock {
+            index: 0,
+            decompressed_data: Vec::with_capacity(crate::write::MAXIMUM_COMPRESS_UNIT_SIZE),
+            compressed_data: vec![
+                Vec::with_capacity(crate::write::MAXIMUM_COMPRESS_UNIT_SIZE);
+                process_block_num
+            ],
+            loaded_blocks: 0,
+            decompress: Decompress::new(),
+            error: None,
+        }
+    }
+
+    fn decompress(&mut self) {
+        self.decompressed_data.clear();
+        self.error = None;
+        for one in &self.compressed_data[..self.loaded_blocks] {
+            if let Err(e) =
+                super::decompress_block(&mut self.decompressed_data, one, &mut self.decompress)
+            {
+                self.error = Some(e);
+                break;
+            }
+        }
+    }
+}
+
+/// A multi-thread BGZF reader which does not depend on rayon.
+///
+/// Decompression runs in worker threads spawned by [`std::thread`], which are stopped when the
+/// reader is dropped. This reader is available with `std_thread` feature, and provides the same
+/// interface as [`super::BGZFMultiThreadReader`].
+///
+/// No block is loaded until the first read, so constructors do not report a corrupt first block.
+/// It is reported by the first read instead.
+pub struct BGZFThreadReader<R: Read> {
+    reader: R,
+    job_sender: Option<Sender<ReadBlock>>,
+    result_receiver: Receiver<ReadBlock>,
+    workers: Vec<JoinHandle<()>>,
+    block_list: Vec<ReadBlock>,
+    process_block_num: usize,
+    current_read_pos: usize,
+    current_read_buffer: Option<ReadBlock>,
+    read_waiting_blocks: HashMap<u64, ReadBlock>,
+    next_read_index: u64,
+    next_decompress_index: u64,
+    eof_read_index: u64,
+}
+
+impl<R: Read> BGZFThreadReader<R> {
+    /// Create new [`BGZFThreadReader`] from `reader` with threads as many as available CPUs.
+    pub fn new(reader: R) -> Result<Self, BGZFError> {
+        let thread_num = std::thread::available_parallelism()
+            .map(|x| x.get())
+            .unwrap_or(1);
+        Self::with_thread_num(reader, thread_num, DEFAULT_PROCESS_BLOCK_NUM)
+    }
+
+    /// Create new [`BGZFThreadReader`] from `reader`, `thread_num` and `process_block_num`.
+    ///
+    /// `thread_num` worker threads are spawned. `process_block_num` is the number blocks to
+    /// dispatch to a worker at once. Default value is 50.
+    pub fn with_thread_num(
+        reader: R,
+        thread_num: usize,
+        process_block_num: usize,
+    ) -> Result<Self, BGZFError> {
+        let thread_num = thread_num.max(1);
+        let process_block_num = process_block_num.max(1);
+        let (job_sender, job_receiver) = channel::<ReadBlock>();
+        let (result_sender, result_receiver) = channel();
+        let job_receiver = Arc::new(Mutex::new(job_receiver));
+
+        let workers = (0..thread_num)
+            .map(|_| {
+                let job_receiver = job_receiver.clone();
+                let result_sender = result_sender.clone();
+                std::thread::spawn(move || loop {
+                    let job = job_receiver.lock().expect("job receiver lock error").recv();
+                    let mut block = match job {
+                        Ok(block) => block,
+                        Err(_) => break,
+                    };
+                    block.decompress();
+                    if result_sender.send(block).is_err() {
+                        break;
+                    }
+                })
+            })
+            .collect();
+
+        let reader = BGZFThreadReader {
+            reader,
+            job_sender: Some(job_sender),
+            result_receiver,
+            workers,
+            block_list: (0..(thread_num * 2))
+                .map(|_| ReadBlock::new(process_block_num))
+                .collect(),
+            process_block_num,
+            current_read_pos: 0,
+            current_read_buffer: None,
+            read_waiting_blocks: HashMap::new(),
+            next_read_index: 0,
+            next_decompress_index: 0,
+            eof_read_index: u64::MAX,
+        };
+        Ok(reader)
+    }
+
+    /// Set the number of block groups which can be decompressed ahead of the consumer.
+    ///
+    /// At most `queue_size` groups of `process_block_num` blocks are loaded and kept in memory,
+    /// so memory usage stays bounded when the consumer is slow. Default value is twice the number of threads.
+    /// This must be called before reading.
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        let process_block_num = self.process_block_num;
+        self.block_list
+            .resize_with(queue_size.max(1), || ReadBlock::new(process_block_num));
+        self
+    }
+
+    fn dispatch_jobs(&mut self) -> Result<(), BGZFError> {
+        while !self.block_list.is_empty() && self.next_decompress_index < self.eof_read_index {
+            let mut block = self.block_list.pop().unwrap();
+            block.index = self.next_decompress_index;
+            self.next_decompress_index += 1;
+
+            block.loaded_blocks = 0;
+            for one in block.compressed_data.iter_mut() {
+                super::load_block(&mut self.reader, one)?;
+                block.loaded_blocks += 1;
+                if one[..] == EOF_BLOCK {
+                    self.eof_read_index = self.next_decompress_index;
+                    break;
+                }
+            }
+
+            self.job_sender
+                .as_ref()
+                .unwrap()
+                .send(block)
+                .map_err(|_| io::Error::other("worker thread stopped"))?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<R: Read> BufRead for BGZFThreadReader<R> {
+    fn consume(&mut self, amt: usize) {
+        self.current_read_pos += amt;
+    }
+
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        if let Some(b) = self.current_read_buffer.as_ref() {
+            if b.decompressed_data.len() <= self.current_read_pos {
+                self.block_list
+                    .push(self.current_read_buffer.take().unwrap());
+            }
+        }
+
+        self.dispatch_jobs().map_err(Into::<io::Error>::into)?;
+
+        if self.current_read_buffer.is_none() {
+            if self.next_read_index >= self.eof_read_index {
+                return Ok(&[]);
+            }
+
+            while !self.read_waiting_blocks.contains_key(&self.next_read_index) {
+                let block = self
+                    .result_receiver
+                    .recv()
+                    .map_err(|_| io::Error::other("worker thread stopped"))?;
+                self.read_waiting_blocks.insert(block.index, block);
+            }
+            let mut block = self
+                .read_waiting_blocks
+                .remove(&self.next_read_index)
+                .unwrap();
+            if let Some(e) = block.error.take() {
+                // stop reading after an error
+                self.eof_read_index = self.next_read_index;
+                self.block_list.push(block);
+                return Err(e.into());
+            }
+            self.current_read_buffer = Some(block);
+            self.current_read_pos = 0;
+            self.next_read_index += 1;
+        }
+
+        Ok(&self.current_read_buffer.as_ref().unwrap().decompressed_data[self.current_read_pos..])
+    }
+}
+
+impl<R: Read> Read for BGZFThreadReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let internal_buf = self.fill_buf()?;
+        let bytes_to_copy = buf.len().min(internal_buf.len());
+        buf[0..bytes_to_copy].copy_from_slice(&internal_buf[0..bytes_to_copy]);
+        self.consume(bytes_to_copy);
+        Ok(bytes_to_copy)
+    }
+}
+
+impl<R: Read> Drop for BGZFThreadReader<R> {
+    fn drop(&mut self) {
+        // workers exit when the job channel is closed
+        self.job_sender.take();
+        for one in self.workers.drain(..) {
+            let _ = one.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_std_thread_read() -> anyhow::Result<()> {
+        let mut expected_reader = flate2::read::MultiGzDecoder::new(std::fs::File::open(
+            "testfiles/common_all_20180418_half.vcf.gz",
+        )?);
+        let mut expected_buf = Vec::new();
+        expected_reader.read_to_end(&mut expected_buf)?;
+
+        for (thread_num, process_block_num) in [(4, DEFAULT_PROCESS_BLOCK_NUM), (2, 1)] {
+            let mut reader = BGZFThreadReader::with_thread_num(
+                std::fs::File::open("testfiles/common_all_20180418_half.vcf.gz")?,
+                thread_num,
+                process_block_num,
+            )?;
+            let mut read_buf = Vec::new();
+            reader.read_to_end(&mut read_buf)?;
+            assert_eq!(expected_buf.len(), read_buf.len());
+            assert!(expected_buf == read_buf);
+        }
+
+        // bounded queue
+        let mut reader = BGZFThreadReader::with_thread_num(
+            std::fs::File::open("testfiles/common_all_20180418_half.vcf.gz")?,
+            4,
+            3,
+        )?
+        .with_queue_size(2);
+        assert_eq!(reader.block_list.len(), 2);
+        let mut read_buf = Vec::new();
+        reader.read_to_end(&mut read_buf)?;
+        assert!(expected_buf == read_buf);
+
+        // drop in the middle of file
+        let mut reader = BGZFThreadReader::new(std::fs::File::open(
+            "testfiles/common_all_20180418_half.vcf.gz",
+        )?)?;
+        let mut small_buf = [0; 100];
+        reader.read_exact(&mut small_buf)?;
+        assert_eq!(&small_buf[..], &expected_buf[..100]);
+        drop(reader);
+
+        // broken CRC32
+        let mut data = Vec::new();
+        std::fs::File::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+        let block_size = usize::from(u16::from_le_bytes([data[16], data[17]])) + 1;
+        data[block_size - 8] ^= 0xff;
+        let mut reader = BGZFThreadReader::with_thread_num(&data[..], 2, 1)?;
+        let mut read_buf = Vec::new();
+        assert!(reader.read_to_end(&mut read_buf).is_err());
+        assert_eq!(reader.read(&mut small_buf)?, 0);
+
+        Ok(())
+    }
+}
@@ -1,6 +1,7 @@
 use std::collections::HashMap;
 use std::io::{BufRead, Read};
 use std::sync::mpsc::{channel, Receiver, Sender};
+use std::sync::Arc;
 
 use crate::deflate::*;
 use crate::rayon::receive_or_yield;
@@ -9,10 +10,13 @@ use crate::BGZFError;
 const EOF_BLOCK: [u8; 10] = [3, 0, 0, 0, 0, 0, 0, 0, 0, 0];
 const DEFAULT_PROCESS_BLOCK_NUM: usize = 50;
 
+/// A group of blocks decompressed in a thread. Buffers are reused after the data is read.
 struct ReadBlock {
     index: u64,
     decompressed_data: Vec<u8>,
     compressed_data: Vec<Vec<u8>>,
+    /// Number of loaded blocks in `compressed_data`
+    loaded_blocks: usize,
     decompress: Decompress,
 }
 
@@ -27,17 +31,30 @@ impl ReadBlock {
                 Vec::with_capacity(crate::write::MAXIMUM_COMPRESS_UNIT_SIZE);
                 process_block_num
             ],
+            loaded_blocks: 0,
             decompress,
         }
     }
 }
 
-/// A Multi-thread BGZF writer.
+/// A Multi-thread BGZF reader.
 ///
 /// [rayon](https://crates.io/crates/rayon) is used to run decompression in a thread pool.
+/// Buffers of compressed and decompressed data are allocated for twice the number of threads
+/// by default, which can be changed with [`BGZFMultiThreadReader::with_queue_size`].
+/// They are recycled after the data is consumed, so no heap allocation is required once
+/// the buffers are grown to the size of blocks.
+///
+/// No block is loaded until the first read, so constructors do not report a corrupt first block.
+/// It is reported by the first read instead.
+///
+/// The global thread pool of rayon is used by default. Use [`BGZFMultiThreadReader::with_thread_pool`]
+/// to run decompression in a thread pool of your application.
 pub struct BGZFMultiThreadReader<R: Read> {
     reader: R,
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
     block_list: Vec<ReadBlock>,
+    process_block_num: usize,
     current_read_pos: usize,
     current_read_buffer: Option<ReadBlock>,
     read_waiting_blocks: HashMap<u64, ReadBlock>,
@@ -59,12 +76,45 @@ impl<R: Read> BGZFMultiThreadReader<R> {
     /// `process_block_num` is the number blocks to dispatch a new thread.
     /// Default value is 50. If you have fast CPU, larger value can be improve efficiency.
     pub fn with_process_block_num(reader: R, process_block_num: usize) -> Result<Self, BGZFError> {
+        Self::with_options(reader, None, process_block_num)
+    }
+
+    /// Create new [`BGZFMultiThreadReader`] which runs decompression in `thread_pool`.
+    ///
+    /// The global thread pool of rayon is never used or initialized by this reader.
+    pub fn with_thread_pool(
+        reader: R,
+        thread_pool: Arc<rayon::ThreadPool>,
+    ) -> Result<Self, BGZFError> {
+        Self::with_thread_pool_and_process_block_num(reader, thread_pool, DEFAULT_PROCESS_BLOCK_NUM)
+    }
+
+    /// Create new [`BGZFMultiThreadReader`] from `reader`, `thread_pool` and `process_block_num`.
+    pub fn with_thread_pool_and_process_block_num(
+        reader: R,
+        thread_pool: Arc<rayon::ThreadPool>,
+        process_block_num: usize,
+    ) -> Result<Self, BGZFError> {
+        Self::with_options(reader, Some(thread_pool), process_block_num)
+    }
+
+    fn with_options(
+        reader: R,
+        thread_pool: Option<Arc<rayon::ThreadPool>>,
+        process_block_num: usize,
+    ) -> Result<Self, BGZFError> {
+        let thread_num = thread_pool
+            .as_ref()
+            .map(|x| x.current_num_threads())
+            .unwrap_or_else(rayon::current_num_threads);
         let (tx, rx) = channel();
-        let mut reader = BGZFMultiThreadReader {
+        let reader = BGZFMultiThreadReader {
             reader,
-            block_list: (0..(rayon::current_num_threads() * 2))
+            thread_pool,
+            block_list: (0..(thread_num * 2))
                 .map(|_| ReadBlock::new(process_block_num))
                 .collect(),
+            process_block_num,
             current_read_pos: 0,
             current_read_buffer: None,
             read_waiting_blocks: HashMap::new(),
@@ -74,31 +124,45 @@ impl<R: Read> BGZFMultiThreadReader<R> {
             next_decompress_index: 0,
             eof_read_index: u64::MAX,
         };
-        reader.dispatch_read_thread()?;
-
         Ok(reader)
     }
 
+    /// Set the number of block groups which can be decompressed ahead of the consumer.
+    ///
+    /// At most `queue_size` groups of `process_block_num` blocks are loaded and kept in memory,
+    /// so memory usage stays bounded when the consumer is slow. Default value is twice the number of threads.
+    /// This must be called before reading.
+    pub fn with_queue_size(mut self, queue_size: usize) -> Self {
+        let process_block_num = self.process_block_num;
+        self.block_list
+            .resize_with(queue_size.max(1), || ReadBlock::new(process_block_num));
+        self
+    }
+
+    /// Set the number of blocks which can be read and decompressed ahead of the consumer.
+    ///
+    /// This is a shorthand of [`BGZFMultiThreadReader::with_queue_size`] with enough groups
+    /// of `process_block_num` blocks to hold `blocks` blocks. This must be called before reading.
+    pub fn with_readahead(self, blocks: usize) -> Self {
+        let queue_size = blocks.div_ceil(self.process_block_num.max(1));
+        self.with_queue_size(queue_size)
+    }
+
     fn dispatch_read_thread(&mut self) -> Result<(), BGZFError> {
         while !self.block_list.is_empty() && self.next_decompress_index < self.eof_read_index {
             let mut block = self.block_list.pop().unwrap();
             block.index = self.next_decompress_index;
             self.next_decompress_index += 1;
 
-            let mut last_index = 0;
-
-            for i in 0..block.compressed_data.len() {
+            block.loaded_blocks = 0;
+            for one in block.compressed_data.iter_mut() {
                 //eprintln!("load block {}", i);
-                super::load_block(
-                    &mut self.reader,
-                    &mut block.compressed_data.get_mut(i).unwrap(),
-                )
-                .map_err(|e| -> std::io::Error {
+                super::load_block(&mut self.reader, one).map_err(|e| -> std::io::Error {
                     // eprintln!("load block error: {}", e);
                     e.into()
                 })?;
-                last_index = i;
-                if block.compressed_data.get(i).unwrap() == &EOF_BLOCK {
+                block.loaded_blocks += 1;
+                if one[..] == EOF_BLOCK {
                     //self.block_list.clear();
                     // eprintln!("EOF reach: {}", block.index);
                     self.eof_read_index = self.next_decompress_index;
@@ -106,21 +170,23 @@ impl<R: Read> BGZFMultiThreadReader<R> {
                 }
             }
 
-            if last_index != block.compressed_data.len() - 1 {
-                block
-                    .compressed_data
-                    .drain(last_index..block.compressed_data.len());
-            }
-
             let sender = self.reader_sender.clone();
             // eprintln!("spawn: {}", block.index);
-            rayon::spawn(move || {
+            let task = move || {
                 let _i = block.index;
                 block.decompressed_data.clear();
-                for one_compress_data in &block.compressed_data {
+                let compressed_data = &block.compressed_data[..block.loaded_blocks];
+                // grow the buffer at once, instead of per block
+                let total_size: usize = compressed_data
+                    .iter()
+                    .filter_map(|x| crate::block::parse_footer(x).ok())
+                    .map(|x| usize::try_from(x.isize).unwrap_or(0))
+                    .sum();
+                block.decompressed_data.reserve(total_size);
+                for one_compress_data in compressed_data {
                     match super::decompress_block(
                         &mut block.decompressed_data,
-                        &one_compress_data,
+                        one_compress_data,
                         &mut block.decompress,
                     ) {
                         Ok(_) => (),
@@ -132,7 +198,11 @@ impl<R: Read> BGZFMultiThreadReader<R> {
                 }
                 sender.send(Ok(block)).expect("reader send error 1");
                 // eprintln!("done: {}", i);
-            });
+            };
+            match self.thread_pool.as_ref() {
+                Some(pool) => pool.spawn(task),
+                None => rayon::spawn(task),
+            }
         }
 
         Ok(())
@@ -173,7 +243,7 @@ impl<R: Read> BufRead for BGZFMultiThreadReader<R> {
 
         //eprintln!("fill buf 3");
         self.dispatch_read_thread()
-            .map_err(|e| Into::<std::io::Error>::into(e))?;
+            .map_err(Into::<std::io::Error>::into)?;
 
         //eprintln!("fill buf 4");
 
@@ -262,6 +332,16 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_corrupt_first_block() -> anyhow::Result<()> {
+        let mut data = std::fs::read("testfiles/common_all_20180418_half.vcf.gz")?;
+        data[0] = 0;
+        let mut reader = BGZFMultiThreadReader::new(&data[..])?;
+        let mut read_buf = Vec::new();
+        assert!(reader.read_to_end(&mut read_buf).is_err());
+        Ok(())
+    }
+
     #[test]
     fn test_thread_read() -> anyhow::Result<()> {
         let mut expected_reader = flate2::read::MultiGzDecoder::new(std::fs::File::open(
@@ -279,6 +359,13 @@ mod test {
         reader.read_to_end(&mut read_buf)?;
         assert_eq!(expected_buf.len(), read_buf.len());
         assert_eq!(expected_buf, read_buf);
+        // all buffers are returned to the pool
+        assert_eq!(
+            reader.block_list.len()
+                + reader.read_waiting_blocks.len()
+                + usize::from(reader.current_read_buffer.is_some()),
+            rayon::current_num_threads() * 2
+        );
 
         // with single block
         let mut reader = BGZFMultiThreadReader::with_process_block_num(
@@ -310,6 +397,48 @@ mod test {
 
         assert_eq!(expected_buf.len(), read_buf.len());
 
+        // dedicated thread pool
+        let pool = Arc::new(rayon::ThreadPoolBuilder::new().num_threads(3).build()?);
+        let mut reader = BGZFMultiThreadReader::with_thread_pool(
+            std::fs::File::open("testfiles/common_all_20180418_half.vcf.gz")?,
+            pool.clone(),
+        )?;
+        let mut read_buf = Vec::new();
+        reader.read_to_end(&mut read_buf)?;
+        assert_eq!(expected_buf, read_buf);
+        assert_eq!(
+            reader.block_list.len()
+                + reader.read_waiting_blocks.len()
+                + usize::from(reader.current_read_buffer.is_some()),
+            6
+        );
+
+        // bounded queue
+        let mut reader = BGZFMultiThreadReader::with_thread_pool_and_process_block_num(
+            std::fs::File::open("testfiles/common_all_20180418_half.vcf.gz")?,
+            pool,
+            2,
+        )?
+        .with_queue_size(1);
+        let mut read_buf = Vec::new();
+        reader.read_to_end(&mut read_buf)?;
+        assert_eq!(expected_buf, read_buf);
+        assert_eq!(
+            reader.block_list.len() + usize::from(reader.current_read_buffer.is_some()),
+            1
+        );
+
+        // readahead in blocks
+        let mut reader = BGZFMultiThreadReader::with_process_block_num(
+            std::fs::File::open("testfiles/common_all_20180418_half.vcf.gz")?,
+            10,
+        )?
+        .with_readahead(25);
+        assert_eq!(reader.block_list.len(), 3);
+        let mut read_buf = Vec::new();
+        reader.read_to_end(&mut read_buf)?;
+        assert_eq!(expected_buf, read_buf);
+
         Ok(())
     }
 }
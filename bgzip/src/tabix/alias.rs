@@ -0,0 +1,311 @@
+use super::*;
+use std::io::BufRead;
+
+/// Groups of equivalent sequence names, such as `1`, `chr1` and `NC_000001.11`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ContigAliases {
+    groups: Vec<Vec<Vec<u8>>>,
+    lookup: HashMap<Vec<u8>, usize>,
+}
+
+impl ContigAliases {
+    pub fn new() -> Self {
+        ContigAliases::default()
+    }
+
+    /// Register `names` as equivalent names. Groups containing one of `names` are merged.
+    pub fn add<S: AsRef<[u8]>>(&mut self, names: &[S]) {
+        if names.is_empty() {
+            return;
+        }
+        let mut existing: Vec<usize> = names
+            .iter()
+            .filter_map(|x| self.lookup.get(x.as_ref()).copied())
+            .collect();
+        existing.sort_unstable();
+        existing.dedup();
+        let target = if let Some(first) = existing.first() {
+            *first
+        } else {
+            self.groups.push(Vec::new());
+            self.groups.len() - 1
+        };
+
+        // merge from the last group, so that swap_remove never moves a group which is not merged yet
+        for one in existing.iter().skip(1).rev() {
+            let group = self.groups.swap_remove(*one);
+            for name in &group {
+                self.lookup.insert(name.clone(), target);
+            }
+            self.groups[target].extend(group);
+            if let Some(moved) = self.groups.get(*one) {
+                for name in moved {
+                    self.lookup.insert(name.clone(), *one);
+                }
+            }
+        }
+
+        for one in names {
+            if !self.lookup.contains_key(one.as_ref()) {
+                self.groups[target].push(one.as_ref().to_vec());
+                self.lookup.insert(one.as_ref().to_vec(), target);
+            }
+        }
+    }
+
+    /// Register equivalent names with builder style.
+    pub fn with_aliases<S: AsRef<[u8]>>(mut self, names: &[S]) -> Self {
+        self.add(names);
+        self
+    }
+
+    /// Load alias table. Each line is tab separated equivalent names, like `chromAlias.txt` of UCSC.
+    ///
+    /// Lines starting with `#` and empty lines are ignored.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, BGZFError> {
+        let mut aliases = ContigAliases::new();
+        for line in io::BufReader::new(reader).split(b'\n') {
+            let line = line?;
+            let line = strip_line_end(&line);
+            if line.is_empty() || line.starts_with(b"#") {
+                continue;
+            }
+            let names: Vec<&[u8]> = split_columns(line).filter(|x| !x.is_empty()).collect();
+            aliases.add(&names);
+        }
+        Ok(aliases)
+    }
+
+    /// Names equivalent to `name`, including `name` itself. Empty if `name` is not registered.
+    pub fn aliases(&self, name: &[u8]) -> impl Iterator<Item = &[u8]> {
+        self.lookup
+            .get(name)
+            .map(|x| &self.groups[*x][..])
+            .unwrap_or_default()
+            .iter()
+            .map(|x| &x[..])
+    }
+
+    /// Aliases of GRCh38 primary chromosomes: Ensembl names, UCSC names and RefSeq accessions.
+    #[cfg(feature = "contig_aliases")]
+    pub fn grch38() -> Self {
+        ContigAliases::human(&GRCH38_REFSEQ, true)
+    }
+
+    /// Aliases of GRCh37 primary chromosomes: Ensembl names, UCSC names and RefSeq accessions.
+    ///
+    /// `chrM` of hg19 is not an alias of `MT`, because it is a different mitochondrial sequence (NC_001807).
+    #[cfg(feature = "contig_aliases")]
+    pub fn grch37() -> Self {
+        ContigAliases::human(&GRCH37_REFSEQ, false)
+    }
+
+    #[cfg(feature = "contig_aliases")]
+    fn human(refseq: &[&str; 25], ucsc_mitochondrion: bool) -> Self {
+        let mut aliases = ContigAliases::new();
+        for (i, accession) in refseq.iter().enumerate() {
+            let name = match i {
+                0..=21 => (i + 1).to_string(),
+                22 => "X".to_string(),
+                23 => "Y".to_string(),
+                _ => "MT".to_string(),
+            };
+            if name == "MT" && ucsc_mitochondrion {
+                aliases.add(&["MT", "M", "chrM", "chrMT", *accession]);
+            } else if name == "MT" {
+                aliases.add(&["MT", "chrMT", *accession]);
+            } else {
+                aliases.add(&[name.clone(), format!("chr{}", name), accession.to_string()]);
+            }
+        }
+        aliases
+    }
+}
+
+#[cfg(feature = "contig_aliases")]
+const GRCH38_REFSEQ: [&str; 25] = [
+    "NC_000001.11",
+    "NC_000002.12",
+    "NC_000003.12",
+    "NC_000004.12",
+    "NC_000005.10",
+    "NC_000006.12",
+    "NC_000007.14",
+    "NC_000008.11",
+    "NC_000009.12",
+    "NC_000010.11",
+    "NC_000011.10",
+    "NC_000012.12",
+    "NC_000013.11",
+    "NC_000014.9",
+    "NC_000015.10",
+    "NC_000016.10",
+    "NC_000017.11",
+    "NC_000018.10",
+    "NC_000019.10",
+    "NC_000020.11",
+    "NC_000021.9",
+    "NC_000022.11",
+    "NC_000023.11",
+    "NC_000024.10",
+    "NC_012920.1",
+];
+
+#[cfg(feature = "contig_aliases")]
+const GRCH37_REFSEQ: [&str; 25] = [
+    "NC_000001.10",
+    "NC_000002.11",
+    "NC_000003.11",
+    "NC_000004.11",
+    "NC_000005.9",
+    "NC_000006.11",
+    "NC_000007.13",
+    "NC_000008.10",
+    "NC_000009.11",
+    "NC_000010.10",
+    "NC_000011.9",
+    "NC_000012.11",
+    "NC_000013.10",
+    "NC_000014.8",
+    "NC_000015.9",
+    "NC_000016.9",
+    "NC_000017.10",
+    "NC_000018.9",
+    "NC_000019.9",
+    "NC_000020.10",
+    "NC_000021.8",
+    "NC_000022.10",
+    "NC_000023.10",
+    "NC_000024.9",
+    "NC_012920.1",
+];
+
+/// An index which resolves sequence names through [`ContigAliases`].
+///
+/// Names in the wrapped index are used as is, and other names are resolved through their aliases.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AliasedIndex<I: RegionIndex> {
+    index: I,
+    aliases: ContigAliases,
+}
+
+impl<I: RegionIndex> AliasedIndex<I> {
+    pub fn new(index: I, aliases: ContigAliases) -> Self {
+        AliasedIndex { index, aliases }
+    }
+
+    /// Wrapped index
+    pub fn inner(&self) -> &I {
+        &self.index
+    }
+
+    /// Alias table of this index
+    pub fn aliases(&self) -> &ContigAliases {
+        &self.aliases
+    }
+
+    pub fn into_inner(self) -> I {
+        self.index
+    }
+}
+
+impl<I: RegionIndex> RegionIndex for AliasedIndex<I> {
+    fn config(&self) -> TabixConfig {
+        self.index.config()
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.index.name2rid(name).or_else(|| {
+            self.aliases
+                .aliases(name)
+                .find_map(|x| self.index.name2rid(x))
+        })
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        self.index.rid2name(rid)
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.index.sequence_count()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        self.index.region_chunks(rid, begin, end)
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        self.index.first_offset(rid)
+    }
+
+    fn binning_scheme(&self) -> BinningScheme {
+        self.index.binning_scheme()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_contig_aliases() -> anyhow::Result<()> {
+        let aliases = ContigAliases::from_reader(
+            &b"# ucsc\tensembl\trefseq\nchr1\t1\tNC_000001.11\nchr2\t2\n\nchrM\tMT\nM\tchrM\n"[..],
+        )?;
+        assert_eq!(
+            aliases.aliases(b"1").collect::<Vec<_>>(),
+            vec![&b"chr1"[..], b"1", b"NC_000001.11"]
+        );
+        assert_eq!(aliases.aliases(b"M").count(), 3);
+        assert_eq!(aliases.aliases(b"chr3").count(), 0);
+        let merged = aliases.clone().with_aliases(&["2", "1"]);
+        assert_eq!(merged.aliases(b"chr2").count(), 5);
+        let merged = merged.with_aliases(&["chrM", "chr1", "chr22"]);
+        assert_eq!(merged.aliases(b"M").count(), 9);
+        assert_eq!(merged.aliases(b"chr22").count(), 9);
+        assert_eq!(merged.aliases(b"NC_000001.11").count(), 9);
+
+        let index = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let rid = index.name2rid(b"chr1");
+        let mut file = TabixFile::new(
+            File::open("testfiles/generated.bed.gz")?,
+            AliasedIndex::new(index, aliases),
+        )?;
+        assert!(rid.is_some());
+        assert_eq!(file.index().name2rid(b"1"), rid);
+        assert_eq!(file.index().name2rid(b"NC_000001.11"), rid);
+        assert_eq!(file.index().name2rid(b"chr1"), rid);
+        assert_eq!(
+            file.index().name2rid(b"chr3"),
+            file.index().inner().name2rid(b"chr3")
+        );
+        assert_eq!(file.index().name2rid(b"3"), None);
+
+        let records = file
+            .fetch_region("1:1000000-2000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!records.is_empty());
+        assert_eq!(
+            records,
+            file.fetch_region("chr1:1000000-2000000")?
+                .collect::<Result<Vec<_>, _>>()?
+        );
+
+        Ok(())
+    }
+
+    #[cfg(feature = "contig_aliases")]
+    #[test]
+    fn test_builtin_aliases() {
+        let grch38 = ContigAliases::grch38();
+        assert!(grch38.aliases(b"NC_000001.11").any(|x| x == b"chr1"));
+        assert!(grch38.aliases(b"X").any(|x| x == b"NC_000023.11"));
+        assert!(grch38.aliases(b"chrM").any(|x| x == b"MT"));
+        let grch37 = ContigAliases::grch37();
+        assert!(grch37.aliases(b"22").any(|x| x == b"NC_000022.10"));
+        assert_eq!(grch37.aliases(b"NC_000001.11").count(), 0);
+        assert!(grch37.aliases(b"MT").any(|x| x == b"NC_012920.1"));
+        assert_eq!(grch37.aliases(b"chrM").count(), 0);
+    }
+}
@@ -0,0 +1,119 @@
+use super::*;
+use crate::csi::CsiIndex;
+use std::path::Path;
+
+/// Tabix or CSI index, which is selected by [`TabixFile::from_path_auto`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum AnyIndex {
+    Tabix(Tabix),
+    Csi(CsiIndex),
+}
+
+impl AnyIndex {
+    fn inner(&self) -> &dyn RegionIndex {
+        match self {
+            AnyIndex::Tabix(index) => index,
+            AnyIndex::Csi(index) => index,
+        }
+    }
+}
+
+impl RegionIndex for AnyIndex {
+    fn config(&self) -> TabixConfig {
+        self.inner().config()
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.inner().name2rid(name)
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        self.inner().rid2name(rid)
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.inner().sequence_count()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        self.inner().region_chunks(rid, begin, end)
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        self.inner().first_offset(rid)
+    }
+
+    fn binning_scheme(&self) -> BinningScheme {
+        self.inner().binning_scheme()
+    }
+}
+
+impl TabixFile<std::fs::File, AnyIndex> {
+    /// Open BGZF compressed file and its index.
+    ///
+    /// Tabix index (`path` + `.tbi`) is used if it exists. Otherwise, CSI index (`path` + `.csi`) is loaded.
+    pub fn from_path_auto<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        TabixFile::from_path_auto_with_limits(path, Limits::UNLIMITED)
+    }
+
+    /// Open BGZF compressed file and its tabix or CSI index with resource limits.
+    pub fn from_path_auto_with_limits<P: AsRef<Path>>(
+        path: P,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        let mut tbi_path = path.as_ref().as_os_str().to_os_string();
+        tbi_path.push(".tbi");
+        let mut csi_path = path.as_ref().as_os_str().to_os_string();
+        csi_path.push(".csi");
+
+        let index = if Path::new(&tbi_path).exists() || !Path::new(&csi_path).exists() {
+            AnyIndex::Tabix(Tabix::from_reader_with_limits(
+                std::fs::File::open(tbi_path)?,
+                limits,
+            )?)
+        } else {
+            let index = CsiIndex::from_reader_with_limits(std::fs::File::open(csi_path)?, limits)?;
+            if index.config.is_none() {
+                return Err(BGZFError::BadIndex(
+                    "CSI index does not have column configuration",
+                ));
+            }
+            AnyIndex::Csi(index)
+        };
+        TabixFile::with_limits(std::fs::File::open(path)?, index, limits)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_from_path_auto() -> anyhow::Result<()> {
+        let path = "testfiles/common_all_20180418_half.vcf.gz";
+        let mut tabix_file = TabixFile::from_path_auto(path)?;
+        assert!(matches!(tabix_file.index(), AnyIndex::Tabix(_)));
+
+        std::fs::copy(path, "tmp/test-auto.vcf.gz")?;
+        std::fs::copy(format!("{}.csi", path), "tmp/test-auto.vcf.gz.csi")?;
+        let _ = std::fs::remove_file("tmp/test-auto.vcf.gz.tbi");
+        let mut csi_file = TabixFile::from_path_auto("tmp/test-auto.vcf.gz")?;
+        assert!(matches!(csi_file.index(), AnyIndex::Csi(_)));
+        assert_eq!(csi_file.index().config(), TabixConfig::VCF);
+
+        for (name, begin, end) in [(&b"1"[..], 1_000_000, 1_200_000), (b"22", 0, 20_000_000)] {
+            let csi_records = csi_file
+                .fetch_name(name, begin, end)?
+                .collect::<Result<Vec<_>, _>>()?;
+            let tabix_records = tabix_file
+                .fetch_name(name, begin, end)?
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!csi_records.is_empty());
+            assert_eq!(csi_records, tabix_records);
+        }
+
+        assert!(TabixFile::from_path_auto("tmp/test-auto-missing.vcf.gz").is_err());
+
+        Ok(())
+    }
+}
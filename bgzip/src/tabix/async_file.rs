@@ -0,0 +1,163 @@
+use super::*;
+use crate::read::BGZFAsyncReader;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncSeek};
+
+/// BGZF compressed text file with tabix index, which is read asynchronously with [tokio](https://tokio.rs).
+///
+/// This is an asynchronous version of [`TabixFile`] over [`AsyncRead`] + [`AsyncSeek`] sources,
+/// such as `tokio::fs::File` or asynchronous HTTP readers, so remote indexed access does not block threads.
+/// `tokio` flag is required to use this struct.
+pub struct AsyncTabixFile<R: AsyncRead + AsyncSeek + Unpin, I: RegionIndex = Tabix> {
+    reader: BGZFAsyncReader<R>,
+    index: I,
+}
+
+impl<R: AsyncRead + AsyncSeek + Unpin, I: RegionIndex> AsyncTabixFile<R, I> {
+    /// Create new [`AsyncTabixFile`] from BGZF data and loaded index.
+    pub fn new(reader: R, index: I) -> Self {
+        AsyncTabixFile {
+            reader: BGZFAsyncReader::new(reader),
+            index,
+        }
+    }
+
+    /// Index of this file
+    pub fn index(&self) -> &I {
+        &self.index
+    }
+
+    /// Fetch records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    ///
+    /// Records are read lazily with [`AsyncTabixRecords::next_record`].
+    pub fn fetch(&mut self, rid: u32, begin: u64, end: u64) -> AsyncTabixRecords<'_, R, I> {
+        let chunks = self.index.region_chunks(rid, begin, end);
+        let name = self.index.rid2name(rid).unwrap_or_default().to_vec();
+        AsyncTabixRecords {
+            config: self.index.config(),
+            file: self,
+            name,
+            rid,
+            begin,
+            end,
+            chunks,
+            current_chunk: 0,
+            current_pos: None,
+            line: Vec::new(),
+        }
+    }
+
+    /// Fetch records in a region string such as `chr17:42,990,000-42,990,600`. See [`TabixFile::fetch_region`].
+    pub fn fetch_region(&mut self, region: &str) -> Result<AsyncTabixRecords<'_, R, I>, BGZFError> {
+        let (rid, begin, end) = match self.index.name2rid(region.as_bytes()) {
+            Some(rid) => (rid, 0, u64::MAX),
+            None => {
+                let region = Region::parse(region)?;
+                (
+                    self.index.try_name2rid(region.name.as_bytes())?,
+                    region.begin,
+                    region.end,
+                )
+            }
+        };
+        Ok(self.fetch(rid, begin, end))
+    }
+}
+
+/// Records in a region, which are read asynchronously. This struct is created by [`AsyncTabixFile::fetch`].
+pub struct AsyncTabixRecords<'a, R: AsyncRead + AsyncSeek + Unpin, I: RegionIndex = Tabix> {
+    file: &'a mut AsyncTabixFile<R, I>,
+    config: TabixConfig,
+    name: Vec<u8>,
+    rid: u32,
+    begin: u64,
+    end: u64,
+    chunks: Vec<TabixChunk>,
+    current_chunk: usize,
+    current_pos: Option<VirtualOffset>,
+    line: Vec<u8>,
+}
+
+impl<'a, R: AsyncRead + AsyncSeek + Unpin, I: RegionIndex> AsyncTabixRecords<'a, R, I> {
+    /// Read the next record, or `None` after the last record.
+    pub async fn next_record(&mut self) -> Result<Option<TabixEntry>, BGZFError> {
+        loop {
+            let chunk = match self.chunks.get(self.current_chunk) {
+                Some(chunk) => chunk,
+                None => return Ok(None),
+            };
+            match self.current_pos {
+                Some(pos) if pos >= chunk.end => {
+                    self.current_chunk += 1;
+                    continue;
+                }
+                Some(pos) if pos >= chunk.begin => (),
+                _ => {
+                    self.file.reader.bgzf_seek(chunk.begin).await?;
+                    self.current_pos = Some(chunk.begin);
+                }
+            }
+
+            self.line.clear();
+            if self.file.reader.read_until(b'\n', &mut self.line).await? == 0 {
+                self.chunks.clear();
+                return Ok(None);
+            }
+            // move to the next block if the line ends at the end of a block
+            self.file.reader.fill_buf().await?;
+            self.current_pos = Some(self.file.reader.bgzf_pos());
+
+            if self.config.is_meta(&self.line) {
+                continue;
+            }
+            let (name, begin, end) = self.config.parse_record(&self.line)?;
+            if name != self.name || begin >= self.end {
+                // records are sorted, so no more records overlap with the region
+                self.chunks.clear();
+                return Ok(None);
+            }
+            if OverlapMode::Overlap.matches(begin, end, self.begin, self.end) {
+                return Ok(Some(TabixEntry::new(
+                    self.rid,
+                    begin,
+                    end,
+                    strip_line_end(&self.line).to_vec(),
+                )));
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_async_fetch() -> anyhow::Result<()> {
+        let data = std::fs::read("testfiles/generated.bed.gz")?;
+        let index = Tabix::from_reader(std::fs::File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut async_file = AsyncTabixFile::new(std::io::Cursor::new(data), index);
+
+        for region in [
+            "chr1:1-100000",
+            "chr1:12,345,679-13,000,000",
+            "chr5:100,000,001-100,100,000",
+            "chr22",
+            "chr22:11-11",
+        ] {
+            let expected = file.fetch_region(region)?.collect::<Result<Vec<_>, _>>()?;
+            let mut records = async_file.fetch_region(region)?;
+            let mut fetched = Vec::new();
+            while let Some(one) = records.next_record().await? {
+                fetched.push(one);
+            }
+            assert_eq!(fetched, expected, "{}", region);
+        }
+        assert!(matches!(
+            async_file.fetch_region("chrX:1-100"),
+            Err(BGZFError::UnknownContig(_))
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,218 @@
+use super::*;
+use std::ops::Range;
+
+/// Position of a record in an index, which is returned by [`BinningScheme::record_bin`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordBin {
+    /// Bin which contains whole the record
+    pub bin: u32,
+    /// Windows of the linear index overlapping with the record.
+    ///
+    /// A window spans `2^min_shift` bases, and the linear index records the virtual offset of
+    /// the first record overlapping with each window.
+    pub windows: Range<u64>,
+}
+
+/// Parameters of the hierarchical binning scheme used by tabix, BAI and CSI indexes.
+///
+/// The smallest bins span `2^min_shift` bases, and each level of `depth` levels has eight times
+/// larger bins. Tabix and BAI indexes always use [`BinningScheme::TABIX`], which covers 512M bases.
+/// CSI indexes record their own parameters, which can be loaded with [`crate::csi::CsiIndex::binning_scheme`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct BinningScheme {
+    min_shift: u32,
+    depth: u32,
+}
+
+impl BinningScheme {
+    /// Binning scheme of tabix and BAI indexes (`min_shift` = 14, `depth` = 5)
+    pub const TABIX: BinningScheme = BinningScheme {
+        min_shift: MIN_SHIFT,
+        depth: DEPTH,
+    };
+
+    /// Create new binning scheme.
+    ///
+    /// [`BGZFError::BadIndex`] is returned if the maximum position exceeds 2^62, or bin numbers do not fit into `u32`.
+    pub fn new(min_shift: u32, depth: u32) -> Result<Self, BGZFError> {
+        if depth > 10 || min_shift + depth * 3 > 62 {
+            return Err(BGZFError::BadIndex("Too large binning scheme"));
+        }
+        Ok(BinningScheme { min_shift, depth })
+    }
+
+    /// Bit width of the smallest bins
+    pub fn min_shift(&self) -> u32 {
+        self.min_shift
+    }
+
+    /// Number of levels below the root bin
+    pub fn depth(&self) -> u32 {
+        self.depth
+    }
+
+    /// Maximum position which can be indexed with this binning scheme
+    pub fn max_position(&self) -> u64 {
+        1 << (self.min_shift + self.depth * 3)
+    }
+
+    /// Bin number of the pseudo-bin which holds per-sequence metadata
+    pub fn meta_bin(&self) -> u32 {
+        (((1u64 << ((self.depth + 1) * 3)) - 1) / 7 + 1)
+            .try_into()
+            .unwrap()
+    }
+
+    /// Bin number of region [begin, end) (zero-based). `end` must not exceed [`BinningScheme::max_position`].
+    pub fn reg2bin(&self, begin: u64, end: u64) -> u32 {
+        crate::csi::reg2bin(
+            begin.try_into().unwrap(),
+            end.try_into().unwrap(),
+            self.min_shift,
+            self.depth,
+        )
+    }
+
+    /// Bin and linear index windows of a record covering [begin, end) (zero-based, half-open).
+    ///
+    /// A record with zero length is treated as a record of one base, as same as htslib.
+    /// `None` is returned if the record ends after [`BinningScheme::max_position`].
+    pub fn record_bin(&self, begin: u64, end: u64) -> Option<RecordBin> {
+        let end = end.max(begin + 1);
+        if end > self.max_position() {
+            return None;
+        }
+        Some(RecordBin {
+            bin: self.reg2bin(begin, end),
+            windows: (begin >> self.min_shift)..(((end - 1) >> self.min_shift) + 1),
+        })
+    }
+
+    /// Region [begin, end) (zero-based) covered by `bin`. `None` if `bin` is not in this binning scheme.
+    pub fn bin_range(&self, bin: u32) -> Option<Range<u64>> {
+        let (level, index) = self.bin_level(bin)?;
+        let width = 1u64 << (self.min_shift + (self.depth - level) * 3);
+        Some((index * width)..((index + 1) * width))
+    }
+
+    /// Convert `bin` into the bin covering the same region in binning scheme `to`.
+    ///
+    /// The pseudo-bin is converted into the pseudo-bin of `to`. `None` is returned if `min_shift` is different,
+    /// or the region of `bin` is not a bin of `to`.
+    pub fn convert_bin(&self, bin: u32, to: &BinningScheme) -> Option<u32> {
+        if self.min_shift != to.min_shift {
+            return None;
+        }
+        if bin == self.meta_bin() {
+            return Some(to.meta_bin());
+        }
+        let (level, index) = self.bin_level(bin)?;
+        let level = (level + to.depth).checked_sub(self.depth)?;
+        if level > to.depth || index >= 1 << (level * 3) {
+            return None;
+        }
+        Some(
+            ((((1u64 << (level * 3)) - 1) / 7) + index)
+                .try_into()
+                .unwrap(),
+        )
+    }
+
+    /// Level of `bin` and the index of `bin` in the level
+    fn bin_level(&self, bin: u32) -> Option<(u32, u64)> {
+        let bin = u64::from(bin);
+        let mut first = 0;
+        for level in 0..=self.depth {
+            let count = 1u64 << (level * 3);
+            if bin < first + count {
+                return Some((level, bin - first));
+            }
+            first += count;
+        }
+        None
+    }
+
+    /// Bin numbers which may overlap with region [begin, end) (zero-based).
+    ///
+    /// `end` is clamped to [`BinningScheme::max_position`]. An empty list is returned for an empty region.
+    pub fn reg2bins(&self, begin: u64, end: u64) -> Vec<u32> {
+        let end = end.min(self.max_position());
+        if end <= begin {
+            return Vec::new();
+        }
+        crate::csi::reg2bins(
+            begin.try_into().unwrap(),
+            end.try_into().unwrap(),
+            self.min_shift,
+            self.depth,
+        )
+    }
+}
+
+impl Default for BinningScheme {
+    fn default() -> Self {
+        BinningScheme::TABIX
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_binning_scheme() -> anyhow::Result<()> {
+        let tabix = BinningScheme::TABIX;
+        assert_eq!(tabix.max_position(), 1 << 29);
+        assert_eq!(tabix.meta_bin(), META_BIN);
+        assert_eq!(tabix.reg2bin(0, 1), 4681);
+        assert_eq!(tabix.reg2bin(0, 1 << 29), 0);
+        assert_eq!(tabix.reg2bins(100, 200), reg2bins(100, 200));
+        assert!(tabix.reg2bins(200, 100).is_empty());
+        assert_eq!(
+            tabix.record_bin(16383, 16385),
+            Some(RecordBin {
+                bin: 585,
+                windows: 0..2
+            })
+        );
+        assert_eq!(
+            tabix.record_bin(100, 100),
+            Some(RecordBin {
+                bin: 4681,
+                windows: 0..1
+            })
+        );
+        assert_eq!(tabix.record_bin(0, (1 << 29) + 1), None);
+
+        // contigs longer than 512M bases
+        let csi = BinningScheme::new(14, 6)?;
+        assert_eq!(csi.max_position(), 1 << 32);
+        assert_eq!(csi.meta_bin(), 299594);
+        let begin = 3_000_000_000;
+        let bin = csi.reg2bin(begin, begin + 1);
+        assert_eq!(bin, 37449 + u32::try_from(begin >> 14)?);
+        assert!(csi.reg2bins(begin, begin + 100).contains(&bin));
+        assert_eq!(
+            csi.reg2bins(begin, u64::MAX),
+            csi.reg2bins(begin, csi.max_position())
+        );
+
+        assert_eq!(tabix.bin_range(0), Some(0..(1 << 29)));
+        assert_eq!(tabix.bin_range(585), Some(0..(1 << 17)));
+        assert_eq!(tabix.bin_range(4682), Some((1 << 14)..(2 << 14)));
+        assert_eq!(tabix.bin_range(META_BIN), None);
+        assert_eq!(tabix.convert_bin(4682, &csi), Some(37450));
+        assert_eq!(csi.convert_bin(37450, &tabix), Some(4682));
+        assert_eq!(tabix.convert_bin(0, &csi), Some(1));
+        assert_eq!(csi.convert_bin(0, &tabix), None);
+        assert_eq!(csi.convert_bin(2, &tabix), None);
+        assert_eq!(tabix.convert_bin(META_BIN, &csi), Some(csi.meta_bin()));
+        assert_eq!(tabix.convert_bin(0, &BinningScheme::new(13, 6)?), None);
+
+        assert!(BinningScheme::new(14, 11).is_err());
+        assert!(BinningScheme::new(40, 8).is_err());
+        assert!(BinningScheme::new(0, 10).is_ok());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,503 @@
+use super::*;
+use std::io::BufRead;
+
+/// Number of bins in the binning scheme (bins `0..MAX_BIN`)
+const MAX_BIN: u32 = ((1 << ((DEPTH + 1) * 3)) - 1) / 7;
+
+/// Chunks in a small bin are merged into its parent bin if they span less than this size.
+const MIN_MARKER_DIST: u64 = 0x10000;
+
+/// Incremental tabix index builder.
+///
+/// Records must be pushed in sorted order with BGZF virtual file offset at the end of each record.
+/// The algorithm is same as `htslib`, so the built index is equal to an index created by `tabix` command.
+#[derive(Debug, Clone)]
+pub struct TabixBuilder {
+    config: TabixConfig,
+    names: Vec<Vec<u8>>,
+    sequences: Vec<TabixSequence>,
+    line_count: u64,
+    last_rid: Option<usize>,
+    last_bin: Option<u32>,
+    last_begin: u64,
+    last_offset: u64,
+    save_rid: usize,
+    save_bin: Option<u32>,
+    save_offset: u64,
+    offset_begin: u64,
+    number_of_mapped: u64,
+}
+
+impl TabixBuilder {
+    /// Create new [`TabixBuilder`] with column configuration
+    pub fn new(config: TabixConfig) -> Self {
+        TabixBuilder {
+            config,
+            names: Vec::new(),
+            sequences: Vec::new(),
+            line_count: 0,
+            last_rid: None,
+            last_bin: None,
+            last_begin: 0,
+            last_offset: 0,
+            save_rid: 0,
+            save_bin: None,
+            save_offset: 0,
+            offset_begin: 0,
+            number_of_mapped: 0,
+        }
+    }
+
+    /// Create new [`TabixBuilder`] with known sequence names.
+    ///
+    /// Reference sequence ID of each name is its position in `names`, so records can be
+    /// pushed with [`TabixBuilder::push_rid`]. Sequences without records are kept in the index.
+    pub fn with_names<S: AsRef<[u8]>>(config: TabixConfig, names: &[S]) -> Self {
+        let mut builder = TabixBuilder::new(config);
+        for one in names {
+            builder.add_sequence(one.as_ref());
+        }
+        builder
+    }
+
+    /// Add a line of the data file.
+    ///
+    /// `end_offset` is BGZF virtual file offset just after the line.
+    /// Meta lines and skipped lines are not indexed.
+    pub fn add_line(&mut self, line: &[u8], end_offset: VirtualOffset) -> Result<(), BGZFError> {
+        self.line_count += 1;
+        if self.line_count <= self.config.skip_lines() || self.config.is_meta(line) {
+            self.skip_to(end_offset);
+            return Ok(());
+        }
+        let (name, begin, end) = self.config.parse_record(line)?;
+        self.push(name, begin, end, end_offset)
+    }
+
+    /// Skip data which is not indexed, such as header lines, until BGZF virtual file offset `end_offset`.
+    ///
+    /// Data after the first record is not skipped, because chunks must be continuous.
+    pub fn skip_to(&mut self, end_offset: VirtualOffset) {
+        let end_offset = end_offset.into();
+        if self.save_bin.is_none() {
+            self.last_offset = end_offset;
+            self.offset_begin = end_offset;
+        }
+    }
+
+    /// Add a record with zero-based, half-open region.
+    ///
+    /// `end_offset` is BGZF virtual file offset just after the record.
+    /// The record is assumed to start at the end of the previous record.
+    pub fn push(
+        &mut self,
+        name: &[u8],
+        begin: u64,
+        end: u64,
+        end_offset: VirtualOffset,
+    ) -> Result<(), BGZFError> {
+        let rid = self.rid_for_name(name)?;
+        self.push_record(rid, begin, end, end_offset.into())
+    }
+
+    /// Add a record of reference sequence `rid` with zero-based, half-open region.
+    ///
+    /// `rid` is a position of the name given to [`TabixBuilder::with_names`].
+    /// Records of a sequence must be continuous, as same as [`TabixBuilder::push`].
+    /// This function is useful to index data produced by a custom writer, without parsing lines.
+    pub fn push_rid(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+        end_offset: VirtualOffset,
+    ) -> Result<(), BGZFError> {
+        let rid: usize = rid.try_into().unwrap();
+        if rid >= self.sequences.len() {
+            return Err(BGZFError::Other("Invalid reference sequence ID"));
+        }
+        if self.last_rid != Some(rid) && !self.sequences[rid].intervals.is_empty() {
+            return Err(BGZFError::Other("Sequences are not continuous"));
+        }
+        self.push_record(rid, begin, end, end_offset.into())
+    }
+
+    fn push_record(
+        &mut self,
+        rid: usize,
+        begin: u64,
+        end: u64,
+        end_offset: u64,
+    ) -> Result<(), BGZFError> {
+        let RecordBin { bin, windows } = BinningScheme::TABIX
+            .record_bin(begin, end)
+            .ok_or(BGZFError::Other("Too large position for tabix index"))?;
+
+        if self.last_rid != Some(rid) {
+            self.last_rid = Some(rid);
+            self.last_bin = None;
+        } else if self.last_begin > begin {
+            return Err(BGZFError::Other("Unsorted positions"));
+        }
+
+        let sequence = &mut self.sequences[rid];
+        let linear_begin: usize = windows.start.try_into().unwrap();
+        let linear_end: usize = windows.end.try_into().unwrap();
+        if sequence.intervals.len() < linear_end {
+            sequence.intervals.resize(linear_end, u64::MAX);
+        }
+        for one in &mut sequence.intervals[linear_begin..linear_end] {
+            if *one == u64::MAX {
+                *one = self.last_offset;
+            }
+        }
+
+        if self.last_bin != Some(bin) {
+            if let Some(save_bin) = self.save_bin {
+                self.insert_chunk(self.save_rid, save_bin, self.save_offset, self.last_offset);
+                if self.last_bin.is_none() {
+                    // sequence is changed
+                    self.insert_meta(self.save_rid, self.last_offset);
+                    self.offset_begin = self.last_offset;
+                }
+            }
+            self.save_offset = self.last_offset;
+            self.save_bin = Some(bin);
+            self.save_rid = rid;
+            self.last_bin = Some(bin);
+        }
+
+        self.number_of_mapped += 1;
+        self.last_offset = end_offset;
+        self.last_begin = begin;
+        Ok(())
+    }
+
+    /// Finish to build the index.
+    ///
+    /// `final_offset` is BGZF virtual file offset at the end of data.
+    pub fn finish(mut self, final_offset: VirtualOffset) -> Tabix {
+        let final_offset = final_offset.into();
+        if let Some(save_bin) = self.save_bin {
+            self.insert_chunk(self.save_rid, save_bin, self.save_offset, final_offset);
+            self.insert_meta(self.save_rid, final_offset);
+        }
+
+        for sequence in &mut self.sequences {
+            update_intervals(sequence);
+            compress_bins(sequence);
+        }
+
+        let names: Vec<Vec<u8>> = self
+            .names
+            .into_iter()
+            .map(|mut x| {
+                x.push(0);
+                x
+            })
+            .collect();
+
+        Tabix {
+            number_of_references: self.sequences.len().try_into().unwrap(),
+            format: self.config.format,
+            column_for_sequence: self.config.column_for_sequence,
+            column_for_begin: self.config.column_for_begin,
+            column_for_end: self.config.column_for_end,
+            meta: [self.config.meta, 0, 0, 0],
+            skip: self.config.skip,
+            length_of_concatenated_sequence_names: names
+                .iter()
+                .map(|x| x.len())
+                .sum::<usize>()
+                .try_into()
+                .unwrap(),
+            names,
+            sequences: self.sequences,
+            number_of_unplaced: Some(0),
+            name_table: NameTable::default(),
+        }
+    }
+
+    fn rid_for_name(&mut self, name: &[u8]) -> Result<usize, BGZFError> {
+        if let Some(last_rid) = self.last_rid {
+            if self.names[last_rid] == name {
+                return Ok(last_rid);
+            }
+        }
+        if let Some(rid) = self.names.iter().position(|x| x == name) {
+            // a name given to `with_names` can be used once
+            if !self.sequences[rid].intervals.is_empty() {
+                return Err(BGZFError::Other("Sequences are not continuous"));
+            }
+            return Ok(rid);
+        }
+        Ok(self.add_sequence(name))
+    }
+
+    fn add_sequence(&mut self, name: &[u8]) -> usize {
+        self.names.push(name.to_vec());
+        self.sequences.push(TabixSequence {
+            number_of_distinct_bin: 0,
+            bins: HashMap::new(),
+            number_of_intervals: 0,
+            intervals: Vec::new(),
+        });
+        self.names.len() - 1
+    }
+
+    fn insert_chunk(&mut self, rid: usize, bin: u32, begin: u64, end: u64) {
+        let one_bin = self.sequences[rid]
+            .bins
+            .entry(bin)
+            .or_insert_with(|| TabixBin {
+                bin,
+                number_of_chunk: 0,
+                chunks: Vec::new(),
+            });
+        one_bin.chunks.push(TabixChunk {
+            begin: begin.into(),
+            end: end.into(),
+        });
+        one_bin.number_of_chunk += 1;
+    }
+
+    fn insert_meta(&mut self, rid: usize, offset_end: u64) {
+        self.insert_chunk(rid, META_BIN, self.offset_begin, offset_end);
+        self.insert_chunk(rid, META_BIN, self.number_of_mapped, 0);
+        self.number_of_mapped = 0;
+    }
+}
+
+fn update_intervals(sequence: &mut TabixSequence) {
+    let first_offset = sequence
+        .bins
+        .get(&META_BIN)
+        .map(|x| u64::from(x.chunks[0].begin))
+        .unwrap_or(0);
+    let mut last_offset = first_offset;
+    for one in &mut sequence.intervals {
+        if *one == u64::MAX {
+            *one = last_offset;
+        } else {
+            last_offset = *one;
+        }
+    }
+    sequence.number_of_intervals = sequence.intervals.len().try_into().unwrap();
+}
+
+fn compress_bins(sequence: &mut TabixSequence) {
+    // merge a bin into its parent if the bin is too small
+    for level in (1..=DEPTH).rev() {
+        let first_bin = ((1 << (level * 3)) - 1) / 7;
+        let next_level_first_bin = ((1 << ((level + 1) * 3)) - 1) / 7;
+        let mut bins: Vec<u32> = sequence
+            .bins
+            .keys()
+            .copied()
+            .filter(|x| *x >= first_bin && *x < next_level_first_bin.min(MAX_BIN))
+            .collect();
+        bins.sort_unstable();
+        for bin in bins {
+            let one_bin = sequence.bins.get_mut(&bin).unwrap();
+            if level < DEPTH {
+                one_bin.chunks.sort_by_key(|x| (x.begin, x.end));
+            }
+            let first = one_bin.chunks[0].begin.compressed_offset();
+            let last = one_bin.chunks[one_bin.chunks.len() - 1]
+                .end
+                .compressed_offset();
+            let parent = (bin - 1) >> 3;
+            if last - first < MIN_MARKER_DIST && sequence.bins.contains_key(&parent) {
+                let one_bin = sequence.bins.remove(&bin).unwrap();
+                let parent_bin = sequence.bins.get_mut(&parent).unwrap();
+                parent_bin.chunks.extend(one_bin.chunks);
+                parent_bin.number_of_chunk = parent_bin.chunks.len().try_into().unwrap();
+            }
+        }
+    }
+    if let Some(root) = sequence.bins.get_mut(&0) {
+        root.chunks.sort_by_key(|x| (x.begin, x.end));
+    }
+
+    // merge adjacent chunks that start from the same BGZF block
+    for one_bin in sequence.bins.values_mut() {
+        if one_bin.bin >= MAX_BIN {
+            continue;
+        }
+        let mut merged: Vec<TabixChunk> = Vec::with_capacity(one_bin.chunks.len());
+        for one in one_bin.chunks.drain(..) {
+            match merged.last_mut() {
+                Some(last) if last.end.compressed_offset() >= one.begin.compressed_offset() => {
+                    last.end = last.end.max(one.end);
+                }
+                _ => merged.push(one),
+            }
+        }
+        one_bin.chunks = merged;
+        one_bin.number_of_chunk = one_bin.chunks.len().try_into().unwrap();
+    }
+    sequence.number_of_distinct_bin = sequence.bins.len().try_into().unwrap();
+}
+
+/// Compress `reader` into `writer` and build tabix index at the same time.
+///
+/// `reader` does not need to be seekable, so sorted data from a pipe can be
+/// compressed and indexed in one pass. The returned index should be saved with [`Tabix::write`]
+/// after `writer` is closed.
+pub fn compress_and_index<R: BufRead, W: Write>(
+    mut reader: R,
+    writer: &mut BGZFWriter<W>,
+    config: TabixConfig,
+) -> Result<Tabix, BGZFError> {
+    let mut builder = TabixBuilder::new(config);
+    let mut line = Vec::new();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        let size = reader.read_until(b'\n', &mut line)?;
+        if size == 0 {
+            break;
+        }
+        line_number += 1;
+        writer.write_all(&line)?;
+        builder
+            .add_line(&line, writer.bgzf_pos())
+            .map_err(|e| e.with_line(line_number))?;
+    }
+    // flush the last block, so that the final offset points to the end-of-file marker
+    writer.flush()?;
+    Ok(builder.finish(writer.bgzf_pos()))
+}
+
+/// Build tabix index of coordinate-sorted BGZF compressed text.
+///
+/// This function is equivalent to `tabix` command. The returned index can be saved with [`Tabix::write`].
+pub fn index_bgzf<R: Read>(reader: R, config: TabixConfig) -> Result<Tabix, BGZFError> {
+    let mut reader = BGZFReader::new(reader)?;
+    let mut builder = TabixBuilder::new(config);
+    let mut line = Vec::new();
+    let mut end_offset = reader.bgzf_pos();
+    let mut line_number = 0;
+    loop {
+        line.clear();
+        if BufRead::read_until(&mut reader, b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_number += 1;
+        // move to the next block if the line ends at the end of a block
+        reader.fill_buf()?;
+        end_offset = reader.bgzf_pos();
+        builder
+            .add_line(&line, end_offset)
+            .map_err(|e| e.with_line(line_number))?;
+    }
+    Ok(builder.finish(end_offset))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_index_bgzf() -> anyhow::Result<()> {
+        for (path, config) in [
+            ("testfiles/generated.bed.gz", TabixConfig::BED),
+            (
+                "testfiles/common_all_20180418_half.vcf.gz",
+                TabixConfig::VCF,
+            ),
+        ] {
+            let expected = Tabix::from_reader(File::open(format!("{}.tbi", path))?)?;
+            let built = index_bgzf(File::open(path)?, config)?;
+            assert_eq!(built, expected);
+
+            // the written index is loaded as the same index as the shipped .tbi
+            let mut written = Vec::new();
+            built.write(&mut written)?;
+            assert_eq!(Tabix::from_reader(&written[..])?, expected);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_push_rid() -> anyhow::Result<()> {
+        let expected = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let names: Vec<&[u8]> = (0..expected.sequences.len())
+            .map(|x| expected.rid2name(x.try_into().unwrap()).unwrap())
+            .collect();
+
+        let mut reader = BGZFReader::new(File::open("testfiles/generated.bed.gz")?)?;
+        let mut builder = TabixBuilder::with_names(TabixConfig::BED, &names);
+        let mut line = Vec::new();
+        let mut end_offset = reader.bgzf_pos();
+        loop {
+            line.clear();
+            if BufRead::read_until(&mut reader, b'\n', &mut line)? == 0 {
+                break;
+            }
+            reader.fill_buf()?;
+            end_offset = reader.bgzf_pos();
+            let (name, begin, end) = TabixConfig::BED.parse_record(&line)?;
+            let rid = names.iter().position(|x| *x == name).unwrap();
+            builder.push_rid(rid.try_into()?, begin, end, end_offset)?;
+        }
+        assert!(builder.push_rid(0, 0, 1, end_offset).is_err());
+        assert!(builder
+            .push_rid(names.len().try_into()?, 0, 1, end_offset)
+            .is_err());
+        assert_eq!(builder.finish(end_offset).diff(&expected), vec![]);
+
+        let mut builder = TabixBuilder::with_names(TabixConfig::BED, &["chr1", "chr2"]);
+        builder.skip_to(100.into());
+        builder.push(b"chr2", 10, 20, 200.into())?;
+        let index = builder.finish(300.into());
+        assert_eq!(index.rid2name(0), Some(&b"chr1"[..]));
+        assert!(index.sequences[0].bins.is_empty());
+        assert_eq!(
+            index.region_chunks(1, 0, 100),
+            vec![TabixChunk {
+                begin: 100.into(),
+                end: 300.into()
+            }]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_compress_and_index() -> anyhow::Result<()> {
+        let reader = io::BufReader::new(flate2::read::MultiGzDecoder::new(File::open(
+            "testfiles/generated.bed.gz",
+        )?));
+        let mut writer = BGZFWriter::new(
+            File::create("tmp/compress_and_index.bed.gz")?,
+            Compression::default(),
+        );
+        let index = compress_and_index(reader, &mut writer, TabixConfig::BED)?;
+        writer.close()?;
+        index.write(File::create("tmp/compress_and_index.bed.gz.tbi")?)?;
+
+        let loaded = Tabix::from_reader(File::open("tmp/compress_and_index.bed.gz.tbi")?)?;
+        assert_eq!(loaded, index);
+        let rebuilt = index_bgzf(
+            File::open("tmp/compress_and_index.bed.gz")?,
+            TabixConfig::BED,
+        )?;
+        assert_eq!(rebuilt, index);
+
+        let mut writer = BGZFWriter::new(Vec::new(), Compression::default());
+        assert!(matches!(
+            compress_and_index(
+                &b"chr1\t1\t2\nchr1\tx\t3\n"[..],
+                &mut writer,
+                TabixConfig::BED
+            ),
+            Err(BGZFError::ParseError {
+                line: Some(2),
+                column: 2
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,115 @@
+use super::*;
+use std::path::Path;
+
+const CACHE_MAGIC: [u8; 4] = [b'T', b'B', b'C', 1];
+
+/// CRC32 checksum of a tabix index file, which is recorded in an index cache.
+pub fn index_checksum<R: Read>(mut reader: R) -> io::Result<u32> {
+    let mut buffer = vec![0u8; 64 * 1024];
+    let mut checksum = 0;
+    loop {
+        let read_bytes = match reader.read(&mut buffer) {
+            Ok(0) => return Ok(checksum),
+            Ok(n) => n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        };
+        checksum = crate::block::crc32(checksum, &buffer[..read_bytes]);
+    }
+}
+
+impl Tabix {
+    /// Write a cache of this index, which can be loaded with [`Tabix::from_cache`] without decompression.
+    ///
+    /// `checksum` is the checksum of the original index file calculated with [`index_checksum`].
+    /// The cache is not compressed, and its format may be changed in a future version.
+    pub fn write_cache<W: Write>(&self, writer: W, checksum: u32) -> Result<(), BGZFError> {
+        let mut writer = io::BufWriter::new(writer);
+        writer.write_all(&CACHE_MAGIC)?;
+        writer.write_all(&checksum.to_le_bytes())?;
+        self.write_uncompressed(&mut writer)?;
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Load an index cache written by [`Tabix::write_cache`].
+    ///
+    /// [`BGZFError::BadIndex`] is returned if the cache is created from an index file whose checksum
+    /// is not `checksum`.
+    pub fn from_cache<R: Read>(reader: R, checksum: u32) -> Result<Self, BGZFError> {
+        let mut reader = io::BufReader::new(reader);
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != CACHE_MAGIC {
+            return Err(BGZFError::InvalidMagic("tabix index cache"));
+        }
+        if reader.read_le_u32()? != checksum {
+            return Err(BGZFError::BadIndex("Index cache is outdated"));
+        }
+        Tabix::from_uncompressed(reader, Limits::UNLIMITED)
+    }
+
+    /// Load tabix index at `index_path` through a cache at `cache_path`.
+    ///
+    /// The cache is used if it is created from the same index file. Otherwise, the index file is parsed,
+    /// and the cache is created or updated. Failure to write the cache is ignored, so a read-only location can be used.
+    pub fn from_path_cached<P: AsRef<Path>, Q: AsRef<Path>>(
+        index_path: P,
+        cache_path: Q,
+    ) -> Result<Self, BGZFError> {
+        let checksum = index_checksum(std::fs::File::open(index_path.as_ref())?)?;
+        match std::fs::File::open(cache_path.as_ref())
+            .map_err(BGZFError::from)
+            .and_then(|x| Tabix::from_cache(x, checksum))
+        {
+            Ok(index) => return Ok(index),
+            Err(e) => debug!("index cache is not available: {}", e),
+        }
+
+        let index = Tabix::from_reader(std::fs::File::open(index_path)?)?;
+        let written = std::fs::File::create(cache_path.as_ref())
+            .map_err(BGZFError::from)
+            .and_then(|x| index.write_cache(x, checksum));
+        if let Err(e) = written {
+            debug!("failed to write index cache: {}", e);
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_index_cache() -> anyhow::Result<()> {
+        let index_path = "testfiles/generated.bed.gz.tbi";
+        let cache_path = "tmp/test-cache.bed.gz.tbi.cache";
+        let _ = std::fs::remove_file(cache_path);
+
+        let expected = Tabix::from_reader(File::open(index_path)?)?;
+        assert_eq!(Tabix::from_path_cached(index_path, cache_path)?, expected);
+        let checksum = index_checksum(File::open(index_path)?)?;
+        assert_eq!(
+            Tabix::from_cache(File::open(cache_path)?, checksum)?,
+            expected
+        );
+        assert!(matches!(
+            Tabix::from_cache(File::open(cache_path)?, checksum ^ 1),
+            Err(BGZFError::BadIndex(_))
+        ));
+        // loaded from the cache
+        assert_eq!(Tabix::from_path_cached(index_path, cache_path)?, expected);
+
+        // broken cache is rebuilt
+        std::fs::write(cache_path, b"broken")?;
+        assert_eq!(Tabix::from_path_cached(index_path, cache_path)?, expected);
+        assert_eq!(
+            Tabix::from_cache(File::open(cache_path)?, checksum)?,
+            expected
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,217 @@
+use super::*;
+use crate::csi::{CsiBin, CsiIndex, CsiSequence};
+
+impl Tabix {
+    /// Convert this index into CSI index with binning scheme `scheme`, without scanning the data file.
+    ///
+    /// `min_shift` of `scheme` must be 14, and `depth` must not be smaller than 5, so that every bin of
+    /// the tabix index is a bin of `scheme`. `tabix --csi` uses depth 6 or larger for long sequences.
+    /// The linear index is converted into the first offsets of bins, as same as htslib.
+    pub fn to_csi(&self, scheme: BinningScheme) -> Result<CsiIndex, BGZFError> {
+        let mut aux = Vec::new();
+        for value in [
+            self.format,
+            self.column_for_sequence,
+            self.column_for_begin,
+            self.column_for_end,
+            i32::from(self.meta[0]),
+            self.skip,
+        ] {
+            aux.extend_from_slice(&value.to_le_bytes());
+        }
+        let mut names = Vec::new();
+        for one in &self.names {
+            names.extend_from_slice(one);
+            if !one.ends_with(&[0]) {
+                names.push(0);
+            }
+        }
+        aux.extend_from_slice(&count_to_i32(names.len())?.to_le_bytes());
+        aux.extend_from_slice(&names);
+
+        let mut sequences = Vec::new();
+        for one in &self.sequences {
+            let mut bins = HashMap::new();
+            for one_bin in one.bins.values() {
+                let bin = BinningScheme::TABIX
+                    .convert_bin(one_bin.bin, &scheme)
+                    .ok_or(BGZFError::Other("Tabix bins cannot be converted"))?;
+                // first offset of records overlapping with the first window of the bin
+                let loffset = BinningScheme::TABIX
+                    .bin_range(one_bin.bin)
+                    .and_then(|x| {
+                        one.intervals
+                            .get(usize::try_from(x.start >> MIN_SHIFT).unwrap())
+                            .copied()
+                    })
+                    .unwrap_or(0);
+                bins.insert(
+                    bin,
+                    CsiBin {
+                        bin,
+                        loffset,
+                        chunks: one_bin.chunks.clone(),
+                    },
+                );
+            }
+            sequences.push(CsiSequence { bins });
+        }
+
+        Ok(CsiIndex {
+            min_shift: scheme.min_shift(),
+            depth: scheme.depth(),
+            aux,
+            config: Some(self.config()),
+            names: (0..self.names.len())
+                .map(|x| self.rid2name(x.try_into().unwrap()).unwrap().to_vec())
+                .collect(),
+            sequences,
+            number_of_unplaced: self.number_of_unplaced,
+        })
+    }
+
+    /// Convert CSI index into tabix index, if all bins are bins of tabix binning scheme.
+    ///
+    /// [`BGZFError::Other`] is returned if `min_shift` is not 14, or records are located after 512M bases.
+    /// The column configuration must be recorded in the CSI index. CSI index does not have linear index,
+    /// so the linear index is approximated with the first offsets of chunks.
+    pub fn from_csi(csi: &CsiIndex) -> Result<Tabix, BGZFError> {
+        let config = csi.config.ok_or(BGZFError::BadIndex(
+            "CSI index does not have column configuration",
+        ))?;
+        let scheme = csi.binning_scheme();
+
+        let mut sequences = Vec::new();
+        for one in &csi.sequences {
+            let mut bins = HashMap::new();
+            let mut first_offsets = Vec::new();
+            for one_bin in one.bins.values() {
+                let bin = scheme
+                    .convert_bin(one_bin.bin, &BinningScheme::TABIX)
+                    .ok_or(BGZFError::Other("CSI bins cannot be converted"))?;
+                if bin != META_BIN {
+                    let window = BinningScheme::TABIX.bin_range(bin).unwrap().start >> MIN_SHIFT;
+                    if let Some(first) = one_bin.chunks.iter().map(|x| x.begin).min() {
+                        first_offsets.push((window, u64::from(first)));
+                    }
+                }
+                bins.insert(
+                    bin,
+                    TabixBin {
+                        bin,
+                        number_of_chunk: count_to_i32(one_bin.chunks.len())?,
+                        chunks: one_bin.chunks.clone(),
+                    },
+                );
+            }
+
+            // an offset of a window must not be larger than offsets of records overlapping with the window,
+            // which are in bins starting at or before the window
+            first_offsets.sort_unstable();
+            let length = first_offsets.last().map(|x| x.0 + 1).unwrap_or(0);
+            let mut intervals = Vec::new();
+            let mut current = u64::MAX;
+            let mut iter = first_offsets.iter().peekable();
+            for window in 0..length {
+                while let Some((_, offset)) = iter.next_if(|x| x.0 <= window) {
+                    current = current.min(*offset);
+                }
+                intervals.push(current);
+            }
+            // no records overlap with leading windows
+            let first = intervals.iter().copied().min().unwrap_or_default();
+            for one in &mut intervals {
+                if *one == u64::MAX {
+                    *one = first;
+                }
+            }
+            sequences.push(TabixSequence {
+                number_of_distinct_bin: count_to_i32(bins.len())?,
+                bins,
+                number_of_intervals: count_to_i32(intervals.len())?,
+                intervals,
+            });
+        }
+
+        let names: Vec<Vec<u8>> = csi.names.iter().map(|x| [&x[..], &[0]].concat()).collect();
+        Ok(Tabix {
+            number_of_references: count_to_i32(sequences.len())?,
+            format: config.format,
+            column_for_sequence: config.column_for_sequence,
+            column_for_begin: config.column_for_begin,
+            column_for_end: config.column_for_end,
+            meta: [config.meta, 0, 0, 0],
+            skip: config.skip,
+            length_of_concatenated_sequence_names: count_to_i32(
+                names.iter().map(|x| x.len()).sum::<usize>(),
+            )?,
+            names,
+            sequences,
+            number_of_unplaced: csi.number_of_unplaced,
+            name_table: NameTable::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_convert_csi() -> anyhow::Result<()> {
+        let tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let csi = tabix.to_csi(BinningScheme::new(14, 6)?)?;
+        csi.write(File::create("tmp/test-convert.bed.gz.csi")?)?;
+        let loaded = CsiIndex::from_reader(File::open("tmp/test-convert.bed.gz.csi")?)?;
+        assert_eq!(loaded, csi);
+        assert_eq!(loaded.config, Some(tabix.config()));
+        assert!(tabix.to_csi(BinningScheme::new(14, 4)?).is_err());
+        assert!(tabix.to_csi(BinningScheme::new(12, 6)?).is_err());
+
+        let converted = Tabix::from_csi(&csi)?;
+        assert_eq!(converted.names, tabix.names);
+        for (one, expected) in converted.sequences.iter().zip(&tabix.sequences) {
+            assert_eq!(one.bins, expected.bins);
+        }
+
+        // same records are returned from converted indexes
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut csi_file = TabixFile::new(File::open("testfiles/generated.bed.gz")?, csi)?;
+        let mut converted_file =
+            TabixFile::new(File::open("testfiles/generated.bed.gz")?, converted)?;
+        for region in ["chr1:1000000-2000000", "chr5:12345678-13000000", "chr2"] {
+            let expected = file.fetch_region(region)?.collect::<Result<Vec<_>, _>>()?;
+            assert!(!expected.is_empty());
+            assert_eq!(
+                csi_file
+                    .fetch_region(region)?
+                    .collect::<Result<Vec<_>, _>>()?,
+                expected
+            );
+            assert_eq!(
+                converted_file
+                    .fetch_region(region)?
+                    .collect::<Result<Vec<_>, _>>()?,
+                expected
+            );
+        }
+
+        // CSI index created by tabix
+        let csi =
+            CsiIndex::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.csi")?)?;
+        let tabix =
+            Tabix::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?)?;
+        let converted = Tabix::from_csi(&csi)?;
+        assert_eq!(converted.config(), tabix.config());
+        for rid in 0..tabix.sequences.len() {
+            let rid = rid.try_into()?;
+            assert_eq!(
+                converted.region_chunks(rid, 1_000_000, 2_000_000),
+                tabix.region_chunks(rid, 1_000_000, 2_000_000)
+            );
+        }
+
+        Ok(())
+    }
+}
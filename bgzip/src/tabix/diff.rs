@@ -0,0 +1,182 @@
+use super::*;
+
+/// A difference between two tabix indices. This enum is created by [`Tabix::diff`].
+///
+/// `left` values come from `self`, and `right` values come from the other index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TabixDifference {
+    /// Column configuration is different
+    Config {
+        left: TabixConfig,
+        right: TabixConfig,
+    },
+    /// Sequence name is different, or the sequence exists only in one index
+    Contig {
+        rid: u32,
+        left: Option<Vec<u8>>,
+        right: Option<Vec<u8>>,
+    },
+    /// Number of chunks in a bin is different. `None` means the bin does not exist.
+    ChunkCount {
+        rid: u32,
+        bin: u32,
+        left: Option<usize>,
+        right: Option<usize>,
+    },
+    /// Chunks in a bin are different although the number of chunks is same.
+    Chunks { rid: u32, bin: u32 },
+    /// Linear index entry is different. `None` means the entry does not exist.
+    Interval {
+        rid: u32,
+        index: usize,
+        left: Option<u64>,
+        right: Option<u64>,
+    },
+}
+
+impl Tabix {
+    /// Compare two indices and list differences.
+    ///
+    /// This is useful to validate an index built by [`TabixBuilder`] with an index built by `tabix`.
+    /// An empty list is returned if the indices are equivalent.
+    pub fn diff(&self, other: &Tabix) -> Vec<TabixDifference> {
+        let mut differences = Vec::new();
+        if self.config() != other.config() {
+            differences.push(TabixDifference::Config {
+                left: self.config(),
+                right: other.config(),
+            });
+        }
+
+        let sequence_num = self.sequences.len().max(other.sequences.len());
+        for i in 0..sequence_num {
+            let rid: u32 = i.try_into().unwrap();
+            let left_name = self.rid2name(rid).filter(|_| i < self.sequences.len());
+            let right_name = other.rid2name(rid).filter(|_| i < other.sequences.len());
+            if left_name != right_name {
+                differences.push(TabixDifference::Contig {
+                    rid,
+                    left: left_name.map(|x| x.to_vec()),
+                    right: right_name.map(|x| x.to_vec()),
+                });
+            }
+
+            let (left, right) = match (self.sequences.get(i), other.sequences.get(i)) {
+                (Some(left), Some(right)) => (left, right),
+                _ => continue,
+            };
+            diff_bins(rid, left, right, &mut differences);
+            diff_intervals(rid, left, right, &mut differences);
+        }
+
+        differences
+    }
+}
+
+fn diff_bins(
+    rid: u32,
+    left: &TabixSequence,
+    right: &TabixSequence,
+    differences: &mut Vec<TabixDifference>,
+) {
+    let mut bins: Vec<u32> = left.bins.keys().chain(right.bins.keys()).copied().collect();
+    bins.sort_unstable();
+    bins.dedup();
+
+    for bin in bins {
+        let left_bin = left.bins.get(&bin);
+        let right_bin = right.bins.get(&bin);
+        let left_count = left_bin.map(|x| x.chunks.len());
+        let right_count = right_bin.map(|x| x.chunks.len());
+        if left_count != right_count {
+            differences.push(TabixDifference::ChunkCount {
+                rid,
+                bin,
+                left: left_count,
+                right: right_count,
+            });
+        } else if left_bin.map(|x| &x.chunks) != right_bin.map(|x| &x.chunks) {
+            differences.push(TabixDifference::Chunks { rid, bin });
+        }
+    }
+}
+
+fn diff_intervals(
+    rid: u32,
+    left: &TabixSequence,
+    right: &TabixSequence,
+    differences: &mut Vec<TabixDifference>,
+) {
+    let interval_num = left.intervals.len().max(right.intervals.len());
+    for index in 0..interval_num {
+        let left_interval = left.intervals.get(index).copied();
+        let right_interval = right.intervals.get(index).copied();
+        if left_interval != right_interval {
+            differences.push(TabixDifference::Interval {
+                rid,
+                index,
+                left: left_interval,
+                right: right_interval,
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_diff() -> anyhow::Result<()> {
+        let tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        assert_eq!(tabix.diff(&tabix.clone()), vec![]);
+
+        let mut modified = tabix.clone();
+        modified.column_for_end = 0;
+        modified.names[1] = b"chrX\0".to_vec();
+        let bin = *modified.sequences[2].bins.keys().next().unwrap();
+        let chunk = &mut modified.sequences[2].bins.get_mut(&bin).unwrap().chunks[0];
+        chunk.end = (u64::from(chunk.end) + 1).into();
+        modified.sequences[3].intervals.push(1);
+        modified.sequences.pop();
+
+        let differences = tabix.diff(&modified);
+        assert_eq!(differences.len(), 5);
+        assert_eq!(
+            differences[0],
+            TabixDifference::Config {
+                left: tabix.config(),
+                right: modified.config()
+            }
+        );
+        assert_eq!(
+            differences[1],
+            TabixDifference::Contig {
+                rid: 1,
+                left: Some(b"chr2".to_vec()),
+                right: Some(b"chrX".to_vec())
+            }
+        );
+        assert_eq!(differences[2], TabixDifference::Chunks { rid: 2, bin });
+        assert_eq!(
+            differences[3],
+            TabixDifference::Interval {
+                rid: 3,
+                index: tabix.sequences[3].intervals.len(),
+                left: None,
+                right: Some(1)
+            }
+        );
+        assert_eq!(
+            differences[4],
+            TabixDifference::Contig {
+                rid: (tabix.sequences.len() - 1).try_into()?,
+                left: tabix.names.last().map(|x| x[..(x.len() - 1)].to_vec()),
+                right: None
+            }
+        );
+
+        Ok(())
+    }
+}
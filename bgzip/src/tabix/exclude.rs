@@ -0,0 +1,84 @@
+use super::*;
+use std::io::Seek;
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch records of reference sequence `rid` which do not overlap with any of `excluded` regions.
+    ///
+    /// `excluded` is a list of zero-based, half-open regions, such as blacklist regions.
+    /// The regions do not need to be sorted. All chunks of the sequence are read, and each record
+    /// is checked against the merged regions with binary search.
+    pub fn fetch_excluding(
+        &mut self,
+        rid: u32,
+        excluded: &[(u64, u64)],
+    ) -> impl Iterator<Item = Result<TabixEntry, BGZFError>> + '_ {
+        let mut regions: Vec<(u64, u64)> = excluded
+            .iter()
+            .copied()
+            .filter(|(begin, end)| begin < end)
+            .collect();
+        regions.sort_unstable();
+        let mut merged: Vec<(u64, u64)> = Vec::with_capacity(regions.len());
+        for (begin, end) in regions {
+            match merged.last_mut() {
+                Some(last) if begin <= last.1 => last.1 = last.1.max(end),
+                _ => merged.push((begin, end)),
+            }
+        }
+
+        self.fetch(rid, 0, u64::MAX)
+            .filter(move |record| match record {
+                Ok(record) => {
+                    // first region which ends after the beginning of the record
+                    let i = merged.partition_point(|x| x.1 <= record.begin);
+                    merged.get(i).map(|x| x.0 >= record.end).unwrap_or(true)
+                }
+                Err(_) => true,
+            })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fetch_excluding() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr4").unwrap();
+        let all = file
+            .fetch(rid, 0, u64::MAX)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let excluded = [
+            (20_000_000, 30_000_000),
+            (1_000_000, 2_000_000),
+            (1_500_000, 5_000_000),
+            (40_000_000, 40_000_000),
+            (all[0].begin, all[0].begin + 1),
+        ];
+        let records = file
+            .fetch_excluding(rid, &excluded)
+            .collect::<Result<Vec<_>, _>>()?;
+        let expected: Vec<_> = all
+            .iter()
+            .filter(|x| {
+                excluded
+                    .iter()
+                    .all(|(begin, end)| !(x.begin < *end && *begin < x.end))
+            })
+            .cloned()
+            .collect();
+        assert!(!expected.is_empty());
+        assert!(expected.len() < all.len());
+        assert_eq!(records, expected);
+
+        assert_eq!(
+            file.fetch_excluding(rid, &[])
+                .collect::<Result<Vec<_>, _>>()?,
+            all
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,1596 @@
+use super::*;
+use std::io::{BufRead, Seek};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
+use std::time::Instant;
+
+/// A record returned by region queries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TabixEntry {
+    /// Reference sequence ID of the record
+    pub rid: u32,
+    /// Zero-based begin position of the record
+    pub begin: u64,
+    /// Zero-based, exclusive end position of the record
+    pub end: u64,
+    /// Record line without new line character
+    pub data: Vec<u8>,
+    /// BGZF virtual offset of the beginning of the record.
+    ///
+    /// This value is available only when `with_offsets` of the iterator is called.
+    pub offset: Option<VirtualOffset>,
+    /// The line starts with the meta character. Comment lines are returned only when
+    /// [`TabixRecords::with_comments`] is called, and their positions are zero.
+    pub is_comment: bool,
+    columns: ColumnCache,
+}
+
+/// Ranges of tab-separated columns in [`TabixEntry::data`], created at the first access.
+#[derive(Debug, Clone, Default)]
+struct ColumnCache(OnceLock<Vec<Range<usize>>>);
+
+impl PartialEq for ColumnCache {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Eq for ColumnCache {}
+
+impl TabixEntry {
+    /// Create a record without its virtual offset.
+    pub fn new(rid: u32, begin: u64, end: u64, data: Vec<u8>) -> Self {
+        TabixEntry {
+            rid,
+            begin,
+            end,
+            data,
+            offset: None,
+            is_comment: false,
+            columns: ColumnCache::default(),
+        }
+    }
+
+    fn column_ranges(&self) -> &[Range<usize>] {
+        self.columns.0.get_or_init(|| {
+            let mut ranges = Vec::new();
+            let mut begin = 0;
+            for i in memchr::memchr_iter(b'\t', &self.data) {
+                ranges.push(begin..i);
+                begin = i + 1;
+            }
+            ranges.push(begin..self.data.len());
+            ranges
+        })
+    }
+
+    /// Tab-separated columns of the record.
+    ///
+    /// The line is split at the first access, and the positions of columns are reused.
+    /// Columns are not updated if `data` is modified after the first access.
+    pub fn columns(&self) -> impl Iterator<Item = &[u8]> {
+        self.column_ranges()
+            .iter()
+            .map(|x| self.data.get(x.clone()).unwrap_or_default())
+    }
+
+    /// Column at zero-based `index`. `None` if the record has fewer columns.
+    pub fn column(&self, index: usize) -> Option<&[u8]> {
+        let range = self.column_ranges().get(index)?;
+        self.data.get(range.clone())
+    }
+
+    /// Number of tab-separated columns
+    pub fn column_count(&self) -> usize {
+        self.column_ranges().len()
+    }
+}
+
+/// Relation between records and a query region, which is selected with [`TabixRecords::with_overlap`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OverlapMode {
+    /// Records overlapping with the region (half-open intervals)
+    #[default]
+    Overlap,
+    /// Records entirely contained in the region
+    Contained,
+    /// Records beginning in the region, regardless of their ends
+    StartWithin,
+}
+
+impl OverlapMode {
+    /// Check record [begin, end) against region [region_begin, region_end).
+    pub fn matches(&self, begin: u64, end: u64, region_begin: u64, region_end: u64) -> bool {
+        match self {
+            OverlapMode::Overlap => begin < region_end && region_begin < end,
+            OverlapMode::Contained => region_begin <= begin && end <= region_end,
+            OverlapMode::StartWithin => region_begin <= begin && begin < region_end,
+        }
+    }
+}
+
+/// BGZF compressed text file with tabix index.
+pub struct TabixFile<R: Read + Seek, I: RegionIndex = Tabix> {
+    reader: BGZFReader<R>,
+    /// Parsed index, which is shared with clones created by [`TabixFile::try_clone`]
+    index: Arc<I>,
+    merge_gap: u64,
+    lines_scanned: u64,
+    records_returned: u64,
+    /// Path of the data file, which is reopened by [`TabixFile::try_clone`]
+    path: Option<PathBuf>,
+}
+
+/// Counters of a query, to profile index quality and access patterns. See [`TabixFile::stats`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryStats {
+    /// Blocks, bytes and seeks of the BGZF reader
+    pub read: crate::read::ReadStats,
+    /// Number of lines read, including comment lines and records outside of regions
+    pub lines_scanned: u64,
+    /// Number of records returned or counted
+    pub records_returned: u64,
+}
+
+impl TabixFile<std::fs::File> {
+    /// Open BGZF compressed file and its tabix index (`path` + `.tbi`).
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        TabixFile::from_path_with_limits(path, Limits::UNLIMITED)
+    }
+
+    /// Open BGZF compressed file and its tabix index with resource limits.
+    ///
+    /// `limits` are applied to both of the index and the data file.
+    pub fn from_path_with_limits<P: AsRef<Path>>(
+        path: P,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".tbi");
+        TabixFile::from_paths_with_limits(path, index_path, limits)
+    }
+
+    /// Open BGZF compressed file and its tabix index at `index_path`.
+    pub fn from_paths<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        index_path: Q,
+    ) -> Result<Self, BGZFError> {
+        TabixFile::from_paths_with_limits(path, index_path, Limits::UNLIMITED)
+    }
+
+    /// Open BGZF compressed file and its tabix index at `index_path` with resource limits.
+    pub fn from_paths_with_limits<P: AsRef<Path>, Q: AsRef<Path>>(
+        path: P,
+        index_path: Q,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        let index = Tabix::from_reader_with_limits(std::fs::File::open(index_path)?, limits)?;
+        let mut file = TabixFile::with_limits(std::fs::File::open(path.as_ref())?, index, limits)?;
+        file.path = Some(path.as_ref().to_path_buf());
+        Ok(file)
+    }
+}
+
+impl<I: RegionIndex> TabixFile<std::fs::File, I> {
+    /// Create an independent cursor of this file by reopening the data file.
+    ///
+    /// The parsed index is shared with the clone, not copied.
+    /// Options of the reader are copied, and counters start from zero.
+    /// Only files opened with a path, like [`TabixFile::from_path`], can be reopened.
+    pub fn try_clone(&self) -> Result<Self, BGZFError> {
+        let path = self
+            .path
+            .as_ref()
+            .ok_or(BGZFError::Other("The data file was not opened with a path"))?;
+        let mut file = self.try_clone_with(std::fs::File::open(path)?)?;
+        file.path = Some(path.clone());
+        Ok(file)
+    }
+}
+
+impl<R: Read + Seek> TabixFile<R, Tabix> {
+    /// Summaries of reference sequences in the index. See [`Tabix::contig_summaries`].
+    pub fn contigs(&self) -> Vec<ContigSummary> {
+        self.index.contig_summaries()
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Create new [`TabixFile`] from BGZF data and loaded index.
+    pub fn new(reader: R, index: I) -> Result<Self, BGZFError> {
+        TabixFile::with_limits(reader, index, Limits::UNLIMITED)
+    }
+
+    /// Create new [`TabixFile`] which rejects too large blocks and too long lines.
+    pub fn with_limits(reader: R, index: I, limits: Limits) -> Result<Self, BGZFError> {
+        TabixFile::with_shared_index(reader, Arc::new(index), limits)
+    }
+
+    /// Create new [`TabixFile`] which shares parsed `index` with other files.
+    pub(super) fn with_shared_index(
+        reader: R,
+        index: Arc<I>,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        Ok(TabixFile {
+            reader: BGZFReader::with_limits(reader, limits)?,
+            index,
+            merge_gap: 0,
+            lines_scanned: 0,
+            records_returned: 0,
+            path: None,
+        })
+    }
+
+    /// Set the path of the data file to reopen with [`TabixFile::try_clone`].
+    pub(super) fn with_path(mut self, path: &Path) -> Self {
+        self.path = Some(path.to_path_buf());
+        self
+    }
+
+    /// Create an independent cursor of the same data over another handle `reader`, sharing the parsed index.
+    pub fn try_clone_with<R2: Read + Seek>(
+        &self,
+        reader: R2,
+    ) -> Result<TabixFile<R2, I>, BGZFError> {
+        Ok(TabixFile {
+            reader: self.reader.reopen(reader)?,
+            index: Arc::clone(&self.index),
+            merge_gap: self.merge_gap,
+            lines_scanned: 0,
+            records_returned: 0,
+            path: None,
+        })
+    }
+
+    /// Counters since this file was opened or [`TabixFile::reset_stats`] was called.
+    ///
+    /// Call [`TabixFile::reset_stats`] before a fetch to get counters of the query.
+    pub fn stats(&self) -> QueryStats {
+        QueryStats {
+            read: self.reader.stats(),
+            lines_scanned: self.lines_scanned,
+            records_returned: self.records_returned,
+        }
+    }
+
+    /// Reset counters returned by [`TabixFile::stats`].
+    pub fn reset_stats(&mut self) {
+        self.reader.reset_stats();
+        self.lines_scanned = 0;
+        self.records_returned = 0;
+    }
+
+    /// Count lines read and records returned outside of this module.
+    #[cfg(feature = "rayon")]
+    pub(super) fn add_stats(&mut self, lines_scanned: u64, records_returned: u64) {
+        self.lines_scanned += lines_scanned;
+        self.records_returned += records_returned;
+    }
+
+    /// Merge chunks of a query if the gap between them is less than `gap` compressed bytes.
+    ///
+    /// By default, only overlapping chunks are merged. A gap like 64KiB makes a query a few large
+    /// sequential reads instead of many small seeks, which is faster over HTTP or object storage.
+    pub fn with_merge_gap(mut self, gap: u64) -> Self {
+        self.merge_gap = gap;
+        self
+    }
+
+    /// Keep up to `capacity` decompressed blocks in a LRU cache. See [`BGZFReader::with_block_cache`].
+    pub fn with_block_cache(self, capacity: usize) -> Self {
+        TabixFile {
+            reader: self.reader.with_block_cache(capacity),
+            ..self
+        }
+    }
+
+    /// Read and decompress up to `blocks` blocks ahead after each seek. See [`BGZFReader::with_readahead`].
+    pub fn with_readahead(self, blocks: usize) -> Self {
+        TabixFile {
+            reader: self.reader.with_readahead(blocks),
+            ..self
+        }
+    }
+
+    /// Merge nearby chunks of a query as configured with [`TabixFile::with_merge_gap`].
+    pub(super) fn plan_chunks(&self, chunks: Vec<TabixChunk>) -> Vec<TabixChunk> {
+        merge_nearby_chunks(chunks, self.merge_gap)
+    }
+
+    /// Index of this file
+    pub fn index(&self) -> &I {
+        &self.index
+    }
+
+    /// BGZF reader of this file
+    #[cfg(feature = "rayon")]
+    pub(super) fn reader_mut(&mut self) -> &mut BGZFReader<R> {
+        &mut self.reader
+    }
+
+    /// Fetch records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    ///
+    /// Records are read lazily from returned iterator, or with [`TabixRecords::next_record`].
+    /// Only the current line is kept in memory, so collect records only if all of them are needed at once.
+    pub fn fetch(&mut self, rid: u32, begin: u64, end: u64) -> TabixRecords<'_, R, I> {
+        let chunks = self.index.region_chunks(rid, begin, end);
+        self.fetch_chunks(rid, begin, end, chunks)
+    }
+
+    /// Fetch records overlapping with region [begin, end) from `chunks`, which are merged and sorted.
+    pub(super) fn fetch_chunks(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+        chunks: Vec<TabixChunk>,
+    ) -> TabixRecords<'_, R, I> {
+        let chunks = self.plan_chunks(chunks);
+        let config = self.index.config();
+        let name = self.index.rid2name(rid).unwrap_or_default().to_vec();
+        debug!(
+            "fetch {}:{}-{} (rid: {}): {} chunks",
+            String::from_utf8_lossy(&name),
+            begin,
+            end,
+            rid,
+            chunks.len()
+        );
+        TabixRecords {
+            file: self,
+            config,
+            rid,
+            name,
+            begin,
+            end,
+            chunks,
+            current_chunk: 0,
+            current_pos: None,
+            with_offsets: false,
+            with_comments: false,
+            matched: false,
+            projection: None,
+            overlap: OverlapMode::Overlap,
+            deadline: Deadline::default(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Count records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    ///
+    /// Chunks are scanned in the same way as [`TabixFile::fetch`], but records are not copied,
+    /// so this is faster to collect statistics over many regions.
+    pub fn count(&mut self, rid: u32, begin: u64, end: u64) -> Result<u64, BGZFError> {
+        self.for_each_in_region(rid, begin, end, |_| ())
+    }
+
+    /// Call `visit` with each record line overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    ///
+    /// Lines are passed without new line character in a buffer which is reused for all records,
+    /// so no memory is allocated per record. Returns the number of visited records.
+    pub fn for_each_in_region<F: FnMut(&[u8])>(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+        mut visit: F,
+    ) -> Result<u64, BGZFError> {
+        let mut records = self.fetch(rid, begin, end);
+        let mut count = 0;
+        while records.next_line()?.is_some() {
+            records.file.records_returned += 1;
+            visit(strip_line_end(&records.line));
+            count += 1;
+        }
+        Ok(count)
+    }
+
+    /// Iterate all records in the order of reference sequences in the index.
+    ///
+    /// Reading of each sequence starts from the first record offset in the index, so header lines are skipped.
+    /// Use [`TabixAllRecords::with_names`] to get the sequence name with each record.
+    pub fn records(&mut self) -> TabixAllRecords<'_, R, I> {
+        TabixAllRecords {
+            config: self.index.config(),
+            file: self,
+            rid: 0,
+            started: false,
+            with_offsets: false,
+            deadline: Deadline::default(),
+            line: Vec::new(),
+        }
+    }
+
+    /// Fetch records like [`TabixFile::fetch`], with errors converted into [`io::Error`].
+    ///
+    /// This is useful to pass records to functions expecting `io::Result`, such as
+    /// [`Iterator::try_for_each`] in I/O code.
+    pub fn fetch_iter(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+    ) -> impl Iterator<Item = io::Result<TabixEntry>> + '_ {
+        self.fetch(rid, begin, end)
+            .map(|x| x.map_err(|e| e.into_io_error()))
+    }
+
+    /// Fetch records overlapping with region [begin, end) (zero-based) of sequence `name`.
+    ///
+    /// Unlike [`TabixFile::fetch`], an unknown sequence name is reported as [`BGZFError::UnknownContig`].
+    pub fn fetch_name(
+        &mut self,
+        name: &[u8],
+        begin: u64,
+        end: u64,
+    ) -> Result<TabixRecords<'_, R, I>, BGZFError> {
+        let rid = self.index.try_name2rid(name)?;
+        Ok(self.fetch(rid, begin, end))
+    }
+
+    /// Fetch records in a region string such as `chr17:42,990,000-42,990,600` or `chr17`.
+    ///
+    /// The region is parsed with [`Region::parse`]. If the whole string is a sequence name in
+    /// the index, the whole sequence is fetched even if the name contains `:`.
+    pub fn fetch_region(&mut self, region: &str) -> Result<TabixRecords<'_, R, I>, BGZFError> {
+        let (rid, begin, end) = self.resolve_region(region)?;
+        Ok(self.fetch(rid, begin, end))
+    }
+
+    /// Convert a region string into `(rid, begin, end)` in the same way as [`TabixFile::fetch_region`].
+    pub(super) fn resolve_region(&self, region: &str) -> Result<(u32, u64, u64), BGZFError> {
+        match self.index.name2rid(region.as_bytes()) {
+            Some(rid) => Ok((rid, 0, u64::MAX)),
+            None => {
+                let region = Region::parse(region)?;
+                Ok((
+                    self.index.try_name2rid(region.name.as_bytes())?,
+                    region.begin,
+                    region.end,
+                ))
+            }
+        }
+    }
+
+    /// Header lines without new line character, like `tabix -H`.
+    ///
+    /// Header lines are leading lines which start with the meta character or are skipped by `skip` of the configuration.
+    pub fn header_lines(&mut self) -> Result<Vec<Vec<u8>>, BGZFError> {
+        self.reader.bgzf_seek(0)?;
+        let config = self.index.config();
+        let mut lines = Vec::new();
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            if self.reader.read_limited_line(&mut line)? == 0 {
+                break;
+            }
+            if (lines.len() as u64) < config.skip_lines() || config.is_meta(&line) {
+                lines.push(strip_line_end(&line).to_vec());
+            } else {
+                break;
+            }
+        }
+        Ok(lines)
+    }
+
+    /// Iterate records from BGZF virtual offset `position` to the end of file.
+    ///
+    /// `position` must point to the beginning of a line, such as [`TabixEntry::offset`].
+    /// Offsets of records are always included, so they can be used as a token to resume the iteration.
+    /// If `position` is the beginning of the file, lines skipped by `skip` of the configuration are ignored.
+    pub fn cursor_at<P: Into<VirtualOffset>>(
+        &mut self,
+        position: P,
+    ) -> Result<TabixAllRecords<'_, R, I>, BGZFError> {
+        let position = position.into();
+        self.reader.bgzf_seek(position)?;
+        let config = self.index.config();
+        let mut line = Vec::new();
+        if position == VirtualOffset::default() {
+            for _ in 0..config.skip_lines() {
+                line.clear();
+                if self.reader.read_limited_line(&mut line)? == 0 {
+                    break;
+                }
+            }
+        }
+        let rid = loop {
+            let peeked = self.reader.peek_line()?;
+            if peeked.is_empty() {
+                break self.index.sequence_count();
+            }
+            if config.is_meta(peeked) {
+                line.clear();
+                self.reader.read_limited_line(&mut line)?;
+                continue;
+            }
+            let (name, _, _) = config.parse_record(peeked)?;
+            break self.index.try_name2rid(name)?.try_into().unwrap();
+        };
+        Ok(TabixAllRecords {
+            config,
+            file: self,
+            rid,
+            started: true,
+            with_offsets: true,
+            deadline: Deadline::default(),
+            line,
+        })
+    }
+}
+
+/// An iterator over records in a region. This struct is created by [`TabixFile::fetch`].
+pub struct TabixRecords<'a, R: Read + Seek, I: RegionIndex = Tabix> {
+    file: &'a mut TabixFile<R, I>,
+    config: TabixConfig,
+    rid: u32,
+    name: Vec<u8>,
+    begin: u64,
+    end: u64,
+    chunks: Vec<TabixChunk>,
+    current_chunk: usize,
+    current_pos: Option<VirtualOffset>,
+    with_offsets: bool,
+    with_comments: bool,
+    /// A record overlapping with the region has been found
+    matched: bool,
+    /// Selected columns (1-based) and the last column number
+    projection: Option<(Vec<usize>, usize)>,
+    overlap: OverlapMode,
+    deadline: Deadline,
+    line: Vec<u8>,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> TabixRecords<'a, R, I> {
+    /// Include BGZF virtual offsets of records in [`TabixEntry::offset`].
+    pub fn with_offsets(mut self) -> Self {
+        self.with_offsets = true;
+        self
+    }
+
+    /// Fail with [`BGZFError::Timeout`] if reading of records continues after `deadline`.
+    ///
+    /// The deadline is checked when the reader moves to a new block.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline.deadline = Some(deadline);
+        self
+    }
+
+    /// Include comment lines in the region, which start with the meta character, as [`TabixEntry::is_comment`].
+    ///
+    /// Comment lines after the first record overlapping with the region are returned,
+    /// until a record after the region is found.
+    pub fn with_comments(mut self) -> Self {
+        self.with_comments = true;
+        self
+    }
+
+    /// Return only `columns` (1-based) of records in [`TabixEntry::data`], joined with tab.
+    ///
+    /// Columns are returned in the order of `columns`, and a column which is not in a record is returned as
+    /// an empty column. Lines are split only until the last selected column, and other columns are not copied.
+    /// Comment lines are not modified.
+    pub fn with_columns(mut self, columns: &[usize]) -> Self {
+        let last = columns.iter().copied().max().unwrap_or(0);
+        self.projection = Some((columns.to_vec(), last));
+        self
+    }
+
+    /// Return only records for which `filter` returns `true`.
+    ///
+    /// `filter` receives each record line overlapping with the region, without the new line character.
+    /// Records are filtered before they are copied into [`TabixEntry`]. Comment lines are not filtered.
+    pub fn with_filter<F: FnMut(&[u8]) -> bool>(self, filter: F) -> FilteredRecords<'a, R, I, F> {
+        FilteredRecords {
+            records: self,
+            filter,
+        }
+    }
+
+    /// Select records with `mode` instead of [`OverlapMode::Overlap`].
+    pub fn with_overlap(mut self, mode: OverlapMode) -> Self {
+        self.overlap = mode;
+        self
+    }
+
+    /// Iterate lines of records without new line characters, instead of [`TabixEntry`].
+    pub fn byte_lines(self) -> impl Iterator<Item = io::Result<Vec<u8>>> + 'a {
+        self.map(|x| x.map(|x| x.data).map_err(|e| e.into_io_error()))
+    }
+
+    /// Iterate lines of records as [`String`]. Lines which are not valid UTF-8 are reported as [`io::ErrorKind::InvalidData`].
+    pub fn lines(self) -> impl Iterator<Item = io::Result<String>> + 'a {
+        self.byte_lines().map(|x| {
+            String::from_utf8(x?).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        })
+    }
+
+    /// Finish this iteration and return the file.
+    pub(super) fn into_file(self) -> &'a mut TabixFile<R, I> {
+        self.file
+    }
+
+    /// Read the next record, or `None` after the last record.
+    ///
+    /// This is same as [`Iterator::next`], but errors can be propagated with `?` in a `while let` loop.
+    /// Records are read one by one from the file, so a huge region is processed in constant memory.
+    pub fn next_record(&mut self) -> Result<Option<TabixEntry>, BGZFError> {
+        Ok(self
+            .next_line()?
+            .map(|(begin, end, offset)| self.entry(begin, end, offset)))
+    }
+
+    /// Copy the current line into [`TabixEntry`].
+    fn entry(&mut self, begin: u64, end: u64, offset: Option<VirtualOffset>) -> TabixEntry {
+        self.file.records_returned += 1;
+        let line = strip_line_end(&self.line);
+        let is_comment = self.config.is_meta(line);
+        let data = match &self.projection {
+            Some((columns, last)) if !is_comment => project_columns(line, columns, *last),
+            _ => line.to_vec(),
+        };
+        TabixEntry {
+            rid: self.rid,
+            begin,
+            end,
+            data,
+            offset,
+            is_comment,
+            columns: ColumnCache::default(),
+        }
+    }
+
+    /// Read the next overlapping record or comment line into `line`, and return its region and offset.
+    fn next_line(&mut self) -> Result<Option<(u64, u64, Option<VirtualOffset>)>, BGZFError> {
+        loop {
+            let chunk = match self.chunks.get(self.current_chunk) {
+                Some(chunk) => chunk,
+                None => return Ok(None),
+            };
+            match self.current_pos {
+                Some(pos) if pos >= chunk.end => {
+                    trace!("end of chunk {} at {:#x}", self.current_chunk, pos);
+                    self.current_chunk += 1;
+                    continue;
+                }
+                Some(pos) if pos >= chunk.begin => (),
+                _ => {
+                    trace!(
+                        "seek to chunk {}: {:#x}-{:#x}",
+                        self.current_chunk,
+                        chunk.begin,
+                        chunk.end
+                    );
+                    self.file.reader.bgzf_seek(chunk.begin)?;
+                    self.current_pos = Some(chunk.begin);
+                }
+            }
+
+            self.deadline.check(self.file.reader.bgzf_pos())?;
+            let offset = self.current_pos.filter(|_| self.with_offsets);
+            self.line.clear();
+            if self.file.reader.read_limited_line(&mut self.line)? == 0 {
+                self.chunks.clear();
+                return Ok(None);
+            }
+            self.file.lines_scanned += 1;
+            // move to the next block if the line ends at the end of a block
+            self.file.reader.fill_buf()?;
+            self.current_pos = Some(self.file.reader.bgzf_pos());
+
+            if self.config.is_meta(&self.line) {
+                if self.with_comments && self.matched {
+                    return Ok(Some((0, 0, offset)));
+                }
+                continue;
+            }
+            let (name, begin, end) = self.config.parse_record(&self.line)?;
+            if name != self.name || begin >= self.end {
+                // records are sorted, so no more records overlap with the region
+                self.chunks.clear();
+                return Ok(None);
+            }
+            if !self.overlap.matches(begin, end, self.begin, self.end) {
+                continue;
+            }
+
+            self.matched = true;
+            return Ok(Some((begin, end, offset)));
+        }
+    }
+}
+
+/// An iterator over records in a region filtered by a predicate. This struct is created by [`TabixRecords::with_filter`].
+pub struct FilteredRecords<'a, R: Read + Seek, I: RegionIndex, F: FnMut(&[u8]) -> bool> {
+    records: TabixRecords<'a, R, I>,
+    filter: F,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex, F: FnMut(&[u8]) -> bool> FilteredRecords<'a, R, I, F> {
+    fn next_record(&mut self) -> Result<Option<TabixEntry>, BGZFError> {
+        while let Some((begin, end, offset)) = self.records.next_line()? {
+            let line = strip_line_end(&self.records.line);
+            if self.records.config.is_meta(line) || (self.filter)(line) {
+                return Ok(Some(self.records.entry(begin, end, offset)));
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex, F: FnMut(&[u8]) -> bool> Iterator
+    for FilteredRecords<'a, R, I, F>
+{
+    type Item = Result<TabixEntry, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Copy `columns` (1-based) of `line`, which are split until column `last`.
+fn project_columns(line: &[u8], columns: &[usize], last: usize) -> Vec<u8> {
+    let fields: Vec<&[u8]> = split_columns(line).take(last).collect();
+    let mut data = Vec::new();
+    for (i, one) in columns.iter().enumerate() {
+        if i > 0 {
+            data.push(b'\t');
+        }
+        if let Some(field) = one.checked_sub(1).and_then(|x| fields.get(x)) {
+            data.extend_from_slice(field);
+        }
+    }
+    data
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for TabixRecords<'a, R, I> {
+    type Item = Result<TabixEntry, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// An iterator over all records. This struct is created by [`TabixFile::records`].
+pub struct TabixAllRecords<'a, R: Read + Seek, I: RegionIndex = Tabix> {
+    file: &'a mut TabixFile<R, I>,
+    config: TabixConfig,
+    rid: usize,
+    started: bool,
+    with_offsets: bool,
+    deadline: Deadline,
+    line: Vec<u8>,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> TabixAllRecords<'a, R, I> {
+    /// Index of the file
+    pub fn index(&self) -> &I {
+        &self.file.index
+    }
+
+    /// Include BGZF virtual offsets of records in [`TabixEntry::offset`].
+    pub fn with_offsets(mut self) -> Self {
+        self.with_offsets = true;
+        self
+    }
+
+    /// Fail with [`BGZFError::Timeout`] if reading of records continues after `deadline`.
+    ///
+    /// The deadline is checked when the reader moves to a new block.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline.deadline = Some(deadline);
+        self
+    }
+
+    /// Return the sequence name with each record.
+    pub fn with_names(self) -> TabixNamedRecords<'a, R, I> {
+        TabixNamedRecords {
+            records: self,
+            name: None,
+        }
+    }
+
+    fn next_record(&mut self) -> Result<Option<TabixEntry>, BGZFError> {
+        loop {
+            if self.rid >= self.file.index.sequence_count() {
+                return Ok(None);
+            }
+            if !self.started {
+                match self.file.index.first_offset(self.rid.try_into().unwrap()) {
+                    Some(offset) => {
+                        trace!(
+                            "seek to the first record of rid {}: {:#x}",
+                            self.rid,
+                            offset
+                        );
+                        self.file.reader.bgzf_seek(offset)?
+                    }
+                    None => {
+                        self.rid += 1;
+                        continue;
+                    }
+                }
+                self.started = true;
+            }
+
+            self.deadline.check(self.file.reader.bgzf_pos())?;
+            let offset = if self.with_offsets {
+                // move to the next block if the previous line ends at the end of a block
+                self.file.reader.fill_buf()?;
+                Some(self.file.reader.bgzf_pos())
+            } else {
+                None
+            };
+            self.line.clear();
+            if self.file.reader.read_limited_line(&mut self.line)? == 0 {
+                self.rid = self.file.index.sequence_count();
+                return Ok(None);
+            }
+            self.file.lines_scanned += 1;
+            if self.config.is_meta(&self.line) {
+                continue;
+            }
+            let rid: u32 = self.rid.try_into().unwrap();
+            let (name, begin, end) = self.config.parse_record(&self.line)?;
+            if Some(name) != self.file.index.rid2name(rid) {
+                self.rid += 1;
+                self.started = false;
+                continue;
+            }
+
+            self.file.records_returned += 1;
+            return Ok(Some(TabixEntry {
+                rid,
+                begin,
+                end,
+                data: strip_line_end(&self.line).to_vec(),
+                offset,
+                is_comment: false,
+                columns: ColumnCache::default(),
+            }));
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for TabixAllRecords<'a, R, I> {
+    type Item = Result<TabixEntry, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// An iterator over all records with sequence names. This struct is created by [`TabixAllRecords::with_names`].
+///
+/// Items are pairs of the sequence name and a record. A name is validated as UTF-8 once,
+/// and shared by records of the same sequence.
+pub struct TabixNamedRecords<'a, R: Read + Seek, I: RegionIndex = Tabix> {
+    records: TabixAllRecords<'a, R, I>,
+    name: Option<(u32, Arc<str>)>,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> TabixNamedRecords<'a, R, I> {
+    fn next_record(&mut self) -> Result<Option<(Arc<str>, TabixEntry)>, BGZFError> {
+        let entry = match self.records.next_record()? {
+            Some(entry) => entry,
+            None => return Ok(None),
+        };
+        let name = match &self.name {
+            Some((rid, name)) if *rid == entry.rid => name.clone(),
+            _ => {
+                let name = self.records.index().rid2name(entry.rid).unwrap_or_default();
+                let name: Arc<str> = std::str::from_utf8(name)?.into();
+                self.name = Some((entry.rid, name.clone()));
+                name
+            }
+        };
+        Ok(Some((name, entry)))
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for TabixNamedRecords<'a, R, I> {
+    type Item = Result<(Arc<str>, TabixEntry), BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+/// Deadline of a query, which is checked once per block.
+#[derive(Debug, Clone, Default)]
+struct Deadline {
+    deadline: Option<Instant>,
+    last_block: Option<u64>,
+}
+
+impl Deadline {
+    fn check(&mut self, position: VirtualOffset) -> Result<(), BGZFError> {
+        let deadline = match self.deadline {
+            Some(deadline) => deadline,
+            None => return Ok(()),
+        };
+        let block = position.compressed_offset();
+        if self.last_block != Some(block) {
+            self.last_block = Some(block);
+            if Instant::now() >= deadline {
+                return Err(BGZFError::Timeout);
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Find overlapping records with a whole file scan
+    fn scan_region(
+        path: &str,
+        config: TabixConfig,
+        name: &[u8],
+        begin: u64,
+        end: u64,
+    ) -> Vec<TabixEntry> {
+        let rid = Tabix::from_reader(std::fs::File::open(format!("{}.tbi", path)).unwrap())
+            .unwrap()
+            .name2rid(name)
+            .unwrap();
+        let reader = io::BufReader::new(flate2::read::MultiGzDecoder::new(
+            std::fs::File::open(path).unwrap(),
+        ));
+        reader
+            .split(b'\n')
+            .map(|x| {
+                let mut x = x.unwrap();
+                if x.ends_with(b"\r") {
+                    x.pop();
+                }
+                x
+            })
+            .filter(|x| !config.is_meta(x))
+            .filter_map(|x| {
+                let (record_name, record_begin, record_end) = config.parse_record(&x).unwrap();
+                if record_name == name && record_begin < end && begin < record_end {
+                    Some(TabixEntry::new(rid, record_begin, record_end, x))
+                } else {
+                    None
+                }
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_fetch_bed() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        for (name, begin, end) in [
+            (&b"chr1"[..], 0, 100_000),
+            (b"chr1", 12_345_678, 13_000_000),
+            (b"chr5", 100_000_000, 100_100_000),
+            (b"chr22", 0, 1_000_000_000),
+            (b"chr22", 10, 11),
+        ] {
+            let rid = file.index().name2rid(name).unwrap();
+            let records = file.fetch(rid, begin, end).collect::<Result<Vec<_>, _>>()?;
+            let expected = scan_region(
+                "testfiles/generated.bed.gz",
+                TabixConfig::BED,
+                name,
+                begin,
+                end,
+            );
+            assert_eq!(records, expected);
+            assert_eq!(file.count(rid, begin, end)?, u64::try_from(expected.len())?);
+            let mut visited = Vec::new();
+            assert_eq!(
+                file.for_each_in_region(rid, begin, end, |line| visited.push(line.to_vec()))?,
+                u64::try_from(expected.len())?
+            );
+            assert_eq!(
+                visited,
+                expected.iter().map(|x| x.data.clone()).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(file.index().name2rid(b"chrX"), None);
+        assert_eq!(file.count(1000, 0, 100)?, 0);
+
+        let records = file
+            .fetch_region("chr5:100,000,001-100,100,000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        let expected = scan_region(
+            "testfiles/generated.bed.gz",
+            TabixConfig::BED,
+            b"chr5",
+            100_000_000,
+            100_100_000,
+        );
+        assert_eq!(records, expected);
+        assert_eq!(
+            file.fetch_name(b"chr5", 100_000_000, 100_100_000)?
+                .collect::<Result<Vec<_>, _>>()?,
+            expected
+        );
+        let records = file.fetch_region("chr22")?.collect::<Result<Vec<_>, _>>()?;
+        let expected = scan_region(
+            "testfiles/generated.bed.gz",
+            TabixConfig::BED,
+            b"chr22",
+            0,
+            u64::MAX,
+        );
+        assert_eq!(records, expected);
+        assert!(matches!(
+            file.fetch_name(b"chrX", 0, 100),
+            Err(BGZFError::UnknownContig(_))
+        ));
+        assert!(matches!(
+            file.fetch_region("chrX:1-100"),
+            Err(BGZFError::UnknownContig(name)) if name == "chrX"
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_overlap_mode() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr2").unwrap();
+        let all = file
+            .fetch(rid, 0, u64::MAX)
+            .collect::<Result<Vec<_>, _>>()?;
+        let (begin, end) = (12_345_678, 13_000_000);
+        for mode in [
+            OverlapMode::Overlap,
+            OverlapMode::Contained,
+            OverlapMode::StartWithin,
+        ] {
+            let records = file
+                .fetch(rid, begin, end)
+                .with_overlap(mode)
+                .collect::<Result<Vec<_>, _>>()?;
+            let expected: Vec<_> = all
+                .iter()
+                .filter(|x| mode.matches(x.begin, x.end, begin, end))
+                .cloned()
+                .collect();
+            assert!(!expected.is_empty());
+            assert_eq!(records, expected, "{:?}", mode);
+        }
+        assert!(OverlapMode::Overlap.matches(10, 20, 19, 30));
+        assert!(!OverlapMode::Contained.matches(10, 20, 11, 30));
+        assert!(OverlapMode::Contained.matches(10, 20, 10, 20));
+        assert!(!OverlapMode::StartWithin.matches(10, 20, 11, 30));
+        assert!(OverlapMode::StartWithin.matches(10, 40, 10, 30));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_with_offsets() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let rid = file.index().name2rid(b"1").unwrap();
+        let records = file
+            .fetch(rid, 72_000_000, 73_000_000)
+            .with_offsets()
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!records.is_empty());
+        let mut line = Vec::new();
+        for one in records {
+            file.reader.bgzf_seek(one.offset.unwrap())?;
+            line.clear();
+            BufRead::read_until(&mut file.reader, b'\n', &mut line)?;
+            assert_eq!(strip_line_end(&line), one.data);
+        }
+
+        let records = file
+            .records()
+            .with_offsets()
+            .take(10_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        for one in records {
+            file.reader.bgzf_seek(one.offset.unwrap())?;
+            line.clear();
+            BufRead::read_until(&mut file.reader, b'\n', &mut line)?;
+            assert_eq!(strip_line_end(&line), one.data);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_cursor_at() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let records = file
+            .records()
+            .with_offsets()
+            .collect::<Result<Vec<_>, _>>()?;
+        // the last record of a sequence, and the first record of the next sequence
+        let boundary = records
+            .windows(2)
+            .position(|x| x[0].rid != x[1].rid)
+            .unwrap();
+        for i in [0, 1000, boundary, boundary + 1, records.len() - 1] {
+            let resumed = file
+                .cursor_at(records[i].offset.unwrap())?
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(resumed, records[i..]);
+        }
+
+        // header lines are skipped
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let first = file.cursor_at(0)?.next().unwrap()?;
+        assert_eq!(first.rid, 0);
+        assert!(first.data.starts_with(b"1\t"));
+
+        let reader = BGZFReader::at(
+            std::fs::File::open("testfiles/generated.bed.gz")?,
+            records[1000].offset.unwrap(),
+        )?;
+        let line = reader.split(b'\n').next().unwrap()?;
+        assert_eq!(strip_line_end(&line), records[1000].data);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_comments() -> anyhow::Result<()> {
+        let data = b"#header\nchr1\t10\t20\ta\n#inside\nchr1\t30\t40\tb\n#after\nchr1\t50\t60\tc\n";
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::new(&mut compressed, Compression::default());
+        writer.write_all(data)?;
+        writer.close()?;
+        let index = index_bgzf(&compressed[..], TabixConfig::BED)?;
+        let mut file = TabixFile::new(io::Cursor::new(compressed), index)?;
+
+        let records = file.fetch(0, 15, 35).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            records.iter().map(|x| &x.data[..]).collect::<Vec<_>>(),
+            vec![&b"chr1\t10\t20\ta"[..], b"chr1\t30\t40\tb"]
+        );
+        let records = file
+            .fetch(0, 15, 35)
+            .with_comments()
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            records
+                .iter()
+                .map(|x| (&x.data[..], x.is_comment))
+                .collect::<Vec<_>>(),
+            vec![
+                (&b"chr1\t10\t20\ta"[..], false),
+                (b"#inside", true),
+                (b"chr1\t30\t40\tb", false),
+                (b"#after", true)
+            ]
+        );
+        assert_eq!(file.count(0, 15, 35)?, 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_skip_lines() -> anyhow::Result<()> {
+        let data = b"chrom\tstart\tend\n#comment\nchr1\t10\t20\ta\nchr1\t30\t40\tb\n";
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::new(&mut compressed, Compression::default());
+        writer.write_all(data)?;
+        writer.close()?;
+        let config = TabixConfig::BED.with_skip(1);
+        assert_eq!(config.skip_lines(), 1);
+        assert_eq!(TabixConfig::BED.with_skip(-1).skip_lines(), 0);
+        assert!(index_bgzf(&compressed[..], TabixConfig::BED).is_err());
+        let index = index_bgzf(&compressed[..], config)?;
+        assert_eq!(index.config(), config);
+        let mut file = TabixFile::new(io::Cursor::new(compressed), index)?;
+
+        assert_eq!(
+            file.header_lines()?,
+            vec![b"chrom\tstart\tend".to_vec(), b"#comment".to_vec()]
+        );
+        let records = file.fetch(0, 0, 100).collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(records.len(), 2);
+        let from_start = file
+            .cursor_at(0)?
+            .map(|x| x.map(|x| x.data))
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            from_start,
+            vec![b"chr1\t10\t20\ta".to_vec(), b"chr1\t30\t40\tb".to_vec()]
+        );
+        assert_eq!(file.records().count(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_entry_columns() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let rid = file.index().name2rid(b"1").unwrap();
+        let record = file.fetch(rid, 72_700_624, 72_700_625).next().unwrap()?;
+        assert_eq!(record.rid, rid);
+        assert_eq!(record.column_count(), 8);
+        assert_eq!(record.column(2), Some(&b"rs12116859"[..]));
+        assert_eq!(record.column(8), None);
+        assert_eq!(
+            record.columns().take(5).collect::<Vec<_>>(),
+            vec![&b"1"[..], b"72700625", b"rs12116859", b"T", b"A,C"]
+        );
+
+        let entry = TabixEntry::new(3, 0, 1, b"a\t\tb".to_vec());
+        assert_eq!(
+            entry.columns().collect::<Vec<_>>(),
+            vec![&b"a"[..], b"", b"b"]
+        );
+        assert_eq!(entry, TabixEntry::new(3, 0, 1, b"a\t\tb".to_vec()));
+        assert_eq!(TabixEntry::new(3, 0, 1, Vec::new()).column_count(), 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_columns() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let rid = file.index().name2rid(b"1").unwrap();
+        let records = file
+            .fetch(rid, 72_700_624, 72_800_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let projected = file
+            .fetch(rid, 72_700_624, 72_800_000)
+            .with_columns(&[1, 2, 8])
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!records.is_empty());
+        assert_eq!(records.len(), projected.len());
+        for (record, projected) in records.iter().zip(&projected) {
+            assert_eq!((record.begin, record.end), (projected.begin, projected.end));
+            assert_eq!(
+                projected.columns().collect::<Vec<_>>(),
+                vec![
+                    record.column(0).unwrap(),
+                    record.column(1).unwrap(),
+                    record.column(7).unwrap()
+                ]
+            );
+        }
+
+        assert_eq!(project_columns(b"a\tb\tc\td", &[3, 1], 3), b"c\ta");
+        assert_eq!(project_columns(b"a\tb", &[2, 5, 0], 5), b"b\t\t");
+        assert_eq!(project_columns(b"a\tb", &[], 0), b"");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_filter() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        let records = file
+            .fetch(rid, 0, 10_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let long_record = |x: &[u8]| {
+            let (_, begin, end) = TabixConfig::BED.parse_record(x).unwrap();
+            end - begin > 1000
+        };
+        let mut called = 0;
+        let filtered = file
+            .fetch(rid, 0, 10_000_000)
+            .with_filter(|x| {
+                called += 1;
+                long_record(x)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        let expected: Vec<_> = records
+            .iter()
+            .filter(|x| long_record(&x.data))
+            .cloned()
+            .collect();
+        assert!(!expected.is_empty());
+        assert!(expected.len() < records.len());
+        assert_eq!(filtered, expected);
+        assert_eq!(called, records.len());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_merge_gap() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut merged_file =
+            TabixFile::from_path("testfiles/generated.bed.gz")?.with_merge_gap(1 << 20);
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        let chunks = file.index().region_chunks(rid, 10_000_000, 20_000_000);
+        let planned = merged_file.plan_chunks(chunks.clone());
+        assert!(planned.len() < chunks.len());
+        assert_eq!(
+            planned.first().map(|x| x.begin),
+            chunks.first().map(|x| x.begin)
+        );
+        assert_eq!(planned.last().map(|x| x.end), chunks.last().map(|x| x.end));
+        assert_eq!(file.plan_chunks(chunks.clone()), chunks);
+
+        for (begin, end) in [(10_000_000, 20_000_000), (0, 1_000_000), (0, u64::MAX)] {
+            assert_eq!(
+                merged_file
+                    .fetch(rid, begin, end)
+                    .collect::<Result<Vec<_>, _>>()?,
+                file.fetch(rid, begin, end).collect::<Result<Vec<_>, _>>()?
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_query_stats() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        file.reset_stats();
+        assert_eq!(file.stats(), QueryStats::default());
+
+        let records = file
+            .fetch(rid, 10_000_000, 20_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let stats = file.stats();
+        assert_eq!(stats.records_returned, u64::try_from(records.len())?);
+        assert!(stats.lines_scanned > stats.records_returned);
+        assert!(stats.read.blocks_read > 0);
+        assert!(stats.read.compressed_bytes > 0);
+        assert!(
+            stats.read.uncompressed_bytes
+                >= records.iter().map(|x| x.data.len() as u64 + 1).sum::<u64>()
+        );
+        // reading stops at a record after the region, so later chunks may not be visited
+        let chunks = file.index().region_chunks(rid, 10_000_000, 20_000_000);
+        assert!((1..=u64::try_from(chunks.len())?).contains(&stats.read.seeks));
+
+        let mut merged_file =
+            TabixFile::from_path("testfiles/generated.bed.gz")?.with_merge_gap(1 << 20);
+        merged_file.reset_stats();
+        assert_eq!(
+            merged_file.count(rid, 10_000_000, 20_000_000)?,
+            stats.records_returned
+        );
+        let merged_stats = merged_file.stats();
+        assert_eq!(merged_stats.records_returned, stats.records_returned);
+        assert!(merged_stats.read.seeks <= stats.read.seeks);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_try_clone() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?.with_merge_gap(0x10000);
+        let mut cloned = file.try_clone()?;
+        assert!(std::ptr::eq(file.index(), cloned.index()));
+
+        let rid = file.index().name2rid(b"chr2").unwrap();
+        let mut records = file.fetch(rid, 1_000_000, 3_000_000);
+        let first = records.next().unwrap()?;
+        // the clone has its own position
+        let expected = cloned
+            .fetch(rid, 0, 3_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let mut rest = vec![first];
+        for one in records {
+            rest.push(one?);
+        }
+        assert!(expected.ends_with(&rest));
+        assert_eq!(
+            cloned.stats().records_returned,
+            u64::try_from(expected.len())?
+        );
+
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let mut one = file.try_clone()?;
+                Ok(std::thread::spawn(move || {
+                    one.fetch(rid, 1_000_000, 3_000_000)
+                        .collect::<Result<Vec<_>, _>>()
+                }))
+            })
+            .collect::<Result<_, BGZFError>>()?;
+        for one in handles {
+            assert_eq!(one.join().unwrap()?, rest);
+        }
+
+        let from_reader = TabixFile::new(
+            std::fs::File::open("testfiles/generated.bed.gz")?,
+            file.index().clone(),
+        )?;
+        assert!(matches!(from_reader.try_clone(), Err(BGZFError::Other(_))));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_lines() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        let expected: Vec<_> = file
+            .fetch(rid, 12_345_678, 13_000_000)
+            .map(|x| x.map(|x| x.data))
+            .collect::<Result<_, _>>()?;
+        assert!(!expected.is_empty());
+        let lines = file
+            .fetch(rid, 12_345_678, 13_000_000)
+            .byte_lines()
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(lines, expected);
+        let lines = file
+            .fetch(rid, 12_345_678, 13_000_000)
+            .with_columns(&[1, 2])
+            .lines()
+            .collect::<io::Result<Vec<_>>>()?;
+        assert_eq!(lines.len(), expected.len());
+        assert!(lines[0].starts_with("chr1\t"));
+        Ok(())
+    }
+
+    #[test]
+    fn test_block_cache() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut cached_file =
+            TabixFile::from_path("testfiles/generated.bed.gz")?.with_block_cache(64);
+        let rid = file.index().name2rid(b"chr2").unwrap();
+        let regions = [
+            (1_000_000, 3_000_000),
+            (2_000_000, 4_000_000),
+            (0, 1_000),
+            (1_000_000, 3_000_000),
+        ];
+        let mut expected = Vec::new();
+        for (begin, end) in regions {
+            expected.push(file.fetch(rid, begin, end).collect::<Result<Vec<_>, _>>()?);
+        }
+
+        cached_file.reset_stats();
+        for ((begin, end), expected) in regions.iter().zip(&expected).take(3) {
+            assert_eq!(
+                &cached_file
+                    .fetch(rid, *begin, *end)
+                    .collect::<Result<Vec<_>, _>>()?,
+                expected
+            );
+        }
+        let stats = cached_file.stats();
+        assert!(stats.read.cache_hits > 0);
+        assert_eq!(stats.read.cache_misses, stats.read.blocks_read);
+
+        // all blocks of the same region are cached
+        cached_file.reset_stats();
+        assert_eq!(
+            cached_file
+                .fetch(rid, regions[3].0, regions[3].1)
+                .collect::<Result<Vec<_>, _>>()?,
+            expected[3]
+        );
+        let stats = cached_file.stats();
+        assert!(stats.read.cache_hits > 0);
+        assert_eq!(stats.read.blocks_read, 0);
+        assert_eq!(stats.read.seeks, 0);
+
+        // the underlying reader is moved after cached blocks are used
+        assert_eq!(cached_file.header_lines()?, file.header_lines()?);
+        assert_eq!(
+            cached_file
+                .fetch(rid, 10_000_000, 12_000_000)
+                .collect::<Result<Vec<_>, _>>()?,
+            file.fetch(rid, 10_000_000, 12_000_000)
+                .collect::<Result<Vec<_>, _>>()?
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_deadline() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+
+        let mut records = file
+            .fetch(rid, 0, 100_000_000)
+            .with_deadline(Instant::now());
+        assert!(matches!(records.next(), Some(Err(BGZFError::Timeout))));
+        let mut records = file.records().with_deadline(Instant::now());
+        assert!(matches!(records.next(), Some(Err(BGZFError::Timeout))));
+
+        let deadline = Instant::now() + std::time::Duration::from_secs(3600);
+        let with_deadline = file
+            .fetch(rid, 0, 100_000_000)
+            .with_deadline(deadline)
+            .collect::<Result<Vec<_>, _>>()?;
+        let without_deadline = file
+            .fetch(rid, 0, 100_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(with_deadline, without_deadline);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_vcf() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        for (name, begin, end) in [
+            (&b"1"[..], 0, 1_000_000),
+            (b"1", 72_700_000, 72_800_000),
+            (b"11", 1_000_000, 2_000_000),
+            (b"Y", 0, 100_000_000),
+        ] {
+            let rid = file.index().name2rid(name).unwrap();
+            let records = file.fetch(rid, begin, end).collect::<Result<Vec<_>, _>>()?;
+            let expected = scan_region(
+                "testfiles/common_all_20180418_half.vcf.gz",
+                TabixConfig::VCF,
+                name,
+                begin,
+                end,
+            );
+            assert!(!records.is_empty());
+            assert_eq!(records, expected);
+
+            let lines = file
+                .fetch_iter(rid, begin, end)
+                .map(|x| x.map(|x| x.data))
+                .take(3)
+                .collect::<io::Result<Vec<_>>>()?;
+            assert_eq!(
+                lines,
+                expected
+                    .iter()
+                    .take(3)
+                    .map(|x| x.data.clone())
+                    .collect::<Vec<_>>()
+            );
+        }
+
+        let expected: Vec<Vec<u8>> = crate::open("testfiles/common_all_20180418_half.vcf.gz")?
+            .split(b'\n')
+            .map(|x| x.unwrap())
+            .take_while(|x| x.starts_with(b"#"))
+            .collect();
+        let header = file.header_lines()?;
+        assert!(header.last().unwrap().starts_with(b"#CHROM"));
+        assert_eq!(header, expected);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_all_records() -> anyhow::Result<()> {
+        for (path, config) in [
+            ("testfiles/generated.bed.gz", TabixConfig::BED),
+            (
+                "testfiles/common_all_20180418_half.vcf.gz",
+                TabixConfig::VCF,
+            ),
+        ] {
+            let mut file = TabixFile::from_path(path)?;
+            let records = file.records().collect::<Result<Vec<_>, _>>()?;
+            let expected: Vec<_> = io::BufReader::new(flate2::read::MultiGzDecoder::new(
+                std::fs::File::open(path)?,
+            ))
+            .split(b'\n')
+            .map(|x| x.unwrap())
+            .filter(|x| !config.is_meta(x))
+            .map(|x| {
+                let (name, begin, end) = config.parse_record(&x).unwrap();
+                let rid = file.index().name2rid(name).unwrap();
+                let data = strip_line_end(&x).to_vec();
+                TabixEntry::new(rid, begin, end, data)
+            })
+            .collect();
+            assert_eq!(records.len(), expected.len());
+            assert!(records == expected);
+
+            let named = file.records().with_names().collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(named.len(), records.len());
+            for ((name, entry), expected) in named.iter().zip(&records) {
+                assert_eq!(entry, expected);
+                assert_eq!(&**name, file.index().rid2name_str(entry.rid)?);
+            }
+        }
+        Ok(())
+    }
+}
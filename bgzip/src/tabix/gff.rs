@@ -0,0 +1,168 @@
+use super::*;
+use std::io::Seek;
+
+/// A parsed GFF3 record.
+///
+/// The attributes column is kept as is, and parsed when it is accessed.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GffRecord {
+    pub seqid: String,
+    pub source: String,
+    pub feature_type: String,
+    /// 1-based start position
+    pub start: u64,
+    /// 1-based, inclusive end position
+    pub end: u64,
+    /// `None` if the column is `.`.
+    pub score: Option<f64>,
+    /// `+`, `-` or `?`. `None` if the column is `.`.
+    pub strand: Option<char>,
+    /// `None` if the column is `.`.
+    pub phase: Option<u8>,
+    /// Raw attributes column
+    pub attributes: String,
+}
+
+impl GffRecord {
+    /// Parse a GFF3 data line. A new line character at the end is ignored.
+    pub fn from_line(line: &[u8]) -> Result<Self, BGZFError> {
+        let line = std::str::from_utf8(strip_line_end(line))?;
+        let mut columns = line.split('\t');
+        let mut column_number = 0;
+        let mut next_column = || {
+            column_number += 1;
+            columns.next().ok_or_else(|| parse_error(column_number))
+        };
+
+        let seqid = next_column()?.to_string();
+        let source = next_column()?.to_string();
+        let feature_type = next_column()?.to_string();
+        let start = next_column()?.parse().map_err(|_| parse_error(4))?;
+        let end = next_column()?.parse().map_err(|_| parse_error(5))?;
+        let score = optional(next_column()?)
+            .map(|x| x.parse())
+            .transpose()
+            .map_err(|_| parse_error(6))?;
+        let strand = match optional(next_column()?) {
+            None => None,
+            Some(value @ ("+" | "-" | "?")) => value.chars().next(),
+            Some(_) => return Err(parse_error(7)),
+        };
+        let phase = optional(next_column()?)
+            .map(|x| x.parse())
+            .transpose()
+            .map_err(|_| parse_error(8))?;
+        let attributes = next_column().unwrap_or(".").to_string();
+
+        Ok(GffRecord {
+            seqid,
+            source,
+            feature_type,
+            start,
+            end,
+            score,
+            strand,
+            phase,
+            attributes,
+        })
+    }
+
+    /// Iterate key-value pairs in the attributes column. Values are not unescaped.
+    pub fn attributes(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.attributes
+            .split(';')
+            .map(|x| x.trim())
+            .filter(|x| !x.is_empty() && *x != ".")
+            .map(|x| x.split_once('=').unwrap_or((x, "")))
+    }
+
+    /// Get a value of an attribute.
+    pub fn attribute(&self, key: &str) -> Option<&str> {
+        self.attributes()
+            .find(|(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Value of `ID` attribute
+    pub fn id(&self) -> Option<&str> {
+        self.attribute("ID")
+    }
+
+    /// Values of `Parent` attribute
+    pub fn parents(&self) -> Vec<&str> {
+        self.attribute("Parent")
+            .map(|x| x.split(',').collect())
+            .unwrap_or_default()
+    }
+}
+
+/// `None` if `value` is `.`
+fn optional(value: &str) -> Option<&str> {
+    if value == "." {
+        None
+    } else {
+        Some(value)
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch GFF3 records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    pub fn fetch_gff(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+    ) -> impl Iterator<Item = Result<GffRecord, BGZFError>> + '_ {
+        self.fetch(rid, begin, end)
+            .map(|x| x.and_then(|x| GffRecord::from_line(&x.data)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const GFF: &[u8] = b"##gff-version 3
+ctg123\t.\tgene\t1000\t9000\t.\t+\t.\tID=gene00001;Name=EDEN
+ctg123\t.\tmRNA\t1050\t9000\t.\t+\t.\tID=mRNA00001;Parent=gene00001;Name=EDEN.1
+ctg123\t.\texon\t1300\t1500\t.\t+\t.\tID=exon00001;Parent=mRNA00003
+ctg123\t.\tCDS\t3000\t3902\t0.5\t+\t0\tID=cds00001;Parent=mRNA00001,mRNA00002
+ctg123\t.\tCDS\t5000\t5500\t.\t+\t1\tID=cds00002;Parent=mRNA00001
+";
+
+    #[test]
+    fn test_gff_record() -> anyhow::Result<()> {
+        let record = GffRecord::from_line(
+            b"ctg123\t.\tCDS\t3000\t3902\t0.5\t+\t0\tID=cds00001;Parent=mRNA00001,mRNA00002\n",
+        )?;
+        assert_eq!(record.seqid, "ctg123");
+        assert_eq!(record.source, ".");
+        assert_eq!(record.feature_type, "CDS");
+        assert_eq!((record.start, record.end), (3000, 3902));
+        assert_eq!(record.score, Some(0.5));
+        assert_eq!(record.strand, Some('+'));
+        assert_eq!(record.phase, Some(0));
+        assert_eq!(record.id(), Some("cds00001"));
+        assert_eq!(record.parents(), ["mRNA00001", "mRNA00002"]);
+        assert_eq!(record.attribute("Name"), None);
+        assert!(GffRecord::from_line(b"ctg123\t.\tCDS\t3000\t3902\t.\tx\t.\t.").is_err());
+
+        let mut writer = BGZFWriter::new(
+            std::fs::File::create("tmp/test-gff.gff3.gz")?,
+            Compression::default(),
+        );
+        let index = compress_and_index(GFF, &mut writer, TabixConfig::GFF)?;
+        writer.close()?;
+        let mut file = TabixFile::new(std::fs::File::open("tmp/test-gff.gff3.gz")?, index)?;
+        let rid = file.index().name2rid(b"ctg123").unwrap();
+        let records = file
+            .fetch_gff(rid, 1400, 3000)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert_eq!(
+            records.iter().map(|x| x.id().unwrap()).collect::<Vec<_>>(),
+            ["gene00001", "mRNA00001", "exon00001", "cds00001"]
+        );
+
+        Ok(())
+    }
+}
@@ -0,0 +1,161 @@
+use super::*;
+use std::io::{BufRead, Seek};
+
+const KEY_INDEX_MAGIC: [u8; 4] = [b'K', b'I', b'X', 1];
+
+/// Secondary index which maps values of a column, such as rsID or gene ID, to virtual offsets of records.
+///
+/// This index is a sidecar of a tabix index, and records are fetched with [`TabixFile::fetch_key`].
+/// Empty values and `.` are not indexed. The index is saved as BGZF compressed file like tabix index.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyIndex {
+    /// Indexed column (1-based)
+    pub column: u32,
+    /// Pairs of column values and virtual offsets of records, sorted by the values and offsets
+    pub entries: Vec<(Vec<u8>, VirtualOffset)>,
+}
+
+impl KeyIndex {
+    /// Scan BGZF compressed text file, and index values of `column` (1-based).
+    ///
+    /// Header lines are skipped with `config`, as same as [`index_bgzf`].
+    pub fn build<R: Read>(reader: R, config: TabixConfig, column: u32) -> Result<Self, BGZFError> {
+        if column == 0 {
+            return Err(BGZFError::Other("Column number must be 1 or larger"));
+        }
+        let mut reader = BGZFReader::new(reader)?;
+        let mut entries = Vec::new();
+        let mut line = Vec::new();
+        let mut line_number = 0u64;
+        loop {
+            let offset = reader.bgzf_pos();
+            line.clear();
+            if BufRead::read_until(&mut reader, b'\n', &mut line)? == 0 {
+                break;
+            }
+            // move to the next block if the line ends at the end of a block
+            reader.fill_buf()?;
+            line_number += 1;
+            if line_number <= config.skip_lines() || config.is_meta(&line) {
+                continue;
+            }
+            let value = split_columns(strip_line_end(&line))
+                .nth(TryInto::<usize>::try_into(column - 1).unwrap());
+            match value {
+                Some(value) if !value.is_empty() && value != b"." => {
+                    entries.push((value.to_vec(), offset))
+                }
+                _ => (),
+            }
+        }
+        entries.sort();
+        Ok(KeyIndex { column, entries })
+    }
+
+    /// Load BGZF compressed key index.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, BGZFError> {
+        let mut reader = BGZFReader::new(reader)?;
+        let mut magic = [0u8; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != KEY_INDEX_MAGIC {
+            return Err(BGZFError::InvalidMagic("key index"));
+        }
+        let column = reader.read_le_u32()?;
+        let number_of_entries = reader.read_le_u64()?;
+        let mut entries = Vec::new();
+        for _ in 0..number_of_entries {
+            let length = reader.read_le_u32()?;
+            let mut key = Vec::new();
+            (&mut reader).take(length.into()).read_to_end(&mut key)?;
+            if key.len() != TryInto::<usize>::try_into(length).unwrap() {
+                return Err(BGZFError::BadIndex("Unexpected end of key index"));
+            }
+            let offset = reader.read_le_u64()?.into();
+            entries.push((key, offset));
+        }
+        if entries.windows(2).any(|x| x[0] > x[1]) {
+            return Err(BGZFError::BadIndex("Keys are not sorted"));
+        }
+        Ok(KeyIndex { column, entries })
+    }
+
+    /// Write BGZF compressed key index.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), BGZFError> {
+        let mut writer = BGZFWriter::new(writer, Compression::default());
+        writer.write_all(&KEY_INDEX_MAGIC)?;
+        writer.write_all(&self.column.to_le_bytes())?;
+        writer.write_all(&(self.entries.len() as u64).to_le_bytes())?;
+        for (key, offset) in &self.entries {
+            let length: u32 = key
+                .len()
+                .try_into()
+                .map_err(|_| BGZFError::Other("Too long key"))?;
+            writer.write_all(&length.to_le_bytes())?;
+            writer.write_all(key)?;
+            writer.write_all(&u64::from(*offset).to_le_bytes())?;
+        }
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Virtual offsets of records whose value is `key`, in the file order.
+    pub fn offsets(&self, key: &[u8]) -> impl Iterator<Item = VirtualOffset> + '_ {
+        let begin = self.entries.partition_point(|x| x.0.as_slice() < key);
+        let end = self.entries.partition_point(|x| x.0.as_slice() <= key);
+        self.entries[begin..end].iter().map(|x| x.1)
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch records whose value of the indexed column is `key`, with secondary index `index`.
+    ///
+    /// Returned records have their virtual offsets.
+    pub fn fetch_key(
+        &mut self,
+        index: &KeyIndex,
+        key: &[u8],
+    ) -> Result<Vec<TabixEntry>, BGZFError> {
+        let mut records = Vec::new();
+        for offset in index.offsets(key) {
+            match self.cursor_at(offset)?.next() {
+                Some(record) => records.push(record?),
+                None => {
+                    return Err(BGZFError::BadIndex(
+                        "Key index points after the last record",
+                    ))
+                }
+            }
+        }
+        Ok(records)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_key_index() -> anyhow::Result<()> {
+        let path = "testfiles/common_all_20180418_half.vcf.gz";
+        let index = KeyIndex::build(File::open(path)?, TabixConfig::VCF, 3)?;
+        assert!(!index.entries.is_empty());
+        index.write(File::create("tmp/test-key-index.vcf.gz.kix")?)?;
+        let loaded = KeyIndex::from_reader(File::open("tmp/test-key-index.vcf.gz.kix")?)?;
+        assert_eq!(loaded, index);
+
+        let mut file = TabixFile::from_path(path)?;
+        let records = file.fetch_key(&loaded, b"rs12116859")?;
+        assert_eq!(records.len(), 1);
+        let record = &records[0];
+        assert_eq!(file.index().rid2name(record.rid), Some(&b"1"[..]));
+        assert_eq!(record.begin, 72700624);
+        assert!(record.data.starts_with(b"1\t72700625\trs12116859\t"));
+        assert_eq!(record.offset, Some(VirtualOffset::from(4210818610)));
+
+        assert!(file.fetch_key(&loaded, b"rs0")?.is_empty());
+        assert!(KeyIndex::build(File::open(path)?, TabixConfig::VCF, 0).is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,371 @@
+use super::*;
+use std::ops::Range;
+
+/// Tabix index viewed over a buffer of the decompressed index, without copying chunk lists.
+///
+/// The buffer must contain a decompressed `.tbi` file or a cache written by [`Tabix::write_cache`].
+/// Only offsets of bins and names are parsed at creation, and chunks are decoded from the buffer
+/// at each query. With `mmap` feature, [`MappedTabix::open`] maps a file into memory, so that many
+/// indexes can be opened without loading them into the heap.
+#[derive(Debug)]
+pub struct MappedTabix<B: AsRef<[u8]>> {
+    data: B,
+    config: TabixConfig,
+    names: Vec<Range<usize>>,
+    sequences: Vec<MappedSequence>,
+    number_of_unplaced: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct MappedSequence {
+    /// Bin number to range of its chunk list in the buffer
+    bins: HashMap<u32, Range<usize>>,
+    intervals: Range<usize>,
+}
+
+/// Chunks of a bin as a slice of the buffer of [`MappedTabix`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkSlice<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ChunkSlice<'a> {
+    /// Number of chunks
+    pub fn len(&self) -> usize {
+        self.data.len() / 16
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.data.is_empty()
+    }
+
+    /// Chunk at `index`
+    pub fn get(&self, index: usize) -> Option<TabixChunk> {
+        let one = self.data.get(index * 16..(index + 1) * 16)?;
+        Some(TabixChunk {
+            begin: read_u64(one, 0).into(),
+            end: read_u64(one, 8).into(),
+        })
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = TabixChunk> + 'a {
+        let data = self.data;
+        data.chunks_exact(16).map(|x| TabixChunk {
+            begin: read_u64(x, 0).into(),
+            end: read_u64(x, 8).into(),
+        })
+    }
+}
+
+fn read_u64(data: &[u8], offset: usize) -> u64 {
+    u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+}
+
+/// Cursor to walk through the buffer with bounds checking
+struct ByteCursor<'a> {
+    data: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteCursor<'a> {
+    fn take(&mut self, length: usize) -> Result<Range<usize>, BGZFError> {
+        let end = self
+            .position
+            .checked_add(length)
+            .filter(|x| *x <= self.data.len())
+            .ok_or(BGZFError::BadIndex("Unexpected end of index"))?;
+        let range = self.position..end;
+        self.position = end;
+        Ok(range)
+    }
+
+    fn read_i32(&mut self) -> Result<i32, BGZFError> {
+        let range = self.take(4)?;
+        Ok(i32::from_le_bytes(self.data[range].try_into().unwrap()))
+    }
+
+    fn read_u32(&mut self) -> Result<u32, BGZFError> {
+        let range = self.take(4)?;
+        Ok(u32::from_le_bytes(self.data[range].try_into().unwrap()))
+    }
+
+    fn read_count(&mut self, limit: u32, message: &'static str) -> Result<usize, BGZFError> {
+        let count = self.read_i32()?;
+        let count = u32::try_from(count).map_err(|_| BGZFError::BadIndex("Negative count"))?;
+        if count > limit {
+            return Err(BGZFError::LimitExceeded(message));
+        }
+        Ok(count.try_into().unwrap())
+    }
+}
+
+impl<B: AsRef<[u8]>> MappedTabix<B> {
+    /// Parse offsets of a decompressed tabix index or an index cache in `data`.
+    pub fn new(data: B) -> Result<Self, BGZFError> {
+        MappedTabix::with_limits(data, Limits::default())
+    }
+
+    /// Parse offsets of a decompressed tabix index or an index cache in `data`, with resource limits.
+    pub fn with_limits(data: B, limits: Limits) -> Result<Self, BGZFError> {
+        let bytes = data.as_ref();
+        let mut cursor = ByteCursor {
+            data: bytes,
+            position: 0,
+        };
+        let magic = cursor.take(4)?;
+        if bytes[magic.clone()] == [b'T', b'B', b'C', 1] {
+            // skip checksum of the index cache
+            cursor.take(4)?;
+            let magic = cursor.take(4)?;
+            if bytes[magic] != [b'T', b'B', b'I', 1] {
+                return Err(BGZFError::NotTabix);
+            }
+        } else if bytes[magic] != [b'T', b'B', b'I', 1] {
+            return Err(BGZFError::NotTabix);
+        }
+
+        let number_of_references =
+            cursor.read_count(limits.max_references, "Too many reference sequences")?;
+        let format = cursor.read_i32()?;
+        let column_for_sequence = cursor.read_i32()?;
+        let column_for_begin = cursor.read_i32()?;
+        let column_for_end = cursor.read_i32()?;
+        let meta = bytes[cursor.take(4)?][0];
+        let skip = cursor.read_i32()?;
+        let names_length = cursor.read_count(limits.max_names_length, "Too long sequence names")?;
+        let names_range = cursor.take(names_length)?;
+        let mut names = Vec::new();
+        let mut begin = names_range.start;
+        for (i, one) in bytes[names_range.clone()].iter().enumerate() {
+            if *one == 0 {
+                names.push(begin..names_range.start + i);
+                begin = names_range.start + i + 1;
+            }
+        }
+        if begin < names_range.end {
+            names.push(begin..names_range.end);
+        }
+
+        let mut sequences = Vec::new();
+        for _ in 0..number_of_references {
+            let number_of_bins = cursor.read_count(i32::MAX as u32, "Too many bins")?;
+            let mut bins = HashMap::new();
+            for _ in 0..number_of_bins {
+                let bin = cursor.read_u32()?;
+                let number_of_chunks =
+                    cursor.read_count(limits.max_chunks_per_bin, "Too many chunks in a bin")?;
+                bins.insert(bin, cursor.take(number_of_chunks * 16)?);
+            }
+            let number_of_intervals = cursor.read_count(i32::MAX as u32, "Too many intervals")?;
+            let intervals = cursor.take(number_of_intervals * 8)?;
+            sequences.push(MappedSequence { bins, intervals });
+        }
+        let number_of_unplaced = cursor.take(8).ok().map(|x| read_u64(bytes, x.start));
+
+        Ok(MappedTabix {
+            data,
+            config: TabixConfig {
+                format,
+                column_for_sequence,
+                column_for_begin,
+                column_for_end,
+                meta,
+                skip,
+            },
+            names,
+            sequences,
+            number_of_unplaced,
+        })
+    }
+
+    /// Number of records without coordinates. `None` if this optional field is not in the index.
+    pub fn number_of_unplaced(&self) -> Option<u64> {
+        self.number_of_unplaced
+    }
+
+    /// Chunks of `bin` in reference sequence `rid`, without copying them.
+    pub fn chunks(&self, rid: u32, bin: u32) -> Option<ChunkSlice<'_>> {
+        let range = self
+            .sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?
+            .bins
+            .get(&bin)?;
+        Some(ChunkSlice {
+            data: &self.data.as_ref()[range.clone()],
+        })
+    }
+
+    /// Virtual offset of the first record overlapping with each 16kbp window of reference sequence `rid`.
+    pub fn intervals(&self, rid: u32) -> Option<impl ExactSizeIterator<Item = u64> + '_> {
+        let range = self
+            .sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?
+            .intervals
+            .clone();
+        Some(
+            self.data.as_ref()[range]
+                .chunks_exact(8)
+                .map(|x| read_u64(x, 0)),
+        )
+    }
+
+    /// Metadata in the pseudo-bin of reference sequence `rid`.
+    pub fn pseudo_bin(&self, rid: u32) -> Option<PseudoBin> {
+        let chunks = self.chunks(rid, META_BIN)?;
+        match (chunks.len(), chunks.get(0), chunks.get(1)) {
+            (2, Some(range), Some(counts)) => Some(PseudoBin {
+                begin: range.begin,
+                end: range.end,
+                number_of_mapped: counts.begin.into(),
+                number_of_unmapped: counts.end.into(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Copy this index into [`Tabix`].
+    pub fn to_tabix(&self) -> Result<Tabix, BGZFError> {
+        Tabix::from_uncompressed(self.tabix_bytes(), Limits::UNLIMITED)
+    }
+
+    /// Decompressed tabix index in the buffer, without the header of the index cache
+    fn tabix_bytes(&self) -> &[u8] {
+        let data = self.data.as_ref();
+        if data.starts_with(&[b'T', b'B', b'C', 1]) {
+            &data[8..]
+        } else {
+            data
+        }
+    }
+}
+
+#[cfg(feature = "mmap")]
+impl MappedTabix<memmap2::Mmap> {
+    /// Map a decompressed tabix index or an index cache at `path` into memory.
+    ///
+    /// The file must not be modified while it is mapped. Modification causes undefined behaviour.
+    pub fn open<P: AsRef<std::path::Path>>(path: P) -> Result<Self, BGZFError> {
+        let file = std::fs::File::open(path)?;
+        // SAFETY: the index files are not expected to be modified while they are opened, as documented above
+        let data = unsafe { memmap2::Mmap::map(&file)? };
+        MappedTabix::new(data)
+    }
+}
+
+impl<B: AsRef<[u8]>> RegionIndex for MappedTabix<B> {
+    fn config(&self) -> TabixConfig {
+        self.config
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        let data = self.data.as_ref();
+        self.names
+            .iter()
+            .position(|x| &data[x.clone()] == name)
+            .map(|x| x.try_into().unwrap())
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        let range = self.names.get(TryInto::<usize>::try_into(rid).unwrap())?;
+        Some(&self.data.as_ref()[range.clone()])
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.sequences.len()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        let chunks = BinningScheme::TABIX
+            .reg2bins(begin, end)
+            .into_iter()
+            .filter_map(|x| self.chunks(rid, x))
+            .flat_map(|x| x.iter())
+            .collect();
+        let min_offset = self
+            .intervals(rid)
+            .and_then(|mut intervals| intervals.nth(linear_window(begin, intervals.len())?))
+            .unwrap_or(0);
+        trim_chunks(merge_chunks(chunks), min_offset.into())
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        self.pseudo_bin(rid)
+            .map(|x| x.begin)
+            .or_else(|| self.intervals(rid)?.next().map(VirtualOffset::from))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_mapped_tabix() -> anyhow::Result<()> {
+        let tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let mut data = Vec::new();
+        crate::open("testfiles/generated.bed.gz.tbi")?.read_to_end(&mut data)?;
+
+        let mut cache = Vec::new();
+        tabix.write_cache(&mut cache, 1)?;
+
+        for mapped in [MappedTabix::new(&data[..])?, MappedTabix::new(&cache[..])?] {
+            assert_eq!(mapped.to_tabix()?, tabix);
+            assert_eq!(RegionIndex::config(&mapped), tabix.config());
+            assert_eq!(mapped.sequence_count(), tabix.sequences.len());
+            assert_eq!(mapped.number_of_unplaced(), tabix.number_of_unplaced);
+            for rid in 0..tabix.sequences.len() {
+                let rid = rid.try_into()?;
+                assert_eq!(RegionIndex::rid2name(&mapped, rid), tabix.rid2name(rid));
+                let name = tabix.rid2name(rid).unwrap();
+                assert_eq!(RegionIndex::name2rid(&mapped, name), Some(rid));
+                assert_eq!(mapped.pseudo_bin(rid), tabix.pseudo_bin(rid));
+                assert_eq!(
+                    RegionIndex::first_offset(&mapped, rid),
+                    RegionIndex::first_offset(&tabix, rid)
+                );
+                for (begin, end) in [
+                    (0, 1 << 29),
+                    (1_000_000, 2_000_000),
+                    (12_345_678, 12_345_679),
+                ] {
+                    assert_eq!(
+                        RegionIndex::region_chunks(&mapped, rid, begin, end),
+                        tabix.region_chunks(rid, begin, end)
+                    );
+                }
+            }
+        }
+
+        let mut file = TabixFile::new(
+            File::open("testfiles/generated.bed.gz")?,
+            MappedTabix::new(data.clone())?,
+        )?;
+        let mut expected = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let records = file
+            .fetch_region("chr1:1000000-2000000")?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!records.is_empty());
+        assert_eq!(
+            records,
+            expected
+                .fetch_region("chr1:1000000-2000000")?
+                .collect::<Result<Vec<_>, _>>()?
+        );
+
+        assert!(MappedTabix::new(&data[..100]).is_err());
+        assert!(MappedTabix::new(&b"TBI"[..]).is_err());
+
+        Ok(())
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    fn test_mapped_tabix_open() -> anyhow::Result<()> {
+        let tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        tabix.write_cache(File::create("tmp/test-mapped.bed.gz.tbi.cache")?, 0)?;
+        let mapped = MappedTabix::open("tmp/test-mapped.bed.gz.tbi.cache")?;
+        assert_eq!(mapped.to_tabix()?, tabix);
+        Ok(())
+    }
+}
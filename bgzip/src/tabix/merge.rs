@@ -0,0 +1,256 @@
+use super::*;
+
+impl Tabix {
+    /// Merge indexes of BGZF files into an index of the concatenated file, without scanning the data.
+    ///
+    /// `offsets` are file offsets of the parts in the concatenated file, in which end-of-file markers
+    /// of preceding parts are removed, such as [`crate::write::SplitPart::compressed_offset`].
+    /// Parts must have the same column configuration and must be given in the order of concatenation.
+    /// Records of a sequence can continue to the next part, but must not appear after other sequences.
+    pub fn merge(indexes: &[Tabix], offsets: &[u64]) -> Result<Tabix, BGZFError> {
+        if indexes.len() != offsets.len() {
+            return Err(BGZFError::Other(
+                "Numbers of indexes and offsets are different",
+            ));
+        }
+        if offsets.windows(2).any(|x| x[0] > x[1]) {
+            return Err(BGZFError::Other("Offsets are not sorted"));
+        }
+        let config = match indexes.first() {
+            Some(first) => first.config(),
+            None => return Err(BGZFError::Other("No index to merge")),
+        };
+        if indexes.iter().any(|x| x.config() != config) {
+            return Err(BGZFError::Other("Column configurations are different"));
+        }
+
+        let mut merged = MergedIndex::default();
+        for (index, offset) in indexes.iter().zip(offsets) {
+            for (rid, sequence) in index.sequences.iter().enumerate() {
+                let name = index.rid2name(rid.try_into().unwrap()).unwrap_or_default();
+                merged.add(name, sequence, *offset)?;
+            }
+        }
+
+        let mut sequences = merged.sequences;
+        for sequence in &mut sequences {
+            for one_bin in sequence.bins.values_mut() {
+                if one_bin.bin != META_BIN {
+                    one_bin.chunks = merge_adjacent_chunks(std::mem::take(&mut one_bin.chunks));
+                }
+                one_bin.number_of_chunk = count_to_i32(one_bin.chunks.len())?;
+            }
+            sequence.number_of_distinct_bin = count_to_i32(sequence.bins.len())?;
+            sequence.number_of_intervals = count_to_i32(sequence.intervals.len())?;
+        }
+        let names: Vec<Vec<u8>> = merged
+            .names
+            .into_iter()
+            .map(|mut x| {
+                x.push(0);
+                x
+            })
+            .collect();
+        let number_of_unplaced = indexes
+            .iter()
+            .filter_map(|x| x.number_of_unplaced)
+            .reduce(|x, y| x + y);
+
+        Ok(Tabix {
+            number_of_references: count_to_i32(sequences.len())?,
+            format: config.format,
+            column_for_sequence: config.column_for_sequence,
+            column_for_begin: config.column_for_begin,
+            column_for_end: config.column_for_end,
+            meta: [config.meta, 0, 0, 0],
+            skip: config.skip,
+            length_of_concatenated_sequence_names: count_to_i32(
+                names.iter().map(|x| x.len()).sum::<usize>(),
+            )?,
+            names,
+            sequences,
+            number_of_unplaced,
+            name_table: NameTable::default(),
+        })
+    }
+}
+
+#[derive(Debug, Default)]
+struct MergedIndex {
+    names: Vec<Vec<u8>>,
+    sequences: Vec<TabixSequence>,
+    last_rid: Option<usize>,
+}
+
+impl MergedIndex {
+    fn add(&mut self, name: &[u8], sequence: &TabixSequence, offset: u64) -> Result<(), BGZFError> {
+        let rid = match self.names.iter().position(|x| x == name) {
+            Some(rid) => rid,
+            None => {
+                self.names.push(name.to_vec());
+                self.sequences.push(TabixSequence {
+                    number_of_distinct_bin: 0,
+                    bins: HashMap::new(),
+                    number_of_intervals: 0,
+                    intervals: Vec::new(),
+                });
+                self.names.len() - 1
+            }
+        };
+        if sequence.bins.is_empty() {
+            return Ok(());
+        }
+        if self.last_rid != Some(rid) && !self.sequences[rid].bins.is_empty() {
+            return Err(BGZFError::Other("Sequences are not continuous"));
+        }
+        self.last_rid = Some(rid);
+
+        let rebase = |x: VirtualOffset| {
+            VirtualOffset::new(x.compressed_offset() + offset, x.uncompressed_offset())
+        };
+        let merged = &mut self.sequences[rid];
+        for one_bin in sequence.bins.values() {
+            let merged_bin = merged.bins.entry(one_bin.bin).or_insert_with(|| TabixBin {
+                bin: one_bin.bin,
+                number_of_chunk: 0,
+                chunks: Vec::new(),
+            });
+            match (&mut merged_bin.chunks[..], &one_bin.chunks[..]) {
+                // pseudo-bin: range of offsets and numbers of records
+                ([range, counts], [new_range, new_counts]) if one_bin.bin == META_BIN => {
+                    range.end = rebase(new_range.end);
+                    counts.begin = (u64::from(counts.begin) + u64::from(new_counts.begin)).into();
+                    counts.end = (u64::from(counts.end) + u64::from(new_counts.end)).into();
+                }
+                ([], [new_range, new_counts]) if one_bin.bin == META_BIN => {
+                    merged_bin.chunks = vec![
+                        TabixChunk {
+                            begin: rebase(new_range.begin),
+                            end: rebase(new_range.end),
+                        },
+                        new_counts.clone(),
+                    ];
+                }
+                _ => merged_bin
+                    .chunks
+                    .extend(one_bin.chunks.iter().map(|x| TabixChunk {
+                        begin: rebase(x.begin),
+                        end: rebase(x.end),
+                    })),
+            }
+        }
+
+        // windows before the first record of a part are filled with the first offset of the part,
+        // so offsets of preceding parts are kept
+        for (i, one) in sequence.intervals.iter().enumerate() {
+            let one = u64::from(rebase((*one).into()));
+            match merged.intervals.get_mut(i) {
+                Some(current) => *current = (*current).min(one),
+                None => merged.intervals.push(one),
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Merge chunks that start from the same BGZF block as the end of the previous chunk, like [`TabixBuilder`].
+fn merge_adjacent_chunks(mut chunks: Vec<TabixChunk>) -> Vec<TabixChunk> {
+    chunks.sort_by_key(|x| (x.begin, x.end));
+    let mut merged: Vec<TabixChunk> = Vec::with_capacity(chunks.len());
+    for one in chunks {
+        match merged.last_mut() {
+            Some(last) if last.end.compressed_offset() >= one.begin.compressed_offset() => {
+                last.end = last.end.max(one.end);
+            }
+            _ => merged.push(one),
+        }
+    }
+    merged
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::write::{concatenate_parts, BGZFSplitWriter};
+    use std::fs::File;
+
+    #[test]
+    fn test_merge() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+
+        let path = |i: usize| format!("tmp/merge-part{}.bed.gz", i);
+        let mut writer = BGZFSplitWriter::new(
+            |i| File::create(path(i)),
+            Compression::default(),
+            300_000,
+            TabixConfig::BED,
+        );
+        writer.write_all(&data)?;
+        let catalog = writer.close()?;
+        assert!(catalog.parts.len() > 2);
+
+        let mut indexes = Vec::new();
+        for one in &catalog.parts {
+            indexes.push(index_bgzf(File::open(path(one.part))?, TabixConfig::BED)?);
+        }
+        let offsets: Vec<u64> = catalog.parts.iter().map(|x| x.compressed_offset).collect();
+        let merged = Tabix::merge(&indexes, &offsets)?;
+
+        concatenate_parts(
+            catalog
+                .parts
+                .iter()
+                .map(|x| File::open(path(x.part)))
+                .collect::<Result<Vec<_>, _>>()?,
+            File::create("tmp/merge.bed.gz")?,
+        )?;
+        let expected = index_bgzf(File::open("tmp/merge.bed.gz")?, TabixConfig::BED)?;
+        assert_eq!(merged.names, expected.names);
+        for (one, expected) in merged.sequences.iter().zip(&expected.sequences) {
+            assert_eq!(one.pseudo_bin(), expected.pseudo_bin());
+            assert_eq!(one.intervals, expected.intervals);
+        }
+
+        let mut merged_file = TabixFile::new(File::open("tmp/merge.bed.gz")?, merged)?;
+        let mut expected_file = TabixFile::new(File::open("tmp/merge.bed.gz")?, expected)?;
+        for region in ["chr1:1000000-2000000", "chr3", "chr5:12345678-13000000"] {
+            let records = merged_file
+                .fetch_region(region)?
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!records.is_empty());
+            assert_eq!(
+                records,
+                expected_file
+                    .fetch_region(region)?
+                    .collect::<Result<Vec<_>, _>>()?
+            );
+        }
+
+        assert!(Tabix::merge(&indexes, &offsets[1..]).is_err());
+        assert!(Tabix::merge(&indexes[..2], &[offsets[1], offsets[0]]).is_err());
+        assert!(Tabix::merge(&[], &[]).is_err());
+
+        // records of chr1 appear after chr2
+        let mut builder = TabixBuilder::new(TabixConfig::BED);
+        builder.push(b"chr1", 0, 100, 100.into())?;
+        builder.push(b"chr2", 0, 100, 200.into())?;
+        let first = builder.finish(200.into());
+        let mut builder = TabixBuilder::new(TabixConfig::BED);
+        builder.push(b"chr1", 200, 300, 100.into())?;
+        let second = builder.finish(100.into());
+        assert!(Tabix::merge(&[first.clone(), second], &[0, 1000]).is_err());
+
+        let mut builder = TabixBuilder::new(TabixConfig::BED);
+        builder.push(b"chr2", 200, 300, 100.into())?;
+        builder.push(b"chr3", 0, 100, 200.into())?;
+        let merged = Tabix::merge(&[first, builder.finish(200.into())], &[0, 1000])?;
+        assert_eq!(merged.sequences.len(), 3);
+        assert_eq!(
+            merged.sequences[1].pseudo_bin().map(|x| x.number_of_mapped),
+            Some(2)
+        );
+
+        Ok(())
+    }
+}
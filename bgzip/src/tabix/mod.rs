@@ -0,0 +1,1275 @@
+use crate::*;
+use std::collections::HashMap;
+use std::convert::TryInto;
+use std::io::{self, Read, Write};
+use std::sync::OnceLock;
+
+mod alias;
+mod any_index;
+#[cfg(feature = "tokio")]
+mod async_file;
+mod binning;
+mod builder;
+mod cache;
+mod convert;
+mod diff;
+mod exclude;
+mod file;
+mod gff;
+mod key_index;
+mod mapped;
+mod merge;
+mod multi;
+mod nearest;
+mod paired;
+#[cfg(feature = "rayon")]
+mod parallel;
+mod region;
+mod rewrite;
+mod shared;
+#[cfg(feature = "tokio")]
+mod stream;
+mod subset;
+mod vcf;
+mod writer;
+
+pub use alias::{AliasedIndex, ContigAliases};
+pub use any_index::AnyIndex;
+#[cfg(feature = "tokio")]
+pub use async_file::{AsyncTabixFile, AsyncTabixRecords};
+pub use binning::{BinningScheme, RecordBin};
+pub use builder::{compress_and_index, index_bgzf, TabixBuilder};
+pub use cache::index_checksum;
+pub use diff::TabixDifference;
+pub use file::{
+    FilteredRecords, OverlapMode, QueryStats, TabixAllRecords, TabixEntry, TabixFile,
+    TabixNamedRecords, TabixRecords,
+};
+pub use gff::GffRecord;
+pub use key_index::KeyIndex;
+pub use mapped::{ChunkSlice, MappedTabix};
+pub use multi::TabixMultiRecords;
+pub use nearest::FlankingRecords;
+pub use paired::{AnchorRegion, PairedEntry, PairedQuery, PairedRecords};
+pub use region::Region;
+pub use rewrite::rename_contigs;
+pub use shared::SharedTabixFile;
+#[cfg(feature = "tokio")]
+pub use stream::TabixStream;
+pub use subset::subset_contigs;
+pub use vcf::VcfRecord;
+pub use writer::IndexedBGZFWriter;
+
+/// Generic format
+pub const FORMAT_GENERIC: i32 = 0;
+/// SAM format
+pub const FORMAT_SAM: i32 = 1;
+/// VCF format
+pub const FORMAT_VCF: i32 = 2;
+/// Coordinates are zero-based and half-open (UCSC BED rule).
+///
+/// This flag is combined with one of the other formats.
+pub const FORMAT_ZERO_BASED: i32 = 0x10000;
+
+/// Column configuration of a tabix index.
+///
+/// Column numbers are 1-based as same as `tabix` command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TabixConfig {
+    pub format: i32,
+    pub column_for_sequence: i32,
+    pub column_for_begin: i32,
+    /// Set `0` if the end of region is not in a column.
+    pub column_for_end: i32,
+    pub meta: u8,
+    pub skip: i32,
+}
+
+impl TabixConfig {
+    /// Preset for GFF files. Equivalent to `tabix -p gff`.
+    pub const GFF: TabixConfig = TabixConfig {
+        format: FORMAT_GENERIC,
+        column_for_sequence: 1,
+        column_for_begin: 4,
+        column_for_end: 5,
+        meta: b'#',
+        skip: 0,
+    };
+
+    /// Preset for BED files. Equivalent to `tabix -p bed`.
+    pub const BED: TabixConfig = TabixConfig {
+        format: FORMAT_GENERIC | FORMAT_ZERO_BASED,
+        column_for_sequence: 1,
+        column_for_begin: 2,
+        column_for_end: 3,
+        meta: b'#',
+        skip: 0,
+    };
+
+    /// Preset for SAM files. Equivalent to `tabix -p sam`.
+    pub const SAM: TabixConfig = TabixConfig {
+        format: FORMAT_SAM,
+        column_for_sequence: 3,
+        column_for_begin: 4,
+        column_for_end: 0,
+        meta: b'@',
+        skip: 0,
+    };
+
+    /// Preset for VCF files. Equivalent to `tabix -p vcf`.
+    pub const VCF: TabixConfig = TabixConfig {
+        format: FORMAT_VCF,
+        column_for_sequence: 1,
+        column_for_begin: 2,
+        column_for_end: 0,
+        meta: b'#',
+        skip: 0,
+    };
+
+    /// Set the number of leading lines to skip, like `tabix -S`.
+    ///
+    /// Skipped lines are treated as header lines even if they do not start with the meta character.
+    pub fn with_skip(mut self, skip: i32) -> Self {
+        self.skip = skip;
+        self
+    }
+
+    /// Number of leading lines to skip. A negative `skip` is treated as zero.
+    pub fn skip_lines(&self) -> u64 {
+        self.skip.max(0).try_into().unwrap()
+    }
+
+    /// Returns `true` if `line` starts with the meta character.
+    pub fn is_meta(&self, line: &[u8]) -> bool {
+        line.first() == Some(&self.meta)
+    }
+
+    /// Parse a data line and return sequence name and zero-based, half-open region of the record.
+    pub fn parse_record<'a>(&self, line: &'a [u8]) -> Result<(&'a [u8], u64, u64), BGZFError> {
+        let line = strip_line_end(line);
+        let format = self.format & 0xffff;
+
+        let mut name = None;
+        let mut begin = None;
+        let mut end = None;
+        let mut info_end = None;
+        // columns after this are never used
+        let last_column = match format {
+            FORMAT_VCF => 8,
+            FORMAT_SAM => 6,
+            _ => 0,
+        }
+        .max(self.column_for_sequence)
+        .max(self.column_for_begin)
+        .max(self.column_for_end);
+
+        for (i, column) in split_columns(line).enumerate() {
+            let column_index: i32 = (i + 1).try_into().unwrap();
+            if column_index > last_column {
+                break;
+            }
+            if column_index == self.column_for_sequence {
+                name = Some(column);
+            } else if column_index == self.column_for_begin {
+                let value = parse_position(column, column_index)?;
+                begin = Some(if self.format & FORMAT_ZERO_BASED != 0 {
+                    value
+                } else {
+                    value.saturating_sub(1)
+                });
+            } else if column_index == self.column_for_end {
+                end = Some(parse_position(column, column_index)?);
+            } else if format == FORMAT_VCF && column_index == 4 {
+                end = begin.map(|x| x + TryInto::<u64>::try_into(column.len()).unwrap());
+            } else if format == FORMAT_VCF && column_index == 8 {
+                info_end = column
+                    .split(|x| *x == b';')
+                    .find_map(|x| x.strip_prefix(b"END="))
+                    .map(|x| parse_position(x, column_index))
+                    .transpose()?;
+            } else if format == FORMAT_SAM && column_index == 6 {
+                end = begin.map(|x| x + cigar_reference_length(column));
+            }
+        }
+
+        let name = name.ok_or_else(|| parse_error(self.column_for_sequence))?;
+        let begin = begin.ok_or_else(|| parse_error(self.column_for_begin))?;
+        let mut end = end.unwrap_or(begin + 1);
+        if let Some(info_end) = info_end {
+            if info_end > begin {
+                end = info_end;
+            }
+        }
+        if end <= begin {
+            end = begin + 1;
+        }
+
+        Ok((name, begin, end))
+    }
+}
+
+impl std::str::FromStr for TabixConfig {
+    type Err = BGZFError;
+
+    /// Parse a preset name, `gff`, `bed`, `sam` or `vcf` (case-insensitive), like `tabix -p`.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "gff" => Ok(TabixConfig::GFF),
+            "bed" => Ok(TabixConfig::BED),
+            "sam" => Ok(TabixConfig::SAM),
+            "vcf" => Ok(TabixConfig::VCF),
+            _ => Err(BGZFError::Other(
+                "Unknown preset. Preset must be gff, bed, sam or vcf",
+            )),
+        }
+    }
+}
+
+/// Split `line` at tab characters.
+fn split_columns(line: &[u8]) -> impl Iterator<Item = &[u8]> {
+    let mut begin = 0;
+    memchr::memchr_iter(b'\t', line)
+        .chain(std::iter::once(line.len()))
+        .map(move |end| {
+            let column = &line[begin..end];
+            begin = end + 1;
+            column
+        })
+}
+
+fn strip_line_end(line: &[u8]) -> &[u8] {
+    let line = line.strip_suffix(b"\n").unwrap_or(line);
+    line.strip_suffix(b"\r").unwrap_or(line)
+}
+
+fn parse_position(data: &[u8], column: i32) -> Result<u64, BGZFError> {
+    let digits = data.strip_prefix(b"+").unwrap_or(data);
+    if digits.is_empty() {
+        return Err(parse_error(column));
+    }
+    digits.iter().try_fold(0u64, |value, x| {
+        if x.is_ascii_digit() {
+            value
+                .checked_mul(10)
+                .and_then(|value| value.checked_add(u64::from(x - b'0')))
+                .ok_or_else(|| parse_error(column))
+        } else {
+            Err(parse_error(column))
+        }
+    })
+}
+
+fn parse_error(column: i32) -> BGZFError {
+    BGZFError::ParseError {
+        line: None,
+        column: column.try_into().unwrap_or(0),
+    }
+}
+
+fn cigar_reference_length(cigar: &[u8]) -> u64 {
+    let mut length = 0;
+    let mut number = 0;
+    for one in cigar {
+        match one {
+            b'0'..=b'9' => number = number * 10 + u64::from(one - b'0'),
+            b'M' | b'D' | b'N' | b'=' | b'X' => {
+                length += number;
+                number = 0;
+            }
+            _ => number = 0,
+        }
+    }
+    length
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabixChunk {
+    pub begin: VirtualOffset,
+    pub end: VirtualOffset,
+}
+
+impl TabixChunk {
+    pub(crate) fn from_reader<R: Read + BinaryReader>(reader: &mut R) -> io::Result<Self> {
+        let begin = reader.read_le_u64()?.into();
+        let end = reader.read_le_u64()?.into();
+        Ok(TabixChunk { begin, end })
+    }
+
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&u64::from(self.begin).to_le_bytes())?;
+        writer.write_all(&u64::from(self.end).to_le_bytes())?;
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabixBin {
+    pub bin: u32,
+    pub number_of_chunk: i32,
+    pub chunks: Vec<TabixChunk>,
+}
+
+impl TabixBin {
+    fn from_reader<R: Read + BinaryReader>(
+        reader: &mut R,
+        limits: &Limits,
+    ) -> Result<Self, BGZFError> {
+        let bin = reader.read_le_u32()?;
+        let number_of_chunk = reader.read_le_i32()?;
+        if u32::try_from(number_of_chunk).unwrap_or(0) > limits.max_chunks_per_bin {
+            return Err(BGZFError::LimitExceeded("Too many chunks in a bin"));
+        }
+        let mut chunks = Vec::new();
+        for _ in 0..number_of_chunk {
+            chunks.push(TabixChunk::from_reader(reader)?);
+        }
+
+        Ok(TabixBin {
+            bin,
+            number_of_chunk,
+            chunks,
+        })
+    }
+
+    fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&self.bin.to_le_bytes())?;
+        writer.write_all(&count_to_i32(self.chunks.len())?.to_le_bytes())?;
+        for one in &self.chunks {
+            one.write(writer)?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabixSequence {
+    pub number_of_distinct_bin: i32,
+    pub bins: HashMap<u32, TabixBin>,
+    pub number_of_intervals: i32,
+    pub intervals: Vec<u64>,
+}
+
+impl TabixSequence {
+    pub(crate) fn from_reader<R: Read + BinaryReader>(
+        reader: &mut R,
+        limits: &Limits,
+    ) -> Result<Self, BGZFError> {
+        let number_of_distinct_bin = reader.read_le_i32()?;
+        let mut bins = HashMap::new();
+        for _ in 0..number_of_distinct_bin {
+            let one_bin = TabixBin::from_reader(reader, limits)?;
+            bins.insert(one_bin.bin, one_bin);
+        }
+
+        let number_of_intervals = reader.read_le_i32()?;
+
+        let mut intervals = Vec::new();
+        for _ in 0..number_of_intervals {
+            intervals.push(reader.read_le_u64()?);
+        }
+        Ok(TabixSequence {
+            number_of_distinct_bin,
+            bins,
+            number_of_intervals,
+            intervals,
+        })
+    }
+
+    pub(crate) fn write<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&count_to_i32(self.bins.len())?.to_le_bytes())?;
+        let mut bins: Vec<_> = self.bins.values().collect();
+        bins.sort_by_key(|x| x.bin);
+        for one in bins {
+            one.write(writer)?;
+        }
+        writer.write_all(&count_to_i32(self.intervals.len())?.to_le_bytes())?;
+        for one in &self.intervals {
+            writer.write_all(&one.to_le_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Metadata in the pseudo-bin. `None` if the pseudo-bin is not in the index or it is malformed.
+    pub fn pseudo_bin(&self) -> Option<PseudoBin> {
+        let chunks = &self.bins.get(&META_BIN)?.chunks;
+        match &chunks[..] {
+            [range, counts] => Some(PseudoBin {
+                begin: range.begin,
+                end: range.end,
+                number_of_mapped: counts.begin.into(),
+                number_of_unmapped: counts.end.into(),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Chunks of bins overlapping with region [begin, end) in the tabix binning scheme.
+    ///
+    /// The pseudo-bin is never included because its chunks are not offsets of records.
+    /// Chunks are trimmed with the linear index like htslib.
+    pub(crate) fn region_chunks(&self, begin: u64, end: u64) -> Vec<TabixChunk> {
+        let chunks: Vec<TabixChunk> = BinningScheme::TABIX
+            .reg2bins(begin, end)
+            .into_iter()
+            .filter_map(|x| self.bins.get(&x))
+            .flat_map(|x| x.chunks.iter().cloned())
+            .collect();
+        let window = linear_window(begin, self.intervals.len());
+        let min_offset = window.map(|x| self.intervals[x]).unwrap_or(0);
+        trim_chunks(merge_chunks(chunks), min_offset.into())
+    }
+
+    /// First virtual offset recorded in the pseudo-bin or the linear index.
+    pub(crate) fn first_offset(&self) -> Option<VirtualOffset> {
+        self.pseudo_bin()
+            .map(|x| x.begin)
+            .or_else(|| self.intervals.first().map(|x| VirtualOffset::from(*x)))
+    }
+}
+
+/// Metadata of a reference sequence in the pseudo-bin (bin 37450).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PseudoBin {
+    /// Virtual offset of the first record
+    pub begin: VirtualOffset,
+    /// Virtual offset of the end of the last record
+    pub end: VirtualOffset,
+    /// Number of mapped records. All records are counted as mapped in tabix index.
+    pub number_of_mapped: u64,
+    pub number_of_unmapped: u64,
+}
+
+/// Summary of a reference sequence in an index, like `tabix -l` with `samtools idxstats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContigSummary {
+    pub name: Vec<u8>,
+    pub rid: u32,
+    /// Number of bins except the pseudo-bin
+    pub number_of_bins: usize,
+    /// Number of 16kbp intervals in the linear index
+    pub number_of_intervals: usize,
+    /// Number of records recorded in the pseudo-bin. `None` if the index does not have the pseudo-bin.
+    pub estimated_records: Option<u64>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Tabix {
+    pub number_of_references: i32,
+    pub format: i32,
+    pub column_for_sequence: i32,
+    pub column_for_begin: i32,
+    pub column_for_end: i32,
+    pub meta: [u8; 4],
+    pub skip: i32,
+    pub length_of_concatenated_sequence_names: i32,
+    /// NUL terminated sequence names.
+    ///
+    /// Lookups by name and as string use a table built from names at the first call.
+    /// Call [`Tabix::clear_name_table`] after modifying names.
+    pub names: Vec<Vec<u8>>,
+    pub sequences: Vec<TabixSequence>,
+    /// Number of records without coordinates. `None` if this optional field is not in the file.
+    pub number_of_unplaced: Option<u64>,
+    name_table: NameTable,
+}
+
+/// Sequence names validated as UTF-8 and the map from names to reference sequence IDs.
+///
+/// This table is built at the first lookup, and ignored in comparison.
+#[derive(Debug, Clone, Default)]
+struct NameTable(OnceLock<NameLookup>);
+
+#[derive(Debug, Clone)]
+struct NameLookup {
+    /// Names with invalid UTF-8 sequences replaced
+    strings: Vec<Box<str>>,
+    /// UTF-8 validation errors by reference sequence ID
+    errors: HashMap<u32, std::str::Utf8Error>,
+    rids: HashMap<Vec<u8>, u32>,
+}
+
+impl PartialEq for NameTable {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl Tabix {
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, crate::BGZFError> {
+        Tabix::from_reader_with_limits(reader, Limits::UNLIMITED)
+    }
+
+    /// Load tabix index with resource limits. Use this function to load untrusted files.
+    pub fn from_reader_with_limits<R: Read>(
+        reader: R,
+        limits: Limits,
+    ) -> Result<Self, crate::BGZFError> {
+        let mut reader = io::BufReader::new(crate::read::BGZFReader::with_limits(reader, limits)?);
+        Tabix::from_uncompressed(&mut reader, limits)
+    }
+
+    /// Parse decompressed tabix index
+    pub(crate) fn from_uncompressed<R: Read>(
+        mut reader: R,
+        limits: Limits,
+    ) -> Result<Self, crate::BGZFError> {
+        let mut buf: [u8; 4] = [0, 0, 0, 0];
+        reader.read_exact(&mut buf)?;
+        if buf != [b'T', b'B', b'I', 1] {
+            return Err(BGZFError::NotTabix);
+        }
+        let number_of_references = reader.read_le_i32()?;
+        if u32::try_from(number_of_references).unwrap_or(0) > limits.max_references {
+            return Err(BGZFError::LimitExceeded("Too many reference sequences"));
+        }
+        let format = reader.read_le_i32()?;
+        let column_for_sequence = reader.read_le_i32()?;
+        let column_for_begin = reader.read_le_i32()?;
+        let column_for_end = reader.read_le_i32()?;
+        reader.read_exact(&mut buf)?;
+        let meta = buf;
+        let skip = reader.read_le_i32()?;
+        let length_of_concatenated_sequence_names = reader.read_le_i32()?;
+        let names_length: u32 = length_of_concatenated_sequence_names
+            .try_into()
+            .map_err(|_| BGZFError::BadIndex("Invalid length of sequence names"))?;
+        if names_length > limits.max_names_length {
+            return Err(BGZFError::LimitExceeded("Too long sequence names"));
+        }
+        let mut name_bytes: Vec<u8> = vec![0; names_length.try_into().unwrap()];
+        reader.read_exact(&mut name_bytes)?;
+        let names = split_names(&name_bytes);
+
+        let mut sequences = Vec::new();
+        for _ in 0..number_of_references {
+            sequences.push(TabixSequence::from_reader(&mut reader, &limits)?);
+        }
+        let number_of_unplaced = match reader.read_le_u64() {
+            Ok(value) => Some(value),
+            Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(Tabix {
+            number_of_references,
+            format,
+            column_for_sequence,
+            column_for_begin,
+            column_for_end,
+            meta,
+            skip,
+            length_of_concatenated_sequence_names,
+            names,
+            sequences,
+            number_of_unplaced,
+            name_table: NameTable::default(),
+        })
+    }
+
+    /// Find reference sequence ID from sequence name.
+    ///
+    /// If names are duplicated, the first one is found.
+    pub fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        self.name_lookup().rids.get(name).copied()
+    }
+
+    /// Find sequence name from reference sequence ID.
+    pub fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        self.names
+            .get(TryInto::<usize>::try_into(rid).unwrap())
+            .map(|x| x.strip_suffix(&[0]).unwrap_or(x))
+    }
+
+    /// Find sequence name from reference sequence ID as string.
+    ///
+    /// Names are validated as UTF-8 once, at the first lookup.
+    pub fn rid2name_str(&self, rid: u32) -> Result<&str, BGZFError> {
+        let lookup = self.name_lookup();
+        if let Some(e) = lookup.errors.get(&rid) {
+            return Err((*e).into());
+        }
+        lookup
+            .strings
+            .get(TryInto::<usize>::try_into(rid).unwrap())
+            .map(|x| &**x)
+            .ok_or(BGZFError::Other("Invalid reference sequence ID"))
+    }
+
+    /// Iterate sequence names and reference sequence IDs.
+    ///
+    /// Invalid UTF-8 sequences in names are replaced with `U+FFFD`,
+    /// and [`Tabix::rid2name_str`] reports such names as errors.
+    pub fn contig_names(&self) -> impl Iterator<Item = (&str, u32)> {
+        self.name_lookup()
+            .strings
+            .iter()
+            .enumerate()
+            .map(|(i, x)| (&**x, i.try_into().unwrap()))
+    }
+
+    /// Discard the table of names, so that modified names are reflected in lookups.
+    pub fn clear_name_table(&mut self) {
+        self.name_table = NameTable::default();
+    }
+
+    fn name_lookup(&self) -> &NameLookup {
+        self.name_table.0.get_or_init(|| {
+            let mut lookup = NameLookup {
+                strings: Vec::with_capacity(self.names.len()),
+                errors: HashMap::new(),
+                rids: HashMap::with_capacity(self.names.len()),
+            };
+            for (i, name) in self.names.iter().enumerate() {
+                let rid: u32 = i.try_into().unwrap();
+                let name = name.strip_suffix(&[0]).unwrap_or(name);
+                if let Err(e) = std::str::from_utf8(name) {
+                    lookup.errors.insert(rid, e);
+                }
+                lookup
+                    .strings
+                    .push(String::from_utf8_lossy(name).into_owned().into_boxed_str());
+                lookup.rids.entry(name.to_vec()).or_insert(rid);
+            }
+            lookup
+        })
+    }
+
+    /// Metadata in the pseudo-bin of reference sequence `rid`.
+    ///
+    /// Number of records without coordinates is recorded in [`Tabix::number_of_unplaced`].
+    pub fn pseudo_bin(&self, rid: u32) -> Option<PseudoBin> {
+        self.sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?
+            .pseudo_bin()
+    }
+
+    /// Summaries of reference sequences in the order of reference sequence IDs.
+    pub fn contig_summaries(&self) -> Vec<ContigSummary> {
+        self.sequences
+            .iter()
+            .enumerate()
+            .map(|(i, sequence)| {
+                let rid = i.try_into().unwrap();
+                ContigSummary {
+                    name: self.rid2name(rid).unwrap_or_default().to_vec(),
+                    rid,
+                    number_of_bins: sequence.bins.keys().filter(|x| **x != META_BIN).count(),
+                    number_of_intervals: sequence.intervals.len(),
+                    estimated_records: sequence.pseudo_bin().map(|x| x.number_of_mapped),
+                }
+            })
+            .collect()
+    }
+
+    /// List of chunks which may contain records overlapping with region [begin, end) (zero-based).
+    ///
+    /// Overlapping chunks are merged and sorted by begin offset.
+    pub fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        match self.sequences.get(TryInto::<usize>::try_into(rid).unwrap()) {
+            Some(sequence) => sequence.region_chunks(begin, end),
+            None => Vec::new(),
+        }
+    }
+
+    /// Column configuration of this index
+    pub fn config(&self) -> TabixConfig {
+        TabixConfig {
+            format: self.format,
+            column_for_sequence: self.column_for_sequence,
+            column_for_begin: self.column_for_begin,
+            column_for_end: self.column_for_end,
+            meta: self.meta[0],
+            skip: self.skip,
+        }
+    }
+
+    /// Write BGZF compressed .tbi index into `writer`
+    ///
+    /// Numbers of sequences, bins, chunks and intervals, and the length of names are calculated
+    /// from the vectors and maps, so an index modified in memory can be written without updating
+    /// count fields. A NUL character is appended to names without it.
+    pub fn write<W: Write>(&self, writer: W) -> Result<(), BGZFError> {
+        let mut writer = BGZFWriter::with_compress_unit_size(
+            writer,
+            Compression::default(),
+            crate::write::DEFAULT_COMPRESS_UNIT_SIZE,
+            false,
+        )?;
+        self.write_uncompressed(&mut writer)?;
+        writer.close()?;
+        Ok(())
+    }
+
+    /// Write tabix index without BGZF compression
+    pub(crate) fn write_uncompressed<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer.write_all(&[b'T', b'B', b'I', 1])?;
+        writer.write_all(&count_to_i32(self.sequences.len())?.to_le_bytes())?;
+        writer.write_all(&self.format.to_le_bytes())?;
+        writer.write_all(&self.column_for_sequence.to_le_bytes())?;
+        writer.write_all(&self.column_for_begin.to_le_bytes())?;
+        writer.write_all(&self.column_for_end.to_le_bytes())?;
+        writer.write_all(&self.meta)?;
+        writer.write_all(&self.skip.to_le_bytes())?;
+        let mut names = Vec::new();
+        for one in &self.names {
+            names.extend_from_slice(one);
+            if !one.ends_with(&[0]) {
+                names.push(0);
+            }
+        }
+        writer.write_all(&count_to_i32(names.len())?.to_le_bytes())?;
+        writer.write_all(&names)?;
+        for one in &self.sequences {
+            one.write(writer)?;
+        }
+        writer.write_all(&self.number_of_unplaced.unwrap_or(0).to_le_bytes())?;
+        Ok(())
+    }
+}
+
+/// An index which can be used to query regions with [`TabixFile`].
+///
+/// This trait is implemented by [`Tabix`], [`MappedTabix`], [`crate::csi::CsiIndex`], [`crate::bai::BaiIndex`], [`AnyIndex`] and [`AliasedIndex`].
+pub trait RegionIndex {
+    /// Column configuration of the indexed file
+    fn config(&self) -> TabixConfig;
+
+    /// Find reference sequence ID from sequence name.
+    fn name2rid(&self, name: &[u8]) -> Option<u32>;
+
+    /// Find reference sequence ID from sequence name.
+    ///
+    /// [`BGZFError::UnknownContig`] is returned if the name is not in the index.
+    fn try_name2rid(&self, name: &[u8]) -> Result<u32, BGZFError> {
+        self.name2rid(name)
+            .ok_or_else(|| BGZFError::unknown_contig(name))
+    }
+
+    /// Find sequence name from reference sequence ID.
+    fn rid2name(&self, rid: u32) -> Option<&[u8]>;
+
+    /// Number of reference sequences
+    fn sequence_count(&self) -> usize;
+
+    /// List of chunks which may contain records overlapping with region [begin, end) (zero-based).
+    ///
+    /// Overlapping chunks are merged and sorted by begin offset.
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk>;
+
+    /// First virtual offset of records of reference sequence `rid`.
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset>;
+
+    /// Binning scheme of the index. Default implementation returns [`BinningScheme::TABIX`].
+    fn binning_scheme(&self) -> BinningScheme {
+        BinningScheme::TABIX
+    }
+}
+
+impl RegionIndex for Tabix {
+    fn config(&self) -> TabixConfig {
+        Tabix::config(self)
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        Tabix::name2rid(self, name)
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        Tabix::rid2name(self, rid)
+    }
+
+    fn sequence_count(&self) -> usize {
+        self.sequences.len()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        Tabix::region_chunks(self, rid, begin, end)
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        self.sequences
+            .get(TryInto::<usize>::try_into(rid).unwrap())?
+            .first_offset()
+    }
+}
+
+/// Share one parsed index between indexes and files.
+impl<I: RegionIndex + ?Sized> RegionIndex for std::sync::Arc<I> {
+    fn config(&self) -> TabixConfig {
+        (**self).config()
+    }
+
+    fn name2rid(&self, name: &[u8]) -> Option<u32> {
+        (**self).name2rid(name)
+    }
+
+    fn try_name2rid(&self, name: &[u8]) -> Result<u32, BGZFError> {
+        (**self).try_name2rid(name)
+    }
+
+    fn rid2name(&self, rid: u32) -> Option<&[u8]> {
+        (**self).rid2name(rid)
+    }
+
+    fn sequence_count(&self) -> usize {
+        (**self).sequence_count()
+    }
+
+    fn region_chunks(&self, rid: u32, begin: u64, end: u64) -> Vec<TabixChunk> {
+        (**self).region_chunks(rid, begin, end)
+    }
+
+    fn first_offset(&self, rid: u32) -> Option<VirtualOffset> {
+        (**self).first_offset(rid)
+    }
+
+    fn binning_scheme(&self) -> BinningScheme {
+        (**self).binning_scheme()
+    }
+}
+
+/// Sort chunks by begin offset and merge overlapping chunks.
+pub(crate) fn merge_chunks(mut chunks: Vec<TabixChunk>) -> Vec<TabixChunk> {
+    chunks.sort_by_key(|x| x.begin);
+
+    let mut merged: Vec<TabixChunk> = Vec::with_capacity(chunks.len());
+    for one in chunks {
+        match merged.last_mut() {
+            Some(last) if one.begin <= last.end => last.end = last.end.max(one.end),
+            _ => merged.push(one),
+        }
+    }
+    merged
+}
+
+/// Window of the linear index containing position `begin`. The last window is used for positions after it.
+pub(crate) fn linear_window(begin: u64, number_of_intervals: usize) -> Option<usize> {
+    let window = begin >> BinningScheme::TABIX.min_shift();
+    Some(
+        TryInto::<usize>::try_into(window)
+            .unwrap_or(usize::MAX)
+            .min(number_of_intervals.checked_sub(1)?),
+    )
+}
+
+/// Drop sorted chunks ending at or before `min_offset`, and raise begin offsets of other chunks to `min_offset`.
+///
+/// `min_offset` is the linear index entry of the window containing the beginning of the region,
+/// so records before it never overlap with the region.
+pub(crate) fn trim_chunks(chunks: Vec<TabixChunk>, min_offset: VirtualOffset) -> Vec<TabixChunk> {
+    chunks
+        .into_iter()
+        .filter(|x| x.end > min_offset)
+        .map(|x| TabixChunk {
+            begin: x.begin.max(min_offset),
+            end: x.end,
+        })
+        .collect()
+}
+
+/// Merge sorted and merged chunks if the gap between them is less than `gap` compressed bytes.
+///
+/// Records in gaps are read and discarded, but a few large sequential reads are faster than
+/// many small seeks on remote storage.
+pub(crate) fn merge_nearby_chunks(chunks: Vec<TabixChunk>, gap: u64) -> Vec<TabixChunk> {
+    let mut merged: Vec<TabixChunk> = Vec::with_capacity(chunks.len());
+    for one in chunks {
+        match merged.last_mut() {
+            Some(last)
+                if one
+                    .begin
+                    .compressed_offset()
+                    .saturating_sub(last.end.compressed_offset())
+                    < gap =>
+            {
+                last.end = last.end.max(one.end)
+            }
+            _ => merged.push(one),
+        }
+    }
+    merged
+}
+
+fn count_to_i32(count: usize) -> io::Result<i32> {
+    count
+        .try_into()
+        .map_err(|_| io::Error::new(io::ErrorKind::InvalidData, "Too many items in tabix index"))
+}
+
+fn split_names(data: &[u8]) -> Vec<Vec<u8>> {
+    let mut reader = io::BufReader::new(data);
+    let mut result = Vec::new();
+
+    loop {
+        let mut buf = Vec::new();
+        let l = reader.read_until(0, &mut buf).unwrap();
+        if l == 0 {
+            break;
+        }
+        result.push(buf);
+    }
+
+    result
+}
+
+const MIN_SHIFT: u32 = 14;
+const DEPTH: u32 = 5;
+/// Bin number of the pseudo-bin which holds per-sequence metadata
+const META_BIN: u32 = 37450;
+
+/// Calculate the bin of a record covering [beg, end) (zero-based, half-open) in the tabix binning scheme.
+///
+/// The bin is the smallest bin which contains whole the region, as same as `hts_reg2bin` of htslib.
+/// `end` must be larger than `beg`. See [`BinningScheme::record_bin`] to get the linear index windows too.
+pub fn reg2bin(beg: u32, end: u32) -> u32 {
+    crate::csi::reg2bin(beg.into(), end.into(), MIN_SHIFT, DEPTH)
+}
+
+/// Calculate the list of bins that may overlap with region [beg, end) (zero-based, half-open) in the tabix binning scheme.
+///
+/// Bins are listed from the root bin to the smallest bins, as same as `reg2bins` of htslib.
+/// `end` must be larger than `beg`.
+pub fn reg2bins(beg: u32, end: u32) -> Vec<u32> {
+    crate::csi::reg2bins(beg.into(), end.into(), MIN_SHIFT, DEPTH)
+}
+
+#[cfg(test)]
+mod test {
+    use anyhow::anyhow;
+
+    use super::*;
+    use std::fs::File;
+    use std::str;
+
+    #[test]
+    fn test_tabix_read() -> anyhow::Result<()> {
+        let mut reader = File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?;
+        let tabix = Tabix::from_reader(&mut reader)?;
+        //println!("{:?}", tabix);
+
+        let mut chunks_writer = csv::Writer::from_path("tmp/sequence.csv")?;
+        chunks_writer.write_record([
+            "sequence name",
+            "bin index",
+            "bin number",
+            "chunk index",
+            "chunk begin",
+            "chunk end",
+        ])?;
+
+        for (i, one_seq) in tabix.sequences.iter().enumerate() {
+            for (j, (_, one_bin)) in one_seq.bins.iter().enumerate() {
+                for (k, one_chunk) in one_bin.chunks.iter().enumerate() {
+                    chunks_writer.write_record([
+                        str::from_utf8(&tabix.names[i]).unwrap().to_string(),
+                        format!("{}", j),
+                        format!("{}", one_bin.bin),
+                        format!("{}", k),
+                        format!("{}", one_chunk.begin),
+                        format!("{}", one_chunk.end),
+                    ])?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_tabix_write() -> anyhow::Result<()> {
+        for path in [
+            "testfiles/generated.bed.gz.tbi",
+            "testfiles/common_all_20180418_half.vcf.gz.tbi",
+        ] {
+            let tabix = Tabix::from_reader(File::open(path)?)?;
+            assert_eq!(tabix.number_of_unplaced, Some(0));
+            let mut written = Vec::new();
+            tabix.write(&mut written)?;
+            // bins are sorted in written file, so only the size is same as the original file
+            let mut expected = Vec::new();
+            crate::open(path)?.read_to_end(&mut expected)?;
+            let mut actual = Vec::new();
+            crate::read::new_reader(&written[..])?.read_to_end(&mut actual)?;
+            assert_eq!(actual.len(), expected.len());
+            assert_eq!(Tabix::from_reader(&written[..])?, tabix);
+        }
+
+        let mut tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        tabix.sequences.truncate(2);
+        tabix.names.truncate(2);
+        tabix.names[1] = b"chrX".to_vec();
+        let bin = *tabix.sequences[0].bins.keys().next().unwrap();
+        tabix.sequences[0].bins.remove(&bin);
+        let mut written = Vec::new();
+        tabix.write(&mut written)?;
+        let loaded = Tabix::from_reader(&written[..])?;
+        assert_eq!(loaded.number_of_references, 2);
+        assert_eq!(loaded.rid2name(1), Some(&b"chrX"[..]));
+        assert_eq!(loaded.sequences[0].bins, tabix.sequences[0].bins);
+        assert_eq!(
+            loaded.sequences[0].number_of_distinct_bin,
+            TryInto::<i32>::try_into(tabix.sequences[0].bins.len())?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_pseudo_bin() -> anyhow::Result<()> {
+        let tabix = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let pseudo_bin = tabix.pseudo_bin(0).unwrap();
+        assert_eq!(pseudo_bin.number_of_mapped, 10000);
+        assert_eq!(pseudo_bin.number_of_unmapped, 0);
+        assert!(pseudo_bin.begin < pseudo_bin.end);
+        assert_eq!(
+            tabix.pseudo_bin(u32::try_from(tabix.sequences.len())?),
+            None
+        );
+
+        // chunks of the pseudo-bin are not used for queries
+        let chunks = tabix.region_chunks(0, 0, u64::MAX);
+        assert!(!chunks.is_empty());
+        assert!(chunks
+            .iter()
+            .all(|x| pseudo_bin.begin <= x.begin && x.end <= pseudo_bin.end));
+        Ok(())
+    }
+
+    #[test]
+    fn test_contig_names() -> anyhow::Result<()> {
+        let tabix =
+            Tabix::from_reader(File::open("testfiles/common_all_20180418_half.vcf.gz.tbi")?)?;
+        assert_eq!(tabix.rid2name_str(0)?, "1");
+        assert_eq!(tabix.rid2name(0), Some(&b"1"[..]));
+        assert!(tabix.rid2name_str(tabix.names.len().try_into()?).is_err());
+        for (name, rid) in tabix.contig_names() {
+            assert_eq!(tabix.name2rid(name.as_bytes()), Some(rid));
+            assert_eq!(tabix.rid2name_str(rid)?, name);
+        }
+        assert_eq!(tabix.contig_names().count(), tabix.names.len());
+        // renamed sequences are reflected after the table is cleared
+        let mut renamed = tabix.clone();
+        renamed.names[0] = b"chr1\0".to_vec();
+        renamed.clear_name_table();
+        assert_eq!(renamed.rid2name_str(0)?, "chr1");
+        assert_eq!(renamed.contig_names().next(), Some(("chr1", 0)));
+        assert_eq!(renamed.name2rid(b"chr1"), Some(0));
+        assert_eq!(renamed.name2rid(b"1"), None);
+        // invalid UTF-8 names are replaced in iteration
+        renamed.names[1] = b"chr\xff\0".to_vec();
+        renamed.clear_name_table();
+        assert!(renamed.rid2name_str(1).is_err());
+        assert_eq!(renamed.contig_names().nth(1), Some(("chr\u{fffd}", 1)));
+        assert_eq!(renamed.name2rid(b"chr\xff"), Some(1));
+        // the first one of duplicated names is found
+        renamed.names[2] = b"chr1\0".to_vec();
+        renamed.clear_name_table();
+        assert_eq!(renamed.name2rid(b"chr1"), Some(0));
+
+        let file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let summaries = file.contigs();
+        assert_eq!(summaries.len(), file.index().names.len());
+        assert_eq!(summaries[0].name, b"chr1");
+        assert_eq!(summaries[0].estimated_records, Some(10000));
+        assert!(summaries
+            .iter()
+            .all(|x| x.number_of_bins > 0 && x.number_of_intervals > 0));
+        let count = io::BufRead::split(crate::open("testfiles/generated.bed.gz")?, b'\n').count();
+        assert_eq!(
+            summaries
+                .iter()
+                .map(|x| x.estimated_records.unwrap())
+                .sum::<u64>(),
+            u64::try_from(count)?
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_linear_index() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr1").unwrap();
+        let all = file
+            .fetch(rid, 0, u64::MAX)
+            .collect::<Result<Vec<_>, _>>()?;
+        let sequence = file.index().sequences[usize::try_from(rid)?].clone();
+        for (begin, end) in [
+            (10_000_000, 20_000_000),
+            (123_456, 234_567),
+            (1 << 40, 1 << 41),
+        ] {
+            let untrimmed = merge_chunks(
+                BinningScheme::TABIX
+                    .reg2bins(begin, end)
+                    .into_iter()
+                    .filter_map(|x| sequence.bins.get(&x))
+                    .flat_map(|x| x.chunks.iter().cloned())
+                    .collect(),
+            );
+            let chunks = file.index().region_chunks(rid, begin, end);
+            let min_offset = VirtualOffset::from(
+                sequence.intervals[linear_window(begin, sequence.intervals.len()).unwrap()],
+            );
+            assert!(chunks
+                .iter()
+                .all(|x| x.begin >= min_offset && x.end > x.begin));
+            assert!(chunks.len() <= untrimmed.len());
+            if begin == 10_000_000 {
+                assert_ne!(chunks, untrimmed);
+            }
+
+            let expected: Vec<_> = all
+                .iter()
+                .filter(|x| x.begin < end && begin < x.end)
+                .cloned()
+                .collect();
+            assert_eq!(
+                file.fetch(rid, begin, end).collect::<Result<Vec<_>, _>>()?,
+                expected
+            );
+        }
+        assert_eq!(linear_window(1 << 20, 0), None);
+        assert_eq!(linear_window(1 << 20, 10), Some(9));
+        assert_eq!(linear_window(1 << 15, 10), Some(2));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_config_from_str() {
+        assert_eq!("gff".parse::<TabixConfig>().unwrap(), TabixConfig::GFF);
+        assert_eq!("bed".parse::<TabixConfig>().unwrap(), TabixConfig::BED);
+        assert_eq!("SAM".parse::<TabixConfig>().unwrap(), TabixConfig::SAM);
+        assert_eq!("Vcf".parse::<TabixConfig>().unwrap(), TabixConfig::VCF);
+        assert!("bam".parse::<TabixConfig>().is_err());
+        assert!("".parse::<TabixConfig>().is_err());
+    }
+
+    #[test]
+    fn test_split_columns() {
+        for line in [&b""[..], b"chr1", b"chr1\t10\t20", b"\tA\t\tB\t"] {
+            assert_eq!(
+                split_columns(line).collect::<Vec<_>>(),
+                line.split(|x| *x == b'\t').collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(
+            TabixConfig::VCF
+                .parse_record(b"1\t100\trs1\tAT\tA\t.\t.\tEND=150\tGT\n")
+                .unwrap(),
+            (&b"1"[..], 99, 150)
+        );
+        assert!(TabixConfig::BED.parse_record(b"chr1\t+\t20").is_err());
+        assert!(TabixConfig::BED
+            .parse_record(b"chr1\t18446744073709551616\t20")
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_record() {
+        let bed = TabixConfig::BED;
+        assert_eq!(
+            bed.parse_record(b"chr1\t+10\t+20").unwrap(),
+            (&b"chr1"[..], 10, 20)
+        );
+        assert_eq!(
+            bed.parse_record(b"chr1\t18446744073709551614\t18446744073709551615\r\n")
+                .unwrap(),
+            (&b"chr1"[..], u64::MAX - 1, u64::MAX)
+        );
+        for (line, column) in [
+            (&b"chr1\t18446744073709551616\t20"[..], 2),
+            (b"chr1\t10\t99999999999999999999", 3),
+            (b"chr1\t\t20", 2),
+            (b"chr1\t10\t", 3),
+            (b"chr1\t+\t20", 2),
+            (b"chr1\t-10\t20", 2),
+            (b"chr1\t1 0\t20", 2),
+            (b"chr1", 2),
+        ] {
+            assert!(
+                matches!(
+                    bed.parse_record(line),
+                    Err(BGZFError::ParseError { line: None, column: x }) if x == column
+                ),
+                "{}",
+                String::from_utf8_lossy(line)
+            );
+        }
+
+        // columns after the last needed one are not parsed
+        assert_eq!(
+            bed.parse_record(b"chr1\t10\t20\tname\tnot-a-number\t+")
+                .unwrap(),
+            (&b"chr1"[..], 10, 20)
+        );
+        let vcf = TabixConfig::VCF;
+        assert_eq!(
+            vcf.parse_record(b"1\t100\trs1\tAT\tA\t.\t.\tDP=3;END=+150\tGT:END=999\t0/1")
+                .unwrap(),
+            (&b"1"[..], 99, 150)
+        );
+        assert_eq!(
+            vcf.parse_record(b"1\t100\trs1\tAT\tA\t.\t.\tDP=3").unwrap(),
+            (&b"1"[..], 99, 101)
+        );
+        assert!(matches!(
+            vcf.parse_record(b"1\t100\trs1\tAT\tA\t.\t.\tEND=x"),
+            Err(BGZFError::ParseError { column: 8, .. })
+        ));
+        let custom = TabixConfig {
+            column_for_end: 10,
+            ..TabixConfig::BED
+        };
+        assert_eq!(
+            custom
+                .parse_record(b"chr1\t10\t.\t.\t.\t.\t.\t.\t.\t30\tx")
+                .unwrap(),
+            (&b"chr1"[..], 10, 30)
+        );
+    }
+
+    #[test]
+    fn test_bins() -> anyhow::Result<()> {
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(b'\t')
+            .quoting(false)
+            .from_reader(flate2::read::MultiGzDecoder::new(File::open(
+                "testfiles/bins.tsv.gz",
+            )?));
+        for row in reader.records() {
+            let row = row?;
+            let start: u64 = row.get(1).ok_or_else(|| anyhow!("No Start"))?.parse()?;
+            let end: u64 = row.get(2).ok_or_else(|| anyhow!("No End"))?.parse()?;
+            let bin: u32 = row.get(3).ok_or_else(|| anyhow!("No Bin"))?.parse()?;
+            let mut bins: Vec<u32> = row
+                .get(4)
+                .ok_or_else(|| anyhow!("No Bins"))?
+                .split(',')
+                .map(|x| x.parse().expect("Invalid bin"))
+                .collect();
+
+            let calculated_bin = reg2bin(
+                start.try_into().expect("Too large start"),
+                end.try_into().expect("Too large end"),
+            );
+            let mut calculated_bins = reg2bins(
+                start.try_into().expect("Too large start"),
+                end.try_into().expect("Too large end"),
+            );
+            bins.sort();
+            calculated_bins.sort();
+
+            assert_eq!(
+                bin, calculated_bin,
+                "Start: {} / End: {} / Calculated bin: {} / Expected bin: {}",
+                start, end, calculated_bin, bin,
+            );
+            assert_eq!(
+                bins, calculated_bins,
+                "Start: {} / End: {} / Calculated bins: {:?} / Expected bins: {:?}",
+                start, end, calculated_bins, bins,
+            );
+        }
+        Ok(())
+    }
+}
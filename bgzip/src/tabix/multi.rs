@@ -0,0 +1,290 @@
+use super::*;
+use std::collections::VecDeque;
+use std::io::{BufRead, Seek};
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch records overlapping with many regions of `(rid, begin, end)` (zero-based, half-open).
+    ///
+    /// Regions are sorted and chunks of regions in the same reference sequence are merged,
+    /// so each block is read once even if regions are overlapping or adjacent.
+    /// Items are pairs of the index of the region in `regions` and a record. Records are returned
+    /// in the order of the file. A record overlapping with several regions is returned once per region.
+    pub fn fetch_many(&mut self, regions: &[(u32, u64, u64)]) -> TabixMultiRecords<'_, R, I> {
+        let mut sorted: Vec<(usize, (u32, u64, u64))> = regions
+            .iter()
+            .copied()
+            .enumerate()
+            .filter(|(_, (_, begin, end))| begin < end)
+            .collect();
+        sorted.sort_by_key(|(i, (rid, begin, _))| (*rid, *begin, *i));
+
+        let mut groups: Vec<RegionGroup> = Vec::new();
+        for (i, (rid, begin, end)) in sorted {
+            match groups.last_mut() {
+                Some(last) if last.rid == rid => last.regions.push((i, begin, end)),
+                _ => groups.push(RegionGroup {
+                    rid,
+                    regions: vec![(i, begin, end)],
+                }),
+            }
+        }
+        debug!(
+            "fetch {} regions in {} reference sequences",
+            regions.len(),
+            groups.len()
+        );
+
+        let mut groups = groups.into_iter();
+        let (group, records) = match groups.next() {
+            Some(group) => {
+                let records = group.fetch(self);
+                (group, Some(records))
+            }
+            None => (RegionGroup::default(), None),
+        };
+        TabixMultiRecords {
+            records,
+            group,
+            groups,
+            first_active: 0,
+            pending: VecDeque::new(),
+            unique: false,
+        }
+    }
+
+    /// Fetch records overlapping with regions in a BED file, like `tabix -R`.
+    ///
+    /// Lines starting with `#`, `track` or `browser` are skipped, and regions of sequences
+    /// which are not in the index are ignored. Each record is returned once, with the index of the
+    /// first overlapping region among data lines of the BED file.
+    pub fn fetch_bed<B: BufRead>(
+        &mut self,
+        mut bed: B,
+    ) -> Result<TabixMultiRecords<'_, R, I>, BGZFError> {
+        let mut regions = Vec::new();
+        let mut line = Vec::new();
+        let mut line_number = 0;
+        loop {
+            line.clear();
+            if bed.read_until(b'\n', &mut line)? == 0 {
+                break;
+            }
+            line_number += 1;
+            let data = strip_line_end(&line);
+            if data.is_empty()
+                || TabixConfig::BED.is_meta(data)
+                || data.starts_with(b"track")
+                || data.starts_with(b"browser")
+            {
+                continue;
+            }
+            let (name, begin, end) = TabixConfig::BED
+                .parse_record(data)
+                .map_err(|e| e.with_line(line_number))?;
+            // an empty region is never fetched
+            regions.push(match self.index().name2rid(name) {
+                Some(rid) => (rid, begin, end),
+                None => (0, 0, 0),
+            });
+        }
+        Ok(self.fetch_many(&regions).with_unique())
+    }
+}
+
+/// Regions in a reference sequence, which are sorted by begin position
+#[derive(Debug, Clone, Default)]
+struct RegionGroup {
+    rid: u32,
+    regions: Vec<(usize, u64, u64)>,
+}
+
+impl RegionGroup {
+    /// Fetch records in the union of regions, with chunks merged once.
+    fn fetch<'a, R: Read + Seek, I: RegionIndex>(
+        &self,
+        file: &'a mut TabixFile<R, I>,
+    ) -> TabixRecords<'a, R, I> {
+        let chunks = merge_chunks(
+            self.regions
+                .iter()
+                .flat_map(|(_, begin, end)| file.index().region_chunks(self.rid, *begin, *end))
+                .collect(),
+        );
+        let begin = self.regions.first().map(|x| x.1).unwrap_or(0);
+        let end = self.regions.iter().map(|x| x.2).max().unwrap_or(0);
+        file.fetch_chunks(self.rid, begin, end, chunks)
+    }
+}
+
+/// An iterator over records in many regions. This struct is created by [`TabixFile::fetch_many`].
+pub struct TabixMultiRecords<'a, R: Read + Seek, I: RegionIndex = Tabix> {
+    records: Option<TabixRecords<'a, R, I>>,
+    group: RegionGroup,
+    groups: std::vec::IntoIter<RegionGroup>,
+    first_active: usize,
+    pending: VecDeque<(usize, TabixEntry)>,
+    unique: bool,
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> TabixMultiRecords<'a, R, I> {
+    /// Return a record once even if it overlaps with several regions.
+    ///
+    /// The smallest index of overlapping regions, that is the first one in `regions`,
+    /// is returned with the record.
+    pub fn with_unique(mut self) -> Self {
+        self.unique = true;
+        self
+    }
+
+    fn next_record(&mut self) -> Result<Option<(usize, TabixEntry)>, BGZFError> {
+        loop {
+            if let Some(one) = self.pending.pop_front() {
+                return Ok(Some(one));
+            }
+            let records = match self.records.as_mut() {
+                Some(records) => records,
+                None => return Ok(None),
+            };
+            let entry = match records.next().transpose()? {
+                Some(entry) => entry,
+                None => {
+                    let file = self.records.take().unwrap().into_file();
+                    if let Some(group) = self.groups.next() {
+                        self.records = Some(group.fetch(file));
+                        self.group = group;
+                        self.first_active = 0;
+                    }
+                    continue;
+                }
+            };
+
+            // records are sorted by begin position, so finished regions at the head are skipped
+            while self
+                .group
+                .regions
+                .get(self.first_active)
+                .map(|x| x.2 <= entry.begin)
+                .unwrap_or(false)
+            {
+                self.first_active += 1;
+            }
+            let overlapping = self.group.regions[self.first_active..]
+                .iter()
+                .take_while(|(_, begin, _)| *begin < entry.end)
+                .filter(|(_, _, end)| entry.begin < *end)
+                .map(|(i, _, _)| *i);
+            let overlapping: Vec<usize> = if self.unique {
+                overlapping.min().into_iter().collect()
+            } else {
+                overlapping.collect()
+            };
+            if let Some((&last, rest)) = overlapping.split_last() {
+                self.pending
+                    .extend(rest.iter().map(|i| (*i, entry.clone())));
+                self.pending.push_back((last, entry));
+            }
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for TabixMultiRecords<'a, R, I> {
+    type Item = Result<(usize, TabixEntry), BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fetch_many() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let chr1 = file.index().name2rid(b"chr1").unwrap();
+        let chr5 = file.index().name2rid(b"chr5").unwrap();
+        let regions = [
+            (chr5, 1_000_000, 2_000_000),
+            (chr1, 12_345_678, 13_000_000),
+            (chr1, 12_900_000, 13_500_000),
+            (chr1, 0, 100_000),
+            (chr1, 100, 100),
+            (chr5, 1_500_000, 1_600_000),
+        ];
+
+        let mut actual: Vec<Vec<TabixEntry>> = vec![Vec::new(); regions.len()];
+        let mut last = None;
+        for one in file.fetch_many(&regions) {
+            let (i, entry) = one?;
+            // records are returned in the order of the file
+            let key = (regions[i].0, entry.begin);
+            assert!(last <= Some(key));
+            last = Some(key);
+            actual[i].push(entry);
+        }
+
+        for (i, (rid, begin, end)) in regions.iter().enumerate() {
+            let expected = file
+                .fetch(*rid, *begin, *end)
+                .collect::<Result<Vec<_>, _>>()?;
+            assert_eq!(actual[i], expected);
+        }
+        assert!([0, 1, 2, 3, 5].iter().all(|i| !actual[*i].is_empty()));
+        assert!(actual[4].is_empty());
+        assert_eq!(file.fetch_many(&[]).count(), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_bed() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let bed =
+            b"# regions\ntrack name=test\nchr1\t12345678\t13000000\nchr1\t12900000\t13500000\n\
+            chrX\t0\t100\nchr5\t1000000\t2000000\n";
+        let actual = file.fetch_bed(&bed[..])?.collect::<Result<Vec<_>, _>>()?;
+
+        let mut expected = Vec::new();
+        for (name, begin, end, first_end) in [
+            (&b"chr1"[..], 12_345_678, 13_500_000, 13_000_000),
+            (b"chr5", 1_000_000, 2_000_000, 2_000_000),
+        ] {
+            let rid = file.index().name2rid(name).unwrap();
+            for one in file.fetch(rid, begin, end) {
+                let one = one?;
+                let i = match name {
+                    b"chr5" => 3,
+                    _ if one.begin < first_end => 0,
+                    _ => 1,
+                };
+                expected.push((i, one));
+            }
+        }
+        assert!(!expected.is_empty());
+        assert_eq!(actual, expected);
+
+        // the index follows the order of lines even if a later line begins earlier
+        let bed = b"chr1\t12900000\t13500000\nchr1\t12345678\t13000000\n";
+        let actual = file.fetch_bed(&bed[..])?.collect::<Result<Vec<_>, _>>()?;
+        let expected: Vec<_> = expected
+            .into_iter()
+            .filter(|(i, _)| *i != 3)
+            .map(|(_, one)| (if one.end > 12_900_000 { 0 } else { 1 }, one))
+            .collect();
+        assert!(expected
+            .iter()
+            .any(|(i, one)| *i == 0 && one.begin < 13_000_000));
+        assert_eq!(actual, expected);
+
+        assert!(matches!(
+            file.fetch_bed(&b"chr1\t0\t100\nchr1\tabc\t100\n"[..]),
+            Err(BGZFError::ParseError {
+                line: Some(2),
+                column: 2
+            })
+        ));
+
+        Ok(())
+    }
+}
@@ -0,0 +1,189 @@
+use super::*;
+use std::io::Seek;
+
+/// Distance between position `pos` and a record [begin, end), like `bedtools closest -d`.
+///
+/// Zero is returned if the record overlaps with `pos`. A record ending just before `pos` has distance one.
+fn distance(pos: u64, begin: u64, end: u64) -> u64 {
+    if pos < begin {
+        begin - pos
+    } else if end.max(begin + 1) <= pos {
+        pos + 1 - end.max(begin + 1)
+    } else {
+        0
+    }
+}
+
+/// Records around a position. This struct is returned by [`TabixFile::fetch_flanking`].
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct FlankingRecords {
+    /// Records ending at or before the position, closest first
+    pub upstream: Vec<TabixEntry>,
+    /// Records beginning after the position, closest first
+    pub downstream: Vec<TabixEntry>,
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch records closest to position `pos` (zero-based) of reference sequence `rid`, like `bedtools closest`.
+    ///
+    /// Records overlapping with `pos` are returned if exist. Otherwise, the closest upstream and/or
+    /// downstream records are returned. All records with the same distance are returned in the file order.
+    /// The search region is doubled from a window of the index until a record is found,
+    /// so the whole sequence is not scanned unless the sequence has few records.
+    pub fn fetch_nearest(&mut self, rid: u32, pos: u64) -> Result<Vec<TabixEntry>, BGZFError> {
+        let scheme = self.index().binning_scheme();
+        let mut width = 1u64 << scheme.min_shift();
+        loop {
+            let mut nearest = Vec::new();
+            let mut min_distance = u64::MAX;
+            let begin = pos.saturating_sub(width);
+            let end = pos.saturating_add(width).saturating_add(1);
+            for record in self.fetch(rid, begin, end) {
+                let record = record?;
+                let d = distance(pos, record.begin, record.end);
+                if d < min_distance {
+                    min_distance = d;
+                    nearest.clear();
+                }
+                if d == min_distance {
+                    nearest.push(record);
+                }
+            }
+            // every record within `width` is in the searched region
+            if min_distance <= width || (begin == 0 && end >= scheme.max_position()) {
+                return Ok(nearest);
+            }
+            width = width.saturating_mul(2);
+        }
+    }
+
+    /// Fetch `k` records preceding and `k` records following position `pos` (zero-based) of reference sequence `rid`.
+    ///
+    /// Records overlapping with `pos` are not included. Upstream records are sorted by end position
+    /// in descending order, and downstream records are sorted by begin position.
+    /// Downstream records are read forward from `pos`, and upstream records are searched in a region
+    /// doubled backward from `pos` until `k` records are found.
+    pub fn fetch_flanking(
+        &mut self,
+        rid: u32,
+        pos: u64,
+        k: usize,
+    ) -> Result<FlankingRecords, BGZFError> {
+        if k == 0 {
+            return Ok(FlankingRecords::default());
+        }
+        let downstream = self
+            .fetch(rid, pos.saturating_add(1), u64::MAX)
+            .filter(|x| x.as_ref().map(|x| x.begin > pos).unwrap_or(true))
+            .take(k)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut width = 1u64 << self.index().binning_scheme().min_shift();
+        let upstream = loop {
+            let begin = pos.saturating_sub(width);
+            let mut upstream = Vec::new();
+            for record in self.fetch(rid, begin, pos) {
+                let record = record?;
+                if record.end.max(record.begin + 1) <= pos {
+                    upstream.push(record);
+                }
+            }
+            // records which are not searched end before the records found
+            if upstream.len() >= k || begin == 0 {
+                upstream.sort_by_key(|x| std::cmp::Reverse((x.end, x.begin)));
+                upstream.truncate(k);
+                break upstream;
+            }
+            width = width.saturating_mul(2);
+        };
+
+        Ok(FlankingRecords {
+            upstream,
+            downstream,
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_fetch_nearest() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr3").unwrap();
+        let all = file
+            .fetch(rid, 0, u64::MAX)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!all.is_empty());
+
+        let last = all.iter().map(|x| x.end).max().unwrap();
+        for pos in [
+            0,
+            all[0].begin,
+            all[10].end,
+            all[10].end + 1_000,
+            12_345_678,
+            last + 3_000_000,
+        ] {
+            let min_distance = all
+                .iter()
+                .map(|x| distance(pos, x.begin, x.end))
+                .min()
+                .unwrap();
+            let expected: Vec<_> = all
+                .iter()
+                .filter(|x| distance(pos, x.begin, x.end) == min_distance)
+                .cloned()
+                .collect();
+            assert_eq!(file.fetch_nearest(rid, pos)?, expected, "pos: {}", pos);
+        }
+
+        assert!(file.fetch_nearest(1000, 100)?.is_empty());
+        assert_eq!(distance(10, 5, 10), 1);
+        assert_eq!(distance(10, 10, 11), 0);
+        assert_eq!(distance(10, 12, 20), 2);
+        assert_eq!(distance(10, 10, 10), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fetch_flanking() -> anyhow::Result<()> {
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = file.index().name2rid(b"chr3").unwrap();
+        let all = file
+            .fetch(rid, 0, u64::MAX)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        for (pos, k) in [
+            (0, 3),
+            (all[20].begin, 5),
+            (12_345_678, 10),
+            (u64::MAX / 2, 4),
+        ] {
+            let flanking = file.fetch_flanking(rid, pos, k)?;
+            let mut upstream: Vec<_> = all
+                .iter()
+                .filter(|x| x.end.max(x.begin + 1) <= pos)
+                .cloned()
+                .collect();
+            upstream.sort_by_key(|x| std::cmp::Reverse((x.end, x.begin)));
+            upstream.truncate(k);
+            let downstream: Vec<_> = all
+                .iter()
+                .filter(|x| x.begin > pos)
+                .take(k)
+                .cloned()
+                .collect();
+            assert_eq!(flanking.upstream, upstream, "pos: {}", pos);
+            assert_eq!(flanking.downstream, downstream, "pos: {}", pos);
+        }
+        assert_eq!(
+            file.fetch_flanking(rid, 12_345_678, 0)?,
+            FlankingRecords::default()
+        );
+
+        Ok(())
+    }
+}
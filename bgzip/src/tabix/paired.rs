@@ -0,0 +1,276 @@
+use super::*;
+use std::io::Seek;
+
+impl TabixConfig {
+    /// Preset for BEDPE files. Records are indexed by the first interval (columns 1-3).
+    pub const BEDPE: TabixConfig = TabixConfig::BED;
+
+    /// Columns of the second interval of BEDPE files (columns 4-6).
+    ///
+    /// This configuration is used with [`TabixFile::fetch_paired`] to parse the second anchor.
+    pub const BEDPE_SECOND: TabixConfig = TabixConfig {
+        format: FORMAT_GENERIC | FORMAT_ZERO_BASED,
+        column_for_sequence: 4,
+        column_for_begin: 5,
+        column_for_end: 6,
+        meta: b'#',
+        skip: 0,
+    };
+}
+
+/// A region of one anchor. Positions are zero-based and half-open.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AnchorRegion<'a> {
+    pub name: &'a [u8],
+    pub begin: u64,
+    pub end: u64,
+}
+
+impl<'a> AnchorRegion<'a> {
+    pub fn new(name: &'a [u8], begin: u64, end: u64) -> Self {
+        AnchorRegion { name, begin, end }
+    }
+
+    fn overlaps(&self, name: &[u8], begin: u64, end: u64) -> bool {
+        self.name == name && begin < self.end && self.begin < end
+    }
+}
+
+/// Query of records with two intervals.
+///
+/// Only the first anchor is indexed. [`PairedQuery::Second`] and [`PairedQuery::Either`] scan whole file.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PairedQuery<'a> {
+    /// The first anchor overlaps with the region
+    First(AnchorRegion<'a>),
+    /// The second anchor overlaps with the region
+    Second(AnchorRegion<'a>),
+    /// The first anchor overlaps with the first region, and the second anchor overlaps with the second region
+    Both(AnchorRegion<'a>, AnchorRegion<'a>),
+    /// The first anchor or the second anchor overlaps with the region
+    Either(AnchorRegion<'a>),
+}
+
+/// A record with two intervals
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PairedEntry {
+    /// Reference sequence ID of the first anchor
+    pub rid: u32,
+    /// Record with the first anchor interval
+    pub entry: TabixEntry,
+    /// Sequence name of the second anchor
+    pub second_name: Vec<u8>,
+    /// Zero-based begin position of the second anchor
+    pub second_begin: u64,
+    /// Zero-based, exclusive end position of the second anchor
+    pub second_end: u64,
+}
+
+enum PairedSource<'a, R: Read + Seek, I: RegionIndex> {
+    Region(u32, TabixRecords<'a, R, I>),
+    All(TabixAllRecords<'a, R, I>),
+    Empty,
+}
+
+/// An iterator over records with two intervals. This struct is created by [`TabixFile::fetch_paired`].
+pub struct PairedRecords<'a, R: Read + Seek, I: RegionIndex = Tabix> {
+    source: PairedSource<'a, R, I>,
+    second: TabixConfig,
+    query: PairedQuery<'a>,
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch records which have two intervals, such as BEDPE.
+    ///
+    /// The first anchor is parsed with the configuration of the index, and the second anchor is
+    /// parsed with `second` (e.g. [`TabixConfig::BEDPE_SECOND`]).
+    pub fn fetch_paired<'a>(
+        &'a mut self,
+        second: TabixConfig,
+        query: PairedQuery<'a>,
+    ) -> PairedRecords<'a, R, I> {
+        let first = match query {
+            PairedQuery::First(first) | PairedQuery::Both(first, _) => Some(first),
+            PairedQuery::Second(_) | PairedQuery::Either(_) => None,
+        };
+        let source = match first {
+            Some(first) => match self.index().name2rid(first.name) {
+                Some(rid) => PairedSource::Region(rid, self.fetch(rid, first.begin, first.end)),
+                None => PairedSource::Empty,
+            },
+            None => PairedSource::All(self.records()),
+        };
+        PairedRecords {
+            source,
+            second,
+            query,
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> PairedRecords<'a, R, I> {
+    /// Include BGZF virtual offsets of records in [`TabixEntry::offset`].
+    pub fn with_offsets(mut self) -> Self {
+        self.source = match self.source {
+            PairedSource::Region(rid, records) => PairedSource::Region(rid, records.with_offsets()),
+            PairedSource::All(records) => PairedSource::All(records.with_offsets()),
+            PairedSource::Empty => PairedSource::Empty,
+        };
+        self
+    }
+
+    /// Fail with [`BGZFError::Timeout`] if reading of records continues after `deadline`.
+    pub fn with_deadline(mut self, deadline: std::time::Instant) -> Self {
+        self.source = match self.source {
+            PairedSource::Region(rid, records) => {
+                PairedSource::Region(rid, records.with_deadline(deadline))
+            }
+            PairedSource::All(records) => PairedSource::All(records.with_deadline(deadline)),
+            PairedSource::Empty => PairedSource::Empty,
+        };
+        self
+    }
+
+    fn next_record(&mut self) -> Result<Option<PairedEntry>, BGZFError> {
+        loop {
+            let (rid, entry, first_name) = match &mut self.source {
+                PairedSource::Region(rid, records) => match records.next().transpose()? {
+                    Some(entry) => (*rid, entry, None),
+                    None => return Ok(None),
+                },
+                PairedSource::All(records) => match records.next().transpose()? {
+                    Some(entry) => {
+                        let name = records
+                            .index()
+                            .rid2name(entry.rid)
+                            .unwrap_or_default()
+                            .to_vec();
+                        (entry.rid, entry, Some(name))
+                    }
+                    None => return Ok(None),
+                },
+                PairedSource::Empty => return Ok(None),
+            };
+            let (second_name, second_begin, second_end) = self.second.parse_record(&entry.data)?;
+
+            let matched = match self.query {
+                PairedQuery::First(_) => true,
+                PairedQuery::Both(_, second) => {
+                    second.overlaps(second_name, second_begin, second_end)
+                }
+                PairedQuery::Second(second) => {
+                    second.overlaps(second_name, second_begin, second_end)
+                }
+                PairedQuery::Either(region) => {
+                    region.overlaps(second_name, second_begin, second_end)
+                        || region.overlaps(
+                            first_name.as_deref().unwrap_or_default(),
+                            entry.begin,
+                            entry.end,
+                        )
+                }
+            };
+            if !matched {
+                continue;
+            }
+
+            let second_name = second_name.to_vec();
+            return Ok(Some(PairedEntry {
+                rid,
+                entry,
+                second_name,
+                second_begin,
+                second_end,
+            }));
+        }
+    }
+}
+
+impl<'a, R: Read + Seek, I: RegionIndex> Iterator for PairedRecords<'a, R, I> {
+    type Item = Result<PairedEntry, BGZFError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record().transpose()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use rand::prelude::*;
+
+    #[test]
+    fn test_fetch_paired() -> anyhow::Result<()> {
+        let names = ["chr1", "chr2", "chr3"];
+        let mut rng = rand_pcg::Pcg64Mcg::seed_from_u64(488);
+        let mut records: Vec<(usize, u64, u64, usize, u64, u64)> = (0..3000)
+            .map(|_| {
+                let begin1 = rng.gen_range(0..1_000_000);
+                let begin2 = rng.gen_range(0..1_000_000);
+                (
+                    rng.gen_range(0..names.len()),
+                    begin1,
+                    begin1 + rng.gen_range(1..5000),
+                    rng.gen_range(0..names.len()),
+                    begin2,
+                    begin2 + rng.gen_range(1..5000),
+                )
+            })
+            .collect();
+        records.sort();
+        let data: Vec<u8> = records
+            .iter()
+            .enumerate()
+            .flat_map(|(i, x)| {
+                format!(
+                    "{}\t{}\t{}\t{}\t{}\t{}\tpair{}\n",
+                    names[x.0], x.1, x.2, names[x.3], x.4, x.5, i
+                )
+                .into_bytes()
+            })
+            .collect();
+
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::new(&mut compressed, Compression::default());
+        let index = compress_and_index(&data[..], &mut writer, TabixConfig::BEDPE)?;
+        writer.close()?;
+        let mut file = TabixFile::new(io::Cursor::new(compressed), index)?;
+
+        let region1 = AnchorRegion::new(b"chr2", 100_000, 200_000);
+        let region2 = AnchorRegion::new(b"chr3", 500_000, 700_000);
+        for (query, filter) in [
+            (
+                PairedQuery::First(region1),
+                &(|x: &(usize, u64, u64, usize, u64, u64)| {
+                    region1.overlaps(names[x.0].as_bytes(), x.1, x.2)
+                }) as &dyn Fn(&(usize, u64, u64, usize, u64, u64)) -> bool,
+            ),
+            (PairedQuery::Second(region2), &|x| {
+                region2.overlaps(names[x.3].as_bytes(), x.4, x.5)
+            }),
+            (PairedQuery::Both(region1, region2), &|x| {
+                region1.overlaps(names[x.0].as_bytes(), x.1, x.2)
+                    && region2.overlaps(names[x.3].as_bytes(), x.4, x.5)
+            }),
+            (PairedQuery::Either(region1), &|x| {
+                region1.overlaps(names[x.0].as_bytes(), x.1, x.2)
+                    || region1.overlaps(names[x.3].as_bytes(), x.4, x.5)
+            }),
+        ] {
+            let expected: Vec<_> = records.iter().filter(|x| filter(x)).collect();
+            let fetched = file
+                .fetch_paired(TabixConfig::BEDPE_SECOND, query)
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!expected.is_empty());
+            assert_eq!(fetched.len(), expected.len());
+            for (one, expected) in fetched.iter().zip(expected) {
+                assert_eq!(one.rid, TryInto::<u32>::try_into(expected.0)?);
+                assert_eq!((one.entry.begin, one.entry.end), (expected.1, expected.2));
+                assert_eq!(one.second_name, names[expected.3].as_bytes());
+                assert_eq!((one.second_begin, one.second_end), (expected.4, expected.5));
+            }
+        }
+
+        Ok(())
+    }
+}
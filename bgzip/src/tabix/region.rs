@@ -0,0 +1,173 @@
+use super::*;
+use std::fmt;
+use std::str::FromStr;
+
+/// A genomic region with zero-based, half-open coordinates.
+///
+/// The end is [`u64::MAX`] for an open-ended region.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Region {
+    pub name: String,
+    pub begin: u64,
+    pub end: u64,
+}
+
+impl Region {
+    pub fn new<S: Into<String>>(name: S, begin: u64, end: u64) -> Self {
+        Region {
+            name: name.into(),
+            begin,
+            end,
+        }
+    }
+
+    /// Parse a region string like `samtools` and `tabix` commands.
+    ///
+    /// Accepted forms are `chr17`, `chr17:42,990,000`, `chr17:42,990,000-42,990,600`,
+    /// `chr17:-42,990,600` and `chr17:100-`. Positions are 1-based and inclusive, and commas are ignored.
+    /// Positions can have a suffix `bp`, `k`/`kb`, `m`/`mb` or `g`/`gb` (case-insensitive),
+    /// such as `chr1:5kb-2Mb` or `chr1:1.5M-`.
+    pub fn parse(region: &str) -> Result<Self, BGZFError> {
+        let (name, begin, end) = parse_region(region)?;
+        Ok(Region::new(name, begin, end))
+    }
+}
+
+impl FromStr for Region {
+    type Err = BGZFError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Region::parse(s)
+    }
+}
+
+impl fmt::Display for Region {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.begin, self.end) {
+            (0, u64::MAX) => write!(f, "{}", self.name),
+            (begin, u64::MAX) => write!(f, "{}:{}-", self.name, begin + 1),
+            (begin, end) => write!(f, "{}:{}-{}", self.name, begin + 1, end),
+        }
+    }
+}
+
+/// Parse a position with an optional unit suffix. `None` if `value` is empty.
+fn parse_position(value: &str) -> Result<Option<u64>, BGZFError> {
+    const INVALID: BGZFError = BGZFError::Other("Invalid position in region");
+    let value: String = value
+        .chars()
+        .filter(|x| *x != ',')
+        .collect::<String>()
+        .to_ascii_lowercase();
+    if value.is_empty() {
+        return Ok(None);
+    }
+    let (number, unit) = [
+        ("kb", 1_000),
+        ("k", 1_000),
+        ("mb", 1_000_000),
+        ("m", 1_000_000),
+        ("gb", 1_000_000_000),
+        ("g", 1_000_000_000),
+        ("bp", 1),
+    ]
+    .iter()
+    .find_map(|(suffix, unit)| value.strip_suffix(suffix).map(|x| (x, *unit)))
+    .unwrap_or((&value, 1u64));
+
+    let (integer, fraction) = number.split_once('.').unwrap_or((number, ""));
+    if integer.is_empty() && fraction.is_empty() {
+        return Err(INVALID);
+    }
+    let digits = |x: &str| -> Result<u64, BGZFError> {
+        if x.is_empty() {
+            Ok(0)
+        } else if x.bytes().all(|x| x.is_ascii_digit()) {
+            x.parse().map_err(|_| INVALID)
+        } else {
+            Err(INVALID)
+        }
+    };
+    let scale = 10u64
+        .checked_pow(fraction.len().try_into().map_err(|_| INVALID)?)
+        .ok_or(INVALID)?;
+    let fraction = digits(fraction)?.checked_mul(unit).ok_or(INVALID)?;
+    if fraction % scale != 0 {
+        return Err(BGZFError::Other("Position in region is not an integer"));
+    }
+    digits(integer)?
+        .checked_mul(unit)
+        .and_then(|x| x.checked_add(fraction / scale))
+        .map(Some)
+        .ok_or(INVALID)
+}
+
+/// Parse a region string into the sequence name and zero-based, half-open region.
+///
+/// The syntax is described in [`Region::parse`]. The end of region is [`u64::MAX`] if it is not given.
+pub(crate) fn parse_region(region: &str) -> Result<(&str, u64, u64), BGZFError> {
+    let (name, range) = match region.rsplit_once(':') {
+        Some((name, range)) if !name.is_empty() => (name, range),
+        _ => return Ok((region, 0, u64::MAX)),
+    };
+    let (begin, end) = match range.split_once('-') {
+        Some((begin, end)) => (parse_position(begin)?, parse_position(end)?),
+        None => (parse_position(range)?, None),
+    };
+    let begin = begin.unwrap_or(1).saturating_sub(1);
+    let end = end.unwrap_or(u64::MAX);
+    if end <= begin {
+        return Err(BGZFError::Other("Invalid region"));
+    }
+    Ok((name, begin, end))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_region() -> anyhow::Result<()> {
+        assert_eq!(parse_region("chr17")?, ("chr17", 0, u64::MAX));
+        assert_eq!(
+            parse_region("chr17:42,990,000-42,990,600")?,
+            ("chr17", 42_989_999, 42_990_600)
+        );
+        assert_eq!(parse_region("chr17:100")?, ("chr17", 99, u64::MAX));
+        assert_eq!(parse_region("chr17:100-")?, ("chr17", 99, u64::MAX));
+        assert_eq!(parse_region("chr17:-200")?, ("chr17", 0, 200));
+        assert_eq!(parse_region("HLA-A*01:01:1-10")?, ("HLA-A*01:01", 0, 10));
+        assert!(parse_region("chr17:200-100").is_err());
+        assert!(parse_region("chr17:abc").is_err());
+        Ok(())
+    }
+
+    #[test]
+    fn test_region() -> anyhow::Result<()> {
+        assert_eq!(
+            Region::parse("chr1:1,234,567-2,345,678")?,
+            Region::new("chr1", 1_234_566, 2_345_678)
+        );
+        assert_eq!(
+            Region::parse("chr1:5kb-2Mb")?,
+            Region::new("chr1", 4_999, 2_000_000)
+        );
+        assert_eq!(
+            "chr1:1.5M-".parse::<Region>()?,
+            Region::new("chr1", 1_499_999, u64::MAX)
+        );
+        assert_eq!(
+            Region::parse("chr1:100bp-1G")?,
+            Region::new("chr1", 99, 1_000_000_000)
+        );
+        assert!(Region::parse("chr1:1.2345k").is_err());
+        assert!(Region::parse("chr1:kb").is_err());
+        assert!(Region::parse("chr1:1.-5").is_ok());
+        assert!(Region::parse("chr1:99999999999999999999").is_err());
+
+        for one in ["chr1", "chr1:100-", "chr1:100-200"] {
+            assert_eq!(Region::parse(one)?.to_string(), one);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use super::*;
+use std::io::BufRead;
+
+/// Compress `reader` into `writer` with renaming sequence names, and build tabix index with new names.
+///
+/// Sequence names in the sequence column are replaced with `mapping` (e.g. Ensembl to UCSC names).
+/// Names not found in `mapping` are kept. For VCF, `ID` of `##contig` header lines are also renamed.
+/// Renamed data must be still sorted, so that the index can be built in one pass.
+pub fn rename_contigs<R: BufRead, W: Write>(
+    mut reader: R,
+    writer: &mut BGZFWriter<W>,
+    config: TabixConfig,
+    mapping: &HashMap<Vec<u8>, Vec<u8>>,
+) -> Result<Tabix, BGZFError> {
+    let mut builder = TabixBuilder::new(config);
+    let mut line = Vec::new();
+    let mut renamed = Vec::new();
+    let mut line_count = 0;
+    loop {
+        line.clear();
+        if reader.read_until(b'\n', &mut line)? == 0 {
+            break;
+        }
+        line_count += 1;
+
+        renamed.clear();
+        if line_count <= config.skip_lines() {
+            renamed.extend_from_slice(&line);
+        } else if config.is_meta(&line) {
+            rename_contig_header(&line, config, mapping, &mut renamed);
+        } else {
+            rename_column(&line, config, mapping, &mut renamed);
+        }
+        writer.write_all(&renamed)?;
+        builder.add_line(&renamed, writer.bgzf_pos())?;
+    }
+    // flush the last block, so that the final offset points to the end-of-file marker
+    writer.flush()?;
+    Ok(builder.finish(writer.bgzf_pos()))
+}
+
+fn rename_column(
+    line: &[u8],
+    config: TabixConfig,
+    mapping: &HashMap<Vec<u8>, Vec<u8>>,
+    renamed: &mut Vec<u8>,
+) {
+    for (i, column) in split_columns(line).enumerate() {
+        if i > 0 {
+            renamed.push(b'\t');
+        }
+        let column_index: i32 = (i + 1).try_into().unwrap();
+        if column_index != config.column_for_sequence {
+            renamed.extend_from_slice(column);
+            continue;
+        }
+        // a line with only one column has a new line character in the sequence column
+        let (name, line_end) = column.split_at(strip_line_end(column).len());
+        renamed.extend_from_slice(mapping.get(name).map(|x| &x[..]).unwrap_or(name));
+        renamed.extend_from_slice(line_end);
+    }
+}
+
+fn rename_contig_header(
+    line: &[u8],
+    config: TabixConfig,
+    mapping: &HashMap<Vec<u8>, Vec<u8>>,
+    renamed: &mut Vec<u8>,
+) {
+    const CONTIG_PREFIX: &[u8] = b"##contig=<ID=";
+    if config.format & 0xffff != FORMAT_VCF || !line.starts_with(CONTIG_PREFIX) {
+        renamed.extend_from_slice(line);
+        return;
+    }
+    let rest = &line[CONTIG_PREFIX.len()..];
+    let name_len = rest
+        .iter()
+        .position(|x| *x == b',' || *x == b'>')
+        .unwrap_or(rest.len());
+    let name = &rest[..name_len];
+    renamed.extend_from_slice(CONTIG_PREFIX);
+    renamed.extend_from_slice(mapping.get(name).map(|x| &x[..]).unwrap_or(name));
+    renamed.extend_from_slice(&rest[name_len..]);
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_rename_contigs() -> anyhow::Result<()> {
+        let mapping: HashMap<Vec<u8>, Vec<u8>> = (1..=22)
+            .map(|x| {
+                (
+                    format!("chr{}", x).into_bytes(),
+                    format!("{}", x).into_bytes(),
+                )
+            })
+            .collect();
+
+        let mut compressed = Vec::new();
+        let mut writer = BGZFWriter::new(&mut compressed, Compression::default());
+        let index = rename_contigs(
+            crate::read::open("testfiles/generated.bed.gz")?,
+            &mut writer,
+            TabixConfig::BED,
+            &mapping,
+        )?;
+        writer.close()?;
+
+        assert_eq!(index.rid2name(0), Some(&b"1"[..]));
+        let original_index =
+            Tabix::from_reader(std::fs::File::open("testfiles/generated.bed.gz.tbi")?)?;
+        assert_eq!(index.sequences.len(), original_index.sequences.len());
+
+        let renamed = crate::read::new_reader(&compressed[..])?;
+        let original = crate::read::open("testfiles/generated.bed.gz")?;
+        for (renamed, original) in renamed.split(b'\n').zip(original.split(b'\n')) {
+            let renamed = renamed?;
+            let original = original?;
+            assert_eq!(renamed, &original[3..]);
+        }
+
+        let mut renamed_file = TabixFile::new(io::Cursor::new(compressed), index)?;
+        let mut original_file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let rid = renamed_file.index().name2rid(b"5").unwrap();
+        let renamed_records = renamed_file
+            .fetch(rid, 1_000_000, 2_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let rid = original_file.index().name2rid(b"chr5").unwrap();
+        let original_records = original_file
+            .fetch(rid, 1_000_000, 2_000_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!renamed_records.is_empty());
+        assert_eq!(renamed_records.len(), original_records.len());
+
+        let mut renamed = Vec::new();
+        rename_contig_header(
+            b"##contig=<ID=chr2,length=242193529>\n",
+            TabixConfig::VCF,
+            &mapping,
+            &mut renamed,
+        );
+        assert_eq!(renamed, b"##contig=<ID=2,length=242193529>\n");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,179 @@
+use super::*;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// A tabix indexed file whose parsed index is shared by query handles.
+///
+/// This struct does not hold a reader, and it is cheap to clone and can be shared between threads.
+/// Each thread opens its own [`TabixFile`] with [`SharedTabixFile::open`], which reopens the data file
+/// but shares one parsed index, so a thread-per-request server does not keep a copy of a large index per request.
+#[derive(Debug)]
+pub struct SharedTabixFile<I: RegionIndex = Tabix> {
+    path: PathBuf,
+    index: Arc<I>,
+    limits: Limits,
+}
+
+impl<I: RegionIndex> Clone for SharedTabixFile<I> {
+    fn clone(&self) -> Self {
+        SharedTabixFile {
+            path: self.path.clone(),
+            index: self.index.clone(),
+            limits: self.limits,
+        }
+    }
+}
+
+impl SharedTabixFile<Tabix> {
+    /// Load tabix index (`path` + `.tbi`) of BGZF compressed file at `path`.
+    pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Self, BGZFError> {
+        SharedTabixFile::from_path_with_limits(path, Limits::UNLIMITED)
+    }
+
+    /// Load tabix index (`path` + `.tbi`) with resource limits, which are also applied to query handles.
+    pub fn from_path_with_limits<P: AsRef<Path>>(
+        path: P,
+        limits: Limits,
+    ) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".tbi");
+        let index = Tabix::from_reader_with_limits(std::fs::File::open(index_path)?, limits)?;
+        Ok(SharedTabixFile::new(path, Arc::new(index), limits))
+    }
+}
+
+impl<I: RegionIndex> SharedTabixFile<I> {
+    /// Create from the path of BGZF compressed file and its loaded index.
+    pub fn new<P: AsRef<Path>>(path: P, index: Arc<I>, limits: Limits) -> Self {
+        SharedTabixFile {
+            path: path.as_ref().to_path_buf(),
+            index,
+            limits,
+        }
+    }
+
+    /// Shared index
+    pub fn index(&self) -> &Arc<I> {
+        &self.index
+    }
+
+    /// Path of BGZF compressed file
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Open a new query handle, which has its own reader and shares the index.
+    pub fn open(&self) -> Result<TabixFile<std::fs::File, I>, BGZFError> {
+        let file = TabixFile::with_shared_index(
+            std::fs::File::open(&self.path)?,
+            self.index.clone(),
+            self.limits,
+        )?;
+        Ok(file.with_path(&self.path))
+    }
+}
+
+#[cfg(feature = "rayon")]
+impl<I: RegionIndex + Send + Sync> SharedTabixFile<I> {
+    /// Fetch records overlapping with each of `regions` with rayon's global thread pool.
+    ///
+    /// Regions are distributed across threads, and each thread opens its own handle with
+    /// [`SharedTabixFile::open`]. Records are returned in the order of `regions`.
+    /// An unknown sequence name is reported as [`BGZFError::UnknownContig`].
+    pub fn fetch_regions_parallel(
+        &self,
+        regions: &[Region],
+    ) -> Result<Vec<Vec<TabixEntry>>, BGZFError> {
+        use ::rayon::prelude::*;
+
+        regions
+            .par_iter()
+            .map_init(
+                || None::<TabixFile<std::fs::File, I>>,
+                |handle, region| {
+                    let handle = match handle {
+                        Some(handle) => handle,
+                        None => handle.insert(self.open()?),
+                    };
+                    handle
+                        .fetch_name(region.name.as_bytes(), region.begin, region.end)?
+                        .collect()
+                },
+            )
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_shared_tabix_file() -> anyhow::Result<()> {
+        let shared = SharedTabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let regions = [
+            (&b"chr1"[..], 0, 100_000),
+            (b"chr2", 1_000_000, 3_000_000),
+            (b"chr5", 100_000_000, 100_100_000),
+            (b"chr22", 0, 1_000_000_000),
+        ];
+        let expected: Vec<_> = regions
+            .iter()
+            .map(|(name, begin, end)| {
+                let rid = file.index().name2rid(name).unwrap();
+                file.fetch(rid, *begin, *end).collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<_, _>>()?;
+
+        let threads: Vec<_> = regions
+            .iter()
+            .map(|(name, begin, end)| {
+                let shared = shared.clone();
+                let (name, begin, end) = (name.to_vec(), *begin, *end);
+                std::thread::spawn(move || -> Result<Vec<TabixEntry>, BGZFError> {
+                    let mut handle = shared.open()?;
+                    assert!(std::ptr::eq(handle.index(), &**shared.index()));
+                    handle.fetch_name(&name, begin, end)?.collect()
+                })
+            })
+            .collect();
+        for (thread, expected) in threads.into_iter().zip(&expected) {
+            assert_eq!(&thread.join().unwrap()?, expected);
+        }
+
+        // handles can be cloned
+        let handle = shared.open()?;
+        let cloned = handle.try_clone()?;
+        assert!(std::ptr::eq(cloned.index(), &**shared.index()));
+        assert_eq!(Arc::strong_count(shared.index()), 3);
+
+        Ok(())
+    }
+
+    #[cfg(feature = "rayon")]
+    #[test]
+    fn test_fetch_regions_parallel() -> anyhow::Result<()> {
+        let shared = SharedTabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let regions: Vec<_> = (0..200)
+            .map(|i| Region::new("1", i * 1_000_000, i * 1_000_000 + 20_000))
+            .collect();
+        let expected = regions
+            .iter()
+            .map(|x| {
+                file.fetch_name(x.name.as_bytes(), x.begin, x.end)?
+                    .collect::<Result<Vec<_>, _>>()
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(expected.iter().any(|x| !x.is_empty()));
+        assert_eq!(shared.fetch_regions_parallel(&regions)?, expected);
+
+        assert!(matches!(
+            shared.fetch_regions_parallel(&[Region::new("chrX", 0, 100)]),
+            Err(BGZFError::UnknownContig(_))
+        ));
+
+        Ok(())
+    }
+}
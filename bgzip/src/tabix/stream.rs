@@ -0,0 +1,104 @@
+use super::*;
+use futures_core::Stream;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tokio::sync::mpsc;
+
+/// Number of records buffered between the reading thread and [`TabixStream`]
+const STREAM_BUFFER_SIZE: usize = 1024;
+
+/// A stream of records in a region. This struct is created by [`SharedTabixFile::fetch_stream`].
+///
+/// Records are read in a blocking thread of tokio, which waits while the buffer is full,
+/// so a slow consumer applies backpressure to the reader. Reading stops when this stream is dropped.
+pub struct TabixStream {
+    receiver: mpsc::Receiver<Result<TabixEntry, BGZFError>>,
+}
+
+impl Stream for TabixStream {
+    type Item = Result<TabixEntry, BGZFError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        self.receiver.poll_recv(cx)
+    }
+}
+
+impl<I: RegionIndex + Send + Sync + 'static> SharedTabixFile<I> {
+    /// Fetch records overlapping with `region` as a [`Stream`].
+    ///
+    /// A query handle is opened with [`SharedTabixFile::open`] in a blocking thread of the current tokio runtime,
+    /// so this must be called in the runtime. An unknown sequence name is returned as an error in the stream.
+    pub fn fetch_stream(&self, region: Region) -> TabixStream {
+        let (sender, receiver) = mpsc::channel(STREAM_BUFFER_SIZE);
+        let shared = self.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut handle = match shared.open() {
+                Ok(handle) => handle,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            let records = match handle.fetch_name(region.name.as_bytes(), region.begin, region.end)
+            {
+                Ok(records) => records,
+                Err(e) => {
+                    let _ = sender.blocking_send(Err(e));
+                    return;
+                }
+            };
+            for one in records {
+                let is_err = one.is_err();
+                if sender.blocking_send(one).is_err() || is_err {
+                    // the stream was dropped, or no more records after an error
+                    break;
+                }
+            }
+        });
+        TabixStream { receiver }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        std::future::poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    #[tokio::test]
+    async fn test_fetch_stream() -> anyhow::Result<()> {
+        let shared = SharedTabixFile::from_path("testfiles/generated.bed.gz")?;
+        let region = Region::new("chr2", 1_000_000, 30_000_000);
+        let expected = shared
+            .open()?
+            .fetch_name(region.name.as_bytes(), region.begin, region.end)?
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(expected.len() > STREAM_BUFFER_SIZE);
+
+        let mut stream = shared.fetch_stream(region.clone());
+        let mut records = Vec::new();
+        while let Some(one) = next(&mut stream).await {
+            records.push(one?);
+        }
+        assert_eq!(records, expected);
+
+        // dropped in the middle
+        let mut stream = shared.fetch_stream(region);
+        assert_eq!(
+            next(&mut stream).await.transpose()?,
+            expected.first().cloned()
+        );
+        drop(stream);
+
+        let mut stream = shared.fetch_stream(Region::new("chrX", 0, 100));
+        assert!(matches!(
+            next(&mut stream).await,
+            Some(Err(BGZFError::UnknownContig(_)))
+        ));
+        assert!(next(&mut stream).await.is_none());
+
+        Ok(())
+    }
+}
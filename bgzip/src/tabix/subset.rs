@@ -0,0 +1,376 @@
+use super::*;
+use crate::deflate::{Compress, Decompress};
+use std::io::{Seek, SeekFrom};
+
+/// Copy header lines and records of selected sequences into a new BGZF file, and build its index.
+///
+/// Blocks which are fully included in the selected region are copied without recompression.
+/// Partial blocks at the boundaries of sequences are recompressed. End-of-file marker is written at the end.
+/// Sequences are written in the order of the index.
+pub fn subset_contigs<R: Read + Seek, W: Write, S: AsRef<[u8]>>(
+    mut input: R,
+    index: &Tabix,
+    names: &[S],
+    output: W,
+) -> Result<Tabix, BGZFError> {
+    let mut rids = names
+        .iter()
+        .map(|x| index.try_name2rid(x.as_ref()))
+        .collect::<Result<Vec<u32>, _>>()?;
+    rids.sort_unstable();
+    rids.dedup();
+
+    let sequence_ranges: Vec<(u64, u64)> = index
+        .sequences
+        .iter()
+        .map(sequence_range)
+        .collect::<Result<_, _>>()?;
+    let header_end = sequence_ranges.iter().map(|x| x.0).min().unwrap_or(0);
+
+    let mut writer = SubsetWriter {
+        output,
+        position: 0,
+        builder: TabixBuilder::new(index.config()),
+        line: Vec::new(),
+        compress: Compress::new(Compression::default()),
+        compressed_buffer: Vec::new(),
+        raw_buffer: Vec::new(),
+        data_buffer: Vec::new(),
+        decompress: Decompress::new(),
+    };
+    writer.copy(&mut input, 0, header_end)?;
+    for rid in rids {
+        let (begin, end) = sequence_ranges[TryInto::<usize>::try_into(rid).unwrap()];
+        writer.copy(&mut input, begin, end)?;
+    }
+    writer.finish()
+}
+
+impl Tabix {
+    /// Create an index which contains only selected sequences, without copying the data file.
+    ///
+    /// Sequences are kept in the order of this index, and reference sequence IDs are renumbered.
+    /// The returned index can be used to query the same data file, so a small index can be given to
+    /// a worker which processes a part of sequences. [`BGZFError::UnknownContig`] is returned if
+    /// a name is not in this index.
+    pub fn subset<S: AsRef<[u8]>>(&self, names: &[S]) -> Result<Tabix, BGZFError> {
+        let mut rids = names
+            .iter()
+            .map(|x| self.try_name2rid(x.as_ref()))
+            .collect::<Result<Vec<u32>, _>>()?;
+        rids.sort_unstable();
+        rids.dedup();
+
+        let mut subset_names = Vec::new();
+        let mut sequences = Vec::new();
+        for rid in rids {
+            let rid: usize = rid.try_into().unwrap();
+            subset_names.push(self.names[rid].clone());
+            sequences.push(self.sequences[rid].clone());
+        }
+        Ok(Tabix {
+            number_of_references: count_to_i32(sequences.len())?,
+            length_of_concatenated_sequence_names: count_to_i32(
+                subset_names
+                    .iter()
+                    .map(|x| x.len() + usize::from(!x.ends_with(&[0])))
+                    .sum::<usize>(),
+            )?,
+            names: subset_names,
+            sequences,
+            name_table: NameTable::default(),
+            ..self.clone()
+        })
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Write header lines and records overlapping with a region (zero-based, half-open) into a new BGZF file,
+    /// and build its index.
+    ///
+    /// The output is a valid BGZF file with end-of-file marker, which contains a small subset of the original file.
+    /// The returned index should be saved with [`Tabix::write`].
+    pub fn extract<W: Write>(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+        output: W,
+    ) -> Result<Tabix, BGZFError> {
+        let mut builder = TabixBuilder::new(self.index().config());
+        let mut writer = BGZFWriter::new(output, Compression::default());
+        let header_lines = self.header_lines()?;
+        let records = self.fetch(rid, begin, end).map(|x| x.map(|x| x.data));
+        for line in header_lines.into_iter().map(Ok).chain(records) {
+            let mut line = line?;
+            line.push(b'\n');
+            writer.write_all(&line)?;
+            builder.add_line(&line, writer.bgzf_pos())?;
+        }
+        // flush the last block, so that the final offset points to the end-of-file marker
+        writer.flush()?;
+        let index = builder.finish(writer.bgzf_pos());
+        writer.close()?;
+        Ok(index)
+    }
+
+    /// Write header lines and records in a region string, such as `chr17:42,990,000-42,990,600`, into a new BGZF file.
+    ///
+    /// The region is parsed in the same way as [`TabixFile::fetch_region`]. See [`TabixFile::extract`].
+    pub fn extract_region<W: Write>(
+        &mut self,
+        region: &str,
+        output: W,
+    ) -> Result<Tabix, BGZFError> {
+        let (rid, begin, end) = self.resolve_region(region)?;
+        self.extract(rid, begin, end, output)
+    }
+}
+
+/// Range of virtual offsets of a sequence, which is recorded in the pseudo-bin.
+fn sequence_range(sequence: &TabixSequence) -> Result<(u64, u64), BGZFError> {
+    sequence
+        .pseudo_bin()
+        .map(|x| (x.begin.into(), x.end.into()))
+        .ok_or(BGZFError::BadIndex("No pseudo-bin in the index"))
+}
+
+struct SubsetWriter<W: Write> {
+    output: W,
+    position: u64,
+    builder: TabixBuilder,
+    line: Vec<u8>,
+    compress: Compress,
+    compressed_buffer: Vec<u8>,
+    raw_buffer: Vec<u8>,
+    data_buffer: Vec<u8>,
+    decompress: Decompress,
+}
+
+impl<W: Write> SubsetWriter<W> {
+    /// Copy data in range [begin, end) of virtual offsets.
+    fn copy<R: Read + Seek>(
+        &mut self,
+        input: &mut R,
+        begin: u64,
+        end: u64,
+    ) -> Result<(), BGZFError> {
+        let mut block = begin >> 16;
+        while block < (end >> 16) || (block == (end >> 16) && end & 0xffff != 0) {
+            input.seek(SeekFrom::Start(block))?;
+            let header = crate::read::load_block(&mut *input, &mut self.compressed_buffer)?;
+            let block_size = u64::from(header.block_size()?);
+            self.data_buffer.clear();
+            crate::read::decompress_block(
+                &mut self.data_buffer,
+                &self.compressed_buffer,
+                &mut self.decompress,
+            )?;
+
+            let data_begin: usize = if block == begin >> 16 {
+                (begin & 0xffff).try_into().unwrap()
+            } else {
+                0
+            };
+            let data_end: usize = if block == end >> 16 {
+                (end & 0xffff).try_into().unwrap()
+            } else {
+                self.data_buffer.len()
+            };
+            if data_begin == 0 && data_end == self.data_buffer.len() {
+                input.seek(SeekFrom::Start(block))?;
+                self.raw_buffer.resize(block_size.try_into().unwrap(), 0);
+                input.read_exact(&mut self.raw_buffer)?;
+                self.output.write_all(&self.raw_buffer)?;
+                self.add_block(block_size, 0..data_end)?;
+            } else if data_begin < data_end {
+                self.raw_buffer.clear();
+                let block_size = crate::write::write_block(
+                    &mut self.raw_buffer,
+                    &self.data_buffer[data_begin..data_end],
+                    &mut self.compress,
+                )?;
+                self.output.write_all(&self.raw_buffer)?;
+                self.add_block(block_size.try_into().unwrap(), data_begin..data_end)?;
+            }
+
+            block += block_size;
+        }
+        Ok(())
+    }
+
+    /// Add lines in a written block into the index.
+    fn add_block(
+        &mut self,
+        block_size: u64,
+        range: std::ops::Range<usize>,
+    ) -> Result<(), BGZFError> {
+        let data = &self.data_buffer[range];
+        let mut line_start = 0;
+        for (i, _) in data.iter().enumerate().filter(|(_, x)| **x == b'\n') {
+            self.line.extend_from_slice(&data[line_start..(i + 1)]);
+            line_start = i + 1;
+            let end_offset = if line_start == data.len() {
+                VirtualOffset::new(self.position + block_size, 0)
+            } else {
+                VirtualOffset::new(self.position, line_start.try_into().unwrap())
+            };
+            self.builder.add_line(&self.line, end_offset)?;
+            self.line.clear();
+        }
+        self.line.extend_from_slice(&data[line_start..]);
+        self.position += block_size;
+        Ok(())
+    }
+
+    fn finish(mut self) -> Result<Tabix, BGZFError> {
+        if !self.line.is_empty() {
+            return Err(BGZFError::Other("The last line is not terminated"));
+        }
+        self.output.write_all(&crate::EOF_MARKER)?;
+        self.output.flush()?;
+        Ok(self.builder.finish(VirtualOffset::new(self.position, 0)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_subset_contigs() -> anyhow::Result<()> {
+        for (path, config, names) in [
+            (
+                "testfiles/generated.bed.gz",
+                TabixConfig::BED,
+                vec!["chr5", "chr2", "chr22"],
+            ),
+            (
+                "testfiles/common_all_20180418_half.vcf.gz",
+                TabixConfig::VCF,
+                vec!["22"],
+            ),
+        ] {
+            let index = Tabix::from_reader(File::open(format!("{}.tbi", path))?)?;
+            let mut output = Vec::new();
+            let subset_index = subset_contigs(File::open(path)?, &index, &names, &mut output)?;
+            assert!(output.ends_with(&crate::EOF_MARKER));
+
+            let expected: Vec<Vec<u8>> = crate::read::open(path)?
+                .split(b'\n')
+                .map(|x| x.unwrap())
+                .filter(|x| {
+                    config.is_meta(x)
+                        || names.contains(
+                            &std::str::from_utf8(config.parse_record(x).unwrap().0).unwrap(),
+                        )
+                })
+                .collect();
+            let actual: Vec<Vec<u8>> = crate::read::new_reader(&output[..])?
+                .split(b'\n')
+                .collect::<Result<_, _>>()?;
+            assert_eq!(actual.len(), expected.len());
+            assert!(actual == expected);
+
+            // the index is same as the index built from the output
+            let mut rebuilt = TabixBuilder::new(config);
+            let mut reader = BGZFReader::new(&output[..])?;
+            let mut line = Vec::new();
+            let mut end_offset = reader.bgzf_pos();
+            loop {
+                line.clear();
+                if BufRead::read_until(&mut reader, b'\n', &mut line)? == 0 {
+                    break;
+                }
+                reader.fill_buf()?;
+                end_offset = reader.bgzf_pos();
+                rebuilt.add_line(&line, end_offset)?;
+            }
+            assert_eq!(subset_index.diff(&rebuilt.finish(end_offset)), vec![]);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_subset() -> anyhow::Result<()> {
+        let index = Tabix::from_reader(File::open("testfiles/generated.bed.gz.tbi")?)?;
+        let subset = index.subset(&["chr5", "chr2", "chr5"])?;
+        assert_eq!(subset.sequences.len(), 2);
+        assert_eq!(subset.rid2name(0), Some(&b"chr2"[..]));
+        assert_eq!(subset.rid2name(1), Some(&b"chr5"[..]));
+        assert_eq!(subset.sequences[1], index.sequences[4]);
+        assert!(matches!(
+            index.subset(&["chr5", "chrUn"]),
+            Err(BGZFError::UnknownContig(_))
+        ));
+
+        subset.write(File::create("tmp/test-subset.bed.gz.tbi")?)?;
+        let loaded = Tabix::from_reader(File::open("tmp/test-subset.bed.gz.tbi")?)?;
+        assert_eq!(loaded, subset);
+
+        let mut file = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        let mut subset_file = TabixFile::new(File::open("testfiles/generated.bed.gz")?, loaded)?;
+        // records have reference sequence IDs of each index
+        assert_eq!(
+            subset_file
+                .fetch_region("chr5:1000000-2000000")?
+                .map(|x| x.map(|x| x.data))
+                .collect::<Result<Vec<_>, _>>()?,
+            file.fetch_region("chr5:1000000-2000000")?
+                .map(|x| x.map(|x| x.data))
+                .collect::<Result<Vec<_>, _>>()?
+        );
+        assert!(subset_file.fetch_region("chr1").is_err());
+        let names: Vec<u32> = subset_file
+            .records()
+            .map(|x| x.map(|x| x.rid))
+            .collect::<Result<_, _>>()?;
+        assert_eq!(names.first(), Some(&0));
+        assert_eq!(names.last(), Some(&1));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_extract_region() -> anyhow::Result<()> {
+        for (path, region, output_path) in [
+            (
+                "testfiles/generated.bed.gz",
+                "chr1:12,345,679-13,000,000",
+                "tmp/test-extract.bed.gz",
+            ),
+            (
+                "testfiles/common_all_20180418_half.vcf.gz",
+                "1:1000000-1200000",
+                "tmp/test-extract.vcf.gz",
+            ),
+        ] {
+            let mut file = TabixFile::from_path(path)?;
+            let index = file.extract_region(region, File::create(output_path)?)?;
+            index.write(File::create(format!("{}.tbi", output_path))?)?;
+
+            let output = std::fs::read(output_path)?;
+            assert!(crate::validate(&output[..])?.is_valid());
+            assert_eq!(
+                index.diff(&index_bgzf(&output[..], file.index().config())?),
+                vec![]
+            );
+
+            let mut extracted = TabixFile::from_path(output_path)?;
+            assert_eq!(extracted.header_lines()?, file.header_lines()?);
+            let expected = file.fetch_region(region)?.collect::<Result<Vec<_>, _>>()?;
+            let actual = extracted
+                .fetch_region(region)?
+                .collect::<Result<Vec<_>, _>>()?;
+            assert!(!expected.is_empty());
+            assert_eq!(actual, expected);
+            // no records outside of the region
+            assert_eq!(extracted.records().count(), expected.len());
+        }
+
+        Ok(())
+    }
+}
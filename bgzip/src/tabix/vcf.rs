@@ -0,0 +1,137 @@
+use super::*;
+use std::collections::BTreeMap;
+use std::io::Seek;
+
+/// A parsed VCF record
+#[derive(Debug, Clone, PartialEq)]
+pub struct VcfRecord {
+    pub chrom: String,
+    /// 1-based position
+    pub pos: u64,
+    /// IDs. Empty if the column is `.`.
+    pub id: Vec<String>,
+    pub reference: String,
+    /// Alternative alleles. Empty if the column is `.`.
+    pub alt: Vec<String>,
+    /// `None` if the column is `.`.
+    pub qual: Option<f64>,
+    /// Filters. Empty if the column is `.`.
+    pub filter: Vec<String>,
+    /// INFO fields. A value of flag field is `None`.
+    pub info: BTreeMap<String, Option<String>>,
+    /// FORMAT column and sample columns
+    pub samples: Vec<String>,
+}
+
+impl VcfRecord {
+    /// Parse a VCF data line. A new line character at the end is ignored.
+    pub fn from_line(line: &[u8]) -> Result<Self, BGZFError> {
+        let line = std::str::from_utf8(strip_line_end(line))?;
+        let mut columns = line.split('\t');
+        let mut column_number = 0;
+        let mut next_column = || {
+            column_number += 1;
+            columns.next().ok_or_else(|| parse_error(column_number))
+        };
+        let split_list = |value: &str, separator: char| -> Vec<String> {
+            if value == "." {
+                Vec::new()
+            } else {
+                value.split(separator).map(String::from).collect()
+            }
+        };
+
+        let chrom = next_column()?.to_string();
+        let pos = next_column()?.parse().map_err(|_| parse_error(2))?;
+        let id = split_list(next_column()?, ';');
+        let reference = next_column()?.to_string();
+        let alt = split_list(next_column()?, ',');
+        let qual = match next_column()? {
+            "." => None,
+            value => Some(value.parse().map_err(|_| parse_error(6))?),
+        };
+        let filter = split_list(next_column()?, ';');
+        let info = split_list(next_column()?, ';')
+            .into_iter()
+            .map(|x| match x.split_once('=') {
+                Some((key, value)) => (key.to_string(), Some(value.to_string())),
+                None => (x, None),
+            })
+            .collect();
+        let samples = columns.map(String::from).collect();
+
+        Ok(VcfRecord {
+            chrom,
+            pos,
+            id,
+            reference,
+            alt,
+            qual,
+            filter,
+            info,
+            samples,
+        })
+    }
+
+    /// Get a value of INFO field. A flag field returns `Some(None)`.
+    pub fn info(&self, key: &str) -> Option<Option<&str>> {
+        self.info.get(key).map(|x| x.as_deref())
+    }
+}
+
+impl<R: Read + Seek, I: RegionIndex> TabixFile<R, I> {
+    /// Fetch VCF records overlapping with region [begin, end) (zero-based) of reference sequence `rid`.
+    pub fn fetch_vcf(
+        &mut self,
+        rid: u32,
+        begin: u64,
+        end: u64,
+    ) -> impl Iterator<Item = Result<VcfRecord, BGZFError>> + '_ {
+        self.fetch(rid, begin, end)
+            .map(|x| x.and_then(|x| VcfRecord::from_line(&x.data)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_vcf_record() -> anyhow::Result<()> {
+        let record = VcfRecord::from_line(
+            b"1\t919598\trs13303057\tA\tC,T\t50.5\tPASS\tRS=13303057;SLO;CAF=0.5054,0.4946,.\tGT\t0|1\r\n",
+        )?;
+        assert_eq!(record.chrom, "1");
+        assert_eq!(record.pos, 919598);
+        assert_eq!(record.id, ["rs13303057"]);
+        assert_eq!(record.reference, "A");
+        assert_eq!(record.alt, ["C", "T"]);
+        assert_eq!(record.qual, Some(50.5));
+        assert_eq!(record.filter, ["PASS"]);
+        assert_eq!(record.info("CAF"), Some(Some("0.5054,0.4946,.")));
+        assert_eq!(record.info("SLO"), Some(None));
+        assert_eq!(record.info("G5"), None);
+        assert_eq!(record.samples, ["GT", "0|1"]);
+        assert!(VcfRecord::from_line(b"1\t919598\trs13303057").is_err());
+
+        let mut file = TabixFile::from_path("testfiles/common_all_20180418_half.vcf.gz")?;
+        let rid = file.index().name2rid(b"1").unwrap();
+        let entries = file
+            .fetch(rid, 1_000_000, 1_100_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        let records = file
+            .fetch_vcf(rid, 1_000_000, 1_100_000)
+            .collect::<Result<Vec<_>, _>>()?;
+        assert!(!records.is_empty());
+        assert_eq!(records.len(), entries.len());
+        for (record, entry) in records.iter().zip(entries.iter()) {
+            assert_eq!(record.chrom, "1");
+            assert_eq!(record.pos, entry.begin + 1);
+            assert_eq!(record.qual, None);
+            assert!(record.id[0].starts_with("rs"));
+            assert!(record.info("RS").is_some());
+        }
+
+        Ok(())
+    }
+}
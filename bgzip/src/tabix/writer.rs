@@ -0,0 +1,153 @@
+use super::*;
+use std::path::{Path, PathBuf};
+
+/// BGZF writer which builds tabix index while writing, as a one-pass replacement of `bgzip` and `tabix`.
+///
+/// Records must be written in sorted order. Records of a sequence must be continuous.
+/// Use [`IndexedBGZFWriter::close`] to write end-of-file marker and get the index.
+pub struct IndexedBGZFWriter<W: Write> {
+    writer: BGZFWriter<W>,
+    builder: TabixBuilder,
+    index_path: Option<PathBuf>,
+    line_number: u64,
+}
+
+impl IndexedBGZFWriter<std::fs::File> {
+    /// Create a BGZF file at `path`. Tabix index is written into `path` + `.tbi` when the writer is closed.
+    pub fn create<P: AsRef<Path>>(
+        path: P,
+        level: Compression,
+        config: TabixConfig,
+    ) -> Result<Self, BGZFError> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".tbi");
+        let mut writer = IndexedBGZFWriter::new(std::fs::File::create(path)?, level, config);
+        writer.index_path = Some(index_path.into());
+        Ok(writer)
+    }
+}
+
+impl<W: Write> IndexedBGZFWriter<W> {
+    /// Create new [`IndexedBGZFWriter`] with column configuration of the index.
+    pub fn new(writer: W, level: Compression, config: TabixConfig) -> Self {
+        IndexedBGZFWriter {
+            writer: BGZFWriter::new(writer, level),
+            builder: TabixBuilder::new(config),
+            index_path: None,
+            line_number: 0,
+        }
+    }
+
+    /// Write a header line, which is not indexed. New line character is appended.
+    ///
+    /// Header lines must be written before records.
+    pub fn write_header(&mut self, line: &[u8]) -> Result<(), BGZFError> {
+        self.line_number += 1;
+        self.writer.write_line(line)?;
+        self.builder.skip_to(self.writer.virtual_offset());
+        Ok(())
+    }
+
+    /// Write a record line of sequence `name` with zero-based, half-open region. New line character is appended.
+    ///
+    /// Returns BGZF virtual file offset where the record starts.
+    pub fn write_record(
+        &mut self,
+        name: &[u8],
+        begin: u64,
+        end: u64,
+        line: &[u8],
+    ) -> Result<VirtualOffset, BGZFError> {
+        self.line_number += 1;
+        let offset = self.writer.write_line(line)?;
+        self.builder
+            .push(name, begin, end, self.writer.virtual_offset())
+            .map_err(|e| e.with_line(self.line_number))?;
+        Ok(offset)
+    }
+
+    /// Write a line, which is parsed with the column configuration. Meta lines are written as header lines.
+    pub fn write_line(&mut self, line: &[u8]) -> Result<(), BGZFError> {
+        self.line_number += 1;
+        self.writer.write_line(line)?;
+        self.builder
+            .add_line(line, self.writer.virtual_offset())
+            .map_err(|e| e.with_line(self.line_number))
+    }
+
+    /// Write end-of-file marker and return the built index.
+    ///
+    /// The index is also written into `path` + `.tbi` if this writer is created with [`IndexedBGZFWriter::create`].
+    pub fn close(mut self) -> Result<Tabix, BGZFError> {
+        // flush the last block, so that the final offset points to the end-of-file marker
+        self.writer.flush_block()?;
+        let index = self.builder.finish(self.writer.virtual_offset());
+        self.writer.close()?;
+        if let Some(index_path) = self.index_path.as_ref() {
+            index.write(std::io::BufWriter::new(std::fs::File::create(index_path)?))?;
+        }
+        Ok(index)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::BufRead;
+
+    #[test]
+    fn test_indexed_writer() -> anyhow::Result<()> {
+        let path = "tmp/test-indexed-writer.bed.gz";
+        let mut writer = IndexedBGZFWriter::create(path, Compression::default(), TabixConfig::BED)?;
+        writer.write_header(b"#chrom\tbegin\tend")?;
+        let mut offsets = Vec::new();
+        for (i, line) in crate::open("testfiles/generated.bed.gz")?
+            .split(b'\n')
+            .enumerate()
+        {
+            let line = strip_line_end(&line?).to_vec();
+            if i % 2 == 0 {
+                writer.write_line(&line)?;
+            } else {
+                let (name, begin, end) = TabixConfig::BED.parse_record(&line)?;
+                let offset = writer.write_record(name, begin, end, &line)?;
+                offsets.push((offset, line.clone()));
+            }
+        }
+        let index = writer.close()?;
+
+        // same as the index built from the written file
+        let rebuilt = index_bgzf(std::fs::File::open(path)?, TabixConfig::BED)?;
+        assert_eq!(index.diff(&rebuilt), vec![]);
+        let saved = Tabix::from_reader(std::fs::File::open(format!("{}.tbi", path))?)?;
+        assert_eq!(index.diff(&saved), vec![]);
+
+        let mut file = TabixFile::from_path(path)?;
+        let mut expected = TabixFile::from_path("testfiles/generated.bed.gz")?;
+        assert_eq!(file.header_lines()?, vec![b"#chrom\tbegin\tend".to_vec()]);
+        assert_eq!(
+            file.fetch_region("chr5:1000000-2000000")?
+                .collect::<Result<Vec<_>, _>>()?,
+            expected
+                .fetch_region("chr5:1000000-2000000")?
+                .collect::<Result<Vec<_>, _>>()?
+        );
+
+        let mut reader = BGZFReader::new(std::fs::File::open(path)?)?;
+        for (offset, line) in offsets.iter().step_by(1000) {
+            reader.bgzf_seek(*offset)?;
+            let mut read_line = Vec::new();
+            BufRead::read_until(&mut reader, b'\n', &mut read_line)?;
+            assert_eq!(read_line, [&line[..], b"\n"].concat());
+        }
+
+        // unsorted records
+        let mut writer = IndexedBGZFWriter::new(Vec::new(), Compression::fast(), TabixConfig::BED);
+        writer.write_record(b"chr1", 100, 200, b"chr1\t100\t200")?;
+        assert!(writer
+            .write_record(b"chr1", 50, 200, b"chr1\t50\t200")
+            .is_err());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,149 @@
+use crate::deflate::Decompress;
+use crate::read::{decompress_block, load_block};
+use crate::BGZFError;
+use std::io::{self, Read};
+
+/// Result of [`validate`].
+#[derive(Debug)]
+pub struct ValidationReport {
+    /// Number of valid blocks, including end-of-file marker
+    pub number_of_blocks: u64,
+    /// Total size of valid blocks
+    pub compressed_size: u64,
+    /// Total size of decompressed data of valid blocks
+    pub uncompressed_size: u64,
+    /// `true` if the last valid block is end-of-file marker
+    pub has_eof_marker: bool,
+    /// File offset of the first invalid block
+    pub error_offset: Option<u64>,
+    /// The first error. Validation stops at the first error.
+    pub error: Option<BGZFError>,
+}
+
+impl ValidationReport {
+    /// `true` if all blocks are valid and the file ends with end-of-file marker.
+    pub fn is_valid(&self) -> bool {
+        self.error.is_none() && self.has_eof_marker
+    }
+}
+
+/// Check all blocks of BGZF data like `bgzip -t`.
+///
+/// Headers, CRC32 and sizes of decompressed data of all blocks, and end-of-file marker are checked.
+/// Errors in the data are recorded in the report. I/O errors except unexpected end of file are returned as [`Err`].
+pub fn validate<R: Read>(reader: R) -> Result<ValidationReport, BGZFError> {
+    let mut reader = CountingReader {
+        reader,
+        position: 0,
+    };
+    let mut report = ValidationReport {
+        number_of_blocks: 0,
+        compressed_size: 0,
+        uncompressed_size: 0,
+        has_eof_marker: false,
+        error_offset: None,
+        error: None,
+    };
+    let mut decompress = Decompress::new();
+    let mut compressed_buffer = Vec::new();
+    let mut buffer = Vec::new();
+
+    loop {
+        let block = reader.position;
+        let result = load_block(&mut reader, &mut compressed_buffer).and_then(|_| {
+            buffer.clear();
+            decompress_block(&mut buffer, &compressed_buffer, &mut decompress)
+        });
+        match result {
+            Ok(()) => {
+                report.number_of_blocks += 1;
+                report.compressed_size = reader.position;
+                report.uncompressed_size += u64::try_from(buffer.len()).unwrap();
+                report.has_eof_marker = buffer.is_empty();
+            }
+            Err(BGZFError::IoError(e))
+                if e.kind() == io::ErrorKind::UnexpectedEof && reader.position == block =>
+            {
+                break;
+            }
+            Err(BGZFError::IoError(e)) if e.kind() != io::ErrorKind::UnexpectedEof => {
+                return Err(e.into())
+            }
+            Err(e) => {
+                debug!("invalid block at {}: {}", block, e);
+                report.error_offset = Some(block);
+                report.error = Some(e.with_block_offset(block));
+                break;
+            }
+        }
+    }
+
+    Ok(report)
+}
+
+/// A reader which counts consumed bytes
+struct CountingReader<R: Read> {
+    reader: R,
+    position: u64,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read_bytes = self.reader.read(buf)?;
+        self.position += u64::try_from(read_bytes).unwrap();
+        Ok(read_bytes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::fs::File;
+
+    #[test]
+    fn test_validate() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        File::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+        let mut expected = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut expected)?;
+
+        let report = validate(&data[..])?;
+        assert!(report.is_valid(), "{:?}", report);
+        assert_eq!(report.compressed_size, u64::try_from(data.len())?);
+        assert_eq!(report.uncompressed_size, u64::try_from(expected.len())?);
+        assert!(report.number_of_blocks > 1);
+
+        // without end-of-file marker
+        let report = validate(&data[..(data.len() - crate::EOF_MARKER.len())])?;
+        assert!(report.error.is_none());
+        assert!(!report.has_eof_marker);
+        assert!(!report.is_valid());
+
+        // truncated in the middle of a block
+        let report = validate(&data[..(data.len() - 100)])?;
+        assert!(matches!(report.error, Some(BGZFError::TruncatedBlock)));
+        assert!(report.error_offset.unwrap() < u64::try_from(data.len())? - 100);
+
+        // broken CRC32 of the second block
+        let second_block = usize::from(u16::from_le_bytes([data[16], data[17]])) + 1;
+        let third_block = second_block
+            + usize::from(u16::from_le_bytes([
+                data[second_block + 16],
+                data[second_block + 17],
+            ]))
+            + 1;
+        data[third_block - 8] ^= 0xff;
+        let report = validate(&data[..])?;
+        assert_eq!(report.number_of_blocks, 1);
+        assert_eq!(report.error_offset, Some(u64::try_from(second_block)?));
+        assert!(matches!(
+            report.error,
+            Some(BGZFError::CorruptedBlock {
+                offset: Some(_),
+                ..
+            })
+        ));
+
+        Ok(())
+    }
+}
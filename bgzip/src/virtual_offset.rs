@@ -0,0 +1,125 @@
+use crate::BGZFError;
+use std::fmt;
+use std::str::FromStr;
+
+/// BGZF virtual file offset described in [BGZF format](https://samtools.github.io/hts-specs/SAMv1.pdf).
+///
+/// Upper 48 bits are the file offset of a BGZF block, and lower 16 bits are the offset in
+/// the uncompressed data of the block. Order of virtual offsets is the same as order of positions in the file.
+///
+/// This type is displayed as `<compressed offset>:<uncompressed offset>`.
+/// [`FromStr`] accepts the same format and a raw integer value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct VirtualOffset(u64);
+
+impl VirtualOffset {
+    /// Create new virtual offset from the file offset of a block and the offset in the block.
+    ///
+    /// `compressed_offset` must be smaller than 2^48.
+    pub const fn new(compressed_offset: u64, uncompressed_offset: u16) -> Self {
+        debug_assert!(compressed_offset < 1 << 48);
+        VirtualOffset(compressed_offset << 16 | uncompressed_offset as u64)
+    }
+
+    /// File offset of the BGZF block
+    pub const fn compressed_offset(self) -> u64 {
+        self.0 >> 16
+    }
+
+    /// Offset in the uncompressed data of the block
+    pub const fn uncompressed_offset(self) -> u16 {
+        (self.0 & 0xffff) as u16
+    }
+}
+
+impl From<u64> for VirtualOffset {
+    fn from(value: u64) -> Self {
+        VirtualOffset(value)
+    }
+}
+
+impl From<VirtualOffset> for u64 {
+    fn from(value: VirtualOffset) -> Self {
+        value.0
+    }
+}
+
+impl fmt::Display for VirtualOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}",
+            self.compressed_offset(),
+            self.uncompressed_offset()
+        )
+    }
+}
+
+impl fmt::LowerHex for VirtualOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::LowerHex::fmt(&self.0, f)
+    }
+}
+
+impl FromStr for VirtualOffset {
+    type Err = BGZFError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.split_once(':') {
+            Some((compressed, uncompressed)) => {
+                let compressed: u64 = compressed.parse().map_err(|_| BGZFError::ParseError {
+                    line: None,
+                    column: 1,
+                })?;
+                let uncompressed: u16 =
+                    uncompressed.parse().map_err(|_| BGZFError::ParseError {
+                        line: None,
+                        column: 2,
+                    })?;
+                if compressed >= 1 << 48 {
+                    return Err(BGZFError::Other("Too large compressed offset"));
+                }
+                Ok(VirtualOffset::new(compressed, uncompressed))
+            }
+            None => s
+                .parse::<u64>()
+                .map(VirtualOffset)
+                .map_err(|_| BGZFError::ParseError {
+                    line: None,
+                    column: 1,
+                }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_virtual_offset() -> anyhow::Result<()> {
+        let offset = VirtualOffset::new(64250, 1234);
+        assert_eq!(offset.compressed_offset(), 64250);
+        assert_eq!(offset.uncompressed_offset(), 1234);
+        assert_eq!(u64::from(offset), 64250 << 16 | 1234);
+        assert_eq!(VirtualOffset::from(64250 << 16 | 1234), offset);
+        assert!(VirtualOffset::new(64250, 1235) > offset);
+        assert!(VirtualOffset::new(64251, 0) > offset);
+
+        assert_eq!(offset.to_string(), "64250:1234");
+        assert_eq!("64250:1234".parse::<VirtualOffset>()?, offset);
+        assert_eq!(
+            (64250u64 << 16 | 1234)
+                .to_string()
+                .parse::<VirtualOffset>()?,
+            offset
+        );
+        assert!("64250:65536".parse::<VirtualOffset>().is_err());
+        assert!("x:1".parse::<VirtualOffset>().is_err());
+        assert!(format!("{}:0", 1u64 << 48)
+            .parse::<VirtualOffset>()
+            .is_err());
+
+        Ok(())
+    }
+}
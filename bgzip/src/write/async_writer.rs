@@ -0,0 +1,208 @@
+use super::{write_block, DEFAULT_COMPRESS_UNIT_SIZE, EXTRA_COMPRESS_BUFFER_SIZE};
+use crate::deflate::{Compress, Compression};
+use crate::BGZFError;
+use std::future::Future;
+use std::io;
+use std::pin::Pin;
+use std::task::{ready, Context, Poll};
+use tokio::io::AsyncWrite;
+use tokio::task::JoinHandle;
+
+type CompressResult = (Compress, Vec<u8>, Vec<u8>, io::Result<usize>);
+
+/// An asynchronous BGZF writer for [tokio](https://tokio.rs).
+///
+/// Written data is buffered until a block is filled, and the block is compressed with
+/// [`tokio::task::spawn_blocking`] while the caller prepares next block.
+/// Unlike [`crate::write::BGZFWriter`], end-of-file marker is not written on drop.
+/// Please call [`tokio::io::AsyncWriteExt::shutdown`] to finish BGZF.
+/// `tokio` flag is required to use this struct.
+pub struct BGZFAsyncWriter<W: AsyncWrite + Unpin> {
+    writer: W,
+    original_data: Vec<u8>,
+    spare_data: Vec<u8>,
+    compressed_buffer: Vec<u8>,
+    written_compressed_bytes: usize,
+    compress: Option<Compress>,
+    compress_unit_size: usize,
+    pending: Option<JoinHandle<CompressResult>>,
+    closed: bool,
+    current_uncompressed_pos: u64,
+}
+
+impl<W: AsyncWrite + Unpin> BGZFAsyncWriter<W> {
+    /// Create new asynchronous BGZF writer from [`tokio::io::AsyncWrite`]
+    pub fn new(writer: W, level: Compression) -> Self {
+        Self::with_compress_unit_size(writer, level, DEFAULT_COMPRESS_UNIT_SIZE)
+            .expect("Unreachable (BGZFAsyncWriter)")
+    }
+
+    /// Create new asynchronous BGZF writer with compress unit size.
+    ///
+    /// Default value of compress unit size is 65280.
+    pub fn with_compress_unit_size(
+        writer: W,
+        level: Compression,
+        compress_unit_size: usize,
+    ) -> Result<Self, BGZFError> {
+        if compress_unit_size >= crate::write::MAXIMUM_COMPRESS_UNIT_SIZE {
+            return Err(BGZFError::TooLargeCompressUnit);
+        }
+
+        Ok(BGZFAsyncWriter {
+            writer,
+            original_data: Vec::with_capacity(compress_unit_size),
+            spare_data: Vec::with_capacity(compress_unit_size),
+            compressed_buffer: Vec::with_capacity(compress_unit_size + EXTRA_COMPRESS_BUFFER_SIZE),
+            written_compressed_bytes: 0,
+            compress: Some(Compress::new(level)),
+            compress_unit_size,
+            pending: None,
+            closed: false,
+            current_uncompressed_pos: 0,
+        })
+    }
+
+    /// Current write position.
+    pub fn pos(&self) -> u64 {
+        self.current_uncompressed_pos + u64::try_from(self.original_data.len()).unwrap()
+    }
+
+    /// Unwraps this writer, returning the underlying writer.
+    ///
+    /// Buffered data which are not compressed yet will be lost.
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    /// Start compression of buffered data in a blocking thread.
+    fn start_compression(&mut self) {
+        let original_data = std::mem::replace(
+            &mut self.original_data,
+            std::mem::take(&mut self.spare_data),
+        );
+        let mut compressed_buffer = std::mem::take(&mut self.compressed_buffer);
+        let mut compress = self.compress.take().expect("Unreachable (BGZFAsyncWriter)");
+        self.current_uncompressed_pos += u64::try_from(original_data.len()).unwrap();
+
+        self.pending = Some(tokio::task::spawn_blocking(move || {
+            compressed_buffer.clear();
+            let result = write_block(&mut compressed_buffer, &original_data, &mut compress)
+                .map_err(io::Error::other);
+            (compress, original_data, compressed_buffer, result)
+        }));
+    }
+
+    /// Wait for pending compression and write compressed data to the underlying writer.
+    fn poll_write_compressed(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        if let Some(pending) = self.pending.as_mut() {
+            let (compress, mut original_data, compressed_buffer, result) =
+                ready!(Pin::new(pending).poll(cx)).map_err(io::Error::other)?;
+            self.pending = None;
+            original_data.clear();
+            self.spare_data = original_data;
+            self.compress = Some(compress);
+            self.compressed_buffer = compressed_buffer;
+            self.written_compressed_bytes = 0;
+            result?;
+        }
+
+        while self.written_compressed_bytes < self.compressed_buffer.len() {
+            let written_bytes = ready!(Pin::new(&mut self.writer)
+                .poll_write(cx, &self.compressed_buffer[self.written_compressed_bytes..]))?;
+            if written_bytes == 0 {
+                return Poll::Ready(Err(io::ErrorKind::WriteZero.into()));
+            }
+            self.written_compressed_bytes += written_bytes;
+        }
+        Poll::Ready(Ok(()))
+    }
+
+    /// Compress and write all buffered data.
+    fn poll_write_all_blocks(&mut self, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        loop {
+            ready!(self.poll_write_compressed(cx))?;
+            if self.original_data.is_empty() {
+                return Poll::Ready(Ok(()));
+            }
+            self.start_compression();
+        }
+    }
+}
+
+impl<W: AsyncWrite + Unpin> AsyncWrite for BGZFAsyncWriter<W> {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        let this = self.get_mut();
+        if this.original_data.len() >= this.compress_unit_size {
+            ready!(this.poll_write_compressed(cx))?;
+            this.start_compression();
+        }
+
+        let to_write_bytes = buf
+            .len()
+            .min(this.compress_unit_size - this.original_data.len());
+        this.original_data.extend_from_slice(&buf[..to_write_bytes]);
+        Poll::Ready(Ok(to_write_bytes))
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_all_blocks(cx))?;
+        Pin::new(&mut this.writer).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        let this = self.get_mut();
+        ready!(this.poll_write_all_blocks(cx))?;
+        if !this.closed {
+            this.compressed_buffer.clear();
+            this.compressed_buffer.extend_from_slice(&crate::EOF_MARKER);
+            this.written_compressed_bytes = 0;
+            this.closed = true;
+            ready!(this.poll_write_compressed(cx))?;
+        }
+        Pin::new(&mut this.writer).poll_shutdown(cx)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::io::Read;
+    use tokio::io::AsyncWriteExt;
+
+    #[tokio::test]
+    async fn test_async_writer() -> anyhow::Result<()> {
+        let mut expected = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut expected)?;
+
+        let mut writer = BGZFAsyncWriter::new(Vec::new(), Compression::default());
+        for chunk in expected.chunks(1000) {
+            writer.write_all(chunk).await?;
+        }
+        assert_eq!(writer.pos(), u64::try_from(expected.len())?);
+        writer.shutdown().await?;
+        let compressed = writer.into_inner();
+        assert!(compressed.ends_with(&crate::EOF_MARKER));
+
+        let mut data = Vec::new();
+        crate::BGZFReader::new(&compressed[..])?.read_to_end(&mut data)?;
+        assert!(data == expected);
+
+        let mut writer =
+            BGZFAsyncWriter::with_compress_unit_size(Vec::new(), Compression::fast(), 100)?;
+        writer.write_all(b"1234").await?;
+        writer.flush().await?;
+        writer.write_all(b"5678").await?;
+        writer.shutdown().await?;
+        let mut data = Vec::new();
+        crate::BGZFReader::new(&writer.into_inner()[..])?.read_to_end(&mut data)?;
+        assert_eq!(data, b"12345678");
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+use super::*;
+use crate::EOF_MARKER;
+use std::io::{BufReader, Read};
+
+const COPY_BUFFER_SIZE: usize = 64 * 1024;
+
+/// Concatenate BGZF data into one BGZF file without recompression, like `samtools cat`.
+///
+/// Compressed blocks are copied verbatim. End-of-file markers at the end of inputs are removed,
+/// and one end-of-file marker is written at the end. [`BGZFError::NotBGZF`] is returned if an input
+/// does not start with a BGZF block. Returns the size of the written file.
+pub fn concatenate<R: Read, W: Write, I: IntoIterator<Item = R>>(
+    inputs: I,
+    mut writer: W,
+) -> Result<u64, BGZFError> {
+    let mut size = 0;
+    for one in inputs {
+        let mut reader = BufReader::new(one);
+        if !crate::read::is_bgzf(&mut reader)? {
+            return Err(BGZFError::NotBGZF);
+        }
+        size += copy_without_eof_marker(reader, &mut writer)?;
+    }
+    writer.write_all(&EOF_MARKER)?;
+    writer.flush()?;
+    Ok(size + u64::try_from(EOF_MARKER.len()).unwrap())
+}
+
+/// Concatenate BGZF files at `paths` into a new file at `output`. See [`concatenate`].
+pub fn concatenate_files<P: AsRef<Path>, Q: AsRef<Path>, I: IntoIterator<Item = P>>(
+    paths: I,
+    output: Q,
+) -> Result<u64, BGZFError> {
+    let inputs = paths
+        .into_iter()
+        .map(std::fs::File::open)
+        .collect::<io::Result<Vec<_>>>()?;
+    concatenate(inputs, io::BufWriter::new(std::fs::File::create(output)?))
+}
+
+/// Copy all data from `reader` to `writer` except a trailing end-of-file marker.
+/// Returns the number of written bytes.
+pub(crate) fn copy_without_eof_marker<R: Read, W: Write>(
+    mut reader: R,
+    writer: &mut W,
+) -> io::Result<u64> {
+    let mut buffer = vec![0u8; COPY_BUFFER_SIZE + EOF_MARKER.len()];
+    let mut filled = 0;
+    let mut written = 0;
+    loop {
+        match reader.read(&mut buffer[filled..]) {
+            Ok(0) => break,
+            Ok(n) => filled += n,
+            Err(e) if e.kind() == io::ErrorKind::Interrupted => continue,
+            Err(e) => return Err(e),
+        }
+        // keep the last bytes which may be an end-of-file marker
+        if filled > EOF_MARKER.len() {
+            let flush_size = filled - EOF_MARKER.len();
+            writer.write_all(&buffer[..flush_size])?;
+            written += u64::try_from(flush_size).unwrap();
+            buffer.copy_within(flush_size..filled, 0);
+            filled = EOF_MARKER.len();
+        }
+    }
+    if buffer[..filled] != EOF_MARKER {
+        writer.write_all(&buffer[..filled])?;
+        written += u64::try_from(filled).unwrap();
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_concatenate() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+
+        let mut paths = Vec::new();
+        for (i, chunk) in data.chunks(data.len() / 3 + 1).enumerate() {
+            let path = format!("tmp/test-cat-{}.bed.gz", i);
+            let mut writer = BGZFWriter::new(std::fs::File::create(&path)?, Compression::default());
+            writer.write_all(chunk)?;
+            writer.close()?;
+            paths.push(path);
+        }
+        // without end-of-file marker
+        let compressed = std::fs::read(&paths[1])?;
+        std::fs::write(
+            &paths[1],
+            &compressed[..(compressed.len() - EOF_MARKER.len())],
+        )?;
+
+        let size = concatenate_files(&paths, "tmp/test-cat.bed.gz")?;
+        let concatenated = std::fs::read("tmp/test-cat.bed.gz")?;
+        assert_eq!(size, u64::try_from(concatenated.len())?);
+        assert!(crate::validate(&concatenated[..])?.is_valid());
+        // only one end-of-file marker at the end
+        assert_eq!(
+            concatenated
+                .windows(EOF_MARKER.len())
+                .filter(|x| *x == EOF_MARKER)
+                .count(),
+            1
+        );
+        let mut actual = Vec::new();
+        crate::open("tmp/test-cat.bed.gz")?.read_to_end(&mut actual)?;
+        assert!(actual == data);
+
+        assert!(matches!(
+            concatenate([&b"plain text"[..]], Vec::new()),
+            Err(BGZFError::NotBGZF)
+        ));
+
+        Ok(())
+    }
+}
@@ -1,35 +1,43 @@
 //! BGZF writer
 
+#[cfg(feature = "tokio")]
+mod async_writer;
+mod cat;
+mod split;
 #[cfg(feature = "rayon")]
 mod thread;
 
+#[cfg(feature = "tokio")]
+pub use async_writer::BGZFAsyncWriter;
+pub use cat::{concatenate, concatenate_files};
+pub use split::{concatenate_parts, BGZFSplitWriter, SplitCatalog, SplitPart};
 #[cfg(feature = "rayon")]
 pub use thread::BGZFMultiThreadWriter;
 
 use crate::header::BGZFHeader;
 use crate::index::{BGZFIndex, BGZFIndexEntry};
-use crate::{deflate::*, BGZFError};
+use crate::{deflate::*, BGZFError, VirtualOffset};
 use std::convert::TryInto;
 use std::io::{self, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 enum AdaptiveWriter<W: Write> {
     Plain(io::BufWriter<W>),
-    BGZF(BGZFWriter<W>),
+    Bgzf(BGZFWriter<W>),
 }
 
 impl<W: Write> Write for AdaptiveWriter<W> {
     fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
         match self {
             AdaptiveWriter::Plain(x) => x.write(buf),
-            AdaptiveWriter::BGZF(x) => x.write(buf),
+            AdaptiveWriter::Bgzf(x) => x.write(buf),
         }
     }
 
     fn flush(&mut self) -> io::Result<()> {
         match self {
             AdaptiveWriter::Plain(x) => x.flush(),
-            AdaptiveWriter::BGZF(x) => x.flush(),
+            AdaptiveWriter::Bgzf(x) => x.flush(),
         }
     }
 }
@@ -44,7 +52,7 @@ pub fn create<P: AsRef<Path>>(path: P) -> io::Result<impl Write> {
         .map(|s| s == "gz")
         .unwrap_or(false)
     {
-        Ok(AdaptiveWriter::BGZF(BGZFWriter::new(
+        Ok(AdaptiveWriter::Bgzf(BGZFWriter::new(
             std::fs::File::create(path)?,
             Compression::default(),
         )))
@@ -66,6 +74,7 @@ pub struct BGZFWriter<W: io::Write> {
     current_compressed_pos: u64,
     current_uncompressed_pos: u64,
     bgzf_index: Option<BGZFIndex>,
+    index_path: Option<PathBuf>,
 }
 
 /// Default BGZF compress unit size
@@ -76,6 +85,22 @@ pub const MAXIMUM_COMPRESS_UNIT_SIZE: usize = 64 * 1024;
 
 pub(crate) const EXTRA_COMPRESS_BUFFER_SIZE: usize = 200;
 
+impl BGZFWriter<std::fs::File> {
+    /// Create BGZF file at `path`. Existing file is truncated.
+    pub fn create<P: AsRef<Path>>(path: P, level: Compression) -> io::Result<Self> {
+        Ok(BGZFWriter::new(std::fs::File::create(path)?, level))
+    }
+
+    /// Create BGZF file at `path`, and write GZI index into `path` + `.gzi` when the writer is closed.
+    ///
+    /// The output can be opened with [`crate::read::IndexedBGZFReader::from_path`] at once.
+    pub fn create_with_index<P: AsRef<Path>>(path: P, level: Compression) -> io::Result<Self> {
+        let mut index_path = path.as_ref().as_os_str().to_os_string();
+        index_path.push(".gzi");
+        Ok(BGZFWriter::create(path, level)?.with_index_path(index_path))
+    }
+}
+
 impl<W: io::Write> BGZFWriter<W> {
     /// Create new BGZF writer from [`std::io::Write`]
     pub fn new(writer: W, level: Compression) -> Self {
@@ -110,14 +135,41 @@ impl<W: io::Write> BGZFWriter<W> {
             } else {
                 None
             },
+            index_path: None,
         })
     }
 
+    /// Write GZI index into `path` when [`BGZFWriter::close`] is called.
+    ///
+    /// Recording of the index is enabled if it is disabled. This must be called before writing data.
+    pub fn with_index_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        if self.bgzf_index.is_none() {
+            self.bgzf_index = Some(BGZFIndex::new());
+        }
+        self.index_path = Some(path.as_ref().to_path_buf());
+        self
+    }
+
     /// Get BGZF virtual file offset. This position is not equal to real file offset,
     /// but equal to virtual file offset described in [BGZF format](https://samtools.github.io/hts-specs/SAMv1.pdf).
-    /// Please read "4.1.1 Random access" to learn more.       
-    pub fn bgzf_pos(&self) -> u64 {
-        self.current_compressed_pos << 16 | (self.original_data.len() & 0xffff) as u64
+    /// Please read "4.1.1 Random access" to learn more.
+    ///
+    /// This is same as [`BGZFWriter::virtual_offset`].
+    pub fn bgzf_pos(&self) -> VirtualOffset {
+        self.virtual_offset()
+    }
+
+    /// BGZF virtual file offset where the next written byte will land.
+    ///
+    /// [`VirtualOffset::compressed_offset`] is the offset of the current block in the compressed file, which is written
+    /// when the block is full or flushed, and [`VirtualOffset::uncompressed_offset`] is the offset in uncompressed data of the block.
+    /// The offset can be recorded in an index while writing, and passed to [`crate::BGZFReader::bgzf_seek`].
+    pub fn virtual_offset(&self) -> VirtualOffset {
+        // compress unit size is smaller than 64KiB, so the offset in a block fits into 16 bits
+        VirtualOffset::new(
+            self.current_compressed_pos,
+            self.original_data.len().try_into().unwrap(),
+        )
     }
 
     /// Current write position.
@@ -125,6 +177,33 @@ impl<W: io::Write> BGZFWriter<W> {
         self.current_uncompressed_pos + TryInto::<u64>::try_into(self.original_data.len()).unwrap()
     }
 
+    /// Write `record` and return BGZF virtual file offset where the record starts.
+    ///
+    /// The offset can be recorded in an index, e.g. with [`crate::index::BGZFIndex`] or [`crate::tabix::TabixBuilder`].
+    pub fn write_record(&mut self, record: &[u8]) -> io::Result<VirtualOffset> {
+        let offset = self.virtual_offset();
+        self.write_all(record)?;
+        Ok(offset)
+    }
+
+    /// Write `line` followed by a new line character and return BGZF virtual file offset where the line starts.
+    pub fn write_line(&mut self, line: &[u8]) -> io::Result<VirtualOffset> {
+        let offset = self.write_record(line)?;
+        self.write_all(b"\n")?;
+        Ok(offset)
+    }
+
+    /// Close the current block even if it is not full. Nothing is written if no data is buffered.
+    ///
+    /// Data written after this call starts at the beginning of a new block, so record boundaries can be
+    /// aligned to block boundaries. Unlike [`Write::flush`], the underlying writer is not flushed.
+    pub fn flush_block(&mut self) -> io::Result<()> {
+        if !self.original_data.is_empty() {
+            self.write_block()?;
+        }
+        Ok(())
+    }
+
     fn write_block(&mut self) -> io::Result<()> {
         self.compressed_buffer.clear();
         write_block(
@@ -132,7 +211,7 @@ impl<W: io::Write> BGZFWriter<W> {
             &self.original_data,
             &mut self.compress,
         )
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .map_err(std::io::Error::other)?;
         self.writer.write_all(&self.compressed_buffer)?;
 
         self.current_uncompressed_pos +=
@@ -146,6 +225,7 @@ impl<W: io::Write> BGZFWriter<W> {
                 uncompressed_offset: self.current_uncompressed_pos,
             });
         }
+        self.original_data.clear();
 
         Ok(())
     }
@@ -153,6 +233,7 @@ impl<W: io::Write> BGZFWriter<W> {
     /// Write end-of-file marker and close BGZF.
     ///
     /// Explicitly call of this method is not required unless you need .gzi index.
+    /// The index is also written into the file given to [`BGZFWriter::with_index_path`].
     /// Drop trait will write end-of-file marker automatically.
     /// If you need to handle I/O errors while closing, please use this method.
     pub fn close(mut self) -> io::Result<Option<BGZFIndex>> {
@@ -164,6 +245,9 @@ impl<W: io::Write> BGZFWriter<W> {
 
         if let Some(index) = self.bgzf_index.as_mut() {
             index.entries.pop();
+            if let Some(index_path) = self.index_path.take() {
+                index.write(io::BufWriter::new(std::fs::File::create(index_path)?))?;
+            }
         }
 
         Ok(self.bgzf_index.take())
@@ -184,18 +268,16 @@ impl<W: io::Write> io::Write for BGZFWriter<W> {
                 .extend_from_slice(&buf[process_start_pos..(process_start_pos + to_write_bytes)]);
             if self.original_data.len() >= self.compress_unit_size {
                 self.write_block()?;
-                self.original_data.clear();
             }
             process_start_pos += to_write_bytes;
         }
 
         Ok(buf.len())
     }
+    /// Write buffered data as a block, and flush the underlying writer.
     fn flush(&mut self) -> io::Result<()> {
-        if !self.original_data.is_empty() {
-            self.write_block()?;
-        }
-        Ok(())
+        self.flush_block()?;
+        self.writer.flush()
     }
 }
 
@@ -387,7 +469,13 @@ mod test {
             if size == 0 {
                 break;
             }
-            writer.write_all(&line.as_bytes())?;
+            let written_pos = match line.strip_suffix('\n') {
+                Some(stripped) if line_list.len() % 2 == 0 => {
+                    writer.write_line(stripped.as_bytes())?
+                }
+                _ => writer.write_record(line.as_bytes())?,
+            };
+            assert_eq!(pos, written_pos);
             line_list.push((pos, line.clone()));
         }
         writer.close()?;
@@ -405,6 +493,87 @@ mod test {
         Ok(())
     }
 
+    #[test]
+    fn test_virtual_offset() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        let mut writer = BGZFWriter::new(&mut output, Compression::default());
+        assert_eq!(writer.virtual_offset(), VirtualOffset::new(0, 0));
+        writer.write_all(b"chr1\t100\t200\n")?;
+        assert_eq!(writer.virtual_offset(), VirtualOffset::new(0, 13));
+        writer.flush()?;
+        let block_size = writer.virtual_offset().compressed_offset();
+        assert_eq!(writer.virtual_offset().uncompressed_offset(), 0);
+        writer.write_all(b"chr1\t300\t400\n")?;
+        assert_eq!(writer.virtual_offset(), VirtualOffset::new(block_size, 13));
+        assert_eq!(writer.virtual_offset(), writer.bgzf_pos());
+        writer.close()?;
+
+        let mut reader = BGZFReader::new(std::io::Cursor::new(&output[..]))?;
+        reader.bgzf_seek(block_size << 16 | 5)?;
+        let mut line = String::new();
+        reader.read_line(&mut line)?;
+        assert_eq!(line, "300\t400\n");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_flush_block() -> anyhow::Result<()> {
+        let mut output = Vec::new();
+        let mut writer = BGZFWriter::new(&mut output, Compression::default());
+        let records: [&[u8]; 3] = [b"first\n", b"second\n", b"third\n"];
+        let mut offsets = Vec::new();
+        for one in records {
+            offsets.push(writer.write_record(one)?);
+            writer.flush_block()?;
+            // nothing is written for an empty block
+            writer.flush_block()?;
+        }
+        writer.close()?;
+
+        assert!(offsets.iter().all(|x| x.uncompressed_offset() == 0));
+        let report = crate::validate(&output[..])?;
+        assert!(report.is_valid());
+        assert_eq!(report.number_of_blocks, 4);
+        let mut reader = BGZFReader::new(std::io::Cursor::new(&output[..]))?;
+        for (offset, expected) in offsets.iter().zip(records).rev() {
+            reader.bgzf_seek(*offset)?;
+            let mut line = Vec::new();
+            BufRead::read_until(&mut reader, b'\n', &mut line)?;
+            assert_eq!(line, expected);
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_create_with_index() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+        let mut writer = BGZFWriter::create_with_index(
+            "tmp/test-create-with-index.bed.gz",
+            Compression::fast(),
+        )?;
+        writer.write_all(&data)?;
+        let index = writer.close()?.unwrap();
+
+        let saved = BGZFIndex::from_reader(File::open("tmp/test-create-with-index.bed.gz.gzi")?)?;
+        assert_eq!(saved, index);
+        assert_eq!(
+            saved,
+            BGZFIndex::from_bgzf(File::open("tmp/test-create-with-index.bed.gz")?)?
+        );
+
+        let mut reader =
+            crate::read::IndexedBGZFReader::from_path("tmp/test-create-with-index.bed.gz")?;
+        std::io::Seek::seek(&mut reader, std::io::SeekFrom::Start(1_000_000))?;
+        let mut buf = [0u8; 100];
+        reader.read_exact(&mut buf)?;
+        assert_eq!(&buf[..], &data[1_000_000..1_000_100]);
+
+        Ok(())
+    }
+
     #[test]
     fn test_create() -> anyhow::Result<()> {
         let data = include_bytes!("../../testfiles/reg2bin.c");
@@ -418,6 +587,13 @@ mod test {
         reader.read_to_end(&mut buffer)?;
         assert_eq!(buffer, data);
 
+        let mut writer = BGZFWriter::create("tmp/test_create2.txt.gz", Compression::default())?;
+        writer.write_all(&data[..])?;
+        writer.close()?;
+        let mut buffer = Vec::new();
+        crate::BGZFReader::from_path("tmp/test_create2.txt.gz")?.read_to_end(&mut buffer)?;
+        assert_eq!(buffer, data);
+
         Ok(())
     }
 }
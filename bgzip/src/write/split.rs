@@ -0,0 +1,344 @@
+use super::*;
+use crate::tabix::TabixConfig;
+use std::io::{BufRead, Read};
+
+/// A part of output written by [`BGZFSplitWriter`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitPart {
+    /// Zero-based number of the part
+    pub part: usize,
+    /// Number of records, excluding meta lines
+    pub records: u64,
+    /// Sequence name of the first record. Empty if the part has no records.
+    pub first_name: Vec<u8>,
+    /// Zero-based begin position of the first record
+    pub first_begin: u64,
+    /// Sequence name of the last record. Empty if the part has no records.
+    pub last_name: Vec<u8>,
+    /// Maximum zero-based, exclusive end position of records of the last sequence
+    pub last_end: u64,
+    /// Offset of this part in the concatenated file, in which end-of-file markers of preceding parts are removed
+    pub compressed_offset: u64,
+    /// Size of this part including the end-of-file marker
+    pub compressed_size: u64,
+    /// Uncompressed offset of this part in the concatenated data
+    pub uncompressed_offset: u64,
+    /// Uncompressed size of this part
+    pub uncompressed_size: u64,
+}
+
+impl SplitPart {
+    /// Convert BGZF virtual file offset in this part into virtual file offset in the concatenated file.
+    pub fn concatenated_offset(&self, virtual_offset: u64) -> u64 {
+        virtual_offset + (self.compressed_offset << 16)
+    }
+}
+
+/// Catalog of parts written by [`BGZFSplitWriter`].
+///
+/// A catalog is saved as a tab-separated text with a header line.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct SplitCatalog {
+    pub parts: Vec<SplitPart>,
+}
+
+const CATALOG_HEADER: &str = "#part\trecords\tfirst_name\tfirst_begin\tlast_name\tlast_end\tcompressed_offset\tcompressed_size\tuncompressed_offset\tuncompressed_size\n";
+
+impl SplitCatalog {
+    /// Load a catalog written by [`SplitCatalog::write`].
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self, BGZFError> {
+        let mut parts = Vec::new();
+        for line in io::BufReader::new(reader).split(b'\n') {
+            let line = line?;
+            if line.is_empty() || line.starts_with(b"#") {
+                continue;
+            }
+            let columns: Vec<&[u8]> = line.split(|x| *x == b'\t').collect();
+            if columns.len() != 10 {
+                return Err(BGZFError::Other("Invalid number of columns in catalog"));
+            }
+            let number = |i: usize| -> Result<u64, BGZFError> {
+                std::str::from_utf8(columns[i])?
+                    .parse()
+                    .map_err(|_| BGZFError::Other("Invalid number in catalog"))
+            };
+            parts.push(SplitPart {
+                part: number(0)?.try_into().unwrap(),
+                records: number(1)?,
+                first_name: columns[2].to_vec(),
+                first_begin: number(3)?,
+                last_name: columns[4].to_vec(),
+                last_end: number(5)?,
+                compressed_offset: number(6)?,
+                compressed_size: number(7)?,
+                uncompressed_offset: number(8)?,
+                uncompressed_size: number(9)?,
+            });
+        }
+        Ok(SplitCatalog { parts })
+    }
+
+    /// Write the catalog as a tab-separated text.
+    pub fn write<W: Write>(&self, mut writer: W) -> io::Result<()> {
+        writer.write_all(CATALOG_HEADER.as_bytes())?;
+        for one in &self.parts {
+            write!(writer, "{}\t{}\t", one.part, one.records)?;
+            writer.write_all(&one.first_name)?;
+            write!(writer, "\t{}\t", one.first_begin)?;
+            writer.write_all(&one.last_name)?;
+            writeln!(
+                writer,
+                "\t{}\t{}\t{}\t{}\t{}",
+                one.last_end,
+                one.compressed_offset,
+                one.compressed_size,
+                one.uncompressed_offset,
+                one.uncompressed_size
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// A BGZF writer which rolls over to a new file when the compressed size exceeds a limit.
+///
+/// Input is split at line boundaries. A part is closed with end-of-file marker at the first
+/// line boundary after compressed size of the part reaches `max_size`, so a part can be larger
+/// than `max_size` by one BGZF block. Records are parsed with [`TabixConfig`] to record
+/// ranges of parts in [`SplitCatalog`].
+///
+/// Parts can be concatenated into one BGZF file with [`concatenate_parts`].
+pub struct BGZFSplitWriter<W: Write, F: FnMut(usize) -> io::Result<W>> {
+    create_part: F,
+    level: Compression,
+    max_size: u64,
+    config: TabixConfig,
+    writer: Option<BGZFWriter<W>>,
+    current: SplitPart,
+    catalog: SplitCatalog,
+    line: Vec<u8>,
+    line_count: u64,
+}
+
+impl<W: Write, F: FnMut(usize) -> io::Result<W>> BGZFSplitWriter<W, F> {
+    /// Create new split writer.
+    ///
+    /// `create_part` is called with the number of a part to create a writer for the part.
+    pub fn new(create_part: F, level: Compression, max_size: u64, config: TabixConfig) -> Self {
+        BGZFSplitWriter {
+            create_part,
+            level,
+            max_size,
+            config,
+            writer: None,
+            current: new_part(0, 0, 0),
+            catalog: SplitCatalog::default(),
+            line: Vec::new(),
+            line_count: 0,
+        }
+    }
+
+    /// Catalog of parts which are already closed
+    pub fn catalog(&self) -> &SplitCatalog {
+        &self.catalog
+    }
+
+    /// Write the last line and close the current part, and returns the catalog of all parts.
+    ///
+    /// One empty part is created if no data was written.
+    pub fn close(mut self) -> Result<SplitCatalog, BGZFError> {
+        if !self.line.is_empty() {
+            let line = std::mem::take(&mut self.line);
+            self.write_line(&line)?;
+        }
+        if self.writer.is_none() && self.catalog.parts.is_empty() {
+            self.writer = Some(BGZFWriter::new(
+                (self.create_part)(self.current.part)?,
+                self.level,
+            ));
+        }
+        self.close_part()?;
+        Ok(std::mem::take(&mut self.catalog))
+    }
+
+    fn write_line(&mut self, line: &[u8]) -> Result<(), BGZFError> {
+        self.line_count += 1;
+        if self.line_count > self.config.skip_lines() && !self.config.is_meta(line) {
+            let (name, begin, end) = self.config.parse_record(line)?;
+            let current = &mut self.current;
+            if current.records == 0 {
+                current.first_name = name.to_vec();
+                current.first_begin = begin;
+            }
+            if current.records == 0 || current.last_name != name {
+                current.last_name = name.to_vec();
+                current.last_end = end;
+            }
+            current.last_end = current.last_end.max(end);
+            current.records += 1;
+        }
+
+        let writer = match self.writer.as_mut() {
+            Some(writer) => writer,
+            None => self.writer.insert(BGZFWriter::new(
+                (self.create_part)(self.current.part)?,
+                self.level,
+            )),
+        };
+        writer.write_all(line)?;
+        self.current.uncompressed_size += TryInto::<u64>::try_into(line.len()).unwrap();
+        if writer.bgzf_pos().compressed_offset() >= self.max_size {
+            self.close_part()?;
+        }
+        Ok(())
+    }
+
+    fn close_part(&mut self) -> Result<(), BGZFError> {
+        let mut writer = match self.writer.take() {
+            Some(writer) => writer,
+            None => return Ok(()),
+        };
+        writer.flush()?;
+        let data_size = writer.bgzf_pos().compressed_offset();
+        writer.close()?;
+
+        let next = new_part(
+            self.current.part + 1,
+            self.current.compressed_offset + data_size,
+            self.current.uncompressed_offset + self.current.uncompressed_size,
+        );
+        let mut part = std::mem::replace(&mut self.current, next);
+        let size = data_size + EOF_MARKER_SIZE;
+        part.compressed_size = size;
+        self.catalog.parts.push(part);
+        Ok(())
+    }
+}
+
+impl<W: Write, F: FnMut(usize) -> io::Result<W>> Write for BGZFSplitWriter<W, F> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut rest = buf;
+        while let Some(i) = memchr::memchr(b'\n', rest) {
+            self.line.extend_from_slice(&rest[..(i + 1)]);
+            let line = std::mem::take(&mut self.line);
+            let result = self.write_line(&line);
+            self.line = line;
+            self.line.clear();
+            result.map_err(|e| e.into_io_error())?;
+            rest = &rest[(i + 1)..];
+        }
+        self.line.extend_from_slice(rest);
+        Ok(buf.len())
+    }
+
+    /// Lines are written when a new line character is written, so this function does nothing.
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+const EOF_MARKER_SIZE: u64 = crate::EOF_MARKER.len() as u64;
+
+fn new_part(part: usize, compressed_offset: u64, uncompressed_offset: u64) -> SplitPart {
+    SplitPart {
+        part,
+        records: 0,
+        first_name: Vec::new(),
+        first_begin: 0,
+        last_name: Vec::new(),
+        last_end: 0,
+        compressed_offset,
+        compressed_size: 0,
+        uncompressed_offset,
+        uncompressed_size: 0,
+    }
+}
+
+/// Concatenate BGZF files into one BGZF file.
+///
+/// End-of-file markers at the end of parts are removed, and one end-of-file marker is written at the end.
+/// Returns the size of the written file. Use [`super::concatenate`] to check that inputs are BGZF.
+pub fn concatenate_parts<R: Read, W: Write, I: IntoIterator<Item = R>>(
+    parts: I,
+    mut writer: W,
+) -> io::Result<u64> {
+    let mut size = 0;
+    for one in parts {
+        size += super::cat::copy_without_eof_marker(one, &mut writer)?;
+    }
+    writer.write_all(&crate::EOF_MARKER)?;
+    writer.flush()?;
+    Ok(size + EOF_MARKER_SIZE)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::BGZFReader;
+    use std::fs::File;
+
+    #[test]
+    fn test_split_writer() -> anyhow::Result<()> {
+        let mut data = Vec::new();
+        crate::open("testfiles/generated.bed.gz")?.read_to_end(&mut data)?;
+
+        let path = |i: usize| format!("tmp/split-part{}.bed.gz", i);
+        let mut writer = BGZFSplitWriter::new(
+            |i| File::create(path(i)),
+            Compression::default(),
+            300_000,
+            TabixConfig::BED,
+        );
+        // write in small pieces to split lines between calls
+        for one in data.chunks(1000) {
+            writer.write_all(one)?;
+        }
+        let catalog = writer.close()?;
+        assert!(catalog.parts.len() > 2);
+
+        let mut loaded = Vec::new();
+        catalog.write(&mut loaded)?;
+        assert_eq!(SplitCatalog::from_reader(&loaded[..])?, catalog);
+
+        let mut uncompressed_offset = 0;
+        for one in &catalog.parts {
+            let part_data = std::fs::read(path(one.part))?;
+            assert!(part_data.ends_with(&crate::EOF_MARKER));
+            assert_eq!(one.compressed_size, u64::try_from(part_data.len())?);
+            assert_eq!(one.uncompressed_offset, uncompressed_offset);
+            uncompressed_offset += one.uncompressed_size;
+
+            let mut first_line = Vec::new();
+            crate::open(path(one.part))?.read_until(b'\n', &mut first_line)?;
+            let (name, begin, _) = TabixConfig::BED.parse_record(&first_line)?;
+            assert_eq!((name, begin), (&one.first_name[..], one.first_begin));
+        }
+        assert_eq!(uncompressed_offset, u64::try_from(data.len())?);
+
+        let mut concatenated = Vec::new();
+        let size = concatenate_parts(
+            catalog
+                .parts
+                .iter()
+                .map(|x| File::open(path(x.part)))
+                .collect::<Result<Vec<_>, _>>()?,
+            &mut concatenated,
+        )?;
+        assert_eq!(size, u64::try_from(concatenated.len())?);
+        let mut concatenated_data = Vec::new();
+        crate::read::new_reader(&concatenated[..])?.read_to_end(&mut concatenated_data)?;
+        assert!(concatenated_data == data);
+
+        let last = catalog.parts.last().unwrap();
+        let mut reader = BGZFReader::new(io::Cursor::new(&concatenated))?;
+        reader.bgzf_seek(last.concatenated_offset(0))?;
+        let mut line = Vec::new();
+        BufRead::read_until(&mut reader, b'\n', &mut line)?;
+        assert_eq!(
+            TabixConfig::BED.parse_record(&line)?.0,
+            &last.first_name[..]
+        );
+
+        Ok(())
+    }
+}
@@ -3,7 +3,7 @@ use crate::rayon::receive_or_yield;
 use crate::{deflate::*, index::BGZFIndex, BGZFError};
 use std::collections::HashMap;
 use std::convert::TryInto;
-use std::io::{self, Error, ErrorKind, Write};
+use std::io::{self, Error, Write};
 use std::sync::mpsc::{channel, Receiver, Sender};
 
 const DEFAULT_WRITE_BLOCK_UNIT_NUM: usize = 50;
@@ -45,6 +45,9 @@ impl WriteBlock {
     }
 }
 
+/// Compressed block, or index of the block and compression error
+type CompressResult = Result<WriteBlock, (u64, CompressError)>;
+
 /// A Multi-thread BGZF writer
 ///
 /// [rayon](https://crates.io/crates/rayon) is used to run compression in a thread pool.
@@ -54,19 +57,22 @@ pub struct BGZFMultiThreadWriter<W: Write> {
     write_block_num: usize,
     block_list: Vec<WriteBlock>,
     write_waiting_blocks: HashMap<u64, WriteBlock>,
-    writer_receiver: Receiver<WriteBlock>,
-    writer_sender: Sender<WriteBlock>,
+    writer_receiver: Receiver<CompressResult>,
+    writer_sender: Sender<CompressResult>,
     next_write_index: u64,
     next_compress_index: u64,
+    pending_blocks: u64,
     closed: bool,
+    failed: bool,
 
     current_compressed_pos: u64,
     current_uncompressed_pos: u64,
+    written_bytes: u64,
     bgzf_index: Option<BGZFIndex>,
 }
 
 impl<W: Write> BGZFMultiThreadWriter<W> {
-    /// Create new [`BGZFMultiThreadWriter`] from [`std::io::Read`] and [`Compression`]
+    /// Create new [`BGZFMultiThreadWriter`] from [`std::io::Write`] and [`Compression`]
     pub fn new(writer: W, level: Compression) -> Self {
         Self::with_compress_unit_size(
             writer,
@@ -78,6 +84,9 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
         .expect("Unreachable (BGZFMultiThreadWriter)")
     }
 
+    /// Create new [`BGZFMultiThreadWriter`] with compress unit size.
+    ///
+    /// `write_block_num` blocks of `compress_unit_size` bytes are compressed in one task.
     pub fn with_compress_unit_size(
         writer: W,
         compress_unit_size: usize,
@@ -103,9 +112,12 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
             writer_sender: tx,
             next_write_index: 0,
             next_compress_index: 0,
+            pending_blocks: 0,
             closed: false,
+            failed: false,
             current_uncompressed_pos: 0,
             current_compressed_pos: 0,
+            written_bytes: 0,
             bgzf_index: if create_index {
                 Some(BGZFIndex::new())
             } else {
@@ -115,7 +127,14 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
     }
 
     fn write_blocks(&mut self, mut next_data: WriteBlock) -> io::Result<()> {
-        self.writer.write_all(&next_data.compressed_buffer)?;
+        self.writer
+            .write_all(&next_data.compressed_buffer)
+            .map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Failed to write block {}: {}", next_data.index, e),
+                )
+            })?;
         for one in &next_data.block_sizes {
             self.current_compressed_pos += TryInto::<u64>::try_into(one.compressed_size).unwrap();
             self.current_uncompressed_pos +=
@@ -135,20 +154,35 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
     }
 
     fn process_buffer(&mut self, block: bool, block_all: bool) -> io::Result<()> {
+        if self.failed {
+            return Err(Error::other("BGZF writer is in failed state"));
+        }
+        let result = self.receive_blocks(block, block_all);
+        if result.is_err() {
+            self.failed = true;
+        }
+        result
+    }
+
+    fn receive_blocks(&mut self, block: bool, block_all: bool) -> io::Result<()> {
         let mut current_block = block;
         while self.next_compress_index != self.next_write_index {
             let next_data = if current_block {
                 receive_or_yield(&self.writer_receiver)
-                    .map_err(|_| Error::new(ErrorKind::Other, "Closed channel"))?
+                    .map_err(|_| Error::other("Closed channel"))?
             } else {
                 match self.writer_receiver.try_recv() {
                     Ok(d) => d,
                     Err(std::sync::mpsc::TryRecvError::Empty) => return Ok(()),
                     Err(std::sync::mpsc::TryRecvError::Disconnected) => {
-                        return Err(Error::new(ErrorKind::Other, "Closed channel"))
+                        return Err(Error::other("Closed channel"))
                     }
                 }
             };
+            self.pending_blocks -= 1;
+            let next_data = next_data.map_err(|(index, e)| {
+                Error::other(format!("Failed to compress block {}: {}", index, e))
+            })?;
             // eprintln!(
             //     "fetch thread data: {} / {} / {}",
             //     next_data.index, self.next_write_index, self.next_compress_index
@@ -171,10 +205,21 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
         Ok(())
     }
 
+    /// Wait for all dispatched compression tasks, and discard their results.
+    fn join_workers(&mut self) {
+        while self.pending_blocks > 0 {
+            if receive_or_yield(&self.writer_receiver).is_err() {
+                break;
+            }
+            self.pending_blocks -= 1;
+        }
+    }
+
     fn dispatch_current_block(&mut self) {
         let mut block = self.block_list.remove(0);
         block.index = self.next_compress_index;
         self.next_compress_index += 1;
+        self.pending_blocks += 1;
         let sender = self.writer_sender.clone();
         // eprintln!("spawn thread: {}", block.index);
         let compress_unit_size = self.compress_unit_size;
@@ -191,12 +236,18 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
                 //     String::from_utf8_lossy(&block.raw_buffer[wrote_bytes..(wrote_bytes + 10)])
                 // );
                 let bytes_to_write = (block.raw_buffer.len() - wrote_bytes).min(compress_unit_size);
-                let compressed_size = crate::write::write_block(
+                let compressed_size = match crate::write::write_block(
                     &mut block.compressed_buffer,
                     &block.raw_buffer[wrote_bytes..(wrote_bytes + bytes_to_write)],
                     &mut block.compress,
-                )
-                .expect("Failed to write block");
+                ) {
+                    Ok(size) => size,
+                    Err(e) => {
+                        // the receiver is dropped if the writer is already failed
+                        let _ = sender.send(Err((block.index, e)));
+                        return;
+                    }
+                };
                 wrote_bytes += bytes_to_write;
                 block.block_sizes.push(BlockSize {
                     uncompressed_size: bytes_to_write,
@@ -205,19 +256,33 @@ impl<W: Write> BGZFMultiThreadWriter<W> {
             }
 
             //eprintln!("finished thread: {}", block.index);
-            sender.send(block).expect("failed to send write result");
+            let _ = sender.send(Ok(block));
         });
     }
 
+    /// Current write position.
+    ///
+    /// This position includes data which is not compressed yet.
+    pub fn pos(&self) -> u64 {
+        self.written_bytes
+    }
+
     /// Write end-of-file marker and close BGZF.
     ///
+    /// This method waits for all compression tasks. The first compression or I/O error is
+    /// returned with the index of the failed block, and end-of-file marker is not written in that case.
+    /// End-of-file marker is written exactly once, and Drop trait does nothing after this method.
+    ///
     /// Explicitly call of this method is not required unless you need .gzi index.
-    /// Drop trait will write end-of-file marker automatically.
-    /// If you need to handle I/O errors while closing, please use this method.    
+    /// Drop trait will write end-of-file marker automatically, but it panics on errors.
     pub fn close(mut self) -> io::Result<Option<BGZFIndex>> {
-        self.flush()?;
-        self.writer.write_all(&crate::EOF_MARKER)?;
         self.closed = true;
+        if let Err(e) = self.flush() {
+            self.join_workers();
+            return Err(e);
+        }
+        self.writer.write_all(&crate::EOF_MARKER)?;
+        self.writer.flush()?;
 
         if let Some(index) = self.bgzf_index.as_mut() {
             index.entries.pop();
@@ -245,12 +310,13 @@ impl<W: Write> Write for BGZFMultiThreadWriter<W> {
             wrote_bytes += bytes_to_write;
         }
 
+        self.written_bytes += TryInto::<u64>::try_into(wrote_bytes).unwrap();
         Ok(wrote_bytes)
     }
 
     fn flush(&mut self) -> io::Result<()> {
         self.process_buffer(self.block_list.is_empty(), false)?;
-        if self.block_list[0].raw_buffer.len() > 0 {
+        if !self.block_list[0].raw_buffer.is_empty() {
             self.dispatch_current_block();
         }
         self.process_buffer(true, true)?;
@@ -267,7 +333,10 @@ impl<W: Write> Write for BGZFMultiThreadWriter<W> {
 
 impl<W: Write> Drop for BGZFMultiThreadWriter<W> {
     fn drop(&mut self) {
-        if !self.closed {
+        if self.failed {
+            self.join_workers();
+        } else if !self.closed {
+            self.closed = true;
             self.flush().expect("BGZF: Flash Error");
             self.writer
                 .write_all(&crate::EOF_MARKER)
@@ -349,9 +418,10 @@ mod test {
             if to_write_bytes == 0 {
                 break;
             }
-            wrote_bytes += writer.write(&mut data[wrote_bytes..(wrote_bytes + to_write_bytes)])?;
+            wrote_bytes += writer.write(&data[wrote_bytes..(wrote_bytes + to_write_bytes)])?;
         }
         //eprintln!("wrote_bytes: {}/{}", i, wrote_bytes);
+        assert_eq!(writer.pos(), TryInto::<u64>::try_into(BUF_SIZE)?);
 
         writer
             .close()?
@@ -371,4 +441,73 @@ mod test {
 
         Ok(())
     }
+
+    /// Writer which fails after `limit` bytes
+    struct FailingWriter {
+        data: Vec<u8>,
+        limit: usize,
+    }
+
+    impl Write for FailingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.data.len() + buf.len() > self.limit {
+                return Err(Error::other("disk full"));
+            }
+            self.data.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_close() -> anyhow::Result<()> {
+        let mut rand = rand_pcg::Pcg64Mcg::seed_from_u64(490);
+        let mut data = vec![0; 1_000_000];
+        rand.fill_bytes(&mut data);
+
+        let mut output = Vec::new();
+        let mut writer = BGZFMultiThreadWriter::with_compress_unit_size(
+            &mut output,
+            1024,
+            10,
+            Compression::default(),
+            false,
+        )?;
+        writer.write_all(&data)?;
+        writer.flush()?;
+        writer.close()?;
+        assert!(output.ends_with(&crate::EOF_MARKER));
+        assert_eq!(
+            output
+                .windows(crate::EOF_MARKER.len())
+                .filter(|x| *x == crate::EOF_MARKER)
+                .count(),
+            1
+        );
+
+        let mut writer = BGZFMultiThreadWriter::with_compress_unit_size(
+            FailingWriter {
+                data: Vec::new(),
+                limit: 100_000,
+            },
+            1024,
+            10,
+            Compression::default(),
+            false,
+        )?;
+        let result = writer
+            .write_all(&data)
+            .and_then(|_| writer.close().map(|_| ()));
+        let error = result.unwrap_err();
+        assert!(
+            error.to_string().contains("Failed to write block"),
+            "{}",
+            error
+        );
+
+        Ok(())
+    }
 }